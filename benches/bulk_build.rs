@@ -0,0 +1,48 @@
+use std::hash::BuildHasher;
+
+use bbloom::{BloomFilter, SeededHashBuilder};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const N: usize = 100_000_000;
+const P: f64 = 0.01;
+
+fn build() -> BloomFilter<SeededHashBuilder> {
+    BloomFilter::from_fpp_with_hashers(P, N, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+}
+
+fn bench_bulk_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk_build");
+    group.sample_size(10);
+
+    group.bench_function("insert", |b| {
+        b.iter(|| {
+            let mut filter = build();
+
+            for key in 0..N as u64 {
+                filter.insert(&key);
+            }
+
+            filter
+        })
+    });
+
+    group.bench_function("bulk_set", |b| {
+        let builder_1 = SeededHashBuilder::new(0);
+        let builder_2 = SeededHashBuilder::new(1);
+
+        b.iter(|| {
+            let mut filter = build();
+
+            let digests =
+                (0..N as u64).map(|key| (builder_1.hash_one(key), builder_2.hash_one(key)));
+            filter.bulk_set(digests);
+
+            filter
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_bulk_build);
+criterion_main!(benches);
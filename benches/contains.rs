@@ -0,0 +1,248 @@
+use bbloom::{
+    params::plan_for_fpp,
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    BloomFilter, MultiBloomFilter, ScalableBloomFilter, SeededHashBuilder,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const N: usize = 1 << 20;
+
+fn build(p: f64) -> BloomFilter<SeededHashBuilder> {
+    let mut filter = BloomFilter::from_fpp_with_hashers(
+        p,
+        N,
+        SeededHashBuilder::new(0),
+        SeededHashBuilder::new(1),
+    );
+
+    for i in 0..N as u64 {
+        filter.insert(&i);
+    }
+
+    filter
+}
+
+fn bench_contains(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains");
+
+    let hits: Vec<u64> = (0..1024).map(|i| i * 997 % N as u64).collect();
+    let misses: Vec<u64> = (0..1024).map(|i| N as u64 + i).collect();
+
+    for &p in &[0.01, 0.0001] {
+        let filter = build(p);
+
+        for (name, keys) in [("hit", &hits), ("miss", &misses)].iter() {
+            group.bench_with_input(BenchmarkId::new(*name, p), keys, |b, keys| {
+                b.iter(|| keys.iter().filter(|key| filter.contains(*key)).count())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// A bit array in the raw byte layout of `BloomFilter::to_raw_bytes`, probed by either loop.
+struct RawFilter {
+    bytes: Vec<u8>,
+    m: usize,
+    k: usize,
+}
+
+impl RawFilter {
+    fn new(filter: &BloomFilter<SeededHashBuilder>, k: usize) -> Self {
+        Self {
+            bytes: filter.to_raw_bytes(),
+            m: filter.capacity(),
+            k,
+        }
+    }
+
+    fn get(&self, i: usize) -> bool {
+        (self.bytes[i / 8] >> (7 - i % 8)) & 1 == 1
+    }
+
+    // Tests each bit as soon as its index is computed, as `BloomFilter::contains` does.
+    fn contains_interleaved(&self, h1: u64, h2: u64) -> bool {
+        (0..self.k).all(|i| self.get(DoubleHashing::index(h1, h2, i, self.m)))
+    }
+
+    // Computes all `k` indices into a stack buffer, then tests all of them without branching.
+    fn contains_split(&self, h1: u64, h2: u64) -> bool {
+        const MAX_K: usize = 32;
+
+        let mut indices = [0; MAX_K];
+
+        for (i, index) in indices[..self.k].iter_mut().enumerate() {
+            *index = DoubleHashing::index(h1, h2, i, self.m);
+        }
+
+        indices[..self.k]
+            .iter()
+            .fold(true, |present, &i| present & self.get(i))
+    }
+}
+
+fn bench_contains_split(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains_split");
+
+    for &p in &[0.01, 0.0001] {
+        let plan = plan_for_fpp(p, N);
+        let mut filter = BloomFilter::with_hashers(
+            plan.m(),
+            plan.k(),
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        for i in 0..N as u64 {
+            filter.insert(&i);
+        }
+
+        let raw_filter = RawFilter::new(&filter, plan.k());
+
+        let hits: Vec<_> = (0..1024)
+            .map(|i| filter.hash_key(&(i * 997 % N as u64)))
+            .collect();
+        let misses: Vec<_> = (0..1024)
+            .map(|i| filter.hash_key(&(N as u64 + i)))
+            .collect();
+
+        for (name, hashes) in [("hit", &hits), ("miss", &misses)].iter() {
+            for &(h1, h2) in hashes.iter() {
+                assert_eq!(
+                    raw_filter.contains_split(h1, h2),
+                    raw_filter.contains_interleaved(h1, h2)
+                );
+            }
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("interleaved/{}", name), p),
+                hashes,
+                |b, hashes| {
+                    b.iter(|| {
+                        hashes
+                            .iter()
+                            .filter(|(h1, h2)| raw_filter.contains_interleaved(*h1, *h2))
+                            .count()
+                    })
+                },
+            );
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("split/{}", name), p),
+                hashes,
+                |b, hashes| {
+                    b.iter(|| {
+                        hashes
+                            .iter()
+                            .filter(|(h1, h2)| raw_filter.contains_split(*h1, *h2))
+                            .count()
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn build_scalable(negative_cache: bool) -> ScalableBloomFilter<SeededHashBuilder> {
+    // 7 layers
+    let mut filter =
+        ScalableBloomFilter::with_hashers(0.0001, N >> 8, Default::default(), Default::default());
+
+    if negative_cache {
+        filter = filter.with_negative_cache(0.001, N);
+    }
+
+    for i in 0..N as u64 {
+        filter.insert(&i);
+    }
+
+    filter
+}
+
+fn bench_scalable_contains(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scalable_contains");
+
+    let misses: Vec<u64> = (0..1024).map(|i| N as u64 + i).collect();
+
+    for &negative_cache in &[false, true] {
+        let filter = build_scalable(negative_cache);
+
+        group.bench_with_input(
+            BenchmarkId::new("miss", negative_cache),
+            &misses,
+            |b, keys| b.iter(|| keys.iter().filter(|key| filter.contains(*key)).count()),
+        );
+    }
+
+    group.finish();
+}
+
+const LABEL_COUNT: usize = 8;
+
+fn bench_multi_contains(c: &mut Criterion) {
+    const P: f64 = 0.01;
+    const LABEL_N: usize = N >> 3;
+
+    let mut group = c.benchmark_group("multi_contains");
+
+    let mut filters: Vec<_> = (0..LABEL_COUNT)
+        .map(|_| {
+            BloomFilter::from_fpp_with_hashers(
+                P,
+                LABEL_N,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        })
+        .collect();
+
+    let mut multi_filter = MultiBloomFilter::from_fpp_with_hashers(
+        LABEL_COUNT,
+        P,
+        LABEL_N,
+        SeededHashBuilder::new(0),
+        SeededHashBuilder::new(1),
+    );
+
+    for i in 0..LABEL_N as u64 {
+        for (label, filter) in filters.iter_mut().enumerate() {
+            // Every key is in about half of the labels.
+            if (i >> label) & 1 == 1 {
+                filter.insert(&i);
+                multi_filter.insert(label, &i);
+            }
+        }
+    }
+
+    let keys: Vec<u64> = (0..1024).map(|i| i * 997 % LABEL_N as u64).collect();
+
+    group.bench_with_input("separate", &keys, |b, keys| {
+        b.iter(|| {
+            keys.iter()
+                .map(|key| filters.iter().filter(|filter| filter.contains(key)).count())
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_with_input("shared", &keys, |b, keys| {
+        b.iter(|| {
+            keys.iter()
+                .map(|key| multi_filter.contains_labels(key).len())
+                .sum::<usize>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_contains,
+    bench_contains_split,
+    bench_scalable_contains,
+    bench_multi_contains
+);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+'use strict';
+
+const assert = require('node:assert');
+const fs = require('node:fs');
+const path = require('node:path');
+const test = require('node:test');
+
+const { BloomFilter, ScalableBloomFilter } = require('..');
+
+const FIXTURE = path.join(__dirname, 'fixtures', 'abc.bin');
+const SCALABLE_FIXTURE = path.join(__dirname, 'fixtures', 'abc-scalable.bin');
+
+test('reads a filter written by Rust', () => {
+  const buf = fs.readFileSync(FIXTURE);
+  const filter = BloomFilter.fromBuffer(buf);
+
+  assert.strictEqual(filter.length, 3);
+  assert.ok(filter.contains('a'));
+  assert.ok(filter.contains('b'));
+  assert.ok(filter.contains('c'));
+  assert.ok(!filter.contains('d'));
+
+  assert.deepStrictEqual(filter.toBuffer(), buf);
+});
+
+test('rejects a corrupt buffer', () => {
+  const buf = fs.readFileSync(FIXTURE);
+  buf[buf.length - 1] ^= 0xff;
+  assert.throws(() => BloomFilter.fromBuffer(buf));
+});
+
+test('distinguishes string and Buffer keys', () => {
+  const filter = new BloomFilter(0.001, 16);
+
+  assert.ok(filter.insert(Buffer.from([0x00, 0xff])));
+  assert.ok(!filter.insert(Buffer.from([0x00, 0xff])));
+
+  assert.ok(filter.contains(Buffer.from([0x00, 0xff])));
+  assert.ok(!filter.contains('a'));
+});
+
+test('inserts many keys asynchronously', async () => {
+  const filter = new BloomFilter(0.001, 1024);
+  const keys = Array.from({ length: 1024 }, (_, i) => `key-${i}`);
+
+  const inserted = await filter.insertMany(keys);
+
+  assert.ok(inserted > 1000);
+  assert.ok(keys.every((key) => filter.contains(key)));
+});
+
+test('grows a scalable filter', async () => {
+  const filter = new ScalableBloomFilter(0.001, 8);
+  const keys = Array.from({ length: 64 }, (_, i) => `key-${i}`);
+
+  await filter.insertMany(keys);
+  filter.insert(Buffer.from('bbloom'));
+
+  assert.ok(keys.every((key) => filter.contains(key)));
+  assert.ok(filter.contains(Buffer.from('bbloom')));
+});
+
+test('reads a scalable filter written by Rust', () => {
+  const buf = fs.readFileSync(SCALABLE_FIXTURE);
+  const filter = ScalableBloomFilter.fromBuffer(buf);
+
+  assert.strictEqual(filter.length, 3);
+  assert.ok(filter.contains('a'));
+  assert.ok(filter.contains('b'));
+  assert.ok(filter.contains('c'));
+  assert.ok(!filter.contains('d'));
+
+  assert.deepStrictEqual(filter.toBuffer(), buf);
+});
+
+test('round trips a scalable filter', async () => {
+  const filter = new ScalableBloomFilter(0.001, 8);
+  const keys = Array.from({ length: 64 }, (_, i) => `key-${i}`);
+
+  await filter.insertMany(keys);
+
+  const other = ScalableBloomFilter.fromBuffer(filter.toBuffer());
+
+  assert.strictEqual(other.length, filter.length);
+  assert.ok(keys.every((key) => other.contains(key)));
+  assert.deepStrictEqual(other.toBuffer(), filter.toBuffer());
+});
+
+test('rejects a corrupt scalable buffer', () => {
+  const buf = fs.readFileSync(SCALABLE_FIXTURE);
+  buf[buf.length - 1] ^= 0xff;
+  assert.throws(() => ScalableBloomFilter.fromBuffer(buf));
+});
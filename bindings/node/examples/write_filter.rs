@@ -0,0 +1,35 @@
+//! Writes a Bloom filter of the given keys to stdout in the bbloom binary format.
+//!
+//! The filter uses the same hash builders as the Node.js bindings, so the output can be read
+//! using `BloomFilter.fromBuffer`.
+//!
+//! Usage: `cargo run --example write_filter -- <key>... > filter.bin`
+
+use std::{
+    env,
+    io::{self, Write},
+};
+
+use bbloom::{codegen::SEEDS, BloomFilter, SeededHashBuilder};
+
+const FALSE_POSITIVE_PROBABILITY: f64 = 0.001;
+
+fn main() -> io::Result<()> {
+    let keys: Vec<_> = env::args().skip(1).collect();
+
+    let mut filter = BloomFilter::from_fpp_with_hashers(
+        FALSE_POSITIVE_PROBABILITY,
+        keys.len(),
+        SeededHashBuilder::new(SEEDS.0),
+        SeededHashBuilder::new(SEEDS.1),
+    );
+
+    for key in &keys {
+        filter.insert(key.as_str());
+    }
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    filter.write_to(&mut handle)?;
+    handle.flush()
+}
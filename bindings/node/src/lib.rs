@@ -0,0 +1,298 @@
+//! Node.js bindings for bbloom.
+//!
+//! Filters use [`bbloom::SeededHashBuilder`]s seeded with [`bbloom::codegen::SEEDS`], so a
+//! buffer written by `toBuffer` can be read by a Rust program using the same hash builders, and
+//! vice versa.
+//!
+//! Keys are either strings or `Buffer`s. A string is hashed as a Rust `str` and a `Buffer` as a
+//! Rust `[u8]`, so the string `"a"` and the buffer `Buffer.from("a")` are different keys.
+//! `Buffer`s are hashed in place, without copying.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bbloom::{codegen::SEEDS, SeededHashBuilder};
+use napi::{
+    bindgen_prelude::{AsyncTask, Buffer, Either},
+    Env, Error, Result, Status, Task,
+};
+use napi_derive::napi;
+
+type Key = Either<String, Buffer>;
+
+type Inner = bbloom::BloomFilter<SeededHashBuilder>;
+type ScalableInner = bbloom::ScalableBloomFilter<SeededHashBuilder>;
+
+fn builders() -> (SeededHashBuilder, SeededHashBuilder) {
+    (
+        SeededHashBuilder::new(SEEDS.0),
+        SeededHashBuilder::new(SEEDS.1),
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| Error::new(Status::GenericFailure, "filter lock is poisoned"))
+}
+
+fn keys_to_owned(keys: Vec<Key>) -> Vec<Either<String, Vec<u8>>> {
+    keys.into_iter()
+        .map(|key| match key {
+            Either::A(s) => Either::A(s),
+            Either::B(buf) => Either::B(buf.to_vec()),
+        })
+        .collect()
+}
+
+/// A Bloom filter.
+#[napi]
+pub struct BloomFilter {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[napi]
+impl BloomFilter {
+    /// Creates a filter that targets a false positive probability `p` with an expected number
+    /// of inserted elements `n`.
+    #[napi(constructor)]
+    pub fn new(p: f64, n: u32) -> Self {
+        let (builder_1, builder_2) = builders();
+        let inner = Inner::from_fpp_with_hashers(p, n as usize, builder_1, builder_2);
+        Self::from_inner(inner)
+    }
+
+    /// Reads a filter from the bbloom binary format.
+    #[napi(factory)]
+    pub fn from_buffer(buf: Buffer) -> Result<Self> {
+        let (builder_1, builder_2) = builders();
+
+        Inner::from_bytes_with_hashers(&buf, builder_1, builder_2)
+            .map(Self::from_inner)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+    }
+
+    /// Writes the filter in the bbloom binary format.
+    #[napi]
+    pub fn to_buffer(&self) -> Result<Buffer> {
+        let filter = lock(&self.inner)?;
+        Ok(filter.to_bytes().into())
+    }
+
+    /// Adds a key to the filter, returning whether it was not already (maybe) present.
+    #[napi]
+    pub fn insert(&self, key: Key) -> Result<bool> {
+        let mut filter = lock(&self.inner)?;
+
+        Ok(match key {
+            Either::A(s) => filter.insert(s.as_str()),
+            Either::B(buf) => filter.insert(&buf[..]),
+        })
+    }
+
+    /// Adds keys to the filter on a worker thread, resolving to the number of keys that were not
+    /// already (maybe) present.
+    #[napi(ts_return_type = "Promise<number>")]
+    pub fn insert_many(&self, keys: Vec<Key>) -> AsyncTask<InsertMany<Inner>> {
+        AsyncTask::new(InsertMany {
+            filter: Arc::clone(&self.inner),
+            keys: keys_to_owned(keys),
+        })
+    }
+
+    /// Tests whether a key may be in the filter or definitely not in the filter.
+    #[napi]
+    pub fn contains(&self, key: Key) -> Result<bool> {
+        let filter = lock(&self.inner)?;
+
+        Ok(match key {
+            Either::A(s) => filter.contains(s.as_str()),
+            Either::B(buf) => filter.contains(&buf[..]),
+        })
+    }
+
+    /// The number of elements in the filter.
+    #[napi(getter)]
+    pub fn length(&self) -> Result<u32> {
+        let filter = lock(&self.inner)?;
+        Ok(filter.len() as u32)
+    }
+
+    fn from_inner(inner: Inner) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+}
+
+/// A scalable Bloom filter.
+#[napi]
+pub struct ScalableBloomFilter {
+    inner: Arc<Mutex<ScalableInner>>,
+}
+
+#[napi]
+impl ScalableBloomFilter {
+    /// Creates a filter that targets a false positive probability `p` with an initial expected
+    /// number of inserted elements `n`.
+    #[napi(constructor)]
+    pub fn new(p: f64, n: u32) -> Self {
+        let (builder_1, builder_2) = builders();
+        let inner = ScalableInner::with_hashers(p, n as usize, builder_1, builder_2);
+        Self::from_inner(inner)
+    }
+
+    /// Reads a filter from the bbloom binary format.
+    #[napi(factory)]
+    pub fn from_buffer(buf: Buffer) -> Result<Self> {
+        let (builder_1, builder_2) = builders();
+
+        ScalableInner::from_bytes_with_hashers(&buf, builder_1, builder_2)
+            .map(Self::from_inner)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+    }
+
+    /// Writes the filter in the bbloom binary format.
+    #[napi]
+    pub fn to_buffer(&self) -> Result<Buffer> {
+        let filter = lock(&self.inner)?;
+        Ok(filter.to_bytes().into())
+    }
+
+    /// Adds a key to the filter, returning whether it was not already (maybe) present in the
+    /// last layer.
+    #[napi]
+    pub fn insert(&self, key: Key) -> Result<bool> {
+        let mut filter = lock(&self.inner)?;
+
+        Ok(match key {
+            Either::A(s) => filter.insert(s.as_str()),
+            Either::B(buf) => filter.insert(&buf[..]),
+        })
+    }
+
+    /// Adds keys to the filter on a worker thread, resolving to the number of keys that were not
+    /// already (maybe) present in the last layer.
+    #[napi(ts_return_type = "Promise<number>")]
+    pub fn insert_many(&self, keys: Vec<Key>) -> AsyncTask<InsertMany<ScalableInner>> {
+        AsyncTask::new(InsertMany {
+            filter: Arc::clone(&self.inner),
+            keys: keys_to_owned(keys),
+        })
+    }
+
+    /// Tests whether a key may be in the filter or definitely not in the filter.
+    #[napi]
+    pub fn contains(&self, key: Key) -> Result<bool> {
+        let filter = lock(&self.inner)?;
+
+        Ok(match key {
+            Either::A(s) => filter.contains(s.as_str()),
+            Either::B(buf) => filter.contains(&buf[..]),
+        })
+    }
+
+    /// The number of elements in the filter.
+    #[napi(getter)]
+    pub fn length(&self) -> Result<u32> {
+        let filter = lock(&self.inner)?;
+        Ok(filter.len() as u32)
+    }
+
+    fn from_inner(inner: ScalableInner) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+}
+
+trait Insert {
+    fn insert_str(&mut self, key: &str) -> bool;
+    fn insert_bytes(&mut self, key: &[u8]) -> bool;
+}
+
+impl Insert for Inner {
+    fn insert_str(&mut self, key: &str) -> bool {
+        self.insert(key)
+    }
+
+    fn insert_bytes(&mut self, key: &[u8]) -> bool {
+        self.insert(key)
+    }
+}
+
+impl Insert for ScalableInner {
+    fn insert_str(&mut self, key: &str) -> bool {
+        self.insert(key)
+    }
+
+    fn insert_bytes(&mut self, key: &[u8]) -> bool {
+        self.insert(key)
+    }
+}
+
+#[doc(hidden)]
+pub struct InsertMany<T> {
+    filter: Arc<Mutex<T>>,
+    keys: Vec<Either<String, Vec<u8>>>,
+}
+
+impl<T> Task for InsertMany<T>
+where
+    T: Insert + Send,
+{
+    type Output = u32;
+    type JsValue = u32;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut filter = lock(&self.filter)?;
+        let mut inserted = 0;
+
+        for key in &self.keys {
+            let is_new = match key {
+                Either::A(s) => filter.insert_str(s),
+                Either::B(buf) => filter.insert_bytes(buf),
+            };
+
+            if is_new {
+                inserted += 1;
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_matches_rust_output() {
+        let (builder_1, builder_2) = builders();
+        let mut filter = Inner::from_fpp_with_hashers(0.001, 3, builder_1, builder_2);
+
+        for key in &["a", "b", "c"] {
+            filter.insert(*key);
+        }
+
+        let expected = include_bytes!("../__test__/fixtures/abc.bin");
+        assert_eq!(filter.to_bytes(), &expected[..]);
+    }
+
+    #[test]
+    fn test_scalable_fixture_matches_rust_output() {
+        let (builder_1, builder_2) = builders();
+        let mut filter = ScalableInner::with_hashers(0.001, 2, builder_1, builder_2);
+
+        for key in &["a", "b", "c"] {
+            filter.insert(*key);
+        }
+
+        let expected = include_bytes!("../__test__/fixtures/abc-scalable.bin");
+        assert_eq!(filter.to_bytes(), &expected[..]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Procedural macros for bbloom.
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Ident, LitFloat, LitStr, Token,
+};
+
+/// Builds a `bbloom::StaticBloomFilter` from string literals at compile time.
+///
+/// The first argument is the target false positive probability, and the second is a list of
+/// string literals. Duplicate literals are inserted once. The filter is hashed using
+/// `bbloom::SeededHashBuilder` with `bbloom::codegen::SEEDS`, and its bit array is embedded as
+/// a `static`, so the expansion can be used to initialize a `static`.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::StaticBloomFilter;
+/// use bbloom_macros::bloom;
+///
+/// static FILTER: StaticBloomFilter = bloom!(fpp = 0.001, ["a", "b", "c"]);
+///
+/// assert!(FILTER.contains("a"));
+/// assert!(!FILTER.contains("d"));
+/// ```
+#[proc_macro]
+pub fn bloom(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as BloomInput);
+
+    let mut seen = HashSet::new();
+    let keys: Vec<_> = input
+        .keys
+        .iter()
+        .map(|lit| lit.value())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let filter = bbloom::codegen::build(keys.iter().map(|s| s.as_str()), input.fpp);
+
+    let m = filter.m;
+    let k = filter.k;
+    let (seed_1, seed_2) = filter.seeds;
+    let len = filter.words.len();
+    let words = &filter.words;
+
+    let expanded = quote! {
+        {
+            static WORDS: [u64; #len] = [#(#words),*];
+            ::bbloom::StaticBloomFilter::from_static(&WORDS, #m, #k, (#seed_1, #seed_2))
+        }
+    };
+
+    expanded.into()
+}
+
+struct BloomInput {
+    fpp: f64,
+    keys: Punctuated<LitStr, Token![,]>,
+}
+
+impl Parse for BloomInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+
+        if name != "fpp" {
+            return Err(syn::Error::new(name.span(), "expected `fpp`"));
+        }
+
+        input.parse::<Token![=]>()?;
+
+        let lit: LitFloat = input.parse()?;
+        let fpp: f64 = lit.base10_parse()?;
+
+        if !(fpp > 0.0 && fpp < 1.0) {
+            return Err(syn::Error::new(
+                lit.span(),
+                "false positive probability must be in (0.0, 1.0)",
+            ));
+        }
+
+        input.parse::<Token![,]>()?;
+
+        let content;
+        bracketed!(content in input);
+
+        let mut keys = Punctuated::new();
+
+        while !content.is_empty() {
+            if !content.peek(LitStr) {
+                return Err(content.error("expected a string literal"));
+            }
+
+            keys.push_value(content.parse()?);
+
+            if content.is_empty() {
+                break;
+            }
+
+            keys.push_punct(content.parse()?);
+        }
+
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(Self { fpp, keys })
+    }
+}
@@ -0,0 +1,31 @@
+use bbloom::StaticBloomFilter;
+use bbloom_macros::bloom;
+
+static FILTER: StaticBloomFilter = bloom!(
+    fpp = 0.001,
+    ["rust", "noodles", "bbloom", "sam", "bam", "cram", "vcf", "bcf", "bbloom"],
+);
+
+#[test]
+fn test_bloom_contains_every_literal() {
+    for key in &[
+        "rust", "noodles", "bbloom", "sam", "bam", "cram", "vcf", "bcf",
+    ] {
+        assert!(FILTER.contains(*key));
+    }
+}
+
+#[test]
+fn test_bloom_deduplicates_literals() {
+    let expected = bloom!(fpp = 0.001, ["a", "b"]);
+    let actual = bloom!(fpp = 0.001, ["a", "b", "a"]);
+
+    assert_eq!(actual.capacity(), expected.capacity());
+    assert!(actual.contains("a"));
+}
+
+#[test]
+fn test_bloom_with_no_literals() {
+    let filter = bloom!(fpp = 0.001, []);
+    assert!(!filter.contains("a"));
+}
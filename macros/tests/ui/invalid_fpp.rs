@@ -0,0 +1,6 @@
+use bbloom::StaticBloomFilter;
+use bbloom_macros::bloom;
+
+static FILTER: StaticBloomFilter = bloom!(fpp = 1.5, ["a"]);
+
+fn main() {}
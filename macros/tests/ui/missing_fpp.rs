@@ -0,0 +1,6 @@
+use bbloom::StaticBloomFilter;
+use bbloom_macros::bloom;
+
+static FILTER: StaticBloomFilter = bloom!(["a"]);
+
+fn main() {}
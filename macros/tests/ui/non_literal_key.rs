@@ -0,0 +1,8 @@
+use bbloom::StaticBloomFilter;
+use bbloom_macros::bloom;
+
+const KEY: &str = "a";
+
+static FILTER: StaticBloomFilter = bloom!(fpp = 0.001, [KEY]);
+
+fn main() {}
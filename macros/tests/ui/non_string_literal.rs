@@ -0,0 +1,6 @@
+use bbloom::StaticBloomFilter;
+use bbloom_macros::bloom;
+
+static FILTER: StaticBloomFilter = bloom!(fpp = 0.001, ["a", 8]);
+
+fn main() {}
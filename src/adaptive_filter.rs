@@ -0,0 +1,280 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+use crate::{
+    bloom_filter::optimal_number_of_elements, scalable_bloom_filter::TIGHTENING_RATIO, BloomFilter,
+    DefaultHashBuilder, Filter, ScalableBloomFilter,
+};
+
+enum Inner<S> {
+    Plain(BloomFilter<S>),
+    Scaled(ScalableBloomFilter<S>),
+}
+
+/// A Bloom filter that converts itself into a scalable Bloom filter when it reaches its capacity.
+///
+/// The filter starts as a single [`BloomFilter`]. When the number of elements reaches the capacity
+/// of the filter, the next insert converts it into a [`ScalableBloomFilter`] that uses the existing
+/// filter as its first layer (see [`ScalableBloomFilter::from_filter`]). The conversion happens at
+/// most once.
+///
+/// To bound the compounded false positive probability of all layers by the target `p`, the first
+/// filter targets `p * (1 - r)`, where `r` is the tightening ratio of the scalable filter. This
+/// costs a few more bits per element than a plain filter for `p`.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{AdaptiveFilter, SeededHashBuilder};
+///
+/// let mut filter = AdaptiveFilter::with_hashers(
+///     0.01,
+///     64,
+///     SeededHashBuilder::new(0),
+///     SeededHashBuilder::new(1),
+/// );
+///
+/// for i in 0..64 {
+///     filter.insert(&i);
+/// }
+///
+/// assert!(!filter.is_scaled());
+///
+/// filter.insert(&64);
+/// assert!(filter.is_scaled());
+/// assert!(filter.contains(&0));
+/// ```
+pub struct AdaptiveFilter<S = DefaultHashBuilder> {
+    inner: Inner<S>,
+    // the false positive probability of the first filter
+    initial_fpp: f64,
+    // the number of elements the first filter holds before it is scaled
+    capacity: usize,
+}
+
+impl AdaptiveFilter<DefaultHashBuilder> {
+    /// Creates a new adaptive filter that targets a false positive probability `p` ([0.0, 1.0])
+    /// with an expected number of inserted elements `n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    /// let _filter = AdaptiveFilter::new(0.0001, 64);
+    /// ```
+    pub fn new(p: f64, n: usize) -> Self {
+        Self::with_hashers(p, n, DefaultHashBuilder::new(), DefaultHashBuilder::new())
+    }
+}
+
+impl<S> AdaptiveFilter<S>
+where
+    S: BuildHasher + Clone + Default,
+{
+    /// Creates a new adaptive filter that targets a false positive probability `p` ([0.0, 1.0])
+    /// with an expected number of inserted elements `n`, using `builder_1` and `builder_2` to hash
+    /// the data in the first filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{AdaptiveFilter, SeededHashBuilder};
+    ///
+    /// let _filter = AdaptiveFilter::with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// ```
+    pub fn with_hashers(p: f64, n: usize, builder_1: S, builder_2: S) -> Self {
+        let initial_fpp = p * (1.0 - TIGHTENING_RATIO);
+        let filter = BloomFilter::from_fpp_with_hashers(initial_fpp, n, builder_1, builder_2);
+        let capacity = optimal_number_of_elements(initial_fpp, filter.capacity());
+
+        Self {
+            inner: Inner::Plain(filter),
+            initial_fpp,
+            capacity,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    ///
+    /// let mut filter = AdaptiveFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        match &self.inner {
+            Inner::Plain(filter) => filter.contains(key),
+            Inner::Scaled(filter) => filter.contains(key),
+        }
+    }
+
+    /// Adds a value to the filter, converting the filter into a scalable filter if it is at
+    /// capacity.
+    ///
+    /// Returns whether the value was not previously in the filter. After the conversion, this
+    /// follows [`ScalableBloomFilter::insert`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    ///
+    /// let mut filter = AdaptiveFilter::new(0.0001, 64);
+    /// assert!(filter.insert("a"));
+    /// assert!(!filter.insert("a"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        if let Inner::Plain(filter) = &self.inner {
+            if filter.len() >= self.capacity {
+                self.scale();
+            }
+        }
+
+        match &mut self.inner {
+            Inner::Plain(filter) => filter.insert(key),
+            Inner::Scaled(filter) => filter.insert(key),
+        }
+    }
+
+    /// Returns whether the filter was converted into a scalable filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    /// let filter = AdaptiveFilter::new(0.0001, 64);
+    /// assert!(!filter.is_scaled());
+    /// ```
+    pub fn is_scaled(&self) -> bool {
+        matches!(self.inner, Inner::Scaled(_))
+    }
+
+    /// Returns the number of elements in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    ///
+    /// let mut filter = AdaptiveFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            Inner::Plain(filter) => filter.len(),
+            Inner::Scaled(filter) => filter.len(),
+        }
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    /// let filter = AdaptiveFilter::new(0.0001, 64);
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a one-line, human-readable description of the underlying filter.
+    ///
+    /// See [`BloomFilter::summary`] and [`ScalableBloomFilter::summary`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::AdaptiveFilter;
+    /// let filter = AdaptiveFilter::new(0.0001, 64);
+    /// assert!(filter.summary().starts_with("BloomFilter {"));
+    /// ```
+    pub fn summary(&self) -> String {
+        match &self.inner {
+            Inner::Plain(filter) => filter.summary(),
+            Inner::Scaled(filter) => filter.summary(),
+        }
+    }
+
+    fn scale(&mut self) {
+        // A minimal filter stands in while the current one is moved into the scalable filter.
+        let placeholder = BloomFilter::with_hashers(1, 1, S::default(), S::default());
+
+        if let Inner::Plain(filter) = mem::replace(&mut self.inner, Inner::Plain(placeholder)) {
+            self.inner = Inner::Scaled(filter.into_scalable(self.initial_fpp));
+        }
+    }
+}
+
+impl<S> Filter for AdaptiveFilter<S>
+where
+    S: BuildHasher + Clone + Default,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[test]
+    fn test_insert_past_capacity() {
+        const P: f64 = 0.01;
+        const N: usize = 1000;
+
+        let mut filter = AdaptiveFilter::with_hashers(
+            P,
+            N,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        let mut upgrades = 0;
+
+        for i in 0..3 * N {
+            let was_scaled = filter.is_scaled();
+            filter.insert(&i);
+
+            if !was_scaled && filter.is_scaled() {
+                upgrades += 1;
+            }
+        }
+
+        assert_eq!(upgrades, 1);
+        assert!((0..3 * N).all(|i| filter.contains(&i)));
+
+        let fpp = match &filter.inner {
+            Inner::Plain(_) => unreachable!(),
+            Inner::Scaled(filter) => filter.estimated_fpp(),
+        };
+
+        assert!(fpp < P, "{} >= {}", fpp, P);
+    }
+}
@@ -0,0 +1,599 @@
+//! Bit array storage backends.
+//!
+//! A [`BloomFilter`](crate::BloomFilter) probes its bit array through the [`BitStore`] trait, so
+//! the same filter logic runs over different storage. The default store is a heap-allocated
+//! [`BitVec`]. [`WordBits`] is a heap-allocated array of 64-bit words, and [`ArrayBits`] is an
+//! inline array of 64-bit words with a size fixed at compile time.
+//!
+//! The storage can also be provided by the caller, e.g., to allocate it from an arena:
+//! [`WordBits::from_boxed_slice`] takes ownership of a boxed slice of words, and [`SliceBits`]
+//! borrows a mutable slice of words for the lifetime of the filter.
+//!
+//! [`StaticBits`] is a read-only array of 64-bit words in static memory, e.g., embedded in the
+//! binary. With the `mmap` feature, [`MappedBits`] is a read-only array in a memory-mapped file.
+
+#[cfg(feature = "mmap")]
+mod mapped_bits;
+
+#[cfg(feature = "mmap")]
+pub use self::mapped_bits::MappedBits;
+
+use bit_vec::BitVec;
+
+const WORD_BITS: usize = 64;
+
+/// A fixed-length array of bits.
+///
+/// Word-level access uses 64-bit words, where bit `j` of word `i` is bit `64 * i + j` of the
+/// array. Bits past the end of the array in the last word are always unset.
+pub trait BitStore {
+    /// Returns the number of bits in the array.
+    fn len_bits(&self) -> usize;
+
+    /// Returns whether bit `i` is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    fn get(&self, i: usize) -> bool;
+
+    /// Sets bit `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    fn set(&mut self, i: usize);
+
+    /// Returns the number of 64-bit words that cover the array.
+    fn word_count(&self) -> usize {
+        self.len_bits().div_ceil(WORD_BITS)
+    }
+
+    /// Returns the 64-bit word `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    fn word(&self, i: usize) -> u64;
+
+    /// Replaces the 64-bit word `i`.
+    ///
+    /// Bits past the end of the array are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    fn set_word(&mut self, i: usize, word: u64);
+
+    /// Returns the number of set bits.
+    fn count_ones(&self) -> usize {
+        (0..self.word_count())
+            .map(|i| self.word(i).count_ones() as usize)
+            .sum()
+    }
+}
+
+impl BitStore for BitVec {
+    fn len_bits(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self[i]
+    }
+
+    fn set(&mut self, i: usize) {
+        BitVec::set(self, i, true);
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        assert!(i < self.word_count(), "word index out of bounds");
+
+        let blocks = self.storage();
+        let lo = blocks[2 * i];
+        let hi = blocks.get(2 * i + 1).copied().unwrap_or(0);
+
+        (u64::from(hi) << 32) | u64::from(lo)
+    }
+
+    fn set_word(&mut self, i: usize, word: u64) {
+        assert!(i < self.word_count(), "word index out of bounds");
+
+        // Bits past the end of the array must stay unset in the storage blocks.
+        let end = (i + 1) * WORD_BITS;
+        let word = if end > self.len() {
+            word & (u64::MAX >> (end - self.len()))
+        } else {
+            word
+        };
+
+        // SAFETY: The blocks are replaced with the halves of `word`, with no bits set past the end
+        // of the array, and the length of the storage is unchanged.
+        let blocks = unsafe { self.storage_mut() };
+
+        blocks[2 * i] = word as u32;
+
+        if let Some(block) = blocks.get_mut(2 * i + 1) {
+            *block = (word >> 32) as u32;
+        }
+    }
+
+    fn count_ones(&self) -> usize {
+        self.storage().iter().map(|b| b.count_ones() as usize).sum()
+    }
+}
+
+/// A heap-allocated array of 64-bit words.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{bit_store::WordBits, BloomFilter, SeededHashBuilder};
+///
+/// let bits = WordBits::new(1227);
+/// let mut filter =
+///     BloomFilter::from_store(bits, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+///
+/// filter.insert("a");
+/// assert!(filter.contains("a"));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WordBits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl WordBits {
+    /// Creates an array of `len` unset bits.
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+        }
+    }
+
+    /// Creates an array of `len` unset bits stored in `words`.
+    ///
+    /// The words that cover the array are cleared, and any words past them are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not fit in `words`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::bit_store::{BitStore, WordBits};
+    ///
+    /// let words = vec![u64::MAX; 20].into_boxed_slice();
+    /// let bits = WordBits::from_boxed_slice(words, 1227);
+    ///
+    /// assert_eq!(bits.len_bits(), 1227);
+    /// assert_eq!(bits.count_ones(), 0);
+    /// ```
+    pub fn from_boxed_slice(words: Box<[u64]>, len: usize) -> Self {
+        let word_count = len.div_ceil(WORD_BITS);
+        assert!(word_count <= words.len(), "length exceeds slice capacity");
+
+        let mut words = words.into_vec();
+        words.truncate(word_count);
+        words.fill(0);
+
+        Self { words, len }
+    }
+}
+
+impl BitStore for WordBits {
+    fn len_bits(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit index out of bounds");
+        get_word_bit(&self.words, i)
+    }
+
+    fn set(&mut self, i: usize) {
+        assert!(i < self.len, "bit index out of bounds");
+        set_word_bit(&mut self.words, i);
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        self.words[i]
+    }
+
+    fn set_word(&mut self, i: usize, word: u64) {
+        self.words[i] = word & word_mask(self.len, i);
+    }
+}
+
+/// An inline array of `N` 64-bit words.
+///
+/// The array does not allocate, so it can be used for small filters that live on the stack or
+/// inside another structure.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{bit_store::ArrayBits, BloomFilter, SeededHashBuilder};
+///
+/// let bits: ArrayBits<20> = ArrayBits::new(1227);
+/// let mut filter =
+///     BloomFilter::from_store(bits, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+///
+/// filter.insert("a");
+/// assert!(filter.contains("a"));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArrayBits<const N: usize> {
+    words: [u64; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayBits<N> {
+    /// Creates an array of `len` unset bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not fit in `N` words.
+    pub fn new(len: usize) -> Self {
+        assert!(len <= N * WORD_BITS, "length exceeds array capacity");
+        Self { words: [0; N], len }
+    }
+}
+
+impl<const N: usize> BitStore for ArrayBits<N> {
+    fn len_bits(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit index out of bounds");
+        get_word_bit(&self.words, i)
+    }
+
+    fn set(&mut self, i: usize) {
+        assert!(i < self.len, "bit index out of bounds");
+        set_word_bit(&mut self.words, i);
+    }
+
+    fn word_count(&self) -> usize {
+        self.len.div_ceil(WORD_BITS)
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        assert!(i < self.word_count(), "word index out of bounds");
+        self.words[i]
+    }
+
+    fn set_word(&mut self, i: usize, word: u64) {
+        assert!(i < self.word_count(), "word index out of bounds");
+        self.words[i] = word & word_mask(self.len, i);
+    }
+}
+
+/// An array of 64-bit words borrowed from the caller.
+///
+/// The filter borrows the words mutably for as long as it exists, and the words are never freed
+/// by the filter. When the filter is dropped, the words hold its bit array (see
+/// [`BitStore::word`]), so the caller can, e.g., read or persist it.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{bit_store::SliceBits, BloomFilter, SeededHashBuilder};
+///
+/// let mut words = [0; 20];
+///
+/// {
+///     let bits = SliceBits::new(&mut words, 1227);
+///     let mut filter =
+///         BloomFilter::from_store(bits, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+///
+///     filter.insert("a");
+///     assert!(filter.contains("a"));
+/// }
+///
+/// assert!(words.iter().any(|&word| word != 0));
+/// ```
+#[derive(Debug, Eq, PartialEq)]
+pub struct SliceBits<'a> {
+    words: &'a mut [u64],
+    len: usize,
+}
+
+impl<'a> SliceBits<'a> {
+    /// Creates an array of `len` unset bits over `words`.
+    ///
+    /// The words that cover the array are cleared. Any words past them are not used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not fit in `words`.
+    pub fn new(words: &'a mut [u64], len: usize) -> Self {
+        let word_count = len.div_ceil(WORD_BITS);
+        assert!(word_count <= words.len(), "length exceeds slice capacity");
+
+        let words = &mut words[..word_count];
+        words.fill(0);
+
+        Self { words, len }
+    }
+}
+
+impl BitStore for SliceBits<'_> {
+    fn len_bits(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit index out of bounds");
+        get_word_bit(self.words, i)
+    }
+
+    fn set(&mut self, i: usize) {
+        assert!(i < self.len, "bit index out of bounds");
+        set_word_bit(self.words, i);
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        self.words[i]
+    }
+
+    fn set_word(&mut self, i: usize, word: u64) {
+        self.words[i] = word & word_mask(self.len, i);
+    }
+}
+
+/// A read-only array of 64-bit words in static memory.
+///
+/// This is the bit array of a [`StaticBloomFilter`](crate::StaticBloomFilter), which can be
+/// created in a constant expression.
+///
+/// # Panics
+///
+/// The array cannot be modified. [`BitStore::set`] and [`BitStore::set_word`] panic.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::bit_store::{BitStore, StaticBits};
+///
+/// static WORDS: [u64; 2] = [0b10, 1];
+/// static BITS: StaticBits = StaticBits::new(&WORDS, 100);
+///
+/// assert!(BITS.get(1));
+/// assert!(BITS.get(64));
+/// assert_eq!(BITS.count_ones(), 2);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StaticBits {
+    words: &'static [u64],
+    len: usize,
+}
+
+impl StaticBits {
+    /// Creates an array of `len` bits over `words`.
+    ///
+    /// Bits past the end of the array in the last word must be unset. Any words past it are not
+    /// used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not fit in `words`.
+    pub const fn new(words: &'static [u64], len: usize) -> Self {
+        assert!(
+            len.div_ceil(WORD_BITS) <= words.len(),
+            "length exceeds slice capacity"
+        );
+
+        Self { words, len }
+    }
+
+    // Returns the number of bits in the array, like `BitStore::len_bits`, in a constant expression.
+    pub(crate) const fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl BitStore for StaticBits {
+    fn len_bits(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit index out of bounds");
+        get_word_bit(self.words, i)
+    }
+
+    fn set(&mut self, _: usize) {
+        panic!("static bits are read-only");
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        assert!(i < self.word_count(), "word index out of bounds");
+        self.words[i]
+    }
+
+    fn set_word(&mut self, _: usize, _: u64) {
+        panic!("static bits are read-only");
+    }
+}
+
+fn get_word_bit(words: &[u64], i: usize) -> bool {
+    (words[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1
+}
+
+fn set_word_bit(words: &mut [u64], i: usize) {
+    words[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+}
+
+// Returns the mask of bits of word `i` that are within an array of `len` bits.
+fn word_mask(len: usize, i: usize) -> u64 {
+    let end = len.saturating_sub(i * WORD_BITS);
+
+    if end >= WORD_BITS {
+        u64::MAX
+    } else {
+        (1 << end) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BloomFilter, SeededHashBuilder};
+
+    const M: usize = 1227;
+    const K: usize = 7;
+
+    fn build<B>(bits: B) -> BloomFilter<SeededHashBuilder, B>
+    where
+        B: BitStore,
+    {
+        let mut filter = BloomFilter::from_store(
+            bits,
+            K,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        for i in 0..128 {
+            filter.insert(&i);
+        }
+
+        filter
+    }
+
+    fn assert_store_matches_default<B>(bits: B)
+    where
+        B: BitStore,
+    {
+        let expected = build(BitVec::from_elem(M, false));
+        let actual = build(bits);
+
+        assert_eq!(actual.capacity(), M);
+        assert_eq!(actual.len(), expected.len());
+        assert_eq!(actual.to_words(), expected.to_words());
+        assert!(actual.iter_ones().eq(expected.iter_ones()));
+
+        for i in 0..128 {
+            assert!(actual.contains(&i));
+        }
+
+        for i in 128..256 {
+            assert_eq!(actual.contains(&i), expected.contains(&i));
+        }
+    }
+
+    #[test]
+    fn test_word_bits() {
+        assert_store_matches_default(WordBits::new(M));
+    }
+
+    #[test]
+    fn test_array_bits() {
+        assert_store_matches_default(ArrayBits::<20>::new(M));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_array_bits_new_with_too_many_bits() {
+        ArrayBits::<1>::new(65);
+    }
+
+    #[test]
+    fn test_word_bits_from_boxed_slice() {
+        let words = vec![u64::MAX; 24].into_boxed_slice();
+        assert_store_matches_default(WordBits::from_boxed_slice(words, M));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_word_bits_from_boxed_slice_with_too_many_bits() {
+        WordBits::from_boxed_slice(Box::new([0]), 65);
+    }
+
+    #[test]
+    fn test_slice_bits() {
+        let mut words = [u64::MAX; 24];
+        assert_store_matches_default(SliceBits::new(&mut words, M));
+
+        // The words hold the bit array after the filter is dropped, and unused words are
+        // untouched.
+        let expected = build(BitVec::from_elem(M, false)).to_words();
+        assert_eq!(&words[..expected.len()], &expected[..]);
+        assert!(words[expected.len()..].iter().all(|&word| word == u64::MAX));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_bits_new_with_too_many_bits() {
+        SliceBits::new(&mut [0], 65);
+    }
+
+    fn assert_word_access<B>(mut bits: B)
+    where
+        B: BitStore,
+    {
+        assert_eq!(bits.len_bits(), 100);
+        assert_eq!(bits.word_count(), 2);
+
+        bits.set(1);
+        bits.set(64);
+        bits.set(99);
+        assert!(bits.get(1));
+        assert!(!bits.get(2));
+        assert_eq!(bits.word(0), 0b10);
+        assert_eq!(bits.word(1), 1 << 35 | 1);
+        assert_eq!(bits.count_ones(), 3);
+
+        bits.set_word(1, u64::MAX);
+        assert_eq!(bits.word(1), (1 << 36) - 1);
+        assert_eq!(bits.count_ones(), 37);
+    }
+
+    #[test]
+    fn test_word_access() {
+        assert_word_access(BitVec::from_elem(100, false));
+        assert_word_access(WordBits::new(100));
+        assert_word_access(ArrayBits::<2>::new(100));
+        assert_word_access(WordBits::from_boxed_slice(Box::new([0; 2]), 100));
+        assert_word_access(SliceBits::new(&mut [0; 2], 100));
+    }
+
+    #[test]
+    fn test_static_bits() {
+        let filter = build(BitVec::from_elem(M, false));
+        let words = Box::leak(filter.to_words().into_boxed_slice());
+        let bits = StaticBits::new(words, M);
+
+        assert_eq!(bits.len_bits(), M);
+        assert_eq!(bits.word_count(), 20);
+        assert!((0..bits.word_count()).all(|i| bits.word(i) == words[i]));
+        assert!((0..M).filter(|&i| bits.get(i)).eq(filter.iter_ones()));
+        assert_eq!(bits.count_ones(), filter.bit_count());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_static_bits_set() {
+        static WORDS: [u64; 2] = [0; 2];
+        let mut bits = StaticBits::new(&WORDS, 100);
+        bits.set(1);
+    }
+
+    #[test]
+    fn test_bit_vec_set_word_with_short_last_word() {
+        // The last word is covered by a single 32-bit storage block.
+        let mut bits = BitVec::from_elem(80, false);
+
+        bits.set_word(0, u64::MAX);
+        bits.set_word(1, u64::MAX);
+
+        assert_eq!(bits.word(0), u64::MAX);
+        assert_eq!(bits.word(1), (1 << 16) - 1);
+        assert_eq!(bits.count_ones(), 80);
+        assert!(bits.all());
+    }
+}
@@ -0,0 +1,137 @@
+use memmap2::Mmap;
+
+use super::{BitStore, WORD_BITS};
+
+/// A read-only bit array in a memory-mapped file.
+///
+/// The bits are the payload of a filter in the binary format (see [`crate::io`]), i.e., packed
+/// most significant bit first. The pages of the file are loaded by the operating system as they
+/// are probed and can be evicted under memory pressure, so a mapped filter does not keep its bit
+/// array resident.
+///
+/// This is created with [`BloomFilter::map_path`](crate::BloomFilter::map_path) and is only
+/// available with the `mmap` feature.
+///
+/// # Panics
+///
+/// The array cannot be modified. [`BitStore::set`] and [`BitStore::set_word`] panic.
+#[derive(Debug)]
+pub struct MappedBits {
+    map: Mmap,
+    // the byte offset of the payload in the map
+    offset: usize,
+    len: usize,
+}
+
+impl MappedBits {
+    pub(crate) fn new(map: Mmap, offset: usize, len: usize) -> Self {
+        assert!(
+            offset + len.div_ceil(8) <= map.len(),
+            "payload exceeds the mapped file"
+        );
+
+        Self { map, offset, len }
+    }
+
+    // Returns the bit array packed most significant bit first.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.map[self.offset..self.offset + self.len.div_ceil(8)]
+    }
+}
+
+impl BitStore for MappedBits {
+    fn len_bits(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit index out of bounds");
+        let byte = self.as_bytes()[i / 8];
+        (byte >> (7 - i % 8)) & 1 == 1
+    }
+
+    fn set(&mut self, _: usize) {
+        panic!("mapped bits are read-only");
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        assert!(i < self.word_count(), "word index out of bounds");
+
+        let start = i * WORD_BITS / 8;
+        let end = (start + WORD_BITS / 8).min(self.len.div_ceil(8));
+
+        self.as_bytes()[start..end]
+            .iter()
+            .enumerate()
+            .fold(0, |word, (j, b)| {
+                word | (u64::from(b.reverse_bits()) << (8 * j))
+            })
+    }
+
+    fn set_word(&mut self, _: usize, _: u64) {
+        panic!("mapped bits are read-only");
+    }
+
+    fn count_ones(&self) -> usize {
+        self.as_bytes()
+            .iter()
+            .map(|b| b.count_ones() as usize)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io, process,
+    };
+
+    use bit_vec::BitVec;
+
+    use super::*;
+
+    fn map_bytes(name: &str, data: &[u8]) -> io::Result<Mmap> {
+        let src = env::temp_dir().join(format!("bbloom-mapped-bits-{}-{}", name, process::id()));
+        fs::write(&src, data)?;
+
+        let file = File::open(&src)?;
+        // SAFETY: The file is private to this test and not modified while it is mapped.
+        let map = unsafe { Mmap::map(&file) }?;
+
+        fs::remove_file(&src)?;
+
+        Ok(map)
+    }
+
+    #[test]
+    fn test_bits() -> io::Result<()> {
+        let mut expected = BitVec::from_elem(75, false);
+
+        for &i in &[0, 7, 8, 63, 64, 74] {
+            expected.set(i, true);
+        }
+
+        let mut data = vec![0xff; 3];
+        data.extend(expected.to_bytes());
+
+        let bits = MappedBits::new(map_bytes("bits", &data)?, 3, 75);
+
+        assert_eq!(bits.len_bits(), 75);
+        assert_eq!(bits.word_count(), 2);
+        assert!((0..75).all(|i| bits.get(i) == expected[i]));
+        assert_eq!(bits.word(0), BitStore::word(&expected, 0));
+        assert_eq!(bits.word(1), BitStore::word(&expected, 1));
+        assert_eq!(bits.count_ones(), 6);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "read-only")]
+    fn test_set() {
+        let mut bits = MappedBits::new(map_bytes("set", &[0; 8]).unwrap(), 0, 64);
+        bits.set(0);
+    }
+}
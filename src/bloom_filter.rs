@@ -1,9 +1,50 @@
-use std::f64;
-use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "borsh")]
+pub(crate) mod borsh;
+mod build_error;
+mod bulk;
+mod fold;
+mod fold_report;
+mod incompatible_filters;
+#[cfg(feature = "mmap")]
+pub(crate) mod mapped;
+#[cfg(feature = "roaring")]
+mod roaring;
+#[cfg(feature = "serde")]
+mod serde;
+mod union_error;
+
+#[cfg(feature = "roaring")]
+pub use self::roaring::OutOfRangeError;
+pub use self::{
+    build_error::BuildError, fold_report::FoldReport, incompatible_filters::IncompatibleFilters,
+    union_error::UnionError,
+};
+
+use std::{
+    collections::HashSet,
+    f64, fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    io::{self, BufRead, Read, Write},
+    marker::PhantomData,
+    mem,
+    path::Path,
+};
 
 use bit_vec::BitVec;
 
-use crate::{double_hasher::DoubleHasher, DefaultHashBuilder};
+#[cfg(feature = "instrumentation")]
+use crate::probe_stats::ProbeStats;
+use crate::{
+    bit_store::BitStore,
+    double_hasher::DoubleHasher,
+    io::{
+        base64, bloom_filter_reader, hex, path, read_bloom_filter, read_bloom_filter_payload,
+        write_bloom_filter, DecodeError, Header, Parts, ReadError, SerializedReader, CHECKSUM_LEN,
+        HEADER_LEN,
+    },
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    DefaultHashBuilder, Filter, HashQualityReport, ScalableBloomFilter, VerifyReport,
+};
 
 /// A probabilistic data structure to test whether an element may be in a set or definitely not in
 /// a set.
@@ -27,8 +68,8 @@ use crate::{double_hasher::DoubleHasher, DefaultHashBuilder};
 /// assert!(filter.contains("b"));
 /// assert!(!filter.contains("c"));
 /// ```
-pub struct BloomFilter<S = DefaultHashBuilder> {
-    bits: BitVec,
+pub struct BloomFilter<S = DefaultHashBuilder, B = BitVec, P = DoubleHashing> {
+    bits: B,
 
     // bit array length
     m: usize,
@@ -36,9 +77,16 @@ pub struct BloomFilter<S = DefaultHashBuilder> {
     n: usize,
     // number of hash functions
     k: usize,
+    // user-defined identifier of the key type and hash builders
+    label: Option<u64>,
 
     builder_1: S,
     builder_2: S,
+
+    probe_strategy: PhantomData<fn() -> P>,
+
+    #[cfg(feature = "instrumentation")]
+    probe_stats: ProbeStats,
 }
 
 impl BloomFilter<DefaultHashBuilder> {
@@ -46,10 +94,18 @@ impl BloomFilter<DefaultHashBuilder> {
     /// an expected number of inserted elements `n`.
     ///
     /// The optimal size of the bit array `m` and number of hash functions `k` are automatically
-    /// calculated. See "[Optimal number of hash functions][1]".
+    /// calculated. See "[Optimal number of hash functions][1]". If `n` is 0, a minimal filter
+    /// (`m` = 1, `k` = 1) is created.
+    ///
+    /// `p` should be in (0.0, 1.0). A `p` of 1.0 or more creates a minimal filter that reports
+    /// every element as present. See [`Self::try_from_fpp`] to validate `p` instead.
     ///
     /// [1]: https://en.wikipedia.org/wiki/Bloom_filter#Optimal_number_of_hash_functions
     ///
+    /// # Panics
+    ///
+    /// Panics if the size of the bit array overflows, e.g., when `p` is 0.0.
+    ///
     /// # Examples
     ///
     /// ```
@@ -60,6 +116,27 @@ impl BloomFilter<DefaultHashBuilder> {
         Self::from_fpp_with_hashers(p, n, DefaultHashBuilder::new(), DefaultHashBuilder::new())
     }
 
+    /// Creates a new bloom filter that targets a false positive probability `p` ((0.0, 1.0)) with
+    /// an expected number of inserted elements `n`, failing if `p` is out of range, `n` is 0, or
+    /// the size of the bit array overflows.
+    ///
+    /// See [`Self::try_from_fpp_with_hashers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, BuildError};
+    ///
+    /// assert!(BloomFilter::try_from_fpp(0.0001, 64).is_ok());
+    /// assert_eq!(
+    ///     BloomFilter::try_from_fpp(0.0, 64).err(),
+    ///     Some(BuildError::InvalidFpp(0.0))
+    /// );
+    /// ```
+    pub fn try_from_fpp(p: f64, n: usize) -> Result<Self, BuildError> {
+        Self::try_from_fpp_with_hashers(p, n, DefaultHashBuilder::new(), DefaultHashBuilder::new())
+    }
+
     /// Creates a new bloom filter with a predetermined bit array size `m` and number of hash
     /// functions `k`.
     ///
@@ -72,6 +149,34 @@ impl BloomFilter<DefaultHashBuilder> {
     pub fn new(m: usize, k: usize) -> Self {
         Self::with_hashers(m, k, DefaultHashBuilder::new(), DefaultHashBuilder::new())
     }
+
+    /// Creates a new bloom filter that targets a false positive probability `p` ([0.0, 1.0]) for
+    /// the keys of `iter` and inserts them.
+    ///
+    /// See [`Self::from_fpp_iter_with_hashers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let filter = BloomFilter::from_fpp_iter(0.0001, vec!["a", "b"]);
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// ```
+    pub fn from_fpp_iter<I>(p: f64, iter: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Hash,
+    {
+        Self::from_fpp_iter_with_hashers(
+            p,
+            iter,
+            DefaultHashBuilder::new(),
+            DefaultHashBuilder::new(),
+        )
+    }
 }
 
 impl<S> BloomFilter<S>
@@ -83,10 +188,18 @@ where
     /// data.
     ///
     /// The optimal size of the bit array `m` and number of hash functions `k` are automatically
-    /// calculated. See "[Optimal number of hash functions][1]".
+    /// calculated. See "[Optimal number of hash functions][1]". If `n` is 0, a minimal filter
+    /// (`m` = 1, `k` = 1) is created.
+    ///
+    /// `p` should be in (0.0, 1.0). A `p` of 1.0 or more creates a minimal filter that reports
+    /// every element as present. See [`Self::try_from_fpp_with_hashers`] to validate `p` instead.
     ///
     /// [1]: https://en.wikipedia.org/wiki/Bloom_filter#Optimal_number_of_hash_functions
     ///
+    /// # Panics
+    ///
+    /// Panics if the size of the bit array overflows, e.g., when `p` is 0.0.
+    ///
     /// # Examples
     ///
     /// ```
@@ -105,6 +218,84 @@ where
         Self::with_hashers(m, k, builder_1, builder_2)
     }
 
+    /// Creates a new bloom filter that targets a false positive probability `p` ((0.0, 1.0)) with
+    /// an expected number of inserted elements `n`, using `builder_1` and `builder_2` to hash the
+    /// data, failing if `p` is out of range, `n` is 0, or the size of the bit array overflows.
+    ///
+    /// This is like [`Self::from_fpp_with_hashers`], but `p` must be strictly between 0.0 and 1.0,
+    /// `n` must be at least 1, and the size of the bit array must be at most `isize::MAX`. Unlike
+    /// [`Self::from_fpp_with_hashers`], an `n` of 0 is an error rather than a minimal filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, BuildError, SeededHashBuilder};
+    ///
+    /// let result = BloomFilter::try_from_fpp_with_hashers(
+    ///     1.0,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    ///
+    /// assert!(matches!(result, Err(BuildError::InvalidFpp(_))));
+    /// ```
+    pub fn try_from_fpp_with_hashers(
+        p: f64,
+        n: usize,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, BuildError> {
+        if !(p > 0.0 && p < 1.0) {
+            return Err(BuildError::InvalidFpp(p));
+        }
+
+        if n == 0 {
+            return Err(BuildError::ZeroElements);
+        }
+
+        let m = checked_optimal_required_bits(p, n).ok_or(BuildError::CapacityOverflow)?;
+        let k = optimal_number_of_hash_functions(m, n);
+
+        Ok(Self::with_hashers(m, k, builder_1, builder_2))
+    }
+
+    /// Creates a new bloom filter that targets a false positive probability `p` ([0.0, 1.0]) for
+    /// the keys of `iter` and inserts them, using `builder_1` and `builder_2` to hash the data.
+    ///
+    /// The expected number of inserted elements `n` is the number of keys (see
+    /// [`Self::from_fpp_with_hashers`]). Counting them takes a pass over the keys before inserting
+    /// them, so they are first collected into a `Vec`. This holds every key in memory at once.
+    /// When the number of keys is known or can be bounded, create the filter with
+    /// [`Self::from_fpp_with_hashers`] and [`Extend::extend`] it instead.
+    ///
+    /// Duplicate keys are counted in `n`, which makes the filter larger than needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter = BloomFilter::from_fpp_iter_with_hashers(
+    ///     0.0001,
+    ///     0..64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    ///
+    /// assert!((0..64).all(|i| filter.contains(&i)));
+    /// ```
+    pub fn from_fpp_iter_with_hashers<I>(p: f64, iter: I, builder_1: S, builder_2: S) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Hash,
+    {
+        let keys: Vec<_> = iter.into_iter().collect();
+        let mut filter = Self::from_fpp_with_hashers(p, keys.len(), builder_1, builder_2);
+        filter.extend(keys);
+        filter
+    }
+
     /// Creates a new bloom filter with a predetermined bit array size `m` and number of hash
     /// functions `k`, using `builder_1` and `builder_2` to hash the data.
     ///
@@ -116,178 +307,3192 @@ where
     /// let _filter = BloomFilter::with_hashers(1227, 14, RandomState::new(), RandomState::new());
     /// ```
     pub fn with_hashers(m: usize, k: usize, builder_1: S, builder_2: S) -> Self {
-        Self {
-            bits: BitVec::from_elem(m, false),
-            m,
-            n: 0,
-            k,
-            builder_1,
-            builder_2,
-        }
+        Self::from_store(BitVec::from_elem(m, false), k, builder_1, builder_2)
     }
 
-    /// Returns the size of the bit array `m`.
+    /// Reads a filter in the binary format (see [`crate::io`]), using `builder_1` and `builder_2`
+    /// to hash the data.
+    ///
+    /// The hash builders must produce the same hashes as the ones used to build the filter.
     ///
     /// # Examples
     ///
     /// ```
-    /// use bbloom::BloomFilter;
-    /// let filter = BloomFilter::new(1227, 14);
-    /// assert_eq!(filter.capacity(), 1227);
+    /// # use std::io;
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter =
+    ///     BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    /// filter.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter = BloomFilter::read_from_with_hashers(
+    ///     &mut &buf[..],
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// )?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn capacity(&self) -> usize {
-        self.m
+    pub fn read_from_with_hashers<R>(
+        reader: &mut R,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        Self::read_from_with_probe_strategy(reader, builder_1, builder_2)
     }
 
-    /// Tests whether an element may be in the filter or definitely not in the filter.
+    /// Decodes a filter in the binary format (see [`crate::io`]), using `builder_1` and
+    /// `builder_2` to hash the data.
     ///
-    /// Remember that false positives can occur, meaning that if this returns `true`, there is only
-    /// a possibility that the element is in the filter. If this returns `false`, the element is
-    /// definitely not in the filter.
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter =
+    ///     BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    /// filter.insert("a");
+    ///
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter = BloomFilter::from_bytes_with_hashers(
+    ///     &buf,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// )?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_bytes_with_hashers(
+        buf: &[u8],
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError> {
+        Self::from_bytes_with_probe_strategy(buf, builder_1, builder_2)
+    }
+
+    /// Creates a filter from a raw bit array (see [`Self::to_raw_bytes`]) with a bit array size
+    /// `m` and number of hash functions `k`, using `builder_1` and `builder_2` to hash the data.
+    ///
+    /// The raw bit array has no framing, so the parameters must be known. It does not include the
+    /// number of elements, so [`Self::len`] is estimated from the number of set bits (see
+    /// [`Self::estimate_cardinality`]). Any trailing bits in the last byte are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReadError::InvalidParameters`] if `m` or `k` is 0 or `bytes` is not `ceil(m / 8)`
+    /// bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter =
+    ///     BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    /// filter.insert("a");
+    ///
+    /// let bytes = filter.to_raw_bytes();
+    ///
+    /// let filter = BloomFilter::from_raw_bytes_with_hashers(
+    ///     &bytes,
+    ///     1227,
+    ///     7,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// )?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert_eq!(filter.len(), 1);
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_raw_bytes_with_hashers(
+        bytes: &[u8],
+        m: usize,
+        k: usize,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError> {
+        Self::from_raw_bytes_with_probe_strategy(bytes, m, k, builder_1, builder_2)
+    }
+
+    /// Converts the filter into a scalable Bloom filter that targets a false positive probability
+    /// `p` ([0.0, 1.0]).
+    ///
+    /// The filter becomes the first layer, and its elements are kept. See
+    /// [`ScalableBloomFilter::from_filter`] for how the capacity of the first layer is determined.
+    /// Subsequent growth follows the scalable schedule, starting from `p`.
     ///
     /// # Examples
     ///
     /// ```
     /// use bbloom::BloomFilter;
     ///
-    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 2);
     /// filter.insert("a");
     /// filter.insert("b");
     ///
+    /// let mut filter = filter.into_scalable(0.0001);
+    /// filter.insert("c");
+    ///
     /// assert!(filter.contains("a"));
     /// assert!(filter.contains("b"));
-    /// assert!(!filter.contains("c"));
+    /// assert!(filter.contains("c"));
     /// ```
-    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
-        let hasher = self.build_hasher(key);
-
-        for hash in hasher.take(self.k) {
-            let i = (hash as usize) % self.m;
-
-            if !self.bits[i] {
-                return false;
-            }
-        }
-
-        true
+    pub fn into_scalable(self, p: f64) -> ScalableBloomFilter<S>
+    where
+        S: Clone + Default,
+    {
+        ScalableBloomFilter::from_filter(self, p)
     }
+}
 
-    /// Adds a value to the bloom filter.
-    ///
-    /// Returns whether the value is already (maybe) in the filter or not. Duplicate values do not
-    /// affect the load factor.
+impl<S, P> BloomFilter<S, BitVec, P>
+where
+    S: BuildHasher,
+    P: ProbeStrategy,
+{
+    /// Writes the filter in the binary format (see [`crate::io`]).
     ///
     /// # Examples
     ///
     /// ```
+    /// # use std::io;
     /// use bbloom::BloomFilter;
     ///
-    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
-    /// assert!(filter.insert("a"));
-    /// assert!(filter.insert("b"));
-    /// assert!(!filter.insert("b"));
+    /// let filter = BloomFilter::new(1227, 7);
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// # Ok::<(), io::Error>(())
     /// ```
-    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
-        let mut present = true;
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write_bloom_filter(
+            writer,
+            self.m,
+            self.k,
+            self.n,
+            &self.bits_to_bytes(),
+            self.label,
+            P::ID,
+        )
+    }
 
-        let hasher = self.build_hasher(key);
+    /// Writes the filter in the binary format (see [`crate::io`]) to a file at `path`.
+    ///
+    /// The filter is written to a temporary file in the same directory, which is synced and then
+    /// renamed over `path`. An existing file at `path` is replaced atomically, i.e., readers see
+    /// either the previous file or the complete new one, never a partially written file. If
+    /// writing fails, an existing file is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use bbloom::BloomFilter;
+    ///
+    /// let filter = BloomFilter::new(1227, 7);
+    /// filter.write_to_path("filter.bin")?;
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn write_to_path<Q>(&self, path: Q) -> io::Result<()>
+    where
+        Q: AsRef<Path>,
+    {
+        path::write_atomically(path, |writer| self.write_to(writer))
+    }
 
-        for hash in hasher.take(self.k) {
-            let i = (hash as usize) % self.m;
+    /// Returns the number of bytes the filter is encoded as in the binary format (see
+    /// [`crate::io`]).
+    ///
+    /// This is the length of the buffer returned by [`Self::to_bytes`] and the number of bytes
+    /// written by [`Self::write_to`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(1227, 7);
+    /// assert_eq!(filter.serialized_len(), filter.to_bytes().len());
+    /// ```
+    pub fn serialized_len(&self) -> usize {
+        let probe_strategy_len = if P::ID == DoubleHashing::ID { 0 } else { 1 };
+        let label_len = if self.label.is_some() { 8 } else { 0 };
+        HEADER_LEN + probe_strategy_len + label_len + self.m.div_ceil(8) + CHECKSUM_LEN
+    }
 
-            if !self.bits[i] {
-                present = false;
-                self.bits.set(i, true);
-            }
-        }
+    /// Encodes the filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(1227, 7);
+    /// let buf = filter.to_bytes();
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.serialized_len());
+        self.write_to(&mut buf)
+            .expect("writing to a Vec should not fail");
+        buf
+    }
 
-        if !present {
-            self.n += 1;
-        }
+    /// Encodes the filter in the binary format (see [`crate::io`]) as base64 using the standard
+    /// alphabet with padding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(8, 1);
+    /// assert!(filter.to_base64().starts_with("QkJMTQ"));
+    /// ```
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.to_bytes())
+    }
 
-        !present
+    /// Encodes the filter in the binary format (see [`crate::io`]) as base64 using the URL and
+    /// filename safe alphabet without padding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(8, 1);
+    /// let s = filter.to_base64_url();
+    /// assert!(!s.contains(&['+', '/', '='][..]));
+    /// ```
+    pub fn to_base64_url(&self) -> String {
+        base64::encode_url_safe(&self.to_bytes())
     }
 
-    /// Returns the number of elements `n` in the filter.
+    /// Encodes the filter in the binary format (see [`crate::io`]) as lowercase hexadecimal.
     ///
     /// # Examples
     ///
     /// ```
     /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(8, 1);
+    /// assert!(filter.to_hex().starts_with("42424c4d"));
+    /// ```
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.to_bytes())
+    }
+
+    /// Removes all elements from the filter.
     ///
-    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
-    /// assert_eq!(filter.len(), 0);
+    /// The bit array is zeroed in place, so this does not allocate. The size of the bit array
+    /// `m`, number of hash functions `k`, hash builders, label, and probe strategy are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
     ///
+    /// let mut filter = BloomFilter::new(1227, 7);
     /// filter.insert("a");
-    /// assert_eq!(filter.len(), 1);
     ///
-    /// filter.insert("b");
-    /// assert_eq!(filter.len(), 2);
+    /// filter.clear();
+    ///
+    /// assert!(filter.is_empty());
+    /// assert!(!filter.contains("a"));
     /// ```
-    pub fn len(&self) -> usize {
-        self.n
+    pub fn clear(&mut self) {
+        self.bits.clear();
+        self.n = 0;
     }
 
-    /// Returns `true` if the bloom filter contains no elements.
+    /// Returns the bit array as raw bytes.
+    ///
+    /// Bit `i` is stored in byte `i / 8` at bit `7 - i % 8`, i.e., most significant bit first,
+    /// and any trailing bits in the last byte are 0. This is `ceil(m / 8)` bytes with no framing:
+    /// unlike the binary format (see [`crate::io`]), it does not include the parameters of the
+    /// filter, the number of elements, or a checksum. It is the same layout as the bit array in
+    /// the binary format.
+    ///
+    /// The bit array is not stored as bytes, so this allocates.
     ///
     /// # Examples
     ///
     /// ```
     /// use bbloom::BloomFilter;
     ///
-    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
-    /// assert!(filter.is_empty());
-    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
     /// filter.insert("a");
-    /// assert!(!filter.is_empty());
+    ///
+    /// let bytes = filter.to_raw_bytes();
+    /// assert_eq!(bytes.len(), 154);
+    /// assert!(bytes.iter().any(|&b| b != 0));
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.n == 0
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        self.bits_to_bytes()
     }
 
-    fn build_hasher<H>(&self, key: &H) -> DoubleHasher
-    where
-        H: Hash + ?Sized,
-    {
-        DoubleHasher::new(key, &self.builder_1, &self.builder_2)
+    /// Adds all elements of `other` to this filter.
+    ///
+    /// The bit array of `other` is combined into this filter (bitwise OR). The result has the
+    /// same bits as a filter that all elements of both filters were inserted into.
+    ///
+    /// The number of elements is the sum of the numbers of elements of both filters. This is an
+    /// upper bound rather than an exact count: an element that was inserted into both filters is
+    /// counted twice, and a filter cannot tell which elements the two have in common.
+    ///
+    /// # Errors
+    ///
+    /// The filters must be compatible, i.e., have the same `m`, `k`, and label (see
+    /// [`IncompatibleFilters`]). They must also have been built with the same hash builders,
+    /// which cannot be checked. This filter is unchanged if an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     || BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut shard = new_filter();
+    /// shard.insert("a");
+    ///
+    /// let mut filter = new_filter();
+    /// filter.insert("b");
+    /// filter.union(&shard)?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// assert_eq!(filter.len(), 2);
+    /// # Ok::<(), bbloom::UnionError>(())
+    /// ```
+    pub fn union(&mut self, other: &Self) -> Result<(), UnionError> {
+        self.check_compatible(other)?;
+
+        self.bits.or(&other.bits);
+        self.n = self.n.saturating_add(other.n);
+
+        Ok(())
     }
-}
+
+    /// Keeps only the elements of this filter that may also be in `other`.
+    ///
+    /// The bit array of `other` is combined into this filter (bitwise AND). Every element in both
+    /// filters remains in the result, so there are no false negatives.
+    ///
+    /// However, the result has a higher false positive probability than a filter that only the
+    /// elements in both filters were inserted into. A bit set by an element of this filter and,
+    /// separately, by a different element of `other` stays set, so elements in neither filter or
+    /// only one filter can still match all of their bits. The estimate in [`Self::summary`] is
+    /// based on the load, so it accounts for these bits, but [`Self::expected_fpp_for`] does not.
+    ///
+    /// The number of elements of the intersection cannot be known, so it is set to the smaller of
+    /// the numbers of elements of both filters. This is an upper bound on the number of elements
+    /// in both filters, assuming each count is exact.
+    ///
+    /// # Errors
+    ///
+    /// The filters must be compatible, i.e., have the same `m`, `k`, and label (see
+    /// [`IncompatibleFilters`]). They must also have been built with the same hash builders,
+    /// which cannot be checked. This filter is unchanged if an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     || BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut a = new_filter();
+    /// a.insert("a");
+    /// a.insert("b");
+    ///
+    /// let mut b = new_filter();
+    /// b.insert("b");
+    /// b.insert("c");
+    ///
+    /// a.intersection(&b)?;
+    ///
+    /// assert!(a.contains("b"));
+    /// assert!(!a.contains("a"));
+    /// assert_eq!(a.len(), 2);
+    /// # Ok::<(), bbloom::IncompatibleFilters>(())
+    /// ```
+    pub fn intersection(&mut self, other: &Self) -> Result<(), IncompatibleFilters> {
+        self.check_compatible(other)?;
+
+        self.bits.and(&other.bits);
+        self.n = self.n.min(other.n);
+
+        Ok(())
+    }
+
+    // Returns the bit array packed most significant bit first.
+    pub(crate) fn bits_to_bytes(&self) -> Vec<u8> {
+        self.bits.to_bytes()
+    }
+
+    // Like `read_from_with_hashers`, `from_bytes_with_hashers`, and `from_raw_bytes_with_hashers`
+    // but for any probe strategy. These are separate so that the public functions keep inferring
+    // the default strategy.
+    fn read_from_with_probe_strategy<R>(
+        reader: &mut R,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        let parts = read_bloom_filter(reader, P::ID)?;
+        Ok(Self::from_parts(parts, builder_1, builder_2))
+    }
+
+    fn from_raw_bytes_with_probe_strategy(
+        bytes: &[u8],
+        m: usize,
+        k: usize,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError> {
+        if m == 0 || k == 0 || bytes.len() != m.div_ceil(8) {
+            return Err(ReadError::InvalidParameters);
+        }
+
+        let parts = Parts {
+            m,
+            k,
+            n: 0,
+            bits: bytes.to_vec(),
+            label: None,
+        };
+
+        let mut filter = Self::from_parts(parts, builder_1, builder_2);
+        // This saturates if all bits are set.
+        filter.n = filter.estimate_cardinality().round() as usize;
+
+        Ok(filter)
+    }
+
+    fn from_bytes_with_probe_strategy(
+        mut buf: &[u8],
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError> {
+        let filter = Self::read_from_with_probe_strategy(&mut buf, builder_1, builder_2)?;
+
+        if buf.is_empty() {
+            Ok(filter)
+        } else {
+            Err(ReadError::TrailingData)
+        }
+    }
+
+    pub(crate) fn from_parts(parts: Parts, builder_1: S, builder_2: S) -> Self {
+        let mut bits = BitVec::from_bytes(&parts.bits);
+        bits.truncate(parts.m);
+
+        let mut filter = Self::from_store_with_probe_strategy(bits, parts.k, builder_1, builder_2);
+        filter.n = parts.n;
+        filter.label = parts.label;
+
+        filter
+    }
+}
+
+impl<S, B> BloomFilter<S, B>
+where
+    S: BuildHasher,
+    B: BitStore,
+{
+    /// Creates a new bloom filter over the bit array `bits` with `k` hash functions, using
+    /// `builder_1` and `builder_2` to hash the data.
+    ///
+    /// The size of the bit array `m` is the length of `bits`. `bits` is expected to have no bits
+    /// set, as the number of elements starts at 0.
+    ///
+    /// See [`crate::bit_store`] for the available stores.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{bit_store::WordBits, BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter = BloomFilter::from_store(
+    ///     WordBits::new(1227),
+    ///     7,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    ///
+    /// assert_eq!(filter.capacity(), 1227);
+    /// ```
+    pub fn from_store(bits: B, k: usize, builder_1: S, builder_2: S) -> Self {
+        Self::from_store_with_probe_strategy(bits, k, builder_1, builder_2)
+    }
+}
+
+impl<S, B, P> BloomFilter<S, B, P>
+where
+    S: BuildHasher,
+    B: BitStore,
+    P: ProbeStrategy,
+{
+    // Like `from_store` but for any probe strategy. This is separate so that `from_store` keeps
+    // inferring the default strategy.
+    fn from_store_with_probe_strategy(bits: B, k: usize, builder_1: S, builder_2: S) -> Self {
+        let m = bits.len_bits();
+
+        Self {
+            bits,
+            m,
+            n: 0,
+            k,
+            label: None,
+            builder_1,
+            builder_2,
+            probe_strategy: PhantomData,
+            #[cfg(feature = "instrumentation")]
+            probe_stats: ProbeStats::default(),
+        }
+    }
+
+    /// Changes the probe strategy of an empty filter.
+    ///
+    /// See [`crate::probe_strategy`] for the available strategies.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the filter has any bits set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{probe_strategy::EnhancedDoubleHashing, BloomFilter};
+    ///
+    /// let mut filter =
+    ///     BloomFilter::new(1227, 7).with_probe_strategy::<EnhancedDoubleHashing>();
+    ///
+    /// filter.insert("a");
+    /// assert!(filter.contains("a"));
+    /// ```
+    pub fn with_probe_strategy<Q>(self) -> BloomFilter<S, B, Q>
+    where
+        Q: ProbeStrategy,
+    {
+        assert_eq!(self.bits.count_ones(), 0, "filter is not empty");
+
+        BloomFilter {
+            bits: self.bits,
+            m: self.m,
+            n: self.n,
+            k: self.k,
+            label: self.label,
+            builder_1: self.builder_1,
+            builder_2: self.builder_2,
+            probe_strategy: PhantomData,
+            #[cfg(feature = "instrumentation")]
+            probe_stats: self.probe_stats,
+        }
+    }
+
+    /// Attaches a user-defined label to the filter.
+    ///
+    /// A label identifies what the filter represents, e.g., the key type and hash builders. Filters
+    /// with different labels are [incompatible](`IncompatibleFilters`), even if they have the same
+    /// `m` and `k`. The label is stored in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let users = BloomFilter::new(1227, 7).with_label(1);
+    /// let orders = BloomFilter::new(1227, 7).with_label(2);
+    ///
+    /// assert!(users.iou(&orders).is_err());
+    /// ```
+    pub fn with_label(mut self, label: u64) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Returns the user-defined label of the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let filter = BloomFilter::new(1227, 7);
+    /// assert_eq!(filter.label(), None);
+    ///
+    /// let filter = filter.with_label(8);
+    /// assert_eq!(filter.label(), Some(8));
+    /// ```
+    pub fn label(&self) -> Option<u64> {
+        self.label
+    }
+
+    /// Returns the size of the bit array `m`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(1227, 14);
+    /// assert_eq!(filter.capacity(), 1227);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.m
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// Remember that false positives can occur, meaning that if this returns `true`, there is only
+    /// a possibility that the element is in the filter. If this returns `false`, the element is
+    /// definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    /// filter.insert("b");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// assert!(!filter.contains("c"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        let (present, _probes) = self.contains_instrumented(key);
+
+        #[cfg(feature = "instrumentation")]
+        self.probe_stats.record(_probes);
+
+        present
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter, also
+    /// returning the number of bits that were examined.
+    ///
+    /// A definitely absent element can be rejected before all `k` bits are examined, so a low
+    /// number of probes means an early rejection. Unlike [`Self::contains`], this is never recorded
+    /// in the running average of probes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.contains_instrumented("a"), (true, 7));
+    ///
+    /// let (present, probes) = filter.contains_instrumented("b");
+    /// assert!(!present);
+    /// assert!(probes >= 1 && probes <= 7);
+    /// ```
+    pub fn contains_instrumented<H: Hash + ?Sized>(&self, key: &H) -> (bool, usize) {
+        self.probe(self.build_hasher(key))
+    }
+
+    /// Returns the number of bits of an element that are set and the number of hash functions
+    /// `k`.
+    ///
+    /// All `k` bits are examined, even after one is found unset. The score is `k` if and only if
+    /// [`Self::contains`] returns `true`. A high score for an absent element is a near miss, and
+    /// consistently low scores for known elements can indicate a filter built with different hash
+    /// builders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.match_score("a"), (7, 7));
+    ///
+    /// let (score, k) = filter.match_score("b");
+    /// assert!(score < k);
+    /// ```
+    pub fn match_score<H: Hash + ?Sized>(&self, key: &H) -> (usize, usize) {
+        let hasher = self.build_hasher(key);
+        let score = self.indices(hasher).filter(|&i| self.bits.get(i)).count();
+        (score, self.k)
+    }
+
+    /// Returns the fraction of bits of an element that are set.
+    ///
+    /// This is 1.0 if and only if [`Self::contains`] returns `true`. See [`Self::match_score`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.match_fraction("a"), 1.0);
+    /// assert!(filter.match_fraction("b") < 1.0);
+    /// ```
+    pub fn match_fraction<H: Hash + ?Sized>(&self, key: &H) -> f64 {
+        let (score, k) = self.match_score(key);
+        score as f64 / k as f64
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter, where the
+    /// element is the bytes written by `f`.
+    ///
+    /// `f` is given a hasher that writes to both underlying hashers. Calling this with a closure
+    /// that writes the same values as the one given to [`Self::insert_with`] tests for the same
+    /// element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// struct Record {
+    ///     id: u64,
+    ///     name: String,
+    ///     score: f64,
+    /// }
+    ///
+    /// let record = Record { id: 8, name: String::from("ndls"), score: 0.5 };
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    ///
+    /// // `score` does not contribute to the key.
+    /// filter.insert_with(|hasher| {
+    ///     hasher.write_u64(record.id);
+    ///     hasher.write(record.name.as_bytes());
+    /// });
+    ///
+    /// assert!(filter.contains_with(|hasher| {
+    ///     hasher.write_u64(8);
+    ///     hasher.write(b"ndls");
+    /// }));
+    /// ```
+    pub fn contains_with<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&mut dyn Hasher),
+    {
+        let (present, _probes) =
+            self.probe(DoubleHasher::with(&self.builder_1, &self.builder_2, f));
+
+        #[cfg(feature = "instrumentation")]
+        self.probe_stats.record(_probes);
+
+        present
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter, where the
+    /// element is all bytes read from `reader`.
+    ///
+    /// See [`Self::insert_from_reader`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert_from_reader(&b"ndls"[..])?;
+    ///
+    /// assert!(filter.contains_from_reader(&b"ndls"[..])?);
+    /// assert!(!filter.contains_from_reader(&b"bbloom"[..])?);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn contains_from_reader<R>(&self, reader: R) -> io::Result<bool>
+    where
+        R: Read,
+    {
+        let hasher = DoubleHasher::from_reader(&self.builder_1, &self.builder_2, reader)?;
+        let (present, _probes) = self.probe(hasher);
+
+        #[cfg(feature = "instrumentation")]
+        self.probe_stats.record(_probes);
+
+        Ok(present)
+    }
+
+    /// Returns the pair of hashes of an element, `(h1, h2)`, from which its `k` bit indices are
+    /// derived.
+    ///
+    /// The hashes depend only on the hash builders, so an element can be hashed once and then
+    /// tested against or inserted into any number of filters built with the same hash builders,
+    /// using [`Self::contains_hashes`] and [`Self::insert_hashes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     |m| BloomFilter::with_hashers(m, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut a = new_filter(1227);
+    /// let mut b = new_filter(4096);
+    ///
+    /// let (h1, h2) = a.hash_key("a");
+    /// a.insert_hashes(h1, h2);
+    /// b.insert_hashes(h1, h2);
+    ///
+    /// assert!(a.contains("a"));
+    /// assert!(b.contains("a"));
+    /// ```
+    pub fn hash_key<H: Hash + ?Sized>(&self, key: &H) -> (u64, u64) {
+        self.build_hasher(key).hashes()
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter, given its
+    /// hashes from [`Self::hash_key`].
+    ///
+    /// The hashes must come from a filter with the same hash builders as this one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// let (h1, h2) = filter.hash_key("a");
+    /// assert!(filter.contains_hashes(h1, h2));
+    /// ```
+    pub fn contains_hashes(&self, h1: u64, h2: u64) -> bool {
+        let (present, _probes) = self.probe(DoubleHasher::from_hashes(h1, h2));
+
+        #[cfg(feature = "instrumentation")]
+        self.probe_stats.record(_probes);
+
+        present
+    }
+
+    fn probe(&self, hasher: DoubleHasher) -> (bool, usize) {
+        probe_bits::<_, P>(&self.bits, self.k, hasher)
+    }
+
+    /// Returns the average number of bits examined per call to [`Self::contains`] since the filter
+    /// was created or the statistics were last reset.
+    ///
+    /// This is 0.0 if no queries were recorded.
+    ///
+    /// This is only available with the `instrumentation` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// filter.contains("a");
+    /// filter.contains("a");
+    /// assert_eq!(filter.avg_probes(), 7.0);
+    /// ```
+    #[cfg(feature = "instrumentation")]
+    pub fn avg_probes(&self) -> f64 {
+        self.probe_stats.average()
+    }
+
+    /// Resets the running average of probes per query.
+    ///
+    /// This is only available with the `instrumentation` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    /// filter.contains("a");
+    ///
+    /// filter.reset_probe_stats();
+    /// assert_eq!(filter.avg_probes(), 0.0);
+    /// ```
+    #[cfg(feature = "instrumentation")]
+    pub fn reset_probe_stats(&self) {
+        self.probe_stats.reset();
+    }
+
+    /// Adds a value to the bloom filter.
+    ///
+    /// Returns whether the value is already (maybe) in the filter or not. Duplicate values do not
+    /// affect the load factor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.insert("a"));
+    /// assert!(filter.insert("b"));
+    /// assert!(!filter.insert("b"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let hasher = self.build_hasher(key);
+        self.set(hasher)
+    }
+
+    /// Inserts an element into the filter, where the element is the bytes written by `f`.
+    ///
+    /// `f` is given a hasher that writes to both underlying hashers, which gives full control over
+    /// which values make up the key, e.g., a subset of the fields of a struct. This returns
+    /// whether the element was not previously in the filter (see [`Self::insert`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    ///
+    /// assert!(filter.insert_with(|hasher| hasher.write(b"a")));
+    /// assert!(!filter.insert_with(|hasher| hasher.write(b"a")));
+    /// ```
+    pub fn insert_with<F>(&mut self, f: F) -> bool
+    where
+        F: FnOnce(&mut dyn Hasher),
+    {
+        let hasher = DoubleHasher::with(&self.builder_1, &self.builder_2, f);
+        self.set(hasher)
+    }
+
+    /// Inserts an element into the filter, where the element is all bytes read from `reader`.
+    ///
+    /// The data is streamed through the hashers in fixed-size chunks, so it is never held in
+    /// memory at once. This is the same element as `insert_with(|hasher| hasher.write(data))`
+    /// given the whole data, for hashers where consecutive writes are equivalent to one
+    /// concatenated write, e.g., the standard library's default hasher and [`crate::SeededHasher`].
+    ///
+    /// This returns whether the element was not previously in the filter (see [`Self::insert`]).
+    /// If reading fails, the filter is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// let document = vec![0; 1 << 20];
+    ///
+    /// assert!(filter.insert_from_reader(Cursor::new(&document))?);
+    /// assert!(filter.contains_with(|hasher| hasher.write(&document)));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn insert_from_reader<R>(&mut self, reader: R) -> io::Result<bool>
+    where
+        R: Read,
+    {
+        let hasher = DoubleHasher::from_reader(&self.builder_1, &self.builder_2, reader)?;
+        Ok(self.set(hasher))
+    }
+
+    /// Inserts an element into the filter, given its hashes from [`Self::hash_key`].
+    ///
+    /// The hashes must come from a filter with the same hash builders as this one. This returns
+    /// whether the element was not previously in the filter (see [`Self::insert`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    ///
+    /// let (h1, h2) = filter.hash_key("a");
+    /// assert!(filter.insert_hashes(h1, h2));
+    /// assert!(filter.contains("a"));
+    /// ```
+    pub fn insert_hashes(&mut self, h1: u64, h2: u64) -> bool {
+        self.set(DoubleHasher::from_hashes(h1, h2))
+    }
+
+    fn set(&mut self, hasher: DoubleHasher) -> bool {
+        let mut present = true;
+
+        for i in self.indices(hasher) {
+            if !self.bits.get(i) {
+                present = false;
+                self.bits.set(i);
+            }
+        }
+
+        if !present {
+            self.n += 1;
+        }
+
+        !present
+    }
+
+    /// Verifies that every key in a newline-delimited list may be in the filter.
+    ///
+    /// Keys are read one line at a time, so memory use is bounded by the longest line. Line
+    /// endings (`\n` or `\r\n`) are not part of a key. Since a filter has no false negatives, any
+    /// key reported absent means the filter is corrupt or was built from different keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    /// filter.insert("b");
+    ///
+    /// let report = filter.verify_against(&b"a\nb\n"[..])?;
+    /// assert!(report.is_ok());
+    /// assert_eq!(report.checked(), 2);
+    ///
+    /// let report = filter.verify_against(&b"a\nc\n"[..])?;
+    /// assert!(!report.is_ok());
+    /// assert_eq!(report.first_false_negative(), Some(2));
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn verify_against<R>(&self, mut reader: R) -> io::Result<VerifyReport>
+    where
+        R: BufRead,
+    {
+        let mut report = VerifyReport::default();
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+
+            let key = line
+                .strip_suffix('\n')
+                .map(|s| s.strip_suffix('\r').unwrap_or(s))
+                .unwrap_or(&line);
+
+            report.checked += 1;
+
+            if !self.contains(key) {
+                report.false_negatives += 1;
+
+                if report.first_false_negative.is_none() {
+                    report.first_false_negative = Some(report.checked);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Checks how uniformly the hash builders distribute the probe indices of a sample of keys.
+    ///
+    /// A weak hasher clusters probe indices, which raises the false positive probability well
+    /// above the target. The keys are only hashed, not inserted. See [`HashQualityReport`] for how
+    /// the report is judged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter = BloomFilter::from_fpp_with_hashers(
+    ///     0.0001,
+    ///     1000,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// let report = filter.hash_quality_report((0..1000).map(|i| format!("key-{}", i)));
+    ///
+    /// assert_eq!(report.samples(), 1000);
+    /// assert!(report.looks_uniform());
+    /// ```
+    pub fn hash_quality_report<I>(&self, sample_keys: I) -> HashQualityReport
+    where
+        I: IntoIterator,
+        I::Item: Hash,
+    {
+        let bucket_count = self.m.min(HashQualityReport::BUCKETS);
+        let mut loads = vec![0; bucket_count];
+        let mut samples = 0;
+        let mut equal_hashes = 0;
+
+        for key in sample_keys {
+            let hasher = self.build_hasher(&key);
+
+            if hasher.has_equal_hashes() {
+                equal_hashes += 1;
+            }
+
+            for i in self.indices(hasher) {
+                let bucket = ((i as u128 * bucket_count as u128) / self.m as u128) as usize;
+                loads[bucket] += 1;
+            }
+
+            samples += 1;
+        }
+
+        let total: u64 = loads.iter().sum();
+        let expected = total as f64 / bucket_count as f64;
+
+        let chi_squared = if total == 0 {
+            0.0
+        } else {
+            loads
+                .iter()
+                .map(|&load| {
+                    let d = load as f64 - expected;
+                    d * d / expected
+                })
+                .sum()
+        };
+
+        HashQualityReport {
+            samples,
+            loads,
+            chi_squared,
+            equal_hashes,
+        }
+    }
+
+    /// Returns the number of elements `n` in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// assert_eq!(filter.len(), 0);
+    ///
+    /// filter.insert("a");
+    /// assert_eq!(filter.len(), 1);
+    ///
+    /// filter.insert("b");
+    /// assert_eq!(filter.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the bloom filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.is_empty());
+    ///
+    /// filter.insert("a");
+    /// assert!(!filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Estimates the number of distinct elements in the filter from the proportion of set bits.
+    ///
+    /// This uses the estimator from "Mathematical correlation of bit densities" (Swamidass and
+    /// Baldi, 2007), `-(m / k) * ln(1 - X / m)`, where `X` is the number of set bits. Unlike
+    /// [`Self::len`], it does not depend on the count of novel inserts, e.g., for filters combined
+    /// by a union. The estimate is infinite if every bit is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.001, 1000);
+    /// assert_eq!(filter.estimate_cardinality(), 0.0);
+    ///
+    /// for i in 0..1000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!((filter.estimate_cardinality() - 1000.0).abs() < 50.0);
+    /// ```
+    pub fn estimate_cardinality(&self) -> f64 {
+        let m = self.m as f64;
+        let k = self.k as f64;
+        let x = self.bits.count_ones() as f64;
+        (m / k) * (m / (m - x)).ln()
+    }
+
+    /// Returns the expected false positive probability after `n` distinct elements are inserted,
+    /// (1 - e^(-kn/m))^k.
+    ///
+    /// This depends only on the size of the bit array `m` and the number of hash functions `k`,
+    /// not on the current contents of the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let filter = BloomFilter::from_fpp(0.01, 1000);
+    /// assert!(filter.expected_fpp_for(1000) < 0.011);
+    /// assert!(filter.expected_fpp_for(2000) > 0.1);
+    /// ```
+    pub fn expected_fpp_for(&self, n: usize) -> f64 {
+        false_positive_probability(self.m, self.k, n)
+    }
+
+    /// Returns the estimated false positive probability given the number of elements `n`,
+    /// (1 - e^(-kn/m))^k.
+    ///
+    /// This is 0.0 for an empty filter and grows toward 1.0 as elements are inserted, which makes
+    /// it useful for monitoring a filter that receives more elements than it was sized for. It is
+    /// computed from `n`, so it is less accurate after combining filters, e.g., using
+    /// [`Self::union`], which overcounts `n`. See [`Self::summary`] for an estimate based on the
+    /// proportion of set bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.01, 1000);
+    /// assert_eq!(filter.estimated_fpp(), 0.0);
+    ///
+    /// for i in 0..1000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!(filter.estimated_fpp() < 0.011);
+    ///
+    /// for i in 1000..2000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!(filter.estimated_fpp() > 0.1);
+    /// ```
+    pub fn estimated_fpp(&self) -> f64 {
+        self.expected_fpp_for(self.n).min(1.0)
+    }
+
+    /// Returns the number of set bits.
+    ///
+    /// Probes of different elements can set the same bit, so this is at most `k * n`. The
+    /// proportion of set bits is `bit_count / m`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// assert_eq!(filter.bit_count(), 0);
+    ///
+    /// filter.insert("a");
+    /// assert!((1..=7).contains(&filter.bit_count()));
+    /// ```
+    pub fn bit_count(&self) -> usize {
+        self.bits.count_ones()
+    }
+
+    /// Returns an iterator over the indices of set bits in increasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// assert_eq!(filter.iter_ones().count(), 0);
+    ///
+    /// filter.insert("a");
+    ///
+    /// let ones: Vec<_> = filter.iter_ones().collect();
+    /// assert!((1..=7).contains(&ones.len()));
+    /// assert!(ones.windows(2).all(|w| w[0] < w[1]));
+    /// ```
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        const BITS: usize = u64::BITS as usize;
+
+        let m = self.m;
+
+        (0..self.bits.word_count())
+            .flat_map(move |i| {
+                let mut word = self.bits.word(i);
+
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        None
+                    } else {
+                        let j = word.trailing_zeros() as usize;
+                        word &= word - 1;
+                        Some(i * BITS + j)
+                    }
+                })
+            })
+            .take_while(move |&i| i < m)
+    }
+
+    /// Returns the bit-level intersection over union (Jaccard index) of the bit arrays of two
+    /// filters.
+    ///
+    /// This is the number of bits set in both filters divided by the number of bits set in either.
+    /// It measures how similar the bit arrays are, e.g., to monitor whether replicas converge. If
+    /// neither filter has any bits set, this is 1.0.
+    ///
+    /// # Errors
+    ///
+    /// The filters must be compatible, i.e., have the same `m`, `k`, and label (see
+    /// [`IncompatibleFilters`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     || BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut a = new_filter();
+    /// a.insert("a");
+    ///
+    /// let mut b = new_filter();
+    /// b.insert("a");
+    /// assert_eq!(a.iou(&b)?, 1.0);
+    ///
+    /// b.insert("b");
+    /// let iou = a.iou(&b)?;
+    /// assert!(iou > 0.0 && iou < 1.0);
+    /// # Ok::<(), bbloom::IncompatibleFilters>(())
+    /// ```
+    pub fn iou(&self, other: &BloomFilter<S, B, P>) -> Result<f64, IncompatibleFilters> {
+        self.check_compatible(other)?;
+
+        let mut intersection = 0u64;
+        let mut union = 0u64;
+
+        for i in 0..self.bits.word_count() {
+            let (a, b) = (self.bits.word(i), other.bits.word(i));
+
+            intersection += u64::from((a & b).count_ones());
+            union += u64::from((a | b).count_ones());
+        }
+
+        if union == 0 {
+            Ok(1.0)
+        } else {
+            Ok(intersection as f64 / union as f64)
+        }
+    }
+
+    /// Returns a reader of the filter in the binary format (see [`crate::io`]).
+    ///
+    /// The reader produces the same bytes as [`Self::write_to`], but the bit array is encoded in
+    /// chunks as it is read rather than all at once. See [`SerializedReader`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read};
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// filter.insert("a");
+    ///
+    /// let mut reader = filter.serialized_reader();
+    /// let mut buf = Vec::with_capacity(reader.len_hint());
+    /// io::copy(&mut reader, &mut buf)?;
+    ///
+    /// assert_eq!(buf, filter.to_bytes());
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn serialized_reader(&self) -> SerializedReader<'_> {
+        bloom_filter_reader(self.k, self.n, &self.bits, self.label, P::ID)
+    }
+
+    /// Adds all elements of a filter in the binary format (see [`crate::io`]) to this filter.
+    ///
+    /// The bit array of the serialized filter is combined into this filter (bitwise OR) as it is
+    /// parsed, without decoding the serialized filter. The number of elements of the serialized
+    /// filter is added to the number of elements of this filter, which may overcount elements
+    /// that are in both.
+    ///
+    /// The buffer is fully validated before this filter is changed, so this filter is unchanged
+    /// if an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// The serialized filter must be valid, have no trailing data, and use the same probe
+    /// strategy (see [`crate::io::ReadError`]). It must be compatible with this filter, i.e.,
+    /// have the same `m`, `k`, and label (see [`IncompatibleFilters`]). It must also have been
+    /// built with the same hash builders, which cannot be checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     || BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut peer = new_filter();
+    /// peer.insert("a");
+    /// let buf = peer.to_bytes();
+    ///
+    /// let mut filter = new_filter();
+    /// filter.insert("b");
+    /// filter.union_from_bytes(&buf)?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// # Ok::<(), bbloom::UnionError>(())
+    /// ```
+    pub fn union_from_bytes(&mut self, buf: &[u8]) -> Result<(), UnionError> {
+        // The first pass only validates, since the checksum is at the end of the buffer.
+        let expected = (self.m, self.k, self.label);
+        let mut reader = buf;
+
+        read_bloom_filter_payload(
+            &mut reader,
+            P::ID,
+            |header| check_compatible_with_header(expected, header),
+            |_, _| {},
+        )?;
+
+        if !reader.is_empty() {
+            return Err(ReadError::TrailingData.into());
+        }
+
+        self.union_from_reader(&mut &buf[..])
+    }
+
+    /// Adds all elements of a filter in the binary format (see [`crate::io`]) read from `reader`
+    /// to this filter.
+    ///
+    /// This is like [`Self::union_from_bytes`] but streams the bit array of the serialized
+    /// filter in fixed-size chunks, so memory use does not depend on the size of the filter.
+    /// Only one filter is read. Data after it is not read.
+    ///
+    /// Since the checksum is at the end of the data, this filter is only guaranteed to be
+    /// unchanged if an error is returned before the bit array, e.g., for an invalid header or an
+    /// incompatible filter. If the bit array is truncated or its checksum does not match, this
+    /// filter may be partially combined and should be discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     || BloomFilter::with_hashers(1227, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut peer = new_filter();
+    /// peer.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// peer.write_to(&mut buf)?;
+    ///
+    /// let mut filter = new_filter();
+    /// filter.union_from_reader(&mut &buf[..])?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn union_from_reader<R>(&mut self, reader: &mut R) -> Result<(), UnionError>
+    where
+        R: Read,
+    {
+        let expected = (self.m, self.k, self.label);
+        let bits = &mut self.bits;
+
+        let header = read_bloom_filter_payload(
+            reader,
+            P::ID,
+            |header| check_compatible_with_header(expected, header),
+            |first_word, chunk| {
+                for (i, bytes) in chunk.chunks(8).enumerate() {
+                    // Payload bytes are most significant bit first.
+                    let word = bytes.iter().enumerate().fold(0, |word, (j, b)| {
+                        word | u64::from(b.reverse_bits()) << (8 * j)
+                    });
+
+                    if word != 0 {
+                        let i = first_word + i;
+                        bits.set_word(i, bits.word(i) | word);
+                    }
+                }
+            },
+        )?;
+
+        self.n = self.n.saturating_add(header.n);
+
+        Ok(())
+    }
+
+    /// Returns a one-line, human-readable description of the filter.
+    ///
+    /// This includes the size of the bit array `m`, the number of hash functions `k`, the number
+    /// of elements `n`, the proportion of set bits (load), and the estimated false positive
+    /// probability given the load, i.e., `load^k`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let filter = BloomFilter::new(1227, 7);
+    ///
+    /// assert_eq!(
+    ///     filter.summary(),
+    ///     "BloomFilter { m: 1227, k: 7, n: 0, load: 0.0%, est_fpp: 0.0000 }"
+    /// );
+    /// ```
+    pub fn summary(&self) -> String {
+        format!(
+            "BloomFilter {{ m: {}, k: {}, n: {}, load: {:.1}%, est_fpp: {:.4} }}",
+            self.m,
+            self.k,
+            self.n,
+            self.load() * 100.0,
+            self.load_fpp(),
+        )
+    }
+
+    /// Returns the ratio of the memory used by the filter to the estimated memory used by a
+    /// `HashSet<String>` holding the same number of elements `n`, with keys of `avg_key_bytes`
+    /// bytes on average.
+    ///
+    /// A ratio less than 1 means the filter uses less memory. The filter uses its struct size plus
+    /// `ceil(m / 8)` bytes for the bit array. The `HashSet` estimate is a rough model of the
+    /// standard library's implementation, assuming
+    ///
+    ///   * buckets are a power of two that keeps the load at most 7/8 (4 or 8 buckets for fewer
+    ///     than 8 elements, and none when empty);
+    ///   * each bucket holds a `String` (pointer, capacity, and length) and 1 control byte, plus 16
+    ///     trailing control bytes per table;
+    ///   * each key has a separate heap allocation of exactly `avg_key_bytes` bytes; and
+    ///   * allocator overhead and unused string capacity are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.01, 1000);
+    ///
+    /// for i in 0..1000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!(filter.memory_savings_vs_hashset(16) < 0.1);
+    /// ```
+    pub fn memory_savings_vs_hashset(&self, avg_key_bytes: usize) -> f64 {
+        let filter_bytes = mem::size_of::<Self>() + self.m.div_ceil(8);
+        let hash_set_bytes = estimated_hash_set_memory(self.n, avg_key_bytes);
+        filter_bytes as f64 / hash_set_bytes as f64
+    }
+
+    pub(crate) fn hash_count(&self) -> usize {
+        self.k
+    }
+
+    // Returns the indices of the `k` probes for a key.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn key_indices<H>(&self, key: &H) -> impl Iterator<Item = usize>
+    where
+        H: Hash + ?Sized,
+    {
+        self.indices(self.build_hasher(key))
+    }
+
+    pub(crate) fn bit_store(&self) -> &B {
+        &self.bits
+    }
+
+    pub(crate) fn hash_builders(&self) -> (&S, &S) {
+        (&self.builder_1, &self.builder_2)
+    }
+
+    // Returns the bit array packed into 64-bit words, where bit `i` is bit `i % 64` of word
+    // `i / 64`.
+    pub(crate) fn to_words(&self) -> Vec<u64> {
+        (0..self.bits.word_count())
+            .map(|i| self.bits.word(i))
+            .collect()
+    }
+
+    pub(crate) fn check_compatible(&self, other: &Self) -> Result<(), IncompatibleFilters> {
+        check_compatible(
+            (self.m, self.k, self.label),
+            (other.m, other.k, other.label),
+        )
+    }
+
+    // Returns the proportion of set bits.
+    pub(crate) fn load(&self) -> f64 {
+        self.bit_count() as f64 / self.m as f64
+    }
+
+    // Returns the false positive probability given the proportion of set bits.
+    pub(crate) fn load_fpp(&self) -> f64 {
+        self.load().powi(self.k as i32)
+    }
+
+    fn build_hasher<H>(&self, key: &H) -> DoubleHasher
+    where
+        H: Hash + ?Sized,
+    {
+        DoubleHasher::new(key, &self.builder_1, &self.builder_2)
+    }
+
+    // Returns the indices of the `k` probes for the hashes of a key.
+    fn indices(&self, hasher: DoubleHasher) -> impl Iterator<Item = usize> {
+        let (h1, h2) = hasher.hashes();
+        let m = self.m;
+        (0..self.k).map(move |i| P::index(h1, h2, i, m))
+    }
+}
+
+impl<S, P> BloomFilter<S, BitVec, P>
+where
+    S: BuildHasher + Default,
+    P: ProbeStrategy,
+{
+    /// Reads a filter in the binary format (see [`crate::io`]), creating the hash builders using
+    /// [`Default`].
+    ///
+    /// The hash builders must produce the same hashes as the ones used to build the filter.
+    /// [`std::collections::hash_map::RandomState`] is randomly seeded, so a filter read using it
+    /// will not find previously inserted elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::read_from(&mut &buf[..])?;
+    /// assert_eq!(filter.capacity(), 1227);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_from<R>(reader: &mut R) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        Self::read_from_with_probe_strategy(reader, S::default(), S::default())
+    }
+
+    /// Decodes a filter in the binary format (see [`crate::io`]), creating the hash builders
+    /// using [`Default`].
+    ///
+    /// See [`Self::read_from`] for the requirements on the hash builders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_bytes(&buf)?;
+    /// assert_eq!(filter.capacity(), 1227);
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, ReadError> {
+        Self::from_bytes_with_probe_strategy(buf, S::default(), S::default())
+    }
+
+    /// Creates a filter from a raw bit array (see [`Self::to_raw_bytes`]) with a bit array size
+    /// `m` and number of hash functions `k`, creating the hash builders using [`Default`].
+    ///
+    /// See [`Self::from_raw_bytes_with_hashers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+    /// filter.insert("a");
+    ///
+    /// let bytes = filter.to_raw_bytes();
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_raw_bytes(&bytes, 1227, 7)?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_raw_bytes(bytes: &[u8], m: usize, k: usize) -> Result<Self, ReadError> {
+        Self::from_raw_bytes_with_probe_strategy(bytes, m, k, S::default(), S::default())
+    }
+
+    /// Reads a filter in the binary format (see [`crate::io`]) from a file at `path`, creating
+    /// the hash builders using [`Default`].
+    ///
+    /// See [`Self::read_from`] for the requirements on the hash builders. This fails if there is
+    /// data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::read_from_path("filter.bin")?;
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn read_from_path<Q>(path: Q) -> Result<Self, ReadError>
+    where
+        Q: AsRef<Path>,
+    {
+        path::read_from_path(path, |reader| Self::read_from(reader))
+    }
+
+    /// Decodes a filter from base64 (see [`Self::to_base64`]), creating the hash builders using
+    /// [`Default`].
+    ///
+    /// Both the standard and URL and filename safe alphabets are accepted, with or without
+    /// padding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{io::DecodeError, BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+    /// let s = filter.to_base64();
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_base64(&s)?;
+    /// assert_eq!(filter.capacity(), 1227);
+    ///
+    /// assert!(matches!(
+    ///     BloomFilter::<SeededHashBuilder>::from_base64("!"),
+    ///     Err(DecodeError::InvalidEncoding)
+    /// ));
+    /// # Ok::<(), DecodeError>(())
+    /// ```
+    pub fn from_base64(s: &str) -> Result<Self, DecodeError> {
+        let buf = base64::decode(s).ok_or(DecodeError::InvalidEncoding)?;
+        Self::from_bytes(&buf).map_err(DecodeError::InvalidFilter)
+    }
+
+    /// Decodes a filter from hexadecimal (see [`Self::to_hex`]), creating the hash builders using
+    /// [`Default`].
+    ///
+    /// Both uppercase and lowercase digits are accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{io::DecodeError, BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+    /// let s = filter.to_hex();
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_hex(&s)?;
+    /// assert_eq!(filter.capacity(), 1227);
+    /// # Ok::<(), DecodeError>(())
+    /// ```
+    pub fn from_hex(s: &str) -> Result<Self, DecodeError> {
+        let buf = hex::decode(s).ok_or(DecodeError::InvalidEncoding)?;
+        Self::from_bytes(&buf).map_err(DecodeError::InvalidFilter)
+    }
+}
 
 // Calculates the optimal size of the bit array given a target false positive probability `p`
 // ([0.0, 1.0]) and the expected number of inserted elements `n`.
-fn optimal_required_bits(p: f64, n: usize) -> usize {
+//
+// This is at least 1.
+pub(crate) fn optimal_required_bits(p: f64, n: usize) -> usize {
     let ln_2 = f64::consts::LN_2;
     let n = n as f64;
     let m = -(n * p.ln()) / (ln_2 * ln_2);
-    m.ceil() as usize
+    (m.ceil() as usize).max(1)
+}
+
+// Like `optimal_required_bits` but returns `None` if the size of the bit array exceeds
+// `isize::MAX`.
+pub(crate) fn checked_optimal_required_bits(p: f64, n: usize) -> Option<usize> {
+    let ln_2 = f64::consts::LN_2;
+    let m = -(n as f64 * p.ln()) / (ln_2 * ln_2);
+
+    if m <= isize::MAX as f64 {
+        Some(optimal_required_bits(p, n))
+    } else {
+        None
+    }
+}
+
+// Calculates the number of elements a bit array of size `m` can hold while meeting a target false
+// positive probability `p` ([0.0, 1.0]).
+//
+// This is the inverse of `optimal_required_bits` and is at least 1.
+pub(crate) fn optimal_number_of_elements(p: f64, m: usize) -> usize {
+    let ln_2 = f64::consts::LN_2;
+    let m = m as f64;
+    let n = -(m * ln_2 * ln_2) / p.ln();
+    (n.floor() as usize).max(1)
+}
+
+// Calculates the optimal number of hash functions given the size of the bit array `m` and the
+// expected number of inserted elements `n`.
+//
+// This is at least 1.
+pub(crate) fn optimal_number_of_hash_functions(m: usize, n: usize) -> usize {
+    if n == 0 {
+        return 1;
+    }
+
+    let m = m as f64;
+    let n = n as f64;
+    let k = m / n * f64::consts::LN_2;
+    (k.ceil() as usize).max(1)
+}
+
+// Calculates the false positive probability of a bit array of size `m` using `k` hash functions
+// after `n` elements are inserted, (1 - e^(-kn/m))^k.
+pub(crate) fn false_positive_probability(m: usize, k: usize, n: usize) -> f64 {
+    let m = m as f64;
+    let k = k as f64;
+    let n = n as f64;
+    (1.0 - (-k * n / m).exp()).powf(k)
+}
+
+// Checks whether filters with the given `(m, k, label)` are compatible.
+// Tests the bits of the `k` probes of a key, returning whether they are all set and the number of
+// bits that were examined.
+//
+// Hash generation and bit tests are interleaved so that a definitely absent element is rejected at
+// the first unset bit. Computing all `k` indices before testing them was measured to be slower for
+// both present and absent elements (see `bench_contains_split` in `benches/contains.rs`).
+pub(crate) fn probe_bits<B, P>(bits: &B, k: usize, hasher: DoubleHasher) -> (bool, usize)
+where
+    B: BitStore,
+    P: ProbeStrategy,
+{
+    let (h1, h2) = hasher.hashes();
+    let m = bits.len_bits();
+
+    for probes in 0..k {
+        if !bits.get(P::index(h1, h2, probes, m)) {
+            return (false, probes + 1);
+        }
+    }
+
+    (true, k)
+}
+
+fn check_compatible(
+    expected: (usize, usize, Option<u64>),
+    actual: (usize, usize, Option<u64>),
+) -> Result<(), IncompatibleFilters> {
+    let ((expected_m, expected_k, expected_label), (actual_m, actual_k, actual_label)) =
+        (expected, actual);
+
+    if expected_m != actual_m {
+        Err(IncompatibleFilters::Capacity {
+            expected: expected_m,
+            actual: actual_m,
+        })
+    } else if expected_k != actual_k {
+        Err(IncompatibleFilters::HashCount {
+            expected: expected_k,
+            actual: actual_k,
+        })
+    } else if expected_label != actual_label {
+        Err(IncompatibleFilters::Label {
+            expected: expected_label,
+            actual: actual_label,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_compatible_with_header(
+    expected: (usize, usize, Option<u64>),
+    header: &Header,
+) -> Result<(), UnionError> {
+    check_compatible(expected, (header.m, header.k, header.label)).map_err(UnionError::from)
+}
+
+// Estimates the memory used by a `HashSet<String>` with `n` keys of `avg_key_bytes` bytes. See
+// `BloomFilter::memory_savings_vs_hashset` for the assumptions.
+fn estimated_hash_set_memory(n: usize, avg_key_bytes: usize) -> usize {
+    const GROUP_WIDTH: usize = 16;
+
+    let bucket_count = match n {
+        0 => 0,
+        1..=3 => 4,
+        4..=7 => 8,
+        _ => (n * 8 / 7).next_power_of_two(),
+    };
+
+    let table_bytes = if bucket_count == 0 {
+        0
+    } else {
+        bucket_count * (mem::size_of::<String>() + 1) + GROUP_WIDTH
+    };
+
+    mem::size_of::<HashSet<String>>() + table_bytes + n * avg_key_bytes
+}
+
+impl<S, B, P> Filter for BloomFilter<S, B, P>
+where
+    S: BuildHasher,
+    B: BitStore,
+    P: ProbeStrategy,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<S, B, P, H> Extend<H> for BloomFilter<S, B, P>
+where
+    S: BuildHasher,
+    B: BitStore,
+    P: ProbeStrategy,
+    H: Hash,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = H>,
+    {
+        for key in iter {
+            self.insert(&key);
+        }
+    }
 }
 
-// Calculates the optimal number of hash functions given the size of the bit array `m` and the
-// expected number of inserted elements `n`.
-fn optimal_number_of_hash_functions(m: usize, n: usize) -> usize {
-    let m = m as f64;
-    let n = n as f64;
-    let k = m / n * f64::consts::LN_2;
-    k.ceil() as usize
-}
+impl<S, B, P> Clone for BloomFilter<S, B, P>
+where
+    S: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            m: self.m,
+            n: self.n,
+            k: self.k,
+            label: self.label,
+            builder_1: self.builder_1.clone(),
+            builder_2: self.builder_2.clone(),
+            probe_strategy: PhantomData,
+            #[cfg(feature = "instrumentation")]
+            probe_stats: self.probe_stats.clone(),
+        }
+    }
+}
+
+// The bit array and hash builders are omitted. The fill is the proportion of set bits.
+impl<S, B, P> fmt::Debug for BloomFilter<S, B, P>
+where
+    B: BitStore,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fill = self.bits.count_ones() as f64 / self.m as f64;
+
+        f.debug_struct("BloomFilter")
+            .field("m", &self.m)
+            .field("n", &self.n)
+            .field("k", &self.k)
+            .field("fill", &format_args!("{:.2}", fill))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SeededHashBuilder, ShadowedBloomFilter};
+
+    #[test]
+    fn test_optimal_required_bits() {
+        let p = 0.01;
+        let n = 128;
+        let m = optimal_required_bits(p, n);
+        assert_eq!(m, 1227);
+
+        assert_eq!(optimal_required_bits(p, 0), 1);
+    }
+
+    #[test]
+    fn test_optimal_number_of_elements() {
+        assert_eq!(optimal_number_of_elements(0.01, 1227), 128);
+        assert_eq!(optimal_number_of_elements(0.01, 1), 1);
+    }
+
+    #[test]
+    fn test_optimal_number_of_hash_functions() {
+        let m = 1227;
+        let n = 128;
+        let k = optimal_number_of_hash_functions(m, n);
+        assert_eq!(k, 7);
+
+        assert_eq!(optimal_number_of_hash_functions(1, 0), 1);
+    }
+
+    #[test]
+    fn test_false_positive_probability() {
+        assert_eq!(false_positive_probability(1227, 7, 0), 0.0);
+
+        let p = false_positive_probability(1227, 7, 128);
+        assert!((p - 0.01).abs() < 0.0005);
+    }
+
+    #[test]
+    fn test_with_equal_hash_builders() {
+        const P: f64 = 0.01;
+        const N: usize = 1000;
+
+        let builder = SeededHashBuilder::new(0);
+        let mut filter =
+            ShadowedBloomFilter::new(BloomFilter::from_fpp_with_hashers(P, N, builder, builder));
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        for i in 0..N + 100_000 {
+            filter.contains(&i);
+        }
+
+        let fpp = filter.observed_fpp();
+        assert!(fpp < 2.0 * P, "{} >= {}", fpp, 2.0 * P);
+    }
+
+    #[test]
+    fn test_estimated_fpp() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_eq!(filter.estimated_fpp(), 0.0);
+
+        let mut prev_fpp = 0.0;
+
+        for i in 0..4096 {
+            filter.insert(&i);
+
+            let fpp = filter.estimated_fpp();
+            assert!(fpp >= prev_fpp, "{} < {}", fpp, prev_fpp);
+            assert!(fpp <= 1.0);
+            prev_fpp = fpp;
+        }
+
+        assert!(prev_fpp > 0.5);
+
+        // m = 1000, k = 5, n = 100: (1 - e^(-0.5))^5
+        let mut filter = BloomFilter::new(1000, 5);
+        filter.n = 100;
+        assert!((filter.estimated_fpp() - 0.009_430_929).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_try_from_fpp() {
+        for &p in &[0.0, 1.0, -0.5, 1.5, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                BloomFilter::try_from_fpp(p, 64).err(),
+                Some(BuildError::InvalidFpp(p))
+            );
+        }
+
+        assert!(matches!(
+            BloomFilter::try_from_fpp(f64::NAN, 64),
+            Err(BuildError::InvalidFpp(p)) if p.is_nan()
+        ));
+
+        assert_eq!(
+            BloomFilter::try_from_fpp(1e-300, usize::MAX).err(),
+            Some(BuildError::CapacityOverflow)
+        );
+
+        let filter = BloomFilter::try_from_fpp(0.0001, 64).unwrap();
+        let expected = BloomFilter::from_fpp(0.0001, 64);
+        assert_eq!(filter.capacity(), expected.capacity());
+        assert_eq!(filter.hash_count(), expected.hash_count());
+    }
+
+    #[test]
+    fn test_try_from_fpp_with_zero_elements() {
+        assert_eq!(
+            BloomFilter::try_from_fpp(0.0001, 0).err(),
+            Some(BuildError::ZeroElements)
+        );
+
+        let result = BloomFilter::try_from_fpp_with_hashers(
+            0.0001,
+            0,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+        assert_eq!(result.err(), Some(BuildError::ZeroElements));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        filter.extend(0..32);
+        filter.extend(&["a", "b"]);
+        filter.extend(vec![String::from("c")]);
+
+        assert_eq!(filter.len(), 35);
+        assert!((0..32).all(|i| filter.contains(&i)));
+        assert!(filter.contains("a"));
+        assert!(filter.contains("b"));
+        assert!(filter.contains("c"));
+    }
+
+    #[test]
+    fn test_from_fpp_iter_with_hashers() {
+        const P: f64 = 0.01;
+        const N: usize = 1000;
+
+        let filter = BloomFilter::from_fpp_iter_with_hashers(
+            P,
+            (0..N).map(|i| i.to_string()),
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        let expected: BloomFilter<SeededHashBuilder> = BloomFilter::from_fpp_with_hashers(
+            P,
+            N,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_eq!(filter.capacity(), expected.capacity());
+        assert_eq!(filter.hash_count(), expected.hash_count());
+        // Keys that are false positives when inserted are not counted.
+        assert!((N - N / 100..=N).contains(&filter.len()));
+        assert!((0..N).all(|i| filter.contains(&i.to_string())));
+
+        let empty = BloomFilter::from_fpp_iter(P, Vec::<u32>::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_bit_count() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_eq!(filter.bit_count(), 0);
+
+        filter.insert("a");
+        assert!((1..=7).contains(&filter.bit_count()));
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.bit_count(), filter.iter_ones().count());
+        assert!(filter.bit_count() <= 7 * filter.len());
+
+        filter.clear();
+        assert_eq!(filter.bit_count(), 0);
+    }
+
+    #[test]
+    fn test_fmt_debug() {
+        let mut filter = BloomFilter::with_hashers(
+            1000,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_eq!(
+            format!("{:?}", filter),
+            "BloomFilter { m: 1000, n: 0, k: 7, fill: 0.00, .. }"
+        );
+
+        filter.insert("a");
+
+        assert_eq!(
+            format!("{:?}", filter),
+            "BloomFilter { m: 1000, n: 1, k: 7, fill: 0.01, .. }"
+        );
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+        .with_label(8);
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        let mut clone = filter.clone();
+        assert_eq!(clone.bits, filter.bits);
+        assert_eq!(clone.label(), Some(8));
+        assert_eq!(clone.builder_1, filter.builder_1);
+        assert_eq!(clone.builder_2, filter.builder_2);
+
+        clone.insert("a");
+
+        assert_eq!(filter.len(), 64);
+        assert_eq!(clone.len(), 65);
+        assert!(clone.contains("a"));
+        assert!(!filter.contains("a"));
+        assert_ne!(clone.bits, filter.bits);
+    }
+
+    #[test]
+    fn test_raw_bytes() -> Result<(), ReadError> {
+        let new_filter = |bytes: &[u8], m| {
+            BloomFilter::from_raw_bytes_with_hashers(
+                bytes,
+                m,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        };
+
+        let mut expected = new_filter(&[0; 154], 1227)?;
+        assert!(expected.is_empty());
+
+        for i in 0..100 {
+            expected.insert(&i);
+        }
+
+        let bytes = expected.to_raw_bytes();
+        assert_eq!(bytes.len(), 154);
+        assert_eq!(
+            bytes,
+            &expected.to_bytes()[HEADER_LEN..HEADER_LEN + 154],
+            "raw bytes must match the binary format payload"
+        );
+
+        let actual = new_filter(&bytes, 1227)?;
+        assert_eq!(actual.bits, expected.bits);
+        assert!((0..100).all(|i| actual.contains(&i)));
+        assert!((actual.len() as f64 - 100.0).abs() < 10.0);
+
+        assert!(matches!(
+            new_filter(&bytes[..153], 1227),
+            Err(ReadError::InvalidParameters)
+        ));
+        assert!(matches!(
+            new_filter(&bytes, 1240),
+            Err(ReadError::InvalidParameters)
+        ));
+        assert!(matches!(
+            new_filter(&[], 0),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_key() {
+        let new_filter = |m| {
+            BloomFilter::with_hashers(m, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+        };
+
+        let mut filters = [new_filter(1227), new_filter(4096), new_filter(100_003)];
+        let mut expected = [new_filter(1227), new_filter(4096), new_filter(100_003)];
+
+        for i in 0..256 {
+            let (h1, h2) = filters[0].hash_key(&i);
+
+            for (filter, expected) in filters.iter_mut().zip(expected.iter_mut()) {
+                assert_eq!(filter.insert_hashes(h1, h2), expected.insert(&i));
+            }
+        }
+
+        for (filter, expected) in filters.iter().zip(&expected) {
+            assert_eq!(filter.bits, expected.bits);
+            assert_eq!(filter.len(), expected.len());
+
+            for i in 0..1024 {
+                let (h1, h2) = filter.hash_key(&i);
+                assert_eq!(filter.contains_hashes(h1, h2), expected.contains(&i));
+            }
+        }
+
+        // equal hashes, e.g., from clones of the same seeded builder
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(0),
+        );
+        let (h1, h2) = filter.hash_key("a");
+        filter.insert_hashes(h1, h2);
+        assert!(filter.contains("a"));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_clear() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+        .with_label(8);
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        let bits_ptr = filter.bits.storage().as_ptr();
+
+        filter.clear();
+
+        assert_eq!(filter.len(), 0);
+        assert!(filter.is_empty());
+        assert_eq!(filter.capacity(), 1227);
+        assert_eq!(filter.hash_count(), 7);
+        assert_eq!(filter.label(), Some(8));
+        assert_eq!(filter.bits.storage().as_ptr(), bits_ptr);
+        assert!(filter.bits.none());
+        assert!((0..64).all(|i| !filter.contains(&i)));
+
+        filter.insert(&0);
+        assert_eq!(filter.len(), 1);
+        assert!(filter.contains(&0));
+    }
 
     #[test]
-    fn test_optimal_required_bits() {
-        let p = 0.01;
-        let n = 128;
-        let m = optimal_required_bits(p, n);
-        assert_eq!(m, 1227);
+    fn test_insert_from_reader() -> io::Result<()> {
+        use std::io::Cursor;
+
+        use crate::double_hasher::READ_CHUNK_SIZE;
+
+        let sizes = [
+            0,
+            1,
+            READ_CHUNK_SIZE - 1,
+            READ_CHUNK_SIZE,
+            READ_CHUNK_SIZE + 1,
+            3 * READ_CHUNK_SIZE + 5,
+        ];
+
+        for &size in &sizes {
+            let data: Vec<u8> = (0..size).map(|i| (i * 31) as u8).collect();
+
+            let mut expected = BloomFilter::with_hashers(
+                1227,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            );
+            assert!(expected.insert_with(|hasher| hasher.write(&data)));
+
+            let mut actual = BloomFilter::with_hashers(
+                1227,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            );
+            assert!(actual.insert_from_reader(Cursor::new(&data))?);
+            assert_eq!(actual.bits, expected.bits, "size = {}", size);
+
+            // a reader that returns short reads
+            let (a, b) = data.split_at(size / 2);
+            assert!(expected.contains_from_reader(a.chain(b))?);
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn test_optimal_number_of_hash_functions() {
-        let m = 1227;
-        let n = 128;
-        let k = optimal_number_of_hash_functions(m, n);
-        assert_eq!(k, 7);
+    fn test_intersection() -> Result<(), IncompatibleFilters> {
+        let new_filter = || {
+            BloomFilter::with_hashers(
+                4096,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        };
+
+        let mut filter = new_filter();
+        let mut other = new_filter();
+
+        for i in 0..128 {
+            filter.insert(&i);
+        }
+
+        for i in 64..256 {
+            other.insert(&i);
+        }
+
+        let mut expected_bits = filter.bits.clone();
+        expected_bits.and(&other.bits);
+
+        filter.intersection(&other)?;
+
+        assert_eq!(filter.bits, expected_bits);
+        assert_eq!(filter.len(), 128);
+        assert!((64..128).all(|i| filter.contains(&i)));
+        assert!((256..1256).filter(|i| filter.contains(i)).count() < 10);
+
+        assert_eq!(
+            filter.intersection(&new_filter().with_label(8)),
+            Err(IncompatibleFilters::Label {
+                expected: None,
+                actual: Some(8),
+            })
+        );
+
+        assert_eq!(filter.bits, expected_bits);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intersection_with_shared_key() -> Result<(), IncompatibleFilters> {
+        let new_filter = || {
+            BloomFilter::with_hashers(
+                4096,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        };
+
+        let mut a = new_filter();
+        a.insert("shared");
+        a.insert("a1");
+        a.insert("a2");
+
+        let mut b = new_filter();
+        b.insert("shared");
+        b.insert("b1");
+        b.insert("b2");
+
+        a.intersection(&b)?;
+
+        assert!(a.contains("shared"));
+        assert!(["a1", "a2", "b1", "b2"].iter().all(|key| !a.contains(key)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union() -> Result<(), UnionError> {
+        let new_filter = || {
+            BloomFilter::with_hashers(
+                1227,
+                7,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        };
+
+        let mut filter = new_filter();
+        let mut shard = new_filter();
+        let mut expected = new_filter();
+
+        for i in 0..64 {
+            filter.insert(&i);
+            expected.insert(&i);
+        }
+
+        for i in 32..128 {
+            shard.insert(&i);
+            expected.insert(&i);
+        }
+
+        filter.union(&shard)?;
+
+        assert_eq!(filter.bits, expected.bits);
+        // The 32 elements in both filters are counted twice.
+        assert_eq!(filter.len(), 64 + 96);
+        assert!((0..128).all(|i| filter.contains(&i)));
+
+        let expected_bits = filter.bits.clone();
+
+        let other = BloomFilter::with_hashers(
+            1228,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert!(matches!(
+            filter.union(&other),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::Capacity { .. }
+            ))
+        ));
+
+        let other = BloomFilter::with_hashers(
+            1227,
+            6,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert!(matches!(
+            filter.union(&other),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::HashCount { .. }
+            ))
+        ));
+
+        let other = new_filter().with_label(8);
+
+        assert!(matches!(
+            filter.union(&other),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::Label { .. }
+            ))
+        ));
+
+        assert_eq!(filter.bits, expected_bits);
+        assert_eq!(filter.len(), 64 + 96);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_from_bytes() -> Result<(), UnionError> {
+        // The bit array spans more than one payload chunk.
+        const M: usize = 100_003;
+
+        let new_filter = || {
+            BloomFilter::with_hashers(M, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+        };
+
+        let build_filter = || {
+            let mut filter = new_filter();
+
+            for i in 0..512 {
+                filter.insert(&i);
+            }
+
+            filter
+        };
+
+        let mut peer = new_filter();
+
+        for i in 256..1024 {
+            peer.insert(&i);
+        }
+
+        let buf = peer.to_bytes();
+
+        let decoded =
+            BloomFilter::from_bytes_with_hashers(&buf, peer.builder_1, peer.builder_2).unwrap();
+        let mut expected_bits = build_filter().bits;
+        expected_bits.or(&decoded.bits);
+
+        let mut actual = build_filter();
+        actual.union_from_bytes(&buf)?;
+        assert_eq!(actual.bits, expected_bits);
+        assert_eq!(actual.len(), 512 + 768);
+        assert!((0..1024).all(|i| actual.contains(&i)));
+
+        let mut actual = build_filter();
+        actual.union_from_reader(&mut &buf[..])?;
+        assert_eq!(actual.bits, expected_bits);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_from_bytes_with_invalid_filters() {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+        filter.insert("a");
+
+        let expected_bits = filter.bits.clone();
+
+        let peer = |m, k, label| {
+            let mut peer = BloomFilter::with_hashers(
+                m,
+                k,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            );
+
+            if let Some(label) = label {
+                peer = peer.with_label(label);
+            }
+
+            peer.insert("b");
+            peer.to_bytes()
+        };
+
+        assert!(matches!(
+            filter.union_from_bytes(&peer(1228, 7, None)),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::Capacity { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            filter.union_from_bytes(&peer(1227, 6, None)),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::HashCount { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            filter.union_from_bytes(&peer(1227, 7, Some(8))),
+            Err(UnionError::IncompatibleFilters(
+                IncompatibleFilters::Label { .. }
+            ))
+        ));
+
+        let mut buf = peer(1227, 7, None);
+        let i = buf.len() - CHECKSUM_LEN - 1;
+        buf[i] ^= 0xff;
+        assert!(matches!(
+            filter.union_from_bytes(&buf),
+            Err(UnionError::InvalidFilter(
+                ReadError::ChecksumMismatch { .. }
+            ))
+        ));
+
+        let buf = peer(1227, 7, None);
+        assert!(matches!(
+            filter.union_from_bytes(&buf[..buf.len() - 1]),
+            Err(UnionError::InvalidFilter(ReadError::Io(_)))
+        ));
+
+        let mut buf = peer(1227, 7, None);
+        buf.push(0);
+        assert!(matches!(
+            filter.union_from_bytes(&buf),
+            Err(UnionError::InvalidFilter(ReadError::TrailingData))
+        ));
+
+        assert_eq!(filter.bits, expected_bits);
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_estimated_hash_set_memory() {
+        let base = mem::size_of::<HashSet<String>>();
+        let bucket = mem::size_of::<String>() + 1;
+
+        assert_eq!(estimated_hash_set_memory(0, 16), base);
+        assert_eq!(
+            estimated_hash_set_memory(3, 16),
+            base + 4 * bucket + 16 + 3 * 16
+        );
+        assert_eq!(estimated_hash_set_memory(7, 0), base + 8 * bucket + 16);
+        assert_eq!(estimated_hash_set_memory(8, 0), base + 16 * bucket + 16);
+        assert_eq!(
+            estimated_hash_set_memory(1000, 0),
+            base + 2048 * bucket + 16
+        );
+    }
+
+    #[test]
+    fn test_memory_savings_vs_hashset() {
+        let mut filter = BloomFilter::from_fpp(0.01, 1000);
+
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+
+        let small_keys = filter.memory_savings_vs_hashset(8);
+        let large_keys = filter.memory_savings_vs_hashset(64);
+
+        assert!(small_keys < 0.05);
+        assert!(large_keys < small_keys);
+    }
+
+    #[test]
+    fn test_from_fpp_with_zero_expected_elements() {
+        let mut filter = BloomFilter::from_fpp(0.01, 0);
+
+        assert_eq!(filter.capacity(), 1);
+        assert_eq!(filter.k, 1);
+
+        assert!(!filter.contains("a"));
+        assert!(filter.insert("a"));
+        assert!(filter.contains("a"));
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_with_and_contains_with() {
+        let mut filter = BloomFilter::from_fpp(0.0001, 64);
+
+        assert!(filter.insert_with(|mut hasher| (8u64, "ndls").hash(&mut hasher)));
+        assert!(filter.contains(&(8u64, "ndls")));
+        assert!(!filter.insert(&(8u64, "ndls")));
+
+        assert!(filter.insert(&(13u64, "bam")));
+        assert!(filter.contains_with(|mut hasher| (13u64, "bam").hash(&mut hasher)));
+
+        assert_eq!(filter.len(), 2);
+    }
+
+    #[test]
+    fn test_match_score() {
+        // an overfilled filter, so that many absent elements are false positives or near misses
+        let mut filter = BloomFilter::new(4096, 6);
+
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+
+        let mut false_positive_count = 0;
+
+        for i in 0..100_000 {
+            let (score, k) = filter.match_score(&i);
+            assert_eq!(k, 6);
+            assert_eq!(score == k, filter.contains(&i), "i = {}", i);
+            assert_eq!(filter.match_fraction(&i) == 1.0, score == k);
+
+            if i >= 1000 && score == k {
+                false_positive_count += 1;
+            }
+        }
+
+        assert!(false_positive_count > 0);
+
+        // Hash builders that differ from the ones used to build the filter only match by chance.
+        let mismatched = BloomFilter::from_store(
+            filter.bits.clone(),
+            filter.k,
+            SeededHashBuilder::new(8),
+            SeededHashBuilder::new(13),
+        );
+
+        let full_match_count = (0..1000)
+            .filter(|i| {
+                let (score, k) = mismatched.match_score(i);
+                score == k
+            })
+            .count();
+
+        assert!(full_match_count < 1000);
+    }
+
+    #[test]
+    fn test_iter_ones() {
+        fn probe_indices<H: Hash + ?Sized>(filter: &BloomFilter, key: &H) -> Vec<usize> {
+            filter.indices(filter.build_hasher(key)).collect()
+        }
+
+        let mut filter = BloomFilter::new(100, 4);
+        assert_eq!(filter.iter_ones().next(), None);
+
+        let keys = ["a", "b", "c"];
+        let mut expected = Vec::new();
+
+        for key in &keys {
+            filter.insert(key);
+            expected.extend(probe_indices(&filter, key));
+        }
+
+        expected.sort_unstable();
+        expected.dedup();
+
+        assert_eq!(filter.iter_ones().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_hash_quality_report() {
+        use std::hash::BuildHasherDefault;
+
+        // Hashes a key to the total number of bytes written.
+        #[derive(Default)]
+        struct LengthHasher(u64);
+
+        impl Hasher for LengthHasher {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                self.0 += bytes.len() as u64;
+            }
+        }
+
+        let keys = || (0..4096).map(|i| format!("key-{}", i));
+
+        let filter = BloomFilter::from_fpp_with_hashers(
+            0.001,
+            4096,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+        let report = filter.hash_quality_report(keys());
+        assert_eq!(report.samples(), 4096);
+        assert_eq!(report.loads().len(), HashQualityReport::BUCKETS);
+        assert!(!report.is_correlated());
+        assert!(report.looks_uniform());
+
+        let filter = BloomFilter::from_fpp_with_hashers(
+            0.001,
+            4096,
+            BuildHasherDefault::<LengthHasher>::default(),
+            BuildHasherDefault::<LengthHasher>::default(),
+        );
+        let report = filter.hash_quality_report(keys());
+        assert!(report.is_correlated());
+        assert!(report.chi_squared() > 1000.0);
+        assert_eq!(report.min_load(), 0);
+        assert!(!report.looks_uniform());
+    }
+
+    #[test]
+    fn test_iou() -> Result<(), IncompatibleFilters> {
+        let mut a = BloomFilter::new(8, 1);
+        let mut b = BloomFilter::new(8, 1);
+        assert_eq!(a.iou(&b)?, 1.0);
+
+        a.bits.set(0, true);
+        a.bits.set(1, true);
+        b.bits.set(1, true);
+        b.bits.set(2, true);
+        b.bits.set(3, true);
+        assert_eq!(a.iou(&b)?, 0.25);
+        assert_eq!(b.iou(&a)?, 0.25);
+
+        assert_eq!(
+            a.iou(&BloomFilter::new(16, 1)),
+            Err(IncompatibleFilters::Capacity {
+                expected: 8,
+                actual: 16
+            })
+        );
+        assert_eq!(
+            a.iou(&BloomFilter::new(8, 2)),
+            Err(IncompatibleFilters::HashCount {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            a.iou(&BloomFilter::new(8, 1).with_label(13)),
+            Err(IncompatibleFilters::Label {
+                expected: None,
+                actual: Some(13)
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut filter = BloomFilter::new(8, 1);
+
+        filter.bits.set(0, true);
+        filter.bits.set(1, true);
+        filter.n = 2;
+
+        assert_eq!(
+            filter.summary(),
+            "BloomFilter { m: 8, k: 1, n: 2, load: 25.0%, est_fpp: 0.2500 }"
+        );
+    }
+
+    #[test]
+    fn test_verify_against() -> io::Result<()> {
+        let mut filter = BloomFilter::from_fpp(0.0001, 64);
+
+        for i in 0..64 {
+            filter.insert(&format!("key-{}", i));
+        }
+
+        let keys: String = (0..64).map(|i| format!("key-{}\r\n", i)).collect();
+        let report = filter.verify_against(keys.as_bytes())?;
+
+        assert!(report.is_ok());
+        assert_eq!(report.checked(), 64);
+        assert_eq!(report.first_false_negative(), None);
+
+        let report = filter.verify_against(&b"key-0\nkey-1\nbbloom\nkey-2"[..])?;
+
+        assert_eq!(report.checked(), 4);
+        assert_eq!(report.false_negatives(), 1);
+        assert_eq!(report.first_false_negative(), Some(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() -> Result<(), ReadError> {
+        let mut filter = BloomFilter::with_hashers(
+            1227,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        filter.insert("a");
+        filter.insert("b");
+
+        let buf = filter.to_bytes();
+        assert_eq!(buf.len(), filter.serialized_len());
+
+        let actual =
+            BloomFilter::from_bytes_with_hashers(&buf, filter.builder_1, filter.builder_2)?;
+
+        assert_eq!(actual.capacity(), 1227);
+        assert_eq!(actual.hash_count(), 7);
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual.bits, filter.bits);
+
+        let mut buf = buf;
+        buf.push(0);
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_bytes(&buf),
+            Err(ReadError::TrailingData)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_bytes_round_trip() -> Result<(), ReadError> {
+        let mut filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::from_fpp_with_hashers(0.001, 1000, Default::default(), Default::default());
+
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+
+        let buf = filter.to_bytes();
+
+        let actual: BloomFilter<SeededHashBuilder> = BloomFilter::from_bytes(&buf)?;
+        assert_eq!(actual.len(), filter.len());
+        assert!((0..1000).all(|i| actual.contains(&i)));
+        assert!((0..10_000).all(|i| actual.contains(&i) == filter.contains(&i)));
+
+        for len in [0, HEADER_LEN - 1, HEADER_LEN, buf.len() - 1] {
+            assert!(matches!(
+                BloomFilter::<SeededHashBuilder>::from_bytes(&buf[..len]),
+                Err(ReadError::Io(_))
+            ));
+        }
+
+        let mut corrupt = buf.clone();
+        corrupt[HEADER_LEN] ^= 0x01;
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_bytes(&corrupt),
+            Err(ReadError::ChecksumMismatch { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_path_and_read_from_path() -> Result<(), ReadError> {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("bbloom-bloom-filter-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("filter.bin");
+
+        let mut filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+        filter.insert("a");
+        filter.write_to_path(&path)?;
+
+        let actual: BloomFilter<SeededHashBuilder> = BloomFilter::read_from_path(&path)?;
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual.bits, filter.bits);
+
+        filter.insert("b");
+        filter.write_to_path(&path)?;
+
+        let actual: BloomFilter<SeededHashBuilder> = BloomFilter::read_from_path(&path)?;
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual.bits, filter.bits);
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        let mut buf = filter.to_bytes();
+        buf.push(0);
+        fs::write(&path, &buf)?;
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::read_from_path(&path),
+            Err(ReadError::TrailingData)
+        ));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes_with_label() -> Result<(), ReadError> {
+        let mut filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::with_hashers(64, 3, Default::default(), Default::default()).with_label(8);
+        filter.insert("a");
+
+        let buf = filter.to_bytes();
+        assert_eq!(buf.len(), filter.serialized_len());
+
+        let actual: BloomFilter<SeededHashBuilder> = BloomFilter::from_bytes(&buf)?;
+        assert_eq!(actual.label(), Some(8));
+        assert!(actual.contains("a"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes_with_probe_strategy() -> Result<(), ReadError> {
+        use crate::probe_strategy::EnhancedDoubleHashing;
+
+        type Filter = BloomFilter<SeededHashBuilder, BitVec, EnhancedDoubleHashing>;
+
+        let mut filter: Filter =
+            BloomFilter::with_hashers(1227, 7, Default::default(), Default::default())
+                .with_probe_strategy();
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        let default_filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+        assert_ne!(
+            filter.iter_ones().collect::<Vec<_>>(),
+            default_filter.iter_ones().collect::<Vec<_>>()
+        );
+
+        let buf = filter.to_bytes();
+        assert_eq!(buf.len(), filter.serialized_len());
+
+        let actual = Filter::from_bytes(&buf)?;
+        assert_eq!(actual.bits, filter.bits);
+
+        for i in 0..64 {
+            assert!(actual.contains(&i));
+        }
+
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_bytes(&buf),
+            Err(ReadError::ProbeStrategyMismatch {
+                actual: 1,
+                expected: 0
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64_and_hex() -> Result<(), DecodeError> {
+        const BASE64: &str = "QkJMTQEAEAAAAAAAAAACAAAAAAAAAAEAAAAAAAAAAgAAAAAAAAABEG8uKSY=";
+        const HEX: &str = "42424c4d0100100000000000000002000000000000000100000000000000020000000000000001106f2e2926";
+
+        let mut filter =
+            BloomFilter::with_hashers(16, 2, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+        filter.insert("a");
+
+        assert_eq!(filter.to_base64(), BASE64);
+        assert_eq!(filter.to_hex(), HEX);
+
+        let url_safe = filter.to_base64_url();
+        assert_eq!(url_safe, BASE64.trim_end_matches('='));
+
+        for s in &[BASE64, &url_safe] {
+            let actual: BloomFilter<SeededHashBuilder> = BloomFilter::from_base64(s)?;
+            assert_eq!(actual.bits, filter.bits);
+        }
+
+        let actual: BloomFilter<SeededHashBuilder> = BloomFilter::from_hex(HEX)?;
+        assert_eq!(actual.bits, filter.bits);
+
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_base64("QkJMTQ!"),
+            Err(DecodeError::InvalidEncoding)
+        ));
+
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_base64("QkJMTQ"),
+            Err(DecodeError::InvalidFilter(_))
+        ));
+
+        assert!(matches!(
+            BloomFilter::<SeededHashBuilder>::from_hex("42424c4"),
+            Err(DecodeError::InvalidEncoding)
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "instrumentation")]
+    #[test]
+    fn test_avg_probes() {
+        let mut filter = BloomFilter::from_fpp(0.0001, 64);
+
+        for i in 0..16 {
+            filter.insert(&i);
+        }
+
+        for i in 16..1016 {
+            filter.contains(&i);
+        }
+
+        let avg_probes = filter.avg_probes();
+        assert!((1.0..1.5).contains(&avg_probes));
     }
 }
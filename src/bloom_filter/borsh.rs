@@ -0,0 +1,198 @@
+//! Borsh support for [`BloomFilter`].
+//!
+//! A filter is serialized as the following fields, in order:
+//!
+//! | name     | type          | description                             |
+//! |----------|---------------|-----------------------------------------|
+//! | `m`      | `u64`         | size of the bit array                   |
+//! | `k`      | `u64`         | number of hash functions                |
+//! | `n`      | `u64`         | number of inserted elements             |
+//! | `seed_1` | `u64`         | seed of the first hash builder          |
+//! | `seed_2` | `u64`         | seed of the second hash builder         |
+//! | `label`  | `Option<u64>` | user-defined label                      |
+//! | `words`  | `Vec<u64>`    | bit array, `ceil(m / 64)` 64-bit words  |
+//!
+//! In `words`, bit `i` of the bit array is bit `i % 64` of word `i / 64`. Any trailing bits in
+//! the last word are 0.
+//!
+//! Only filters using [`SeededHashBuilder`] are supported, since the hash builders are part of
+//! the encoding. Deserialization validates the parameters like the binary format (see
+//! [`crate::io`]): `m` and `k` must be > 0, and the length of `words` must match `m`. The words
+//! are read incrementally, so a corrupt length fails at the end of the input rather than
+//! allocating for the length.
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use bit_vec::BitVec;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::BloomFilter;
+use crate::{bit_store::BitStore, probe_strategy::ProbeStrategy, SeededHashBuilder};
+
+// The number of words preallocated at a time when reading the bit array.
+const WORD_CHUNK_LEN: usize = 1024;
+
+impl BorshSerialize for BloomFilter<SeededHashBuilder> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        serialize(self, writer)
+    }
+}
+
+impl BorshDeserialize for BloomFilter<SeededHashBuilder> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let m = read_usize(reader)?;
+        let k = read_usize(reader)?;
+        let n = read_usize(reader)?;
+        let seed_1 = u64::deserialize_reader(reader)?;
+        let seed_2 = u64::deserialize_reader(reader)?;
+        let label = Option::<u64>::deserialize_reader(reader)?;
+
+        if m == 0 || k == 0 {
+            return Err(invalid_data("invalid filter parameters"));
+        }
+
+        let word_count = m.div_ceil(64);
+        let len = u32::deserialize_reader(reader)?;
+
+        if usize::try_from(len).ok() != Some(word_count) {
+            return Err(invalid_data("invalid bit array length"));
+        }
+
+        let mut words = Vec::new();
+
+        while words.len() < word_count {
+            words.reserve((word_count - words.len()).min(WORD_CHUNK_LEN));
+            words.push(u64::deserialize_reader(reader)?);
+        }
+
+        let mut bytes: Vec<_> = words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .map(u8::reverse_bits)
+            .collect();
+        bytes.truncate(m.div_ceil(8));
+
+        let mut bits = BitVec::from_bytes(&bytes);
+        bits.truncate(m);
+
+        let mut filter = Self::from_store(
+            bits,
+            k,
+            SeededHashBuilder::new(seed_1),
+            SeededHashBuilder::new(seed_2),
+        );
+        filter.n = n;
+        filter.label = label;
+
+        Ok(filter)
+    }
+}
+
+// Serializes a filter with any bit store.
+pub(crate) fn serialize<B, P, W>(
+    filter: &BloomFilter<SeededHashBuilder, B, P>,
+    writer: &mut W,
+) -> io::Result<()>
+where
+    B: BitStore,
+    P: ProbeStrategy,
+    W: Write,
+{
+    (filter.m as u64).serialize(writer)?;
+    (filter.k as u64).serialize(writer)?;
+    (filter.n as u64).serialize(writer)?;
+    filter.builder_1.seed().serialize(writer)?;
+    filter.builder_2.seed().serialize(writer)?;
+    filter.label.serialize(writer)?;
+    filter.to_words().serialize(writer)
+}
+
+pub(crate) fn read_usize<R>(reader: &mut R) -> io::Result<usize>
+where
+    R: Read,
+{
+    let n = u64::deserialize_reader(reader)?;
+    usize::try_from(n).map_err(|_| invalid_data("value does not fit in usize"))
+}
+
+pub(crate) fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_filter() -> BloomFilter<SeededHashBuilder> {
+        let mut filter = BloomFilter::from_fpp_with_hashers(
+            0.001,
+            128,
+            SeededHashBuilder::new(2),
+            SeededHashBuilder::new(3),
+        )
+        .with_label(8);
+
+        for i in 0..128 {
+            filter.insert(&i);
+        }
+
+        filter
+    }
+
+    #[test]
+    fn test_round_trip() -> io::Result<()> {
+        let filter = build_filter();
+
+        let buf = borsh::to_vec(&filter)?;
+        let word_count = filter.capacity().div_ceil(64);
+        assert_eq!(buf.len(), 8 * 5 + 9 + 4 + 8 * word_count);
+
+        let actual: BloomFilter<SeededHashBuilder> = borsh::from_slice(&buf)?;
+
+        assert_eq!(actual.capacity(), filter.capacity());
+        assert_eq!(actual.hash_count(), filter.hash_count());
+        assert_eq!(actual.len(), filter.len());
+        assert_eq!(actual.label(), Some(8));
+        assert_eq!(actual.builder_1.seed(), 2);
+        assert_eq!(actual.builder_2.seed(), 3);
+        assert_eq!(actual.to_bytes(), filter.to_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_input() -> io::Result<()> {
+        let buf = borsh::to_vec(&build_filter())?;
+
+        // m = 0
+        let mut data = buf.clone();
+        data[..8].copy_from_slice(&0u64.to_le_bytes());
+        assert!(borsh::from_slice::<BloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // k = 0
+        let mut data = buf.clone();
+        data[8..16].copy_from_slice(&0u64.to_le_bytes());
+        assert!(borsh::from_slice::<BloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // The length prefix of the bit array does not match m.
+        let mut data = buf.clone();
+        data[49..53].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(borsh::from_slice::<BloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // m and the length prefix agree but are much larger than the input.
+        let mut data = buf.clone();
+        data[..8].copy_from_slice(&(64 * u64::from(u32::MAX)).to_le_bytes());
+        data[49..53].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(borsh::from_slice::<BloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // truncated
+        assert!(
+            borsh::from_slice::<BloomFilter<SeededHashBuilder>>(&buf[..buf.len() - 1]).is_err()
+        );
+
+        Ok(())
+    }
+}
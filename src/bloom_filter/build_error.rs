@@ -0,0 +1,28 @@
+use std::{error, fmt};
+
+/// An error returned when a Bloom filter cannot be created from a false positive probability.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildError {
+    /// The false positive probability is not in (0.0, 1.0).
+    InvalidFpp(f64),
+    /// The expected number of inserted elements is 0.
+    ZeroElements,
+    /// The size of the bit array overflows.
+    CapacityOverflow,
+}
+
+impl error::Error for BuildError {}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFpp(p) => write!(
+                f,
+                "invalid false positive probability: expected (0.0, 1.0), got {}",
+                p
+            ),
+            Self::ZeroElements => f.write_str("expected number of elements is 0"),
+            Self::CapacityOverflow => f.write_str("capacity overflow"),
+        }
+    }
+}
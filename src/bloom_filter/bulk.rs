@@ -0,0 +1,218 @@
+//! Bulk construction from precomputed digests.
+//!
+//! A digest is the pair of hashes of a key from the two hash builders of a filter, i.e.,
+//! `(builder_1.hash_one(key), builder_2.hash_one(key))`. Inserting a digest sets the same bits as
+//! inserting the key.
+//!
+//! Inserting keys one at a time writes to random positions across the bit array, which is
+//! dominated by cache and TLB misses for large filters. Instead, digests are processed in chunks:
+//! the probe indices of a chunk are expanded and partitioned by range of the bit array, and then
+//! bits are set one range at a time, where each range fits in the cache.
+
+use std::hash::BuildHasher;
+
+use bit_vec::BitVec;
+
+use super::{optimal_number_of_hash_functions, optimal_required_bits, BloomFilter};
+use crate::{bit_store::BitStore, double_hasher::DoubleHasher, probe_strategy::ProbeStrategy};
+
+// The number of digests expanded and partitioned at a time.
+const CHUNK_LEN: usize = 1 << 20;
+
+// The base-2 logarithm of the number of bits in a range, i.e., 128 KiB.
+const RANGE_SHIFT: usize = 20;
+
+impl<S> BloomFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Creates a new bloom filter that targets a false positive probability `p` ([0.0, 1.0]) with
+    /// an expected number of inserted elements `n` and sets the bits of `digests`.
+    ///
+    /// The hash builders are created using [`Default`], so the digests must be computed using
+    /// default hash builders, e.g., [`SeededHashBuilder::default`](crate::SeededHashBuilder).
+    /// See [`Self::bulk_set`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::hash::BuildHasher;
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let builder_1 = SeededHashBuilder::default();
+    /// let builder_2 = SeededHashBuilder::default();
+    ///
+    /// let digests = ["a", "b"]
+    ///     .iter()
+    ///     .map(|key| (builder_1.hash_one(key), builder_2.hash_one(key)));
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> = BloomFilter::bulk_build(0.0001, 64, digests);
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// assert!(!filter.contains("c"));
+    /// ```
+    pub fn bulk_build<I>(p: f64, n: usize, digests: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let m = optimal_required_bits(p, n);
+        let k = optimal_number_of_hash_functions(m, n);
+
+        let mut filter =
+            Self::from_store(BitVec::from_elem(m, false), k, S::default(), S::default());
+
+        filter.bulk_set(digests);
+
+        filter
+    }
+}
+
+impl<S, B, P> BloomFilter<S, B, P>
+where
+    S: BuildHasher,
+    B: BitStore,
+    P: ProbeStrategy,
+{
+    /// Sets the bits of `digests`.
+    ///
+    /// A digest is the pair of hashes of a key from the two hash builders of the filter, i.e.,
+    /// `(builder_1.hash_one(key), builder_2.hash_one(key))`. The resulting bit array is identical
+    /// to inserting each key, but bits are set one cache-sized range of the bit array at a time,
+    /// which is much faster for large filters. Digests are processed in chunks, so memory use is
+    /// bounded regardless of the number of digests.
+    ///
+    /// Since bits are not set in insertion order, whether a digest was previously in the filter is
+    /// unknown. Every digest is counted as an inserted element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::hash::BuildHasher;
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let builder_1 = SeededHashBuilder::new(0);
+    /// let builder_2 = SeededHashBuilder::new(1);
+    ///
+    /// let mut filter = BloomFilter::from_fpp_with_hashers(0.0001, 64, builder_1, builder_2);
+    ///
+    /// let digests = ["a", "b"]
+    ///     .iter()
+    ///     .map(|key| (builder_1.hash_one(key), builder_2.hash_one(key)));
+    ///
+    /// filter.bulk_set(digests);
+    ///
+    /// assert_eq!(filter.len(), 2);
+    /// assert!(filter.contains("a"));
+    /// assert!(filter.contains("b"));
+    /// ```
+    pub fn bulk_set<I>(&mut self, digests: I)
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let range_count = (self.m >> RANGE_SHIFT) + 1;
+
+        let mut digests = digests.into_iter();
+        let mut indices = Vec::new();
+        let mut partitioned_indices = Vec::new();
+        let mut offsets = vec![0; range_count];
+
+        loop {
+            indices.clear();
+
+            let mut digest_count = 0;
+
+            for (h1, h2) in digests.by_ref().take(CHUNK_LEN) {
+                let hasher = DoubleHasher::from_hashes(h1, h2);
+                indices.extend(self.indices(hasher));
+                digest_count += 1;
+            }
+
+            if digest_count == 0 {
+                break;
+            }
+
+            // Partition the indices by range using a counting sort.
+            offsets.iter_mut().for_each(|offset| *offset = 0);
+
+            for &i in &indices {
+                offsets[i >> RANGE_SHIFT] += 1;
+            }
+
+            let mut start = 0;
+
+            for offset in &mut offsets {
+                let count = *offset;
+                *offset = start;
+                start += count;
+            }
+
+            partitioned_indices.resize(indices.len(), 0);
+
+            for &i in &indices {
+                let offset = &mut offsets[i >> RANGE_SHIFT];
+                partitioned_indices[*offset] = i;
+                *offset += 1;
+            }
+
+            for &i in &partitioned_indices {
+                self.bits.set(i);
+            }
+
+            self.n += digest_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[test]
+    fn test_bulk_set_matches_insert() {
+        const N: usize = CHUNK_LEN + 1000;
+
+        let builder_1 = SeededHashBuilder::new(0);
+        let builder_2 = SeededHashBuilder::new(1);
+
+        let mut expected = BloomFilter::from_fpp_with_hashers(0.01, N, builder_1, builder_2);
+        let mut actual = BloomFilter::from_fpp_with_hashers(0.01, N, builder_1, builder_2);
+
+        for i in 0..N {
+            expected.insert(&i);
+        }
+
+        actual.bulk_set((0..N).map(|i| (builder_1.hash_one(i), builder_2.hash_one(i))));
+
+        assert_eq!(actual.bits_to_bytes(), expected.bits_to_bytes());
+        assert_eq!(actual.len(), N);
+    }
+
+    #[test]
+    fn test_bulk_set_with_equal_hashes() {
+        let builder = SeededHashBuilder::new(0);
+
+        let mut expected = BloomFilter::from_fpp_with_hashers(0.01, 1000, builder, builder);
+        let mut actual = BloomFilter::from_fpp_with_hashers(0.01, 1000, builder, builder);
+
+        for i in 0..1000 {
+            expected.insert(&i);
+        }
+
+        actual.bulk_set((0..1000).map(|i| (builder.hash_one(i), builder.hash_one(i))));
+
+        assert_eq!(actual.bits_to_bytes(), expected.bits_to_bytes());
+    }
+
+    #[test]
+    fn test_bulk_build() {
+        let builder = SeededHashBuilder::default();
+        let digests = (0..1000).map(|i| (builder.hash_one(i), builder.hash_one(i)));
+
+        let filter: BloomFilter<SeededHashBuilder> = BloomFilter::bulk_build(0.01, 1000, digests);
+
+        assert_eq!(filter.len(), 1000);
+        assert!((0..1000).all(|i| filter.contains(&i)));
+    }
+}
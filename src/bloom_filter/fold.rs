@@ -0,0 +1,373 @@
+//! Combining filters of different sizes.
+//!
+//! A probe strategy reduces a hash to an index using a [`Reduction`]. For a modulo reduction, when
+//! `m` is a multiple of `m'`, `(h mod m) mod m' = h mod m'`, so a filter with `m` bits can be
+//! folded to `m'` bits by setting bit `i mod m'` for every set bit `i`. Halving a filter is OR-ing
+//! its upper half into its lower half. For a multiply-shift reduction, when `m = r * m'`,
+//! `floor(floor(h * m / 2^64) / r) = floor(h * m' / 2^64)`, so bit `i` maps to bit `i / r`, and
+//! halving a filter is OR-ing adjacent pairs of bits. Either way, the folded filter has the same
+//! bits as if its elements had been inserted into a filter with `m'` bits.
+
+use std::hash::BuildHasher;
+
+use bit_vec::BitVec;
+
+use super::{check_compatible, BloomFilter, FoldReport, IncompatibleFilters};
+use crate::{
+    bit_store::BitStore,
+    probe_strategy::{ProbeStrategy, Reduction},
+};
+
+const WORD_BITS: usize = 64;
+
+impl<S, P> BloomFilter<S, BitVec, P>
+where
+    S: BuildHasher,
+    P: ProbeStrategy,
+{
+    /// Adds all elements of `other` to this filter, where the sizes of the bit arrays `m` can
+    /// differ by a power of two.
+    ///
+    /// The larger of the two filters is folded down to the size of the smaller one before they
+    /// are combined. If this filter is the larger one, it shrinks. The larger filter is never
+    /// copied: `other` is folded as it is read, and this filter is folded into a new bit array.
+    ///
+    /// Folding a filter keeps all of its elements but raises its load, and with it, the false
+    /// positive probability. The returned report includes the number of times the larger filter
+    /// was halved and its estimated false positive probability before and after.
+    ///
+    /// As with [`Self::union_from_reader`], the number of elements of `other` is added to the
+    /// number of elements of this filter, which may overcount elements that are in both.
+    ///
+    /// # Errors
+    ///
+    /// The larger `m` must be the smaller `m` times a power of two, and the filters must have the
+    /// same `k` and label (see [`IncompatibleFilters`]). They must also have been built with the
+    /// same hash builders, which cannot be checked. This filter is unchanged if an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SeededHashBuilder};
+    ///
+    /// let new_filter =
+    ///     |m| BloomFilter::with_hashers(m, 7, SeededHashBuilder::new(0), SeededHashBuilder::new(1));
+    ///
+    /// let mut edge = new_filter(1024);
+    /// edge.insert("a");
+    ///
+    /// let mut filter = new_filter(4096);
+    /// filter.insert("b");
+    ///
+    /// let report = edge.union_folded(&filter)?;
+    ///
+    /// assert_eq!(report.fold_count(), 2);
+    /// assert_eq!(edge.capacity(), 1024);
+    /// assert!(edge.contains("a"));
+    /// assert!(edge.contains("b"));
+    /// # Ok::<(), bbloom::IncompatibleFilters>(())
+    /// ```
+    pub fn union_folded<B>(
+        &mut self,
+        other: &BloomFilter<S, B, P>,
+    ) -> Result<FoldReport, IncompatibleFilters>
+    where
+        B: BitStore,
+    {
+        let (min_m, max_m) = if self.m <= other.m {
+            (self.m, other.m)
+        } else {
+            (other.m, self.m)
+        };
+
+        if !max_m.is_multiple_of(min_m) || !(max_m / min_m).is_power_of_two() {
+            return Err(IncompatibleFilters::Capacity {
+                expected: self.m,
+                actual: other.m,
+            });
+        }
+
+        check_compatible((min_m, self.k, self.label), (min_m, other.k, other.label))?;
+
+        let fold_count = (max_m / min_m).trailing_zeros();
+        let other_words = fold_words(&other.bits, min_m, P::REDUCTION);
+
+        let (unfolded_fpp, folded_fpp) = if self.m > other.m {
+            let unfolded_fpp = self.load_fpp();
+
+            let mut words = fold_words(&self.bits, min_m, P::REDUCTION);
+            let folded_fpp = words_load_fpp(&words, min_m, self.k);
+
+            for (word, other_word) in words.iter_mut().zip(&other_words) {
+                *word |= other_word;
+            }
+
+            let mut bits = BitVec::from_elem(min_m, false);
+
+            for (i, &word) in words.iter().enumerate() {
+                if word != 0 {
+                    bits.set_word(i, word);
+                }
+            }
+
+            self.bits = bits;
+            self.m = min_m;
+
+            (unfolded_fpp, folded_fpp)
+        } else {
+            for (i, &word) in other_words.iter().enumerate() {
+                if word != 0 {
+                    self.bits.set_word(i, self.bits.word(i) | word);
+                }
+            }
+
+            let unfolded_fpp = other.load_fpp();
+            let folded_fpp = words_load_fpp(&other_words, min_m, other.k);
+
+            (unfolded_fpp, folded_fpp)
+        };
+
+        self.n = self.n.saturating_add(other.n);
+
+        Ok(FoldReport {
+            fold_count,
+            capacity: min_m,
+            unfolded_fpp,
+            folded_fpp,
+        })
+    }
+}
+
+// Folds a bit array to `m` bits, where `m` divides its length.
+fn fold_words<B>(bits: &B, m: usize, reduction: Reduction) -> Vec<u64>
+where
+    B: BitStore,
+{
+    let word_count = m.div_ceil(WORD_BITS);
+    let mut words = vec![0; word_count];
+
+    if reduction == Reduction::Modulo && m.is_multiple_of(WORD_BITS) {
+        for i in 0..bits.word_count() {
+            words[i % word_count] |= bits.word(i);
+        }
+    } else {
+        let ratio = bits.len_bits() / m;
+
+        for i in 0..bits.word_count() {
+            let mut word = bits.word(i);
+
+            while word != 0 {
+                let j = WORD_BITS * i + word.trailing_zeros() as usize;
+
+                let j = match reduction {
+                    Reduction::Modulo => j % m,
+                    Reduction::MultiplyShift => j / ratio,
+                };
+
+                words[j / WORD_BITS] |= 1 << (j % WORD_BITS);
+                word &= word - 1;
+            }
+        }
+    }
+
+    words
+}
+
+fn words_load_fpp(words: &[u64], m: usize, k: usize) -> f64 {
+    let ones: usize = words.iter().map(|word| word.count_ones() as usize).sum();
+    (ones as f64 / m as f64).powi(k as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        probe_strategy::{EnhancedDoubleHashing, FastRangeDoubleHashing},
+        SeededHashBuilder,
+    };
+
+    fn new_filter(m: usize) -> BloomFilter<SeededHashBuilder> {
+        BloomFilter::with_hashers(m, 4, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+    }
+
+    #[test]
+    fn test_union_folded() -> Result<(), IncompatibleFilters> {
+        let mut large = new_filter(1 << 16);
+
+        for i in 0..2000 {
+            large.insert(&i);
+        }
+
+        let new_small_filter = || {
+            let mut filter = new_filter(1 << 14);
+
+            for i in 2000..2500 {
+                filter.insert(&i);
+            }
+
+            filter
+        };
+
+        // folding the other filter
+        let mut actual = new_small_filter();
+        let report = actual.union_folded(&large)?;
+
+        assert_eq!(report.fold_count(), 2);
+        assert_eq!(report.capacity(), 1 << 14);
+        assert!(report.fpp_inflation() > 1.0);
+        assert_eq!(actual.capacity(), 1 << 14);
+        assert_eq!(actual.len(), 2500);
+        assert!((0..2500).all(|i| actual.contains(&i)));
+
+        // The folded filter is identical to inserting all elements into the smaller filter.
+        let mut expected = new_filter(1 << 14);
+
+        for i in 0..2500 {
+            expected.insert(&i);
+        }
+
+        assert_eq!(actual.bits_to_bytes(), expected.bits_to_bytes());
+
+        // folding the receiver
+        let report = large.union_folded(&new_small_filter())?;
+
+        assert_eq!(report.fold_count(), 2);
+        assert_eq!(large.capacity(), 1 << 14);
+        assert_eq!(large.bits_to_bytes(), expected.bits_to_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_folded_with_small_filters() -> Result<(), IncompatibleFilters> {
+        let new_filter = |m| {
+            BloomFilter::with_hashers(m, 3, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+                .with_probe_strategy::<EnhancedDoubleHashing>()
+        };
+
+        let mut actual = new_filter(24);
+        let mut other = new_filter(96);
+        let mut expected = new_filter(24);
+
+        for i in 0..4 {
+            actual.insert(&i);
+            expected.insert(&i);
+        }
+
+        for i in 4..8 {
+            other.insert(&i);
+            expected.insert(&i);
+        }
+
+        let report = actual.union_folded(&other)?;
+
+        assert_eq!(report.fold_count(), 2);
+        assert_eq!(actual.bits_to_bytes(), expected.bits_to_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_folded_with_multiply_shift_reduction() -> Result<(), IncompatibleFilters> {
+        let new_filter = |m| {
+            BloomFilter::with_hashers(m, 4, SeededHashBuilder::new(0), SeededHashBuilder::new(1))
+                .with_probe_strategy::<FastRangeDoubleHashing>()
+        };
+
+        for &(small_m, large_m) in &[(1 << 12, 1 << 14), (1000, 8000)] {
+            let mut actual = new_filter(small_m);
+            let mut other = new_filter(large_m);
+            let mut expected = new_filter(small_m);
+
+            for i in 0..200 {
+                actual.insert(&i);
+                expected.insert(&i);
+            }
+
+            for i in 200..400 {
+                other.insert(&i);
+                expected.insert(&i);
+            }
+
+            actual.union_folded(&other)?;
+            assert_eq!(actual.bits_to_bytes(), expected.bits_to_bytes());
+
+            // folding the receiver
+            let mut other = new_filter(large_m);
+
+            for i in 200..400 {
+                other.insert(&i);
+            }
+
+            let mut small = new_filter(small_m);
+
+            for i in 0..200 {
+                small.insert(&i);
+            }
+
+            other.union_folded(&small)?;
+            assert_eq!(other.bits_to_bytes(), expected.bits_to_bytes());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_folded_with_same_size() -> Result<(), IncompatibleFilters> {
+        let mut filter = new_filter(1024);
+        filter.insert("a");
+
+        let mut other = new_filter(1024);
+        other.insert("b");
+
+        let report = filter.union_folded(&other)?;
+
+        assert_eq!(report.fold_count(), 0);
+        assert_eq!(report.fpp_inflation(), 1.0);
+        assert!(filter.contains("a"));
+        assert!(filter.contains("b"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_folded_with_incompatible_filters() {
+        let mut filter = new_filter(1024);
+        filter.insert("a");
+
+        assert_eq!(
+            filter.union_folded(&new_filter(3072)),
+            Err(IncompatibleFilters::Capacity {
+                expected: 1024,
+                actual: 3072,
+            })
+        );
+
+        assert_eq!(
+            filter.union_folded(&new_filter(1000)),
+            Err(IncompatibleFilters::Capacity {
+                expected: 1024,
+                actual: 1000,
+            })
+        );
+
+        let other = BloomFilter::with_hashers(
+            4096,
+            5,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_eq!(
+            filter.union_folded(&other),
+            Err(IncompatibleFilters::HashCount {
+                expected: 4,
+                actual: 5,
+            })
+        );
+
+        assert_eq!(filter.capacity(), 1024);
+        assert!(filter.contains("a"));
+    }
+}
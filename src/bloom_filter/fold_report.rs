@@ -0,0 +1,49 @@
+/// The result of combining filters of different sizes.
+///
+/// See [`BloomFilter::union_folded`](crate::BloomFilter::union_folded).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FoldReport {
+    pub(crate) fold_count: u32,
+    pub(crate) capacity: usize,
+    pub(crate) unfolded_fpp: f64,
+    pub(crate) folded_fpp: f64,
+}
+
+impl FoldReport {
+    /// Returns the number of times the larger filter was halved, i.e., the base-2 logarithm of the
+    /// ratio of the sizes of the bit arrays.
+    ///
+    /// This is 0 if the filters have the same size.
+    pub fn fold_count(&self) -> u32 {
+        self.fold_count
+    }
+
+    /// Returns the size of the bit array `m` of the combined filter.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the estimated false positive probability of the larger filter before it was
+    /// folded, given its load.
+    pub fn unfolded_fpp(&self) -> f64 {
+        self.unfolded_fpp
+    }
+
+    /// Returns the estimated false positive probability of the larger filter after it was folded,
+    /// given its load.
+    pub fn folded_fpp(&self) -> f64 {
+        self.folded_fpp
+    }
+
+    /// Returns the ratio of the estimated false positive probability of the larger filter after it
+    /// was folded to before.
+    ///
+    /// This is 1.0 if the filters have the same size or the larger filter is empty.
+    pub fn fpp_inflation(&self) -> f64 {
+        if self.unfolded_fpp == 0.0 {
+            1.0
+        } else {
+            self.folded_fpp / self.unfolded_fpp
+        }
+    }
+}
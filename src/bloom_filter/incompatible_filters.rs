@@ -0,0 +1,67 @@
+use std::{error, fmt};
+
+/// An error returned when two filters cannot be combined or compared bit by bit.
+///
+/// Two filters are compatible when they have the same size of the bit array `m`, number of hash
+/// functions `k`, and label. Two quotient filters are compatible when their fingerprints have the
+/// same number of bits. Filters must also use the same hash builders, which cannot be checked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IncompatibleFilters {
+    /// The sizes of the bit arrays differ.
+    Capacity {
+        /// The size of the bit array of the receiver.
+        expected: usize,
+        /// The size of the bit array of the other filter.
+        actual: usize,
+    },
+    /// The numbers of hash functions differ.
+    HashCount {
+        /// The number of hash functions of the receiver.
+        expected: usize,
+        /// The number of hash functions of the other filter.
+        actual: usize,
+    },
+    /// The labels differ.
+    Label {
+        /// The label of the receiver.
+        expected: Option<u64>,
+        /// The label of the other filter.
+        actual: Option<u64>,
+    },
+    /// The numbers of fingerprint bits differ.
+    FingerprintBits {
+        /// The number of fingerprint bits of the receiver.
+        expected: u32,
+        /// The number of fingerprint bits of the other filter.
+        actual: u32,
+    },
+}
+
+impl error::Error for IncompatibleFilters {}
+
+impl fmt::Display for IncompatibleFilters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Capacity { expected, actual } => write!(
+                f,
+                "capacity mismatch: expected {}, got {}",
+                expected, actual
+            ),
+            Self::HashCount { expected, actual } => write!(
+                f,
+                "hash count mismatch: expected {}, got {}",
+                expected, actual
+            ),
+            Self::Label { expected, actual } => write!(
+                f,
+                "label mismatch: expected {:?}, got {:?}",
+                expected, actual
+            ),
+            Self::FingerprintBits { expected, actual } => write!(
+                f,
+                "fingerprint bits mismatch: expected {}, got {}",
+                expected, actual
+            ),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Memory-mapped bit array support for [`BloomFilter`].
+
+use std::{
+    fs::File,
+    hash::BuildHasher,
+    io::{self, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+use bit_vec::BitVec;
+use memmap2::Mmap;
+
+use super::BloomFilter;
+use crate::{
+    bit_store::{BitStore, MappedBits},
+    io::{path, read_bloom_filter_payload, write_bloom_filter, Header, ReadError, CHECKSUM_LEN},
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+};
+
+impl<S> BloomFilter<S, MappedBits>
+where
+    S: BuildHasher,
+{
+    /// Maps a filter in the binary format (see [`crate::io`]) from a file at `path`, using
+    /// `builder_1` and `builder_2` to hash the data.
+    ///
+    /// The bit array is not read into memory. It is probed in place, and the operating system
+    /// loads the pages of the file as they are needed. The filter is read-only (see
+    /// [`MappedBits`]). The file is read once to validate it.
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the filter
+    /// exists. Doing so is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{bit_store::MappedBits, BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<_, MappedBits> = unsafe {
+    ///     BloomFilter::map_path_with_hashers(
+    ///         "filter.bin",
+    ///         SeededHashBuilder::new(0),
+    ///         SeededHashBuilder::new(1),
+    ///     )?
+    /// };
+    ///
+    /// filter.contains("a");
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub unsafe fn map_path_with_hashers<Q>(
+        path: Q,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError>
+    where
+        Q: AsRef<Path>,
+    {
+        let (header, bits) = map_bits(path)?;
+
+        let mut filter = Self::from_store(bits, header.k, builder_1, builder_2);
+        filter.n = header.n;
+        filter.label = header.label;
+
+        Ok(filter)
+    }
+}
+
+impl<S> BloomFilter<S, MappedBits>
+where
+    S: BuildHasher + Default,
+{
+    /// Maps a filter in the binary format (see [`crate::io`]) from a file at `path`, creating the
+    /// hash builders using [`Default`].
+    ///
+    /// See [`Self::map_path_with_hashers`].
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the filter
+    /// exists. Doing so is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{bit_store::MappedBits, BloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder, MappedBits> =
+    ///     unsafe { BloomFilter::map_path("filter.bin")? };
+    ///
+    /// filter.contains("a");
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub unsafe fn map_path<Q>(path: Q) -> Result<Self, ReadError>
+    where
+        Q: AsRef<Path>,
+    {
+        Self::map_path_with_hashers(path, S::default(), S::default())
+    }
+}
+
+impl<S, P> BloomFilter<S, MappedBits, P>
+where
+    S: BuildHasher,
+    P: ProbeStrategy,
+{
+    // Writes the filter in the binary format to a file at `path`. See `BloomFilter::write_to_path`.
+    pub(crate) fn write_to_path<Q>(&self, path: Q) -> io::Result<()>
+    where
+        Q: AsRef<Path>,
+    {
+        path::write_atomically(path, |writer| self.write_to(writer))
+    }
+
+    // Replaces the bit array and number of elements with those of a filter in the binary format
+    // mapped from a file at `path`.
+    //
+    // # Safety
+    //
+    // See `map_path_with_hashers`.
+    pub(crate) unsafe fn remap<Q>(&mut self, path: Q) -> Result<(), ReadError>
+    where
+        Q: AsRef<Path>,
+    {
+        let (header, bits) = map_bits(path)?;
+
+        if header.m != self.m || header.k != self.k || header.label != self.label {
+            return Err(ReadError::InvalidParameters);
+        }
+
+        self.bits = bits;
+        self.n = header.n;
+
+        Ok(())
+    }
+
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write_bloom_filter(
+            writer,
+            self.m,
+            self.k,
+            self.n,
+            self.bits.as_bytes(),
+            self.label,
+            P::ID,
+        )
+    }
+
+    // Returns the bit array packed most significant bit first.
+    pub(crate) fn bits_to_bytes(&self) -> Vec<u8> {
+        self.bits.as_bytes().to_vec()
+    }
+
+    // Copies the bit array into memory.
+    pub(crate) fn into_memory(self) -> BloomFilter<S, BitVec, P> {
+        let mut bits = BitVec::from_bytes(self.bits.as_bytes());
+        bits.truncate(self.m);
+        self.with_bits(bits)
+    }
+}
+
+impl<S, B, P> BloomFilter<S, B, P> {
+    // Replaces the bit array with `bits`, which must have the same length and bits set.
+    pub(crate) fn with_bits<C>(self, bits: C) -> BloomFilter<S, C, P>
+    where
+        C: BitStore,
+    {
+        debug_assert_eq!(bits.len_bits(), self.m);
+
+        BloomFilter {
+            bits,
+            m: self.m,
+            n: self.n,
+            k: self.k,
+            label: self.label,
+            builder_1: self.builder_1,
+            builder_2: self.builder_2,
+            probe_strategy: PhantomData,
+            #[cfg(feature = "instrumentation")]
+            probe_stats: self.probe_stats,
+        }
+    }
+}
+
+// Maps a filter in the binary format from a file at `path`, returning its header and bit array.
+//
+// # Safety
+//
+// The file must not be modified or truncated while the bit array exists.
+pub(crate) unsafe fn map_bits<Q>(path: Q) -> Result<(Header, MappedBits), ReadError>
+where
+    Q: AsRef<Path>,
+{
+    let file = File::open(path)?;
+    // SAFETY: The caller guarantees that the file is not modified while it is mapped.
+    let map = unsafe { Mmap::map(&file) }?;
+
+    let mut reader = &map[..];
+    let header = read_bloom_filter_payload::<_, _, _, ReadError>(
+        &mut reader,
+        DoubleHashing::ID,
+        |_| Ok(()),
+        |_, _| {},
+    )?;
+
+    if !reader.is_empty() {
+        return Err(ReadError::TrailingData);
+    }
+
+    let offset = map.len() - CHECKSUM_LEN - header.m.div_ceil(8);
+    let bits = MappedBits::new(map, offset, header.m);
+
+    Ok((header, bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[test]
+    fn test_map_path() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("bbloom-map-path-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let src = dir.join("filter.bin");
+
+        let mut expected = BloomFilter::from_fpp_with_hashers(
+            0.001,
+            128,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+        .with_label(8);
+
+        for i in 0..128 {
+            expected.insert(&i);
+        }
+
+        expected.write_to_path(&src)?;
+
+        let actual: BloomFilter<SeededHashBuilder, MappedBits> = unsafe {
+            BloomFilter::map_path_with_hashers(
+                &src,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )?
+        };
+
+        assert_eq!(actual.capacity(), expected.capacity());
+        assert_eq!(actual.len(), expected.len());
+        assert_eq!(actual.label(), Some(8));
+        assert_eq!(actual.to_words(), expected.to_words());
+        assert!((0..1024).all(|i| actual.contains(&i) == expected.contains(&i)));
+
+        let actual = actual.into_memory();
+        assert_eq!(actual.to_bytes(), expected.to_bytes());
+
+        let mut buf = expected.to_bytes();
+        buf.push(0);
+        fs::write(&src, &buf)?;
+
+        assert!(matches!(
+            unsafe { BloomFilter::<SeededHashBuilder, MappedBits>::map_path(&src) },
+            Err(ReadError::TrailingData)
+        ));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
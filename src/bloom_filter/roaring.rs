@@ -0,0 +1,141 @@
+//! Roaring bitmap support for [`BloomFilter`].
+
+use std::{error, fmt, hash::BuildHasher};
+
+use roaring::RoaringTreemap;
+
+use super::BloomFilter;
+
+/// An error returned when a bit index is outside the bit array of a filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OutOfRangeError {
+    index: u64,
+    m: usize,
+}
+
+impl OutOfRangeError {
+    /// Returns the bit index that is out of range.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+}
+
+impl error::Error for OutOfRangeError {}
+
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bit index out of range: expected < {}, got {}",
+            self.m, self.index
+        )
+    }
+}
+
+impl<S> BloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Returns the positions of the set bits as a roaring bitmap.
+    ///
+    /// This is only available with the `roaring` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    /// assert!(filter.to_roaring().is_empty());
+    ///
+    /// filter.insert("a");
+    /// let bitmap = filter.to_roaring();
+    /// assert!(!bitmap.is_empty() && bitmap.len() <= 7);
+    /// ```
+    pub fn to_roaring(&self) -> RoaringTreemap {
+        self.bits
+            .iter()
+            .enumerate()
+            .filter(|(_, is_set)| *is_set)
+            .map(|(i, _)| i as u64)
+            .collect()
+    }
+
+    /// Sets the bits at the positions in the given roaring bitmap.
+    ///
+    /// This fails if any position is not less than `m`, in which case the filter is not
+    /// modified. The number of elements `n` is not changed.
+    ///
+    /// This is only available with the `roaring` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// use roaring::RoaringTreemap;
+    ///
+    /// let mut filter = BloomFilter::new(1227, 7);
+    ///
+    /// let bitmap: RoaringTreemap = [0, 8, 13].iter().copied().collect();
+    /// filter.or_from_roaring(&bitmap)?;
+    /// assert_eq!(filter.to_roaring(), bitmap);
+    ///
+    /// let bitmap: RoaringTreemap = [1227].iter().copied().collect();
+    /// assert!(filter.or_from_roaring(&bitmap).is_err());
+    /// # Ok::<(), bbloom::OutOfRangeError>(())
+    /// ```
+    pub fn or_from_roaring(&mut self, bitmap: &RoaringTreemap) -> Result<(), OutOfRangeError> {
+        if let Some(index) = bitmap.max() {
+            if index >= self.m as u64 {
+                return Err(OutOfRangeError { index, m: self.m });
+            }
+        }
+
+        for i in bitmap {
+            self.bits.set(i as usize, true);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> Result<(), OutOfRangeError> {
+        let mut filter = BloomFilter::new(1227, 7);
+
+        for i in 0..32 {
+            filter.insert(&i);
+        }
+
+        let bitmap = filter.to_roaring();
+
+        let mut actual =
+            BloomFilter::with_hashers(1227, 7, filter.builder_1.clone(), filter.builder_2.clone());
+        actual.or_from_roaring(&bitmap)?;
+
+        assert_eq!(actual.bits, filter.bits);
+
+        for i in 0..32 {
+            assert!(actual.contains(&i));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_or_from_roaring_with_out_of_range_index() {
+        let mut filter = BloomFilter::new(64, 3);
+
+        let bitmap: RoaringTreemap = [0, 64].iter().copied().collect();
+
+        assert_eq!(
+            filter.or_from_roaring(&bitmap),
+            Err(OutOfRangeError { index: 64, m: 64 })
+        );
+        assert!(filter.to_roaring().is_empty());
+    }
+}
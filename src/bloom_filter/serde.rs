@@ -0,0 +1,193 @@
+//! Serde support for [`BloomFilter`].
+//!
+//! A filter is serialized as a struct named `BloomFilter` with the following fields, in order:
+//!
+//! | name    | type          | description                    |
+//! |---------|---------------|--------------------------------|
+//! | `m`     | `usize`       | size of the bit array          |
+//! | `k`     | `usize`       | number of hash functions       |
+//! | `n`     | `usize`       | number of inserted elements    |
+//! | `bits`  | bytes         | bit array, `ceil(m / 8)` bytes |
+//! | `label` | `Option<u64>` | label, if any                  |
+//!
+//! In `bits`, bit `i` of the bit array is stored in byte `i / 8` at bit `7 - i % 8`, i.e., most
+//! significant bit first. Any trailing bits in the last byte are 0. `bits` is serialized as a
+//! byte string (not a sequence of integers), so in compact formats, e.g., postcard, the encoded
+//! filter is three varints, a varint length, and the raw bytes. When deserializing, the bytes
+//! are borrowed from the input if the format allows it.
+//!
+//! The hash builders are not serialized and are recreated using [`Default`]. A filter must be
+//! deserialized with hash builders that produce the same hashes as the ones used to build it,
+//! e.g., [`crate::SeededHashBuilder`]. [`std::collections::hash_map::RandomState`] is randomly
+//! seeded, so a deserialized filter using it will not find previously inserted elements.
+
+use std::{borrow::Cow, hash::BuildHasher};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::BloomFilter;
+use crate::io::Parts;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename = "BloomFilter")]
+struct Repr<'a> {
+    m: usize,
+    k: usize,
+    n: usize,
+    #[serde(borrow, with = "serde_bytes")]
+    bits: Cow<'a, [u8]>,
+    label: Option<u64>,
+}
+
+impl<S> Serialize for BloomFilter<S> {
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error>
+    where
+        T: Serializer,
+    {
+        let repr = Repr {
+            m: self.m,
+            k: self.k,
+            n: self.n,
+            bits: Cow::Owned(self.bits.to_bytes()),
+            label: self.label,
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de, S> Deserialize<'de> for BloomFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = Repr::deserialize(deserializer)?;
+
+        if repr.m == 0 {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(0),
+                &"m > 0",
+            ));
+        } else if repr.k == 0 {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(0),
+                &"k > 0",
+            ));
+        }
+
+        let expected_len = repr.m.div_ceil(8);
+
+        if repr.bits.len() != expected_len {
+            return Err(de::Error::invalid_length(
+                repr.bits.len(),
+                &"ceil(m / 8) bytes",
+            ));
+        }
+
+        let parts = Parts {
+            m: repr.m,
+            k: repr.k,
+            n: repr.n,
+            bits: repr.bits.into_owned(),
+            label: repr.label,
+        };
+
+        Ok(Self::from_parts(parts, S::default(), S::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    type Filter = BloomFilter<SeededHashBuilder>;
+
+    #[test]
+    fn test_postcard_round_trip() -> postcard::Result<()> {
+        let mut filter = Filter::with_hashers(1227, 7, Default::default(), Default::default());
+        filter.insert("a");
+        filter.insert("b");
+
+        let mut buf = [0; 256];
+        let encoded = postcard::to_slice(&filter, &mut buf)?;
+
+        // m (2), k (1), n (1), len (2), bits (154), label (1)
+        let bits_len = 1227usize.div_ceil(8);
+        assert_eq!(encoded.len(), bits_len + 7);
+
+        let actual: Filter = postcard::from_bytes(encoded)?;
+
+        assert_eq!(actual.capacity(), 1227);
+        assert_eq!(actual.hash_count(), 7);
+        assert_eq!(actual.len(), 2);
+        assert!(actual.contains("a"));
+        assert!(actual.contains("b"));
+        assert!(!actual.contains("c"));
+
+        Ok(())
+    }
+
+    fn build_filter() -> Filter {
+        let mut filter =
+            Filter::from_fpp_with_hashers(0.001, 1000, Default::default(), Default::default())
+                .with_label(8);
+
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+
+        filter
+    }
+
+    fn assert_round_trip(expected: &Filter, actual: &Filter) {
+        assert_eq!(actual.capacity(), expected.capacity());
+        assert_eq!(actual.hash_count(), expected.hash_count());
+        assert_eq!(actual.len(), expected.len());
+        assert_eq!(actual.label(), expected.label());
+        assert_eq!(actual.bits, expected.bits);
+        assert!((0..1000).all(|i| actual.contains(&i)));
+    }
+
+    #[test]
+    fn test_serde_json_round_trip() -> serde_json::Result<()> {
+        let expected = build_filter();
+
+        let json = serde_json::to_string(&expected)?;
+        let actual: Filter = serde_json::from_str(&json)?;
+        assert_round_trip(&expected, &actual);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bincode_round_trip() -> bincode::Result<()> {
+        let expected = build_filter();
+
+        let buf = bincode::serialize(&expected)?;
+
+        // The bit array is encoded as raw bytes.
+        assert!(buf.len() < expected.capacity().div_ceil(8) + 64);
+
+        let actual: Filter = bincode::deserialize(&buf)?;
+        assert_round_trip(&expected, &actual);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_bits_length() {
+        let filter = Filter::with_hashers(64, 3, Default::default(), Default::default());
+
+        let mut buf = [0; 32];
+        let encoded = postcard::to_slice(&filter, &mut buf).unwrap();
+
+        // m = 72
+        encoded[0] = 72;
+
+        assert!(postcard::from_bytes::<Filter>(encoded).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use std::{error, fmt};
+
+use super::IncompatibleFilters;
+use crate::io::ReadError;
+
+/// An error returned when a filter or serialized filter fails to be combined into a filter.
+#[derive(Debug)]
+pub enum UnionError {
+    /// The serialized filter is invalid.
+    InvalidFilter(ReadError),
+    /// The filter is incompatible with the receiver.
+    IncompatibleFilters(IncompatibleFilters),
+}
+
+impl error::Error for UnionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidFilter(e) => Some(e),
+            Self::IncompatibleFilters(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for UnionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFilter(_) => f.write_str("invalid filter"),
+            Self::IncompatibleFilters(_) => f.write_str("incompatible filters"),
+        }
+    }
+}
+
+impl From<ReadError> for UnionError {
+    fn from(e: ReadError) -> Self {
+        Self::InvalidFilter(e)
+    }
+}
+
+impl From<IncompatibleFilters> for UnionError {
+    fn from(e: IncompatibleFilters) -> Self {
+        Self::IncompatibleFilters(e)
+    }
+}
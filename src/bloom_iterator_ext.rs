@@ -0,0 +1,160 @@
+use std::hash::Hash;
+
+use crate::Filter;
+
+/// An extension trait for iterators to test items against a filter.
+///
+/// This is implemented for every iterator. Items can be owned or borrowed, as long as they can be
+/// hashed like the keys inserted into the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{BloomFilter, BloomIteratorExt};
+///
+/// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+/// filter.insert("a");
+/// filter.insert("c");
+///
+/// let candidates = ["a", "b", "c", "d"];
+/// let maybe_present: Vec<_> = candidates.iter().bloom_retain(&filter).collect();
+///
+/// assert_eq!(maybe_present, [&"a", &"c"]);
+/// ```
+pub trait BloomIteratorExt: Iterator + Sized {
+    /// Creates an iterator that only yields items that may be in `filter`.
+    ///
+    /// Items that are definitely not in `filter` are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomIteratorExt, ScalableBloomFilter};
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.insert(&1);
+    /// filter.insert(&3);
+    ///
+    /// let maybe_present: Vec<_> = (0..5).bloom_retain(&filter).collect();
+    /// assert_eq!(maybe_present, [1, 3]);
+    /// ```
+    fn bloom_retain<F>(self, filter: &F) -> BloomRetain<'_, Self, F>
+    where
+        F: Filter,
+        Self::Item: Hash,
+    {
+        BloomRetain { iter: self, filter }
+    }
+
+    /// Consumes the iterator, splitting items into those that may be in `filter` and those that
+    /// are definitely not in `filter`, in that order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, BloomIteratorExt};
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let (maybe_present, absent): (Vec<_>, Vec<_>) =
+    ///     vec!["a", "b"].into_iter().bloom_partition(&filter);
+    ///
+    /// assert_eq!(maybe_present, ["a"]);
+    /// assert_eq!(absent, ["b"]);
+    /// ```
+    fn bloom_partition<B, F>(self, filter: &F) -> (B, B)
+    where
+        B: Default + Extend<Self::Item>,
+        F: Filter,
+        Self::Item: Hash,
+    {
+        self.partition(|item| filter.contains(item))
+    }
+}
+
+impl<I> BloomIteratorExt for I where I: Iterator {}
+
+/// An iterator that only yields items that may be in a filter.
+///
+/// This is created by [`BloomIteratorExt::bloom_retain`].
+pub struct BloomRetain<'a, I, F> {
+    iter: I,
+    filter: &'a F,
+}
+
+impl<I, F> Iterator for BloomRetain<'_, I, F>
+where
+    I: Iterator,
+    I::Item: Hash,
+    F: Filter,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let filter = self.filter;
+        self.iter.find(|item| filter.contains(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BloomFilter, ScalableBloomFilter, SeededHashBuilder};
+
+    const N: u64 = 10_000;
+
+    fn assert_retains_inserted_keys<F>(filter: &mut F)
+    where
+        F: Filter,
+    {
+        for i in (0..2 * N).step_by(2) {
+            filter.insert(&i);
+        }
+
+        // owned items
+        let retained: Vec<_> = (0..2 * N).bloom_retain(filter).collect();
+        assert!((0..2 * N)
+            .step_by(2)
+            .all(|i| retained.binary_search(&i).is_ok()));
+        assert!(retained.len() < (N + N / 20) as usize);
+
+        // borrowed items
+        let keys: Vec<_> = (0..2 * N).collect();
+        let retained: Vec<&u64> = keys.iter().bloom_retain(filter).collect();
+        assert!(retained.len() < (N + N / 20) as usize);
+
+        let (maybe_present, absent): (Vec<_>, Vec<_>) = keys.iter().bloom_partition(filter);
+        assert_eq!(maybe_present, retained);
+        assert_eq!(maybe_present.len() + absent.len(), keys.len());
+        assert!(absent.iter().all(|&&i| i % 2 == 1));
+    }
+
+    #[test]
+    fn test_bloom_retain_with_bloom_filter() {
+        let mut filter = BloomFilter::from_fpp_with_hashers(
+            0.01,
+            N as usize,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_retains_inserted_keys(&mut filter);
+    }
+
+    #[test]
+    fn test_bloom_retain_with_scalable_bloom_filter() {
+        let mut filter = ScalableBloomFilter::with_hashers(
+            0.01,
+            1024,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        assert_retains_inserted_keys(&mut filter);
+    }
+}
@@ -0,0 +1,226 @@
+//! Build-time generation of static Bloom filters.
+//!
+//! [`generate`] is intended to be called from a build script. It builds a Bloom filter from a set
+//! of keys using fixed hasher seeds and writes two files:
+//!
+//!   * a Rust snippet (`out`) that defines `pub static FILTER: bbloom::StaticBloomFilter`, to be
+//!     included using `include!`; and
+//!   * a binary blob (`out` with the extension `bin`) with the same filter parameters and words.
+//!
+//! Because the seeds are fixed, rebuilding from the same keys produces identical output.
+//!
+//! The binary blob is a sequence of little-endian `u64` values:
+//!
+//! | field    | count      |
+//! |----------|------------|
+//! | `m`      | 1          |
+//! | `k`      | 1          |
+//! | seed 1   | 1          |
+//! | seed 2   | 1          |
+//! | `len`    | 1          |
+//! | words    | `len`      |
+//!
+//! # Examples
+//!
+//! In `build.rs`:
+//!
+//! ```no_run
+//! use std::{env, path::PathBuf};
+//!
+//! let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+//! let keys = ["a", "b", "c"];
+//!
+//! bbloom::codegen::generate(keys.iter().copied(), 0.001, &out_dir.join("allowlist.rs"))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! In the crate:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/allowlist.rs"));
+//!
+//! assert!(FILTER.contains("a"));
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{BloomFilter, SeededHashBuilder};
+
+/// The hasher seeds used by generated filters.
+pub const SEEDS: (u64, u64) = (0x6262_6c6f_6f6d_0001, 0x6262_6c6f_6f6d_0002);
+
+/// The parameters and bit array of a generated filter.
+///
+/// These are the arguments to [`crate::StaticBloomFilter::from_static`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GeneratedFilter {
+    /// The size of the bit array `m`.
+    pub m: usize,
+    /// The number of hash functions `k`.
+    pub k: usize,
+    /// The hasher seeds, i.e., [`SEEDS`].
+    pub seeds: (u64, u64),
+    /// The bit array, where bit `i` is bit `i % 64` of word `i / 64`.
+    pub words: Vec<u64>,
+}
+
+/// Builds a Bloom filter from `keys` that targets a false positive probability `p`.
+///
+/// Duplicate keys do not count towards the expected number of inserted elements.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{codegen, StaticBloomFilter};
+///
+/// let filter = codegen::build(["a", "b"].iter().copied(), 0.001);
+/// let words = Box::leak(filter.words.into_boxed_slice());
+/// let filter = StaticBloomFilter::from_static(words, filter.m, filter.k, filter.seeds);
+///
+/// assert!(filter.contains("a"));
+/// ```
+pub fn build<'a, I>(keys: I, p: f64) -> GeneratedFilter
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut keys: Vec<_> = keys.collect();
+    keys.sort_unstable();
+    keys.dedup();
+
+    let mut filter = BloomFilter::from_fpp_with_hashers(
+        p,
+        keys.len(),
+        SeededHashBuilder::new(SEEDS.0),
+        SeededHashBuilder::new(SEEDS.1),
+    );
+
+    for key in keys {
+        filter.insert(key);
+    }
+
+    GeneratedFilter {
+        m: filter.capacity(),
+        k: filter.hash_count(),
+        seeds: SEEDS,
+        words: filter.to_words(),
+    }
+}
+
+/// Builds a Bloom filter from `keys` that targets a false positive probability `p` and writes it
+/// as a Rust snippet to `out` and as a binary blob to `out` with the extension `bin`.
+///
+/// Duplicate keys do not count towards the expected number of inserted elements.
+pub fn generate<'a, I>(keys: I, p: f64, out: &Path) -> io::Result<()>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let GeneratedFilter { m, k, words, .. } = build(keys, p);
+
+    let mut writer = BufWriter::new(File::create(out.with_extension("bin"))?);
+    write_blob(&mut writer, m, k, &words)?;
+    writer.flush()?;
+
+    let mut writer = BufWriter::new(File::create(out)?);
+    write_snippet(&mut writer, m, k, &words)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_blob<W>(writer: &mut W, m: usize, k: usize, words: &[u64]) -> io::Result<()>
+where
+    W: Write,
+{
+    let header = [m as u64, k as u64, SEEDS.0, SEEDS.1, words.len() as u64];
+
+    for n in header.iter().chain(words) {
+        writer.write_all(&n.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn write_snippet<W>(writer: &mut W, m: usize, k: usize, words: &[u64]) -> io::Result<()>
+where
+    W: Write,
+{
+    writeln!(writer, "// @generated by bbloom::codegen")?;
+    writeln!(writer)?;
+    writeln!(writer, "static WORDS: [u64; {}] = [", words.len())?;
+
+    for word in words {
+        writeln!(writer, "    {:#018x},", word)?;
+    }
+
+    writeln!(writer, "];")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "pub static FILTER: ::bbloom::StaticBloomFilter = \
+         ::bbloom::StaticBloomFilter::from_static(&WORDS, {}, {}, ({:#x}, {:#x}));",
+        m, k, SEEDS.0, SEEDS.1
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn test_generate_is_reproducible() -> io::Result<()> {
+        let dir = env::temp_dir().join(format!("bbloom-codegen-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let keys = ["c", "a", "b", "a"];
+
+        let out_1 = dir.join("a.rs");
+        generate(keys.iter().copied(), 0.001, &out_1)?;
+
+        let out_2 = dir.join("b.rs");
+        generate(keys.iter().rev().copied(), 0.001, &out_2)?;
+
+        assert_eq!(fs::read(&out_1)?, fs::read(&out_2)?);
+        assert_eq!(
+            fs::read(out_1.with_extension("bin"))?,
+            fs::read(out_2.with_extension("bin"))?
+        );
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_blob() -> io::Result<()> {
+        let mut buf = Vec::new();
+        write_blob(&mut buf, 64, 3, &[0x0102])?;
+
+        assert_eq!(buf.len(), 6 * 8);
+        assert_eq!(&buf[0..8], &64u64.to_le_bytes());
+        assert_eq!(&buf[8..16], &3u64.to_le_bytes());
+        assert_eq!(&buf[32..40], &1u64.to_le_bytes());
+        assert_eq!(&buf[40..48], &0x0102u64.to_le_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_snippet() -> io::Result<()> {
+        let mut buf = Vec::new();
+        write_snippet(&mut buf, 64, 3, &[0x0102])?;
+        let s = String::from_utf8(buf).unwrap();
+
+        assert!(s.contains("static WORDS: [u64; 1] = [\n    0x0000000000000102,\n];"));
+        assert!(s.contains("from_static(&WORDS, 64, 3,"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,402 @@
+use std::hash::{BuildHasher, Hash};
+
+use crate::{
+    bloom_filter::{optimal_number_of_hash_functions, optimal_required_bits},
+    double_hasher::DoubleHasher,
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    DefaultHashBuilder, Filter,
+};
+
+/// A Bloom filter that supports removing elements using counters.
+///
+/// This is the counting Bloom filter (Fan et al., 2000). Each position of the array is an 8-bit
+/// counter rather than a bit. An insert increments the counters of the element, and a remove
+/// decrements them. An element may be in the filter if all of its counters are nonzero.
+///
+/// A counter saturates at [`u8::MAX`]. A saturated counter is never incremented or decremented
+/// again, since its true count is unknown. This never causes a false negative, but the position
+/// stays set after all of its elements are removed, which slightly increases the false positive
+/// probability. With an optimal number of hash functions, a counter reaching 255 is
+/// astronomically unlikely unless the same values are inserted many times.
+///
+/// The counters use 8 times the memory of the bit array of a [`BloomFilter`](crate::BloomFilter)
+/// with the same false positive probability. See
+/// [`DeletableBloomFilter`](crate::DeletableBloomFilter) for a filter that removes most elements
+/// at a much lower cost.
+///
+/// Removing a key that was never inserted can decrement a counter of another element to 0,
+/// causing a false negative. Only remove keys that are known to be in the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::CountingBloomFilter;
+///
+/// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+///
+/// filter.insert("a");
+/// filter.insert("b");
+/// assert!(filter.contains("a"));
+///
+/// assert!(filter.remove("a"));
+/// assert!(!filter.contains("a"));
+/// assert!(filter.contains("b"));
+/// ```
+pub struct CountingBloomFilter<S = DefaultHashBuilder> {
+    counters: Vec<u8>,
+
+    // counter array length
+    m: usize,
+    // number of inserted elements
+    n: usize,
+    // number of hash functions
+    k: usize,
+
+    builder_1: S,
+    builder_2: S,
+}
+
+impl CountingBloomFilter<DefaultHashBuilder> {
+    /// Creates a new counting bloom filter given a false positive probability `p` ([0.0, 1.0])
+    /// and an expected number of inserted elements `n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    /// let _filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// ```
+    pub fn from_fpp(p: f64, n: usize) -> Self {
+        Self::from_fpp_with_hashers(p, n, DefaultHashBuilder::new(), DefaultHashBuilder::new())
+    }
+
+    /// Creates a new counting bloom filter with a predetermined number of counters `m` and number
+    /// of hash functions `k`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    /// let _filter = CountingBloomFilter::new(1227, 14);
+    /// ```
+    pub fn new(m: usize, k: usize) -> Self {
+        Self::with_hashers(m, k, DefaultHashBuilder::new(), DefaultHashBuilder::new())
+    }
+}
+
+impl<S> CountingBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a new counting bloom filter given a false positive probability `p` ([0.0, 1.0]),
+    /// an expected number of inserted elements `n`, and two hash builders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{CountingBloomFilter, SeededHashBuilder};
+    ///
+    /// let _filter = CountingBloomFilter::from_fpp_with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// ```
+    pub fn from_fpp_with_hashers(p: f64, n: usize, builder_1: S, builder_2: S) -> Self {
+        let m = optimal_required_bits(p, n);
+        let k = optimal_number_of_hash_functions(m, n);
+        Self::with_hashers(m, k, builder_1, builder_2)
+    }
+
+    /// Creates a new counting bloom filter with a predetermined number of counters `m` and number
+    /// of hash functions `k`, using `builder_1` and `builder_2` to hash the data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` or `k` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{CountingBloomFilter, SeededHashBuilder};
+    ///
+    /// let _filter = CountingBloomFilter::with_hashers(
+    ///     1227,
+    ///     14,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// ```
+    pub fn with_hashers(m: usize, k: usize, builder_1: S, builder_2: S) -> Self {
+        assert!(m > 0, "counter count must be at least 1");
+        assert!(k > 0, "hash count must be at least 1");
+
+        Self {
+            counters: vec![0; m],
+            m,
+            n: 0,
+            k,
+            builder_1,
+            builder_2,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    ///
+    /// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.indices(key).all(|i| self.counters[i] > 0)
+    }
+
+    /// Adds a value to the filter.
+    ///
+    /// Returns whether the value was not previously in the filter. The counters are incremented
+    /// either way, so a value inserted twice must be removed twice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    ///
+    /// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.insert("a"));
+    /// assert!(!filter.insert("a"));
+    /// assert_eq!(filter.len(), 2);
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let inserted = !self.contains(key);
+        let indices: Vec<_> = self.indices(key).collect();
+
+        for i in indices {
+            let counter = &mut self.counters[i];
+            *counter = counter.saturating_add(1);
+        }
+
+        self.n += 1;
+
+        inserted
+    }
+
+    /// Removes a value from the filter.
+    ///
+    /// The value's counters are decremented, except those that are saturated. Returns whether the
+    /// value was in the filter. If it was not, the filter is unchanged.
+    ///
+    /// Only remove values that are known to be in the filter (see the
+    /// [type-level documentation](`Self`)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    ///
+    /// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.remove("a"));
+    /// assert!(!filter.remove("a"));
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn remove<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        let indices: Vec<_> = self.indices(key).collect();
+
+        for i in indices {
+            let counter = &mut self.counters[i];
+
+            if *counter < u8::MAX {
+                *counter -= 1;
+            }
+        }
+
+        self.n = self.n.saturating_sub(1);
+
+        true
+    }
+
+    /// Returns the number of elements in the filter.
+    ///
+    /// This is the number of inserts minus the number of removes. A value inserted more than once
+    /// is counted once per insert.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    ///
+    /// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    /// let filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of counters that are saturated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CountingBloomFilter;
+    ///
+    /// let mut filter = CountingBloomFilter::from_fpp(0.0001, 64);
+    ///
+    /// for _ in 0..255 {
+    ///     filter.insert("a");
+    /// }
+    ///
+    /// assert!(filter.saturated_count() > 0);
+    /// ```
+    pub fn saturated_count(&self) -> usize {
+        self.counters.iter().filter(|&&c| c == u8::MAX).count()
+    }
+
+    fn indices<H: Hash + ?Sized>(&self, key: &H) -> impl Iterator<Item = usize> {
+        let (h1, h2) = DoubleHasher::new(key, &self.builder_1, &self.builder_2).hashes();
+        let m = self.m;
+        (0..self.k).map(move |i| DoubleHashing::index(h1, h2, i, m))
+    }
+}
+
+impl<S> Filter for CountingBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn build_filter(p: f64, n: usize) -> CountingBloomFilter<SeededHashBuilder> {
+        CountingBloomFilter::from_fpp_with_hashers(
+            p,
+            n,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+    }
+
+    #[test]
+    fn test_remove_has_no_false_negatives() {
+        const N: usize = 10_000;
+
+        let mut filter = build_filter(0.01, N);
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        for i in (0..N).step_by(2) {
+            assert!(filter.remove(&i));
+        }
+
+        assert_eq!(filter.len(), N / 2);
+        assert!((1..N).step_by(2).all(|i| filter.contains(&i)));
+
+        // Only false positives of the remaining keys are left.
+        let present = (0..N).step_by(2).filter(|i| filter.contains(i)).count();
+        assert!(present < N / 2 / 50, "{}", present);
+
+        for i in (1..N).step_by(2) {
+            assert!(filter.remove(&i));
+        }
+
+        assert!(filter.is_empty());
+        assert!(filter.counters.iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_insert_remove_cycles() {
+        let mut filter = CountingBloomFilter::with_hashers(
+            1024,
+            7,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        for round in 0..8 {
+            let keys: Vec<_> = (0..64).map(|i| (round, i)).collect();
+
+            for key in &keys {
+                filter.insert(key);
+            }
+
+            assert_eq!(filter.len(), keys.len());
+            assert!(keys.iter().all(|key| filter.contains(key)));
+
+            for key in &keys {
+                assert!(filter.remove(key));
+            }
+
+            // Every counter returns to 0, so nothing is left behind between rounds.
+            assert!(filter.is_empty());
+            assert!(filter.counters.iter().all(|&c| c == 0));
+            assert!(keys.iter().all(|key| !filter.contains(key)));
+        }
+    }
+
+    #[test]
+    fn test_remove_with_saturated_counters() {
+        let mut filter = build_filter(0.01, 100);
+
+        for _ in 0..300 {
+            filter.insert("a");
+        }
+
+        let saturated = filter.saturated_count();
+        assert!(saturated > 0);
+
+        filter.insert("b");
+
+        for _ in 0..300 {
+            assert!(filter.remove("a"));
+        }
+
+        // Saturated counters stay set, so "a" is not forgotten and "b" has no false negative.
+        assert!(filter.contains("a"));
+        assert!(filter.contains("b"));
+        assert_eq!(filter.saturated_count(), saturated);
+        assert_eq!(filter.len(), 1);
+    }
+}
@@ -0,0 +1,658 @@
+mod full_error;
+
+pub use self::full_error::FullError;
+
+use std::{
+    hash::{BuildHasher, Hash},
+    io::{self, Read, Write},
+    mem,
+};
+
+use crate::{
+    io::{read_cuckoo_filter, write_cuckoo_filter, ReadError},
+    DefaultHashBuilder, Filter,
+};
+
+// number of fingerprints per bucket `b`
+const BUCKET_SIZE: usize = 4;
+
+// the load factor `from_fpp` sizes the table for
+const TARGET_LOAD_FACTOR: f64 = 0.95;
+
+// the maximum number of relocations in an insert
+const MAX_KICKS: usize = 500;
+
+const MIN_FINGERPRINT_BITS: u32 = 8;
+const MAX_FINGERPRINT_BITS: u32 = 16;
+
+// An empty entry. Fingerprints are never 0.
+const EMPTY: u16 = 0;
+
+type Bucket = [u16; BUCKET_SIZE];
+
+/// A probabilistic data structure that stores fingerprints of elements in a cuckoo hash table.
+///
+/// Each element has a fingerprint of 8 to 16 bits and two candidate buckets of 4 entries. The
+/// second bucket is derived from the first and the fingerprint alone (partial-key cuckoo hashing),
+/// so a fingerprint can be relocated to its other bucket without the original key. Inserting into
+/// two full buckets relocates existing fingerprints, up to a bounded number of times, and fails
+/// with [`FullError`] if no room is found. A table typically fills to a load factor of about 95%
+/// before inserts start failing.
+///
+/// Unlike a Bloom filter, a cuckoo filter supports [removing](`Self::remove`) elements. The false
+/// positive probability is at most `8 / 2^f`, where `f` is the number of fingerprint bits.
+///
+/// An element inserted twice is stored twice, and removing it once removes only one copy: the
+/// element is still reported as present until it is removed again. Removing a key that was never
+/// inserted can remove another key that shares its fingerprint and buckets. Only remove keys that
+/// are known to be in the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::CuckooFilter;
+///
+/// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+///
+/// filter.insert("a")?;
+/// filter.insert("b")?;
+/// assert!(filter.contains("a"));
+///
+/// filter.remove("a");
+/// assert!(!filter.contains("a"));
+/// assert!(filter.contains("b"));
+/// # Ok::<(), bbloom::FullError>(())
+/// ```
+pub struct CuckooFilter<S = DefaultHashBuilder> {
+    buckets: Vec<Bucket>,
+    fingerprint_bits: u32,
+    // number of inserted elements `n`
+    n: usize,
+    builder: S,
+    // the state of the generator that picks entries to relocate
+    rng: u64,
+}
+
+impl CuckooFilter<DefaultHashBuilder> {
+    /// Creates a new cuckoo filter given a false positive probability `p` ([0.0, 1.0]) and an
+    /// expected number of inserted elements `n`.
+    ///
+    /// The fingerprint size is clamped to 8 to 16 bits, so the filter cannot target a false
+    /// positive probability below ~0.0001.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    /// let _filter = CuckooFilter::from_fpp(0.001, 64);
+    /// ```
+    pub fn from_fpp(p: f64, n: usize) -> Self {
+        Self::from_fpp_with_hasher(p, n, DefaultHashBuilder::new())
+    }
+}
+
+impl<S> CuckooFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a new cuckoo filter given a false positive probability `p` ([0.0, 1.0]), an
+    /// expected number of inserted elements `n`, and the hash builder used to compute
+    /// fingerprints and buckets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{CuckooFilter, SeededHashBuilder};
+    /// let _filter = CuckooFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+    /// ```
+    pub fn from_fpp_with_hasher(p: f64, n: usize, builder: S) -> Self {
+        let bucket_count = optimal_number_of_buckets(n);
+        let fingerprint_bits = optimal_number_of_fingerprint_bits(p);
+        Self::with_buckets(bucket_count, fingerprint_bits, builder)
+    }
+
+    fn with_buckets(bucket_count: usize, fingerprint_bits: u32, builder: S) -> Self {
+        Self {
+            buckets: vec![[EMPTY; BUCKET_SIZE]; bucket_count],
+            fingerprint_bits,
+            n: 0,
+            builder,
+            rng: 0x6262_6c6f_6f6d_0003,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+    /// filter.insert("a")?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// # Ok::<(), bbloom::FullError>(())
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        let (i1, fingerprint) = self.index_and_fingerprint(key);
+        let i2 = self.alt_index(i1, fingerprint);
+
+        self.buckets[i1].contains(&fingerprint) || self.buckets[i2].contains(&fingerprint)
+    }
+
+    /// Adds a value to the filter.
+    ///
+    /// If both candidate buckets are full, existing fingerprints are relocated to their other
+    /// buckets. If no room is found after a bounded number of relocations, the relocations are
+    /// undone and this returns [`FullError`]; the filter is unchanged.
+    ///
+    /// A value that is already in the filter is stored again (see the
+    /// [type-level documentation](`Self`)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+    /// filter.insert("a")?;
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// # Ok::<(), bbloom::FullError>(())
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> Result<(), FullError> {
+        let (i1, fingerprint) = self.index_and_fingerprint(key);
+        let i2 = self.alt_index(i1, fingerprint);
+
+        if self.try_place(i1, fingerprint) || self.try_place(i2, fingerprint) {
+            self.n += 1;
+            return Ok(());
+        }
+
+        let mut i = if self.next_random() & 1 == 0 { i1 } else { i2 };
+        let mut fingerprint = fingerprint;
+        let mut path = Vec::new();
+
+        for _ in 0..MAX_KICKS {
+            let slot = (self.next_random() % BUCKET_SIZE as u64) as usize;
+            mem::swap(&mut fingerprint, &mut self.buckets[i][slot]);
+            path.push((i, slot));
+
+            i = self.alt_index(i, fingerprint);
+
+            if self.try_place(i, fingerprint) {
+                self.n += 1;
+                return Ok(());
+            }
+        }
+
+        // Undo the relocations so that no existing fingerprint is lost.
+        for &(i, slot) in path.iter().rev() {
+            mem::swap(&mut fingerprint, &mut self.buckets[i][slot]);
+        }
+
+        Err(FullError)
+    }
+
+    /// Removes one copy of a value from the filter.
+    ///
+    /// Returns whether the value's fingerprint was found in one of its buckets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+    /// filter.insert("a")?;
+    ///
+    /// assert!(filter.remove("a"));
+    /// assert!(!filter.remove("a"));
+    /// assert!(filter.is_empty());
+    /// # Ok::<(), bbloom::FullError>(())
+    /// ```
+    pub fn remove<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let (i1, fingerprint) = self.index_and_fingerprint(key);
+        let i2 = self.alt_index(i1, fingerprint);
+
+        for &i in &[i1, i2] {
+            if let Some(entry) = self.buckets[i].iter_mut().find(|e| **e == fingerprint) {
+                *entry = EMPTY;
+                self.n -= 1;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns the number of entries in the table, i.e., 4 per bucket.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    /// let filter = CuckooFilter::from_fpp(0.001, 64);
+    /// assert_eq!(filter.capacity(), 128);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    /// Returns the number of bits in a fingerprint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    /// let filter = CuckooFilter::from_fpp(0.001, 64);
+    /// assert_eq!(filter.fingerprint_bits(), 13);
+    /// ```
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.fingerprint_bits
+    }
+
+    /// Returns the number of elements in the filter, counting every copy of a value inserted more
+    /// than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+    /// filter.insert("a")?;
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// # Ok::<(), bbloom::FullError>(())
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    /// let filter = CuckooFilter::from_fpp(0.001, 64);
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the proportion of occupied entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let mut filter = CuckooFilter::from_fpp(0.001, 64);
+    /// filter.insert("a")?;
+    ///
+    /// assert_eq!(filter.load_factor(), 1.0 / 128.0);
+    /// # Ok::<(), bbloom::FullError>(())
+    /// ```
+    pub fn load_factor(&self) -> f64 {
+        self.n as f64 / self.capacity() as f64
+    }
+
+    /// Writes the filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use bbloom::CuckooFilter;
+    ///
+    /// let filter = CuckooFilter::from_fpp(0.001, 64);
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let entries: Vec<_> = self.buckets.iter().flatten().copied().collect();
+
+        write_cuckoo_filter(
+            writer,
+            self.buckets.len(),
+            self.fingerprint_bits,
+            self.n,
+            &entries,
+        )
+    }
+
+    /// Encodes the filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::CuckooFilter;
+    /// let filter = CuckooFilter::from_fpp(0.001, 64);
+    /// let buf = filter.to_bytes();
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)
+            .expect("writing to a Vec should not fail");
+        buf
+    }
+
+    // Returns the first bucket of a key and its fingerprint.
+    //
+    // The bucket is taken from the low bits of the hash and the fingerprint from the high 32 bits,
+    // so the two are independent for tables of up to 2^32 buckets.
+    fn index_and_fingerprint<H: Hash + ?Sized>(&self, key: &H) -> (usize, u16) {
+        let hash = self.builder.hash_one(key);
+
+        let index = (hash as usize) & (self.buckets.len() - 1);
+
+        let mask = (1 << self.fingerprint_bits) - 1;
+        let fingerprint = ((hash >> 32) & mask) as u16;
+        let fingerprint = if fingerprint == EMPTY { 1 } else { fingerprint };
+
+        (index, fingerprint)
+    }
+
+    // Returns the other bucket of a fingerprint in bucket `i`.
+    //
+    // This is an involution, i.e., `alt_index(alt_index(i, f), f) == i`, which is what lets a
+    // fingerprint move between its buckets without the key.
+    fn alt_index(&self, i: usize, fingerprint: u16) -> usize {
+        // the multiplier of MurmurHash2
+        let hash = u64::from(fingerprint).wrapping_mul(0x5bd1_e995);
+        (i ^ hash as usize) & (self.buckets.len() - 1)
+    }
+
+    fn try_place(&mut self, i: usize, fingerprint: u16) -> bool {
+        match self.buckets[i].iter_mut().find(|e| **e == EMPTY) {
+            Some(entry) => {
+                *entry = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // xorshift64
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        x
+    }
+}
+
+impl<S> CuckooFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Reads a filter in the binary format (see [`crate::io`]), creating the hash builder using
+    /// [`Default`].
+    ///
+    /// The hash builder must produce the same hashes as the one used to build the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{CuckooFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: CuckooFilter<SeededHashBuilder> =
+    ///     CuckooFilter::from_fpp_with_hasher(0.001, 64, Default::default());
+    /// filter.insert("a")?;
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter: CuckooFilter<SeededHashBuilder> = CuckooFilter::read_from(&mut &buf[..])?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_from<R>(reader: &mut R) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        let parts = read_cuckoo_filter(reader)?;
+
+        let mut filter =
+            Self::with_buckets(parts.bucket_count, parts.fingerprint_bits, S::default());
+
+        for (bucket, entries) in filter
+            .buckets
+            .iter_mut()
+            .zip(parts.entries.chunks_exact(BUCKET_SIZE))
+        {
+            bucket.copy_from_slice(entries);
+        }
+
+        filter.n = parts.n;
+
+        Ok(filter)
+    }
+
+    /// Decodes a filter in the binary format (see [`crate::io`]), creating the hash builder using
+    /// [`Default`].
+    ///
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{CuckooFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: CuckooFilter<SeededHashBuilder> =
+    ///     CuckooFilter::from_fpp_with_hasher(0.001, 64, Default::default());
+    /// filter.insert("a")?;
+    ///
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter: CuckooFilter<SeededHashBuilder> = CuckooFilter::from_bytes(&buf)?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_bytes(mut buf: &[u8]) -> Result<Self, ReadError> {
+        let filter = Self::read_from(&mut buf)?;
+
+        if buf.is_empty() {
+            Ok(filter)
+        } else {
+            Err(ReadError::TrailingData)
+        }
+    }
+}
+
+/// Through [`Filter`], a value is only inserted if it is not already in the filter, so each value
+/// is stored at most once.
+///
+/// # Panics
+///
+/// [`Filter::insert`] panics if the filter is full.
+impl<S> Filter for CuckooFilter<S>
+where
+    S: BuildHasher,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        if self.contains(key) {
+            false
+        } else {
+            self.insert(key).expect("filter is full");
+            true
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+// Calculates the number of buckets that holds `n` elements at the target load factor, rounded up
+// to a power of two.
+//
+// This is at least 1.
+fn optimal_number_of_buckets(n: usize) -> usize {
+    let bucket_count = (n as f64 / BUCKET_SIZE as f64 / TARGET_LOAD_FACTOR).ceil() as usize;
+    bucket_count.max(1).next_power_of_two()
+}
+
+// Calculates the number of fingerprint bits for a target false positive probability `p`, i.e.,
+// f = ceil(log2(2b / p)), clamped to [8, 16].
+fn optimal_number_of_fingerprint_bits(p: f64) -> u32 {
+    let f = (2.0 * BUCKET_SIZE as f64 / p).log2().ceil();
+    (f as u32).clamp(MIN_FINGERPRINT_BITS, MAX_FINGERPRINT_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SeededHashBuilder, ShadowedBloomFilter};
+
+    #[test]
+    fn test_optimal_number_of_buckets() {
+        assert_eq!(optimal_number_of_buckets(0), 1);
+        assert_eq!(optimal_number_of_buckets(4), 2);
+        assert_eq!(optimal_number_of_buckets(64), 32);
+        assert_eq!(optimal_number_of_buckets(1000), 512);
+    }
+
+    #[test]
+    fn test_optimal_number_of_fingerprint_bits() {
+        assert_eq!(optimal_number_of_fingerprint_bits(0.01), 10);
+        assert_eq!(optimal_number_of_fingerprint_bits(0.001), 13);
+        assert_eq!(optimal_number_of_fingerprint_bits(0.5), 8);
+        assert_eq!(optimal_number_of_fingerprint_bits(1e-9), 16);
+    }
+
+    #[test]
+    fn test_alt_index_is_an_involution() {
+        let filter = CuckooFilter::with_buckets(1024, 12, SeededHashBuilder::new(0));
+
+        for i in 0..1024 {
+            for fingerprint in 1..64 {
+                let j = filter.alt_index(i, fingerprint);
+                assert_eq!(filter.alt_index(j, fingerprint), i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_false_positive_probability() {
+        const P: f64 = 0.01;
+        const N: usize = 10_000;
+
+        let mut filter = ShadowedBloomFilter::new(CuckooFilter::from_fpp_with_hasher(
+            P,
+            N,
+            SeededHashBuilder::new(0),
+        ));
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        for i in 0..N + 100_000 {
+            filter.contains(&i);
+        }
+
+        let fpp = filter.observed_fpp();
+        assert!(fpp < P, "{} >= {}", fpp, P);
+    }
+
+    #[test]
+    fn test_insert_until_full() {
+        let mut filter = CuckooFilter::with_buckets(1024, 12, SeededHashBuilder::new(0));
+
+        let mut n = 0;
+
+        while filter.insert(&n).is_ok() {
+            n += 1;
+        }
+
+        assert_eq!(filter.len(), n);
+
+        let load_factor = filter.load_factor();
+        assert!(load_factor >= 0.9, "{} < 0.9", load_factor);
+
+        // A failed insert leaves every stored fingerprint in place.
+        assert!((0..n).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut filter = CuckooFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+
+        for i in 0..64 {
+            filter.insert(&i).unwrap();
+        }
+
+        for i in (0..64).step_by(2) {
+            assert!(filter.remove(&i));
+        }
+
+        assert_eq!(filter.len(), 32);
+        assert!((1..64).step_by(2).all(|i| filter.contains(&i)));
+        assert!((0..64).step_by(2).all(|i| !filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_remove_duplicate() -> Result<(), FullError> {
+        let mut filter = CuckooFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+
+        filter.insert("a")?;
+        filter.insert("a")?;
+        assert_eq!(filter.len(), 2);
+
+        assert!(filter.remove("a"));
+        assert!(filter.contains("a"));
+
+        assert!(filter.remove("a"));
+        assert!(!filter.contains("a"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_insert() {
+        let mut filter = CuckooFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+
+        assert!(Filter::insert(&mut filter, "a"));
+        assert!(!Filter::insert(&mut filter, "a"));
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let mut filter: CuckooFilter<SeededHashBuilder> =
+            CuckooFilter::from_fpp_with_hasher(0.001, 64, Default::default());
+
+        for i in 0..64 {
+            filter.insert(&i)?;
+        }
+
+        let buf = filter.to_bytes();
+        let actual: CuckooFilter<SeededHashBuilder> = CuckooFilter::from_bytes(&buf)?;
+
+        assert_eq!(actual.len(), 64);
+        assert_eq!(actual.fingerprint_bits(), filter.fingerprint_bits());
+        assert_eq!(actual.buckets, filter.buckets);
+
+        Ok(())
+    }
+}
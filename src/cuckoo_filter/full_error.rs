@@ -0,0 +1,13 @@
+use std::{error, fmt};
+
+/// An error returned when a cuckoo filter has no room for another element.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FullError;
+
+impl error::Error for FullError {}
+
+impl fmt::Display for FullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("filter is full")
+    }
+}
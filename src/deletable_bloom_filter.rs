@@ -0,0 +1,486 @@
+use std::hash::{BuildHasher, Hash};
+
+use bit_vec::BitVec;
+
+use crate::{
+    bloom_filter::{optimal_number_of_hash_functions, optimal_required_bits},
+    double_hasher::DoubleHasher,
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    DefaultHashBuilder, Filter,
+};
+
+/// A Bloom filter that supports removing most elements using a collision bitmap.
+///
+/// This is the deletable Bloom filter (Rothenberg et al., 2010). The bit array is split into
+/// regions, and a collision bitmap with one bit per region records whether any bit in the region
+/// was set by more than one insertion. A bit in a collision-free region belongs to a single
+/// element, so it can be cleared without affecting any other element. An element is removable if
+/// at least one of its bits is in a collision-free region.
+///
+/// The collision bitmap costs one bit per region, i.e., a few percent of the bit array for
+/// regions of tens of bits, compared to the 4 to 8 bits per counter of a counting filter. The
+/// fraction of removable elements decreases as the filter fills (see
+/// [`Self::estimated_removable_fraction`]).
+///
+/// Removing a key that was never inserted can clear a bit of another element, causing a false
+/// negative. Only remove keys that are known to be in the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::DeletableBloomFilter;
+///
+/// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+///
+/// filter.insert("a");
+/// filter.insert("b");
+/// assert!(filter.contains("a"));
+///
+/// assert!(filter.remove("a"));
+/// assert!(!filter.contains("a"));
+/// assert!(filter.contains("b"));
+/// ```
+pub struct DeletableBloomFilter<S = DefaultHashBuilder> {
+    bits: BitVec,
+    // one bit per region, set if any bit in the region was set more than once
+    collisions: BitVec,
+
+    // bit array length
+    m: usize,
+    // number of inserted elements
+    n: usize,
+    // number of hash functions
+    k: usize,
+
+    builder_1: S,
+    builder_2: S,
+}
+
+impl DeletableBloomFilter<DefaultHashBuilder> {
+    /// Creates a new deletable bloom filter given a false positive probability `p` ([0.0, 1.0]),
+    /// an expected number of inserted elements `n`, and the number of regions the bit array is
+    /// split into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `regions` is 0 or greater than the size of the bit array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    /// let _filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// ```
+    pub fn from_fpp(p: f64, n: usize, regions: usize) -> Self {
+        Self::from_fpp_with_hashers(
+            p,
+            n,
+            regions,
+            DefaultHashBuilder::new(),
+            DefaultHashBuilder::new(),
+        )
+    }
+}
+
+impl<S> DeletableBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a new deletable bloom filter given a false positive probability `p` ([0.0, 1.0]),
+    /// an expected number of inserted elements `n`, the number of regions the bit array is split
+    /// into, and two hash builders.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `regions` is 0 or greater than the size of the bit array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{DeletableBloomFilter, SeededHashBuilder};
+    ///
+    /// let _filter = DeletableBloomFilter::from_fpp_with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// ```
+    pub fn from_fpp_with_hashers(
+        p: f64,
+        n: usize,
+        regions: usize,
+        builder_1: S,
+        builder_2: S,
+    ) -> Self {
+        let m = optimal_required_bits(p, n);
+        let k = optimal_number_of_hash_functions(m, n);
+
+        assert!((1..=m).contains(&regions), "region count must be in [1, m]");
+
+        Self {
+            bits: BitVec::from_elem(m, false),
+            collisions: BitVec::from_elem(regions, false),
+            m,
+            n: 0,
+            k,
+            builder_1,
+            builder_2,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.indices(key).all(|i| self.bits[i])
+    }
+
+    /// Adds a value to the filter.
+    ///
+    /// Returns whether the value was not previously in the filter.
+    ///
+    /// Every bit that is already set marks its region as collided, even if the value was
+    /// previously in the filter. This keeps a value that is a false positive, or that is inserted
+    /// more than once, from being lost when another element is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// assert!(filter.insert("a"));
+    /// assert!(!filter.insert("a"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let indices: Vec<_> = self.indices(key).collect();
+        let mut inserted = false;
+
+        for i in indices {
+            if self.bits[i] {
+                let region = self.region(i);
+                self.collisions.set(region, true);
+            } else {
+                self.bits.set(i, true);
+                inserted = true;
+            }
+        }
+
+        if inserted {
+            self.n += 1;
+        }
+
+        inserted
+    }
+
+    /// Removes a value from the filter.
+    ///
+    /// The value's bits in collision-free regions are cleared. Returns whether at least one bit
+    /// was cleared, i.e., whether the value is now definitely not in the filter. If all of the
+    /// value's bits are in collided regions, or the value is not in the filter, the filter is
+    /// unchanged and this returns `false`.
+    ///
+    /// Only remove values that are known to be in the filter (see the
+    /// [type-level documentation](`Self`)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.remove("a"));
+    /// assert!(!filter.remove("a"));
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn remove<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        let indices: Vec<_> = self.indices(key).collect();
+        let mut removed = false;
+
+        for i in indices {
+            if !self.collisions[self.region(i)] {
+                self.bits.set(i, false);
+                removed = true;
+            }
+        }
+
+        if removed {
+            self.n = self.n.saturating_sub(1);
+        }
+
+        removed
+    }
+
+    /// Returns whether a value in the filter can be removed, i.e., whether at least one of its
+    /// bits is in a collision-free region.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// filter.insert("a");
+    /// assert!(filter.is_removable("a"));
+    ///
+    /// // Inserting a value twice marks all of its regions as collided.
+    /// filter.insert("a");
+    /// assert!(!filter.is_removable("a"));
+    /// ```
+    pub fn is_removable<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key) && self.indices(key).any(|i| !self.collisions[self.region(i)])
+    }
+
+    /// Returns the number of elements in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    /// let filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of regions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    /// let filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// assert_eq!(filter.region_count(), 64);
+    /// ```
+    pub fn region_count(&self) -> usize {
+        self.collisions.len()
+    }
+
+    /// Returns the number of regions where a bit was set more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.0001, 64, 64);
+    /// assert_eq!(filter.collided_region_count(), 0);
+    ///
+    /// filter.insert("a");
+    /// filter.insert("a");
+    /// assert!(filter.collided_region_count() > 0);
+    /// ```
+    pub fn collided_region_count(&self) -> usize {
+        self.collisions.iter().filter(|&collided| collided).count()
+    }
+
+    /// Returns the expected fraction of elements in the filter that can be removed.
+    ///
+    /// An element is removable unless all of its `k` bits are in collided regions. This is the
+    /// prediction of Rothenberg et al. (2010) for the number of elements in the filter, assuming
+    /// every probe sets a uniformly random bit. Collided regions are never cleared, so after
+    /// removals, this overestimates the fraction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::DeletableBloomFilter;
+    ///
+    /// let mut filter = DeletableBloomFilter::from_fpp(0.01, 1000, 256);
+    /// assert_eq!(filter.estimated_removable_fraction(), 1.0);
+    ///
+    /// for i in 0..1000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!(filter.estimated_removable_fraction() < 0.5);
+    /// ```
+    pub fn estimated_removable_fraction(&self) -> f64 {
+        let m = self.m as f64;
+        let k = self.k as f64;
+        let region_len = m / self.region_count() as f64;
+
+        let probes = k * self.n as f64;
+        let q = 1.0 / m;
+
+        // the probabilities that a bit is set by no probe or exactly one probe
+        let p0 = (1.0 - q).powf(probes);
+        let p1 = probes * q * (1.0 - q).powf(probes - 1.0);
+
+        // the probability that a bit of an element is not set by any other element, and all other
+        // bits in its region are set at most once
+        let clean = (1.0 - q).powf(probes - k) * (p0 + p1).powf(region_len - 1.0);
+
+        1.0 - (1.0 - clean).powf(k)
+    }
+
+    // Returns the region of bit `i`.
+    //
+    // Regions split the bit array as evenly as possible.
+    fn region(&self, i: usize) -> usize {
+        (i as u128 * self.region_count() as u128 / self.m as u128) as usize
+    }
+
+    fn indices<H: Hash + ?Sized>(&self, key: &H) -> impl Iterator<Item = usize> {
+        let (h1, h2) = DoubleHasher::new(key, &self.builder_1, &self.builder_2).hashes();
+        let m = self.m;
+        (0..self.k).map(move |i| DoubleHashing::index(h1, h2, i, m))
+    }
+}
+
+impl<S> Filter for DeletableBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn build_filter(p: f64, n: usize, regions: usize) -> DeletableBloomFilter<SeededHashBuilder> {
+        DeletableBloomFilter::from_fpp_with_hashers(
+            p,
+            n,
+            regions,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+    }
+
+    #[test]
+    fn test_region() {
+        let filter = build_filter(0.01, 1000, 7);
+        assert_eq!(filter.region(0), 0);
+        assert_eq!(filter.region(filter.m - 1), 6);
+
+        let mut counts = [0; 7];
+
+        for i in 0..filter.m {
+            counts[filter.region(i)] += 1;
+        }
+
+        let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+        assert!(max - min <= 1);
+    }
+
+    #[test]
+    fn test_remove_has_no_false_negatives() {
+        const N: usize = 10_000;
+
+        let mut filter = build_filter(0.01, N, 4096);
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        let mut removed = Vec::new();
+
+        for i in (0..N).step_by(2) {
+            if filter.remove(&i) {
+                removed.push(i);
+            }
+        }
+
+        assert!(!removed.is_empty());
+        assert!((1..N).step_by(2).all(|i| filter.contains(&i)));
+
+        let present = removed.iter().filter(|i| filter.contains(*i)).count();
+        assert_eq!(present, 0);
+
+        // Removed keys can be inserted again.
+        for i in &removed {
+            filter.insert(i);
+        }
+
+        assert!((0..N).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_remove_in_collided_regions() {
+        let mut filter = build_filter(0.01, 100, 1);
+
+        filter.insert("a");
+        filter.insert("a");
+        assert_eq!(filter.collided_region_count(), 1);
+
+        assert!(!filter.is_removable("a"));
+        assert!(!filter.remove("a"));
+        assert!(filter.contains("a"));
+        assert_eq!(filter.len(), 1);
+
+        assert!(!filter.remove("b"));
+    }
+
+    #[test]
+    fn test_estimated_removable_fraction() {
+        const N: usize = 10_000;
+
+        for &regions in &[1024, 4096, 16_384] {
+            let mut filter = build_filter(0.01, N, regions);
+
+            for i in 0..N {
+                filter.insert(&i);
+            }
+
+            let removable = (0..N).filter(|i| filter.is_removable(i)).count();
+            let actual = removable as f64 / N as f64;
+
+            let estimated = filter.estimated_removable_fraction();
+            assert!(
+                (actual - estimated).abs() < 0.05,
+                "regions = {}: {} != {}",
+                regions,
+                actual,
+                estimated
+            );
+        }
+    }
+}
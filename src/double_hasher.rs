@@ -1,9 +1,19 @@
-use std::hash::{BuildHasher, Hash, Hasher};
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    io::{self, Read},
+};
+
+/// The size of the buffer used to stream a reader through the hashers.
+pub const READ_CHUNK_SIZE: usize = 8192;
+
+// The value `h2` is mixed with when it is equal to `h1`, i.e., the 64-bit golden ratio.
+const PERTURBATION: u64 = 0x9e37_79b9_7f4a_7c15;
 
 // https://en.wikipedia.org/wiki/Double_hashing
 pub struct DoubleHasher {
     h1: u64,
     h2: u64,
+    has_equal_hashes: bool,
     i: usize,
 }
 
@@ -14,12 +24,69 @@ impl DoubleHasher {
         B: BuildHasher,
         C: BuildHasher,
     {
+        Self::from_hashes(hash(key, builder_1), hash(key, builder_2))
+    }
+
+    // If both hashes are equal, e.g., when both builders are clones of the same seeded builder,
+    // `h1 + i * h2` degenerates to multiples of `h1`. In that case, `h2` is replaced with a
+    // mix of `h1`, which keeps probe sequences as distinct as the keys' `h1` values.
+    pub fn from_hashes(h1: u64, h2: u64) -> Self {
+        let has_equal_hashes = h1 == h2;
+        let h2 = if has_equal_hashes {
+            mix(h1 ^ PERTURBATION)
+        } else {
+            h2
+        };
+
         Self {
-            h1: hash(key, builder_1),
-            h2: hash(key, builder_2),
+            h1,
+            h2,
+            has_equal_hashes,
             i: 0,
         }
     }
+
+    /// Returns the two hashes of the key, `h1` and `h2`.
+    ///
+    /// `h2` is perturbed if the builders produced equal hashes.
+    pub fn hashes(&self) -> (u64, u64) {
+        (self.h1, self.h2)
+    }
+
+    /// Returns whether the builders produced equal hashes for the key.
+    pub fn has_equal_hashes(&self) -> bool {
+        self.has_equal_hashes
+    }
+
+    /// Creates a double hasher from bytes written directly to both hashers by `f`.
+    pub fn with<B, C, F>(builder_1: &B, builder_2: &C, f: F) -> Self
+    where
+        B: BuildHasher,
+        C: BuildHasher,
+        F: FnOnce(&mut dyn Hasher),
+    {
+        let mut hasher = TeeHasher(builder_1.build_hasher(), builder_2.build_hasher());
+        f(&mut hasher);
+
+        Self::from_hashes(hasher.0.finish(), hasher.1.finish())
+    }
+
+    /// Creates a double hasher from all bytes read from `reader`, written to both hashers in
+    /// chunks of [`READ_CHUNK_SIZE`] bytes or less.
+    pub fn from_reader<B, C, R>(builder_1: &B, builder_2: &C, mut reader: R) -> io::Result<Self>
+    where
+        B: BuildHasher,
+        C: BuildHasher,
+        R: Read,
+    {
+        let mut result = Ok(());
+
+        let hasher = Self::with(builder_1, builder_2, |hasher| {
+            result = write_all_from(hasher, &mut reader);
+        });
+
+        result.map(|_| hasher)
+    }
 }
 
 impl Iterator for DoubleHasher {
@@ -46,7 +113,116 @@ where
     H: Hash + ?Sized,
     B: BuildHasher,
 {
-    let mut hasher = builder.build_hasher();
-    key.hash(&mut hasher);
-    hasher.finish()
+    builder.hash_one(key)
+}
+
+fn write_all_from<R>(hasher: &mut dyn Hasher, reader: &mut R) -> io::Result<()>
+where
+    R: Read,
+{
+    let mut buf = [0; READ_CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => hasher.write(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// The finalizer of SplitMix64.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Forwards every write to two hashers. Each typed write is forwarded as is so that hashers with
+// their own integer encodings produce the same hashes as when driven by `Hash`.
+struct TeeHasher<A, B>(A, B);
+
+macro_rules! forward_write {
+    ($($name:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $name(&mut self, n: $ty) {
+                self.0.$name(n);
+                self.1.$name(n);
+            }
+        )*
+    };
+}
+
+impl<A, B> Hasher for TeeHasher<A, B>
+where
+    A: Hasher,
+    B: Hasher,
+{
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+        self.1.write(bytes);
+    }
+
+    forward_write! {
+        write_u8: u8,
+        write_u16: u16,
+        write_u32: u32,
+        write_u64: u64,
+        write_u128: u128,
+        write_usize: usize,
+        write_i8: i8,
+        write_i16: i16,
+        write_i32: i32,
+        write_i64: i64,
+        write_i128: i128,
+        write_isize: isize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[test]
+    fn test_new_with_equal_hashes() {
+        let builder = SeededHashBuilder::new(0);
+
+        let hasher = DoubleHasher::new("a", &builder, &builder);
+        assert!(hasher.has_equal_hashes());
+
+        let (h1, h2) = hasher.hashes();
+        assert_eq!(h1, builder.hash_one("a"));
+        assert_ne!(h2, h1);
+
+        let hasher = DoubleHasher::new("a", &builder, &SeededHashBuilder::new(1));
+        assert!(!hasher.has_equal_hashes());
+    }
+
+    #[test]
+    fn test_with() {
+        let builder_1 = RandomState::new();
+        let builder_2 = RandomState::new();
+
+        let expected: Vec<_> = DoubleHasher::new(&(1u32, "a"), &builder_1, &builder_2)
+            .take(4)
+            .collect();
+
+        let actual: Vec<_> = DoubleHasher::with(&builder_1, &builder_2, |hasher| {
+            hasher.write_u32(1);
+            hasher.write(b"a");
+            hasher.write_u8(0xff);
+        })
+        .take(4)
+        .collect();
+
+        assert_eq!(actual, expected);
+    }
 }
@@ -0,0 +1,597 @@
+//! A memory-mapped Bloom filter with crash-consistent inserts.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    hash::{BuildHasher, Hash},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bit_store::{BitStore, MappedBits},
+    io::{bloom_filter_reader, crc32::Crc32, path, ReadError},
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    BloomFilter, DefaultHashBuilder, Filter,
+};
+
+const WORD_BITS: usize = 64;
+
+// word index + OR mask + checksum
+const RECORD_LEN: usize = 8 + 8 + 4;
+
+// The word index of a record that holds the number of inserted elements instead of an OR mask.
+const COUNT_RECORD_INDEX: u64 = u64::MAX;
+
+/// A Bloom filter in a memory-mapped file whose inserts survive crashes.
+///
+/// The file is a Bloom filter in the binary format (see [`crate::io`]), e.g., written by
+/// [`BloomFilter::write_to_path`]. It is mapped read-only and never modified in place. Inserts are
+/// staged in memory and appended to a journal next to the file, at the path of the file with
+/// `.journal` appended. Queries see both the mapped bits and staged inserts.
+///
+/// [`Self::commit`] syncs the journal, writes a new file with the staged inserts applied, and
+/// clears the journal. The new file replaces the old one atomically (see
+/// [`BloomFilter::write_to_path`]), so the file is always a complete, valid filter. If the process
+/// crashes after the journal is synced, the inserts are applied when the filter is next opened.
+/// An insert is durable once a commit that follows it returns. Inserts that are not committed
+/// may or may not survive a crash.
+///
+/// A commit rewrites the whole file, so it costs time proportional to the size of the filter,
+/// regardless of the number of staged inserts. Commits should be batched accordingly.
+///
+/// A journal record is a word index (`u64`), an OR mask of the bits set in that word (`u64`), and
+/// the CRC-32 of both (`u32`), all little-endian. A record with a word index of `u64::MAX` holds
+/// the number of inserted elements in place of the mask. A torn record at the end of the journal
+/// is ignored.
+///
+/// This is only available with the `mmap` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bbloom::{BloomFilter, DurableBloomFilter, SeededHashBuilder};
+///
+/// let filter: BloomFilter<SeededHashBuilder> =
+///     BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+/// filter.write_to_path("filter.bin")?;
+///
+/// let mut filter: DurableBloomFilter<SeededHashBuilder> =
+///     unsafe { DurableBloomFilter::open("filter.bin")? };
+///
+/// filter.insert("a")?;
+/// assert!(filter.contains("a"));
+///
+/// filter.commit()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct DurableBloomFilter<S = DefaultHashBuilder> {
+    filter: BloomFilter<S, MappedBits>,
+    path: PathBuf,
+    journal: BufWriter<File>,
+    // bits set since the last commit, by word index
+    staged: BTreeMap<usize, u64>,
+    // number of elements inserted since the last commit
+    staged_n: usize,
+}
+
+impl<S> DurableBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Opens a filter in the binary format (see [`crate::io`]) at `path`, using `builder_1` and
+    /// `builder_2` to hash the data.
+    ///
+    /// If a journal exists, its inserts are applied and committed first. A journal is created if
+    /// it does not exist.
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated by any other process while the filter exists
+    /// (see [`BloomFilter::map_path_with_hashers`]). The filter itself only replaces the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be mapped or is invalid, or if the journal cannot be
+    /// read or applied, e.g., when it has a record outside the bit array.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{DurableBloomFilter, SeededHashBuilder};
+    ///
+    /// let filter = unsafe {
+    ///     DurableBloomFilter::open_with_hashers(
+    ///         "filter.bin",
+    ///         SeededHashBuilder::new(0),
+    ///         SeededHashBuilder::new(1),
+    ///     )?
+    /// };
+    ///
+    /// filter.contains("a");
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub unsafe fn open_with_hashers<P>(
+        path: P,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+
+        let filter = BloomFilter::map_path_with_hashers(&path, builder_1, builder_2)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(journal_path(&path))?;
+
+        let mut journal = Vec::new();
+        file.read_to_end(&mut journal)?;
+
+        let mut durable_filter = Self {
+            filter,
+            path,
+            journal: BufWriter::new(file),
+            staged: BTreeMap::new(),
+            staged_n: 0,
+        };
+
+        if !journal.is_empty() {
+            durable_filter.replay(&journal)?;
+            durable_filter.apply().map_err(ReadError::Io)?;
+        }
+
+        Ok(durable_filter)
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// This includes elements that are inserted but not committed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{DurableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: DurableBloomFilter<SeededHashBuilder> =
+    ///     unsafe { DurableBloomFilter::open("filter.bin")? };
+    ///
+    /// filter.insert("a")?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.filter.key_indices(key).all(|i| self.get(i))
+    }
+
+    /// Inserts an element into the filter.
+    ///
+    /// The bits of the element are staged and appended to the journal, but they are not durable
+    /// until [`Self::commit`] returns. This returns whether the element was not previously in the
+    /// filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal cannot be written. The element is staged regardless, so
+    /// queries see it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{DurableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: DurableBloomFilter<SeededHashBuilder> =
+    ///     unsafe { DurableBloomFilter::open("filter.bin")? };
+    ///
+    /// assert!(filter.insert("a")?);
+    /// assert!(!filter.insert("a")?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> io::Result<bool> {
+        let mut masks = BTreeMap::new();
+
+        for i in self.filter.key_indices(key) {
+            if !self.get(i) {
+                *masks.entry(i / WORD_BITS).or_insert(0) |= 1 << (i % WORD_BITS);
+            }
+        }
+
+        if masks.is_empty() {
+            return Ok(false);
+        }
+
+        for (&i, &mask) in &masks {
+            *self.staged.entry(i).or_insert(0) |= mask;
+        }
+
+        self.staged_n += 1;
+
+        for (i, mask) in masks {
+            write_record(&mut self.journal, i as u64, mask)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Makes all inserts durable.
+    ///
+    /// This syncs the journal, atomically replaces the file with one that includes all staged
+    /// inserts, and then clears the journal. If this fails after the journal is synced, the
+    /// inserts are applied when the filter is next opened.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{DurableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: DurableBloomFilter<SeededHashBuilder> =
+    ///     unsafe { DurableBloomFilter::open("filter.bin")? };
+    ///
+    /// filter.insert("a")?;
+    /// filter.commit()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn commit(&mut self) -> io::Result<()> {
+        if self.staged.is_empty() {
+            return Ok(());
+        }
+
+        self.sync_journal()?;
+        self.apply()
+    }
+
+    /// Returns the number of elements in the filter, including elements that are not committed.
+    pub fn len(&self) -> usize {
+        self.filter.len() + self.staged_n
+    }
+
+    /// Returns `true` if there are no elements in the filter.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size of the bit array `m`.
+    pub fn capacity(&self) -> usize {
+        self.filter.capacity()
+    }
+
+    /// Returns whether there are inserts that are not committed.
+    pub fn has_staged(&self) -> bool {
+        !self.staged.is_empty()
+    }
+
+    // Appends the number of staged elements to the journal and syncs it.
+    fn sync_journal(&mut self) -> io::Result<()> {
+        write_record(&mut self.journal, COUNT_RECORD_INDEX, self.staged_n as u64)?;
+        self.journal.flush()?;
+        self.journal.get_ref().sync_data()
+    }
+
+    // Writes a new file with the staged bits, maps it, and clears the journal.
+    fn apply(&mut self) -> io::Result<()> {
+        let bits = StagedBits {
+            bits: self.filter.bit_store(),
+            staged: &self.staged,
+        };
+
+        let mut reader = bloom_filter_reader(
+            self.filter.hash_count(),
+            self.len(),
+            &bits,
+            self.filter.label(),
+            DoubleHashing::ID,
+        );
+
+        path::write_atomically(&self.path, |writer| {
+            io::copy(&mut reader, writer).map(|_| ())
+        })?;
+
+        // SAFETY: The caller of `open_with_hashers` guarantees that the file is not modified while
+        // it is mapped. The new file was fully written and is not modified after it is renamed.
+        unsafe { self.filter.remap(&self.path) }.map_err(|e| match e {
+            ReadError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        })?;
+
+        self.staged.clear();
+        self.staged_n = 0;
+
+        let file = self.journal.get_ref();
+        file.set_len(0)?;
+        file.sync_data()
+    }
+
+    // Stages the records of a journal, stopping at the first torn record.
+    fn replay(&mut self, mut journal: &[u8]) -> Result<(), ReadError> {
+        let word_count = self.filter.bit_store().word_count();
+
+        while journal.len() >= RECORD_LEN {
+            let (record, rest) = journal.split_at(RECORD_LEN);
+            journal = rest;
+
+            let (i, mask) = match read_record(record) {
+                Some(fields) => fields,
+                None => break,
+            };
+
+            if i == COUNT_RECORD_INDEX {
+                self.staged_n += mask as usize;
+            } else if i < word_count as u64 {
+                *self.staged.entry(i as usize).or_insert(0) |= mask;
+            } else {
+                return Err(ReadError::InvalidParameters);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get(&self, i: usize) -> bool {
+        let staged = self
+            .staged
+            .get(&(i / WORD_BITS))
+            .map(|word| (word >> (i % WORD_BITS)) & 1 == 1)
+            .unwrap_or(false);
+
+        staged || self.filter.bit_store().get(i)
+    }
+}
+
+impl<S> DurableBloomFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Opens a filter in the binary format (see [`crate::io`]) at `path`, creating the hash
+    /// builders using [`Default`].
+    ///
+    /// See [`Self::open_with_hashers`].
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated by any other process while the filter exists
+    /// (see [`BloomFilter::map_path_with_hashers`]). The filter itself only replaces the file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{DurableBloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: DurableBloomFilter<SeededHashBuilder> =
+    ///     unsafe { DurableBloomFilter::open("filter.bin")? };
+    ///
+    /// filter.contains("a");
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub unsafe fn open<P>(path: P) -> Result<Self, ReadError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_hashers(path, S::default(), S::default())
+    }
+}
+
+impl<S> Filter for DurableBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    // Journal errors are not surfaced. The element is staged regardless.
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key).unwrap_or(true)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+// The mapped bits with the staged bits set.
+struct StagedBits<'a> {
+    bits: &'a MappedBits,
+    staged: &'a BTreeMap<usize, u64>,
+}
+
+impl BitStore for StagedBits<'_> {
+    fn len_bits(&self) -> usize {
+        self.bits.len_bits()
+    }
+
+    fn get(&self, i: usize) -> bool {
+        (self.word(i / WORD_BITS) >> (i % WORD_BITS)) & 1 == 1
+    }
+
+    fn set(&mut self, _: usize) {
+        panic!("staged bits are read-only");
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        self.bits.word(i) | self.staged.get(&i).copied().unwrap_or(0)
+    }
+
+    fn set_word(&mut self, _: usize, _: u64) {
+        panic!("staged bits are read-only");
+    }
+}
+
+fn journal_path(path: &Path) -> PathBuf {
+    let mut journal_path = OsString::from(path.as_os_str());
+    journal_path.push(".journal");
+    PathBuf::from(journal_path)
+}
+
+fn write_record<W>(writer: &mut W, i: u64, mask: u64) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut record = [0; RECORD_LEN];
+    record[..8].copy_from_slice(&i.to_le_bytes());
+    record[8..16].copy_from_slice(&mask.to_le_bytes());
+
+    let mut crc32 = Crc32::new();
+    crc32.update(&record[..16]);
+    record[16..].copy_from_slice(&crc32.sum().to_le_bytes());
+
+    writer.write_all(&record)
+}
+
+// Returns the word index and mask of a record, or `None` if its checksum does not match.
+fn read_record(record: &[u8]) -> Option<(u64, u64)> {
+    let mut crc32 = Crc32::new();
+    crc32.update(&record[..16]);
+
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&record[16..RECORD_LEN]);
+
+    if crc32.sum() != u32::from_le_bytes(buf) {
+        return None;
+    }
+
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&record[..8]);
+    let i = u64::from_le_bytes(buf);
+    buf.copy_from_slice(&record[8..16]);
+    let mask = u64::from_le_bytes(buf);
+
+    Some((i, mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn new_filter() -> BloomFilter<SeededHashBuilder> {
+        BloomFilter::from_fpp_with_hashers(
+            0.001,
+            256,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+        .with_label(8)
+    }
+
+    fn open(src: &Path) -> Result<DurableBloomFilter<SeededHashBuilder>, ReadError> {
+        unsafe {
+            DurableBloomFilter::open_with_hashers(
+                src,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            )
+        }
+    }
+
+    #[test]
+    fn test_commit() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("bbloom-durable-commit-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let src = dir.join("filter.bin");
+
+        let mut expected = new_filter();
+
+        for i in 0..64 {
+            expected.insert(&i);
+        }
+
+        expected.write_to_path(&src)?;
+
+        let mut filter = open(&src)?;
+        assert_eq!(filter.len(), 64);
+        assert!(!filter.has_staged());
+
+        for i in 64..256 {
+            assert_eq!(filter.insert(&i)?, expected.insert(&i));
+        }
+
+        assert!(filter.has_staged());
+        assert_eq!(filter.len(), expected.len());
+        assert!((0..1024).all(|i| filter.contains(&i) == expected.contains(&i)));
+
+        // Nothing is applied to the file before a commit.
+        assert_eq!(
+            BloomFilter::<SeededHashBuilder>::read_from_path(&src)?.len(),
+            64
+        );
+
+        filter.commit()?;
+
+        assert!(!filter.has_staged());
+        assert_eq!(filter.len(), expected.len());
+        assert!((0..1024).all(|i| filter.contains(&i) == expected.contains(&i)));
+        assert_eq!(fs::read(&src)?, expected.to_bytes());
+        assert_eq!(fs::metadata(journal_path(&src))?.len(), 0);
+
+        drop(filter);
+
+        let filter = open(&src)?;
+        assert_eq!(filter.len(), expected.len());
+        assert!((0..256).all(|i| filter.contains(&i)));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_with_journal() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("bbloom-durable-journal-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let src = dir.join("filter.bin");
+
+        let mut expected = new_filter();
+        expected.write_to_path(&src)?;
+
+        let mut filter = open(&src)?;
+
+        for i in 0..128 {
+            filter.insert(&i)?;
+            expected.insert(&i);
+        }
+
+        // a crash after the journal is synced but before the file is replaced
+        filter.sync_journal()?;
+        drop(filter);
+
+        // The file is still the last committed filter, and it is valid.
+        let actual = BloomFilter::<SeededHashBuilder>::read_from_path(&src)?;
+        assert!(actual.is_empty());
+
+        // a torn record from an insert that was never committed
+        let mut journal = OpenOptions::new().append(true).open(journal_path(&src))?;
+        journal.write_all(&[0xff; RECORD_LEN - 1])?;
+        drop(journal);
+
+        let filter = open(&src)?;
+        assert!(!filter.has_staged());
+        assert_eq!(filter.len(), expected.len());
+        assert!((0..128).all(|i| filter.contains(&i)));
+        assert_eq!(fs::read(&src)?, expected.to_bytes());
+        assert_eq!(fs::metadata(journal_path(&src))?.len(), 0);
+
+        drop(filter);
+
+        // a record outside the bit array
+        let mut journal = OpenOptions::new().append(true).open(journal_path(&src))?;
+        write_record(&mut journal, 1 << 20, 1)?;
+        drop(journal);
+
+        assert!(matches!(open(&src), Err(ReadError::InvalidParameters)));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::hash::Hash;
+
+/// An approximate membership filter.
+///
+/// This is implemented by every mutable filter in the crate, so code that only inserts and tests
+/// elements can be generic over the filter type.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{BloomFilter, Filter, QuotientFilter};
+///
+/// fn insert_all<F: Filter>(filter: &mut F, keys: &[&str]) {
+///     for key in keys {
+///         filter.insert(key);
+///     }
+/// }
+///
+/// let mut bloom_filter = BloomFilter::from_fpp(0.0001, 64);
+/// insert_all(&mut bloom_filter, &["a", "b"]);
+/// assert!(bloom_filter.contains("a"));
+///
+/// let mut quotient_filter = QuotientFilter::from_fpp(0.0001, 64);
+/// insert_all(&mut quotient_filter, &["a", "b"]);
+/// assert!(quotient_filter.contains("a"));
+/// ```
+pub trait Filter {
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool;
+
+    /// Adds a value to the filter.
+    ///
+    /// Returns whether the value was not previously in the filter.
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool;
+
+    /// Returns the number of elements in the filter.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the filter contains no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
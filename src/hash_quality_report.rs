@@ -0,0 +1,73 @@
+/// The result of checking the distribution of probe indices for a sample of keys.
+///
+/// Probe indices are grouped into [`Self::BUCKETS`] equal ranges of the bit array (or one per bit
+/// if the bit array is smaller). A good hasher spreads indices uniformly across buckets.
+///
+/// A report [looks uniform](`Self::looks_uniform`) when
+///
+///   * the chi-squared statistic is at most `dof + 3.09 * sqrt(2 * dof)`, where `dof` is the
+///     number of buckets minus 1. This is a normal approximation of the critical value at a
+///     significance level of 0.001, e.g., ~324.8 for 256 buckets; and
+///   * the two hashes of a key are equal for at most 1% of the sample, i.e., the hash builders are
+///     not [correlated](`Self::is_correlated`).
+///
+/// The chi-squared test is only meaningful when each bucket is expected to receive at least ~5
+/// probes, i.e., the sample has at least `5 * buckets / k` keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HashQualityReport {
+    pub(crate) samples: u64,
+    pub(crate) loads: Vec<u64>,
+    pub(crate) chi_squared: f64,
+    pub(crate) equal_hashes: u64,
+}
+
+impl HashQualityReport {
+    /// The maximum number of buckets probe indices are grouped into.
+    pub const BUCKETS: usize = 256;
+
+    const Z: f64 = 3.09;
+    const MAX_EQUAL_HASHES_RATIO: f64 = 0.01;
+
+    /// Returns the number of sampled keys.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Returns the number of probe indices in each bucket.
+    pub fn loads(&self) -> &[u64] {
+        &self.loads
+    }
+
+    /// Returns the chi-squared statistic of the bucket loads against a uniform distribution.
+    pub fn chi_squared(&self) -> f64 {
+        self.chi_squared
+    }
+
+    /// Returns the smallest bucket load.
+    pub fn min_load(&self) -> u64 {
+        self.loads.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Returns the largest bucket load.
+    pub fn max_load(&self) -> u64 {
+        self.loads.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Returns whether the two hashes of a key were equal for more than 1% of the sample.
+    ///
+    /// Double hashing relies on the two hashes being independent. The filter perturbs the second
+    /// hash when they are equal, but this only spreads probes as well as the first hash alone does;
+    /// it usually means both hash builders are the same.
+    pub fn is_correlated(&self) -> bool {
+        self.equal_hashes as f64 > self.samples as f64 * Self::MAX_EQUAL_HASHES_RATIO
+    }
+
+    /// Returns whether the probe indices look uniformly distributed.
+    ///
+    /// See the [type-level documentation](`Self`) for the thresholds.
+    pub fn looks_uniform(&self) -> bool {
+        let dof = self.loads.len().saturating_sub(1) as f64;
+        let critical_value = dof + Self::Z * (2.0 * dof).sqrt();
+        self.chi_squared <= critical_value && !self.is_correlated()
+    }
+}
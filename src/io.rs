@@ -0,0 +1,1258 @@
+//! Binary serialization of Bloom filters.
+//!
+//! A filter is stored in a versioned format. All integers are little-endian.
+//!
+//! | field          | type      | description                                  |
+//! |----------------|-----------|----------------------------------------------|
+//! | magic number   | `[u8; 4]` | `BBLM`                                       |
+//! | version        | `u8`      | 1 or 2                                       |
+//! | kind           | `u8`      | see below                                    |
+//! | probe strategy | `u8`      | only in version 2                            |
+//! | body           |           | depends on the kind                          |
+//! | checksum       | `u32`     | CRC-32 (IEEE) of all preceding bytes         |
+//!
+//! The probe strategy is the [identifier](`crate::probe_strategy::ProbeStrategy::ID`) of the
+//! strategy used to derive probe indices. Version 1 implies the default strategy
+//! ([`crate::probe_strategy::DoubleHashing`]), and a filter that uses it is always written as
+//! version 1.
+//!
+//! The kinds are
+//!
+//!   * 0 = `BloomFilter`,
+//!   * 1 = `ScalableBloomFilter`,
+//!   * 2 = `BloomFilter` with a label,
+//!   * 3 = `QuotientFilter`,
+//!   * 4 = `CuckooFilter`, and
+//!   * 5 = `ScalableBloomFilter` with a negative cache.
+//!
+//! The body of a `BloomFilter` is a single layer:
+//!
+//! | field         | type      | description                                   |
+//! |---------------|-----------|-----------------------------------------------|
+//! | `m`           | `u64`     | size of the bit array                         |
+//! | `k`           | `u64`     | number of hash functions                      |
+//! | `n`           | `u64`     | number of inserted elements                   |
+//! | payload size  | `u64`     | size of the payload, `ceil(m / 8)`            |
+//! | payload       | `[u8]`    | bit array                                     |
+//!
+//! The body of a `BloomFilter` with a label is the label (`u64`) followed by a single layer. An
+//! unlabeled `BloomFilter` is always written as kind 0.
+//!
+//! The body of a `ScalableBloomFilter` is:
+//!
+//! | field          | type      | description                                  |
+//! |----------------|-----------|----------------------------------------------|
+//! | `n`            | `u64`     | total number of inserted elements            |
+//! | total capacity | `u64`     | total capacity of all layers                 |
+//! | last fpp       | `f64`     | false positive probability of the last layer |
+//! | layer count    | `u64`     | number of layers (> 0)                       |
+//! | layers         |           | layers, in the order they were created       |
+//!
+//! The body of a `ScalableBloomFilter` with a negative cache is the body of a
+//! `ScalableBloomFilter` followed by the negative cache as a single layer. A
+//! `ScalableBloomFilter` without a negative cache is always written as kind 1.
+//!
+//! The body of a `QuotientFilter` is:
+//!
+//! | field          | type      | description                                  |
+//! |----------------|-----------|----------------------------------------------|
+//! | `q`            | `u64`     | number of quotient bits                      |
+//! | `r`            | `u64`     | number of remainder bits                     |
+//! | `n`            | `u64`     | number of inserted elements                  |
+//! | payload size   | `u64`     | size of the payload                          |
+//! | payload        | `[u64]`   | table of `2^q` slots                         |
+//!
+//! The payload is the smallest number of words that holds the table, i.e., its size is
+//! `8 * ceil(2^q * (r + 3) / 64)`. A slot is `r + 3` bits wide: the occupied, continuation, and
+//! shifted flags (from least significant bit) followed by the remainder. Slots are packed from
+//! the least significant bit of the first word, and a slot may span two words.
+//!
+//! The body of a `CuckooFilter` is:
+//!
+//! | field            | type      | description                                |
+//! |------------------|-----------|--------------------------------------------|
+//! | bucket count     | `u64`     | number of buckets, a power of two          |
+//! | fingerprint bits | `u64`     | number of bits in a fingerprint (1-16)     |
+//! | `n`              | `u64`     | number of inserted elements                |
+//! | payload size     | `u64`     | size of the payload, `8 * bucket count`    |
+//! | payload          | `[u16]`   | 4 fingerprints per bucket, 0 if empty      |
+//!
+//! In a payload, bit `i` of the bit array is stored in byte `i / 8` at bit `7 - i % 8`, i.e.,
+//! most significant bit first. Any trailing bits in the last byte are 0.
+//!
+//! The hash builders are not stored. A filter must be read with hash builders that produce the
+//! same hashes as the ones used to build it.
+//!
+//! [`read_metadata`] reads the parameters of a `BloomFilter` or `ScalableBloomFilter` from the
+//! header without reading the payload.
+//!
+//! # Probe indices
+//!
+//! For a filter built with [`crate::SeededHashBuilder`]s, the bits set for a key are fully
+//! determined by the seeds, `m`, `k`, and the probe strategy. This derivation is guaranteed to be
+//! stable, so other implementations can query a filter in this format. All arithmetic is on
+//! unsigned 64-bit integers and wraps.
+//!
+//!   1. The key is converted to bytes. Bytes written by `insert_with` and `contains_with` are
+//!      used as is. Keys hashed through [`std::hash::Hash`] write integers in little-endian
+//!      order with `usize` and `isize` widened to 64 bits, e.g., a `u64` is its 8 bytes, and a
+//!      `str` is its UTF-8 bytes followed by `0xff`.
+//!   2. Each builder hashes the bytes using FNV-1a with an offset basis of
+//!      `0xcbf29ce484222325 ^ fmix64(seed)` and a prime of `0x100000001b3`. The hash is
+//!      `fmix64(state)`, where `fmix64` is the 64-bit finalizer of MurmurHash3. This gives `h1`
+//!      using the first builder and `h2` using the second.
+//!   3. If `h1 == h2`, `h2` is replaced with `splitmix64(h1 ^ 0x9e3779b97f4a7c15)`, where
+//!      `splitmix64` is the finalizer of SplitMix64 (`z = (z ^ (z >> 30)) * 0xbf58476d1ce4e5b9;
+//!      z = (z ^ (z >> 27)) * 0x94d049bb133111eb; z ^ (z >> 31)`).
+//!   4. Probe `i` (0 <= `i` < `k`) sets the bit at
+//!      [`ProbeStrategy::index`](crate::probe_strategy::ProbeStrategy::index)`(h1, h2, i, m)` (see
+//!      [`crate::probe_strategy`]). The index is reduced to the range `[0, m)` as a 64-bit integer
+//!      using the strategy's
+//!      [`Reduction`](crate::probe_strategy::Reduction), which is modulo `m` unless stated
+//!      otherwise.
+//!
+//! Golden vectors for this derivation are in `tests/vectors.json` in the source repository.
+
+pub(crate) mod base64;
+pub(crate) mod crc32;
+pub(crate) mod hex;
+mod metadata;
+pub(crate) mod path;
+mod serialized_reader;
+
+pub use self::{
+    metadata::{read_metadata, FilterKind, FilterMetadata},
+    serialized_reader::SerializedReader,
+};
+
+use std::{
+    convert::TryFrom,
+    error, fmt,
+    io::{self, Read, Write},
+};
+
+use self::{crc32::Crc32, serialized_reader::Segment};
+use crate::{
+    bit_store::BitStore,
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+};
+
+pub(crate) const MAGIC_NUMBER: [u8; 4] = *b"BBLM";
+pub(crate) const VERSION: u8 = 1;
+pub(crate) const VERSION_WITH_PROBE_STRATEGY: u8 = 2;
+pub(crate) const KIND_BLOOM_FILTER: u8 = 0;
+pub(crate) const KIND_SCALABLE_BLOOM_FILTER: u8 = 1;
+pub(crate) const KIND_LABELED_BLOOM_FILTER: u8 = 2;
+pub(crate) const KIND_QUOTIENT_FILTER: u8 = 3;
+pub(crate) const KIND_CUCKOO_FILTER: u8 = 4;
+pub(crate) const KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE: u8 = 5;
+
+// magic number + version + kind + m + k + n + payload size
+pub(crate) const HEADER_LEN: usize = 4 + 1 + 1 + 8 + 8 + 8 + 8;
+pub(crate) const CHECKSUM_LEN: usize = 4;
+
+/// An error returned when a serialized filter fails to be read.
+#[derive(Debug)]
+pub enum ReadError {
+    /// An I/O error, including unexpected EOF.
+    Io(io::Error),
+    /// The magic number is invalid.
+    InvalidMagicNumber([u8; 4]),
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The filter kind is invalid.
+    InvalidKind(u8),
+    /// The filter uses a different probe strategy.
+    ProbeStrategyMismatch {
+        /// The identifier of the probe strategy stored in the data.
+        actual: u8,
+        /// The identifier of the probe strategy of the filter type.
+        expected: u8,
+    },
+    /// The filter parameters are invalid.
+    InvalidParameters,
+    /// The checksum does not match the data.
+    ChecksumMismatch {
+        /// The checksum computed from the data.
+        actual: u32,
+        /// The checksum stored in the data.
+        expected: u32,
+    },
+    /// There is unexpected data after the filter.
+    TrailingData,
+}
+
+impl error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("I/O error"),
+            Self::InvalidMagicNumber(magic_number) => {
+                write!(f, "invalid magic number: {:?}", magic_number)
+            }
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version: {}", version),
+            Self::InvalidKind(kind) => write!(f, "invalid kind: {}", kind),
+            Self::ProbeStrategyMismatch { actual, expected } => write!(
+                f,
+                "probe strategy mismatch: expected {}, got {}",
+                expected, actual
+            ),
+            Self::InvalidParameters => f.write_str("invalid parameters"),
+            Self::ChecksumMismatch { actual, expected } => write!(
+                f,
+                "checksum mismatch: expected {:#010x}, got {:#010x}",
+                expected, actual
+            ),
+            Self::TrailingData => f.write_str("trailing data"),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// An error returned when a text-encoded filter fails to be decoded.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input is not valid in the text encoding.
+    InvalidEncoding,
+    /// The input is validly encoded, but the decoded data is not a valid filter.
+    InvalidFilter(ReadError),
+}
+
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidEncoding => None,
+            Self::InvalidFilter(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding => f.write_str("invalid encoding"),
+            Self::InvalidFilter(_) => f.write_str("invalid filter"),
+        }
+    }
+}
+
+// The decoded fields of a serialized Bloom filter.
+pub(crate) struct Parts {
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+    pub bits: Vec<u8>,
+    pub label: Option<u64>,
+}
+
+// The decoded fields of a serialized Bloom filter, excluding the bit array.
+pub(crate) struct Header {
+    pub m: usize,
+    pub k: usize,
+    pub n: usize,
+    pub label: Option<u64>,
+}
+
+// The decoded fields of a serialized scalable Bloom filter.
+pub(crate) struct ScalableParts {
+    pub n: usize,
+    pub total_capacity: usize,
+    pub last_fpp: f64,
+    pub layers: Vec<Parts>,
+    pub negative_cache: Option<Parts>,
+}
+
+// The decoded fields of a serialized cuckoo filter.
+pub(crate) struct CuckooParts {
+    pub bucket_count: usize,
+    pub fingerprint_bits: u32,
+    pub n: usize,
+    pub entries: Vec<u16>,
+}
+
+// The decoded fields of a serialized quotient filter.
+pub(crate) struct QuotientParts {
+    pub q: u32,
+    pub r: u32,
+    pub n: usize,
+    pub slots: Vec<u64>,
+}
+
+pub(crate) fn write_bloom_filter<W>(
+    writer: &mut W,
+    m: usize,
+    k: usize,
+    n: usize,
+    bits: &[u8],
+    label: Option<u64>,
+    probe_strategy: u8,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut writer = ChecksumWriter::new(writer);
+
+    let kind = if label.is_some() {
+        KIND_LABELED_BLOOM_FILTER
+    } else {
+        KIND_BLOOM_FILTER
+    };
+
+    write_header(&mut writer, kind, probe_strategy)?;
+
+    if let Some(label) = label {
+        writer.write_all(&label.to_le_bytes())?;
+    }
+
+    write_layer(&mut writer, m, k, n, bits)?;
+    writer.finish()
+}
+
+pub(crate) fn write_scalable_bloom_filter<'a, W, I>(
+    writer: &mut W,
+    n: usize,
+    total_capacity: usize,
+    last_fpp: f64,
+    layers: I,
+    negative_cache: Option<(usize, usize, usize, &[u8])>,
+) -> io::Result<()>
+where
+    W: Write,
+    I: ExactSizeIterator<Item = (usize, usize, usize, &'a [u8])>,
+{
+    let mut writer = ChecksumWriter::new(writer);
+
+    let kind = if negative_cache.is_some() {
+        KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE
+    } else {
+        KIND_SCALABLE_BLOOM_FILTER
+    };
+
+    write_header(&mut writer, kind, DoubleHashing::ID)?;
+    writer.write_all(&(n as u64).to_le_bytes())?;
+    writer.write_all(&(total_capacity as u64).to_le_bytes())?;
+    writer.write_all(&last_fpp.to_le_bytes())?;
+    writer.write_all(&(layers.len() as u64).to_le_bytes())?;
+
+    for (m, k, n, bits) in layers {
+        write_layer(&mut writer, m, k, n, bits)?;
+    }
+
+    if let Some((m, k, n, bits)) = negative_cache {
+        write_layer(&mut writer, m, k, n, bits)?;
+    }
+
+    writer.finish()
+}
+
+pub(crate) fn write_quotient_filter<W>(
+    writer: &mut W,
+    q: u32,
+    r: u32,
+    n: usize,
+    slots: &[u64],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut writer = ChecksumWriter::new(writer);
+
+    write_header(&mut writer, KIND_QUOTIENT_FILTER, DoubleHashing::ID)?;
+    writer.write_all(&u64::from(q).to_le_bytes())?;
+    writer.write_all(&u64::from(r).to_le_bytes())?;
+    writer.write_all(&(n as u64).to_le_bytes())?;
+    writer.write_all(&((slots.len() * 8) as u64).to_le_bytes())?;
+
+    for word in slots {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+
+    writer.finish()
+}
+
+pub(crate) fn write_cuckoo_filter<W>(
+    writer: &mut W,
+    bucket_count: usize,
+    fingerprint_bits: u32,
+    n: usize,
+    entries: &[u16],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut writer = ChecksumWriter::new(writer);
+
+    write_header(&mut writer, KIND_CUCKOO_FILTER, DoubleHashing::ID)?;
+    writer.write_all(&(bucket_count as u64).to_le_bytes())?;
+    writer.write_all(&u64::from(fingerprint_bits).to_le_bytes())?;
+    writer.write_all(&(n as u64).to_le_bytes())?;
+    writer.write_all(&((entries.len() * 2) as u64).to_le_bytes())?;
+
+    for entry in entries {
+        writer.write_all(&entry.to_le_bytes())?;
+    }
+
+    writer.finish()
+}
+
+fn write_header<W>(writer: &mut W, kind: u8, probe_strategy: u8) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(&MAGIC_NUMBER)?;
+
+    if probe_strategy == DoubleHashing::ID {
+        writer.write_all(&[VERSION, kind])
+    } else {
+        writer.write_all(&[VERSION_WITH_PROBE_STRATEGY, kind, probe_strategy])
+    }
+}
+
+fn write_layer<W>(writer: &mut W, m: usize, k: usize, n: usize, bits: &[u8]) -> io::Result<()>
+where
+    W: Write,
+{
+    write_layer_header(writer, m, k, n, bits.len())?;
+    writer.write_all(bits)
+}
+
+fn write_layer_header<W>(
+    writer: &mut W,
+    m: usize,
+    k: usize,
+    n: usize,
+    payload_len: usize,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(&(m as u64).to_le_bytes())?;
+    writer.write_all(&(k as u64).to_le_bytes())?;
+    writer.write_all(&(n as u64).to_le_bytes())?;
+    writer.write_all(&(payload_len as u64).to_le_bytes())
+}
+
+// Like `write_bloom_filter` but returns a reader that encodes the filter as it is read.
+pub(crate) fn bloom_filter_reader<'a>(
+    k: usize,
+    n: usize,
+    bits: &'a dyn BitStore,
+    label: Option<u64>,
+    probe_strategy: u8,
+) -> SerializedReader<'a> {
+    let kind = if label.is_some() {
+        KIND_LABELED_BLOOM_FILTER
+    } else {
+        KIND_BLOOM_FILTER
+    };
+
+    let mut header = Vec::new();
+    write_header(&mut header, kind, probe_strategy).expect("writing to a Vec should not fail");
+
+    if let Some(label) = label {
+        header.extend(&label.to_le_bytes());
+    }
+
+    let mut segments = vec![Segment::Bytes(header)];
+    push_layer_segments(&mut segments, k, n, bits);
+
+    SerializedReader::new(segments)
+}
+
+// Like `write_scalable_bloom_filter` but returns a reader that encodes the filter as it is read.
+pub(crate) fn scalable_bloom_filter_reader<'a, I>(
+    n: usize,
+    total_capacity: usize,
+    last_fpp: f64,
+    layers: I,
+    negative_cache: Option<(usize, usize, &'a dyn BitStore)>,
+) -> SerializedReader<'a>
+where
+    I: ExactSizeIterator<Item = (usize, usize, &'a dyn BitStore)>,
+{
+    let kind = if negative_cache.is_some() {
+        KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE
+    } else {
+        KIND_SCALABLE_BLOOM_FILTER
+    };
+
+    let mut header = Vec::new();
+    write_header(&mut header, kind, DoubleHashing::ID).expect("writing to a Vec should not fail");
+    header.extend(&(n as u64).to_le_bytes());
+    header.extend(&(total_capacity as u64).to_le_bytes());
+    header.extend(&last_fpp.to_le_bytes());
+    header.extend(&(layers.len() as u64).to_le_bytes());
+
+    let mut segments = vec![Segment::Bytes(header)];
+
+    for (k, n, bits) in layers.chain(negative_cache) {
+        push_layer_segments(&mut segments, k, n, bits);
+    }
+
+    SerializedReader::new(segments)
+}
+
+fn push_layer_segments<'a>(
+    segments: &mut Vec<Segment<'a>>,
+    k: usize,
+    n: usize,
+    bits: &'a dyn BitStore,
+) {
+    let m = bits.len_bits();
+
+    let mut header = Vec::new();
+    write_layer_header(&mut header, m, k, n, m.div_ceil(8))
+        .expect("writing to a Vec should not fail");
+
+    segments.push(Segment::Bytes(header));
+    segments.push(Segment::Payload(bits, 0));
+}
+
+pub(crate) fn read_bloom_filter<R>(reader: &mut R, probe_strategy: u8) -> Result<Parts, ReadError>
+where
+    R: Read,
+{
+    let mut reader = ChecksumReader::new(reader);
+
+    let label = match read_header(&mut reader, probe_strategy)? {
+        KIND_BLOOM_FILTER => None,
+        KIND_LABELED_BLOOM_FILTER => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            Some(u64::from_le_bytes(buf))
+        }
+        kind => return Err(ReadError::InvalidKind(kind)),
+    };
+
+    let mut parts = read_layer(&mut reader)?;
+    parts.label = label;
+
+    reader.finish()?;
+
+    Ok(parts)
+}
+
+// The size of the chunks passed to the callback of `read_bloom_filter_payload`.
+const PAYLOAD_CHUNK_LEN: usize = 8 * 1024;
+
+// Reads a serialized Bloom filter like `read_bloom_filter` but passes the payload to `f` in chunks
+// rather than collecting it.
+//
+// `validate` is called with the header before the payload is read. `f` is called with the index of
+// the first 64-bit word of each chunk and the chunk, which is a whole number of words (8 bytes)
+// except for the last chunk. Since the checksum is only known at the end, `f` may be called even if
+// reading the filter fails.
+pub(crate) fn read_bloom_filter_payload<R, V, F, E>(
+    reader: &mut R,
+    probe_strategy: u8,
+    validate: V,
+    mut f: F,
+) -> Result<Header, E>
+where
+    R: Read,
+    V: FnOnce(&Header) -> Result<(), E>,
+    F: FnMut(usize, &[u8]),
+    E: From<ReadError>,
+{
+    let mut reader = ChecksumReader::new(reader);
+
+    let label = match read_header(&mut reader, probe_strategy)? {
+        KIND_BLOOM_FILTER => None,
+        KIND_LABELED_BLOOM_FILTER => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf).map_err(ReadError::from)?;
+            Some(u64::from_le_bytes(buf))
+        }
+        kind => return Err(ReadError::InvalidKind(kind).into()),
+    };
+
+    let (mut header, payload_len) = read_layer_header(&mut reader)?;
+    header.label = label;
+
+    validate(&header)?;
+
+    let mut buf = [0; PAYLOAD_CHUNK_LEN];
+    let mut offset = 0;
+
+    while offset < payload_len {
+        let len = (payload_len - offset).min(PAYLOAD_CHUNK_LEN);
+        let chunk = &mut buf[..len];
+        reader.read_exact(chunk).map_err(ReadError::from)?;
+        f(offset / 8, chunk);
+        offset += len;
+    }
+
+    reader.finish()?;
+
+    Ok(header)
+}
+
+pub(crate) fn read_scalable_bloom_filter<R>(reader: &mut R) -> Result<ScalableParts, ReadError>
+where
+    R: Read,
+{
+    let mut reader = ChecksumReader::new(reader);
+
+    let has_negative_cache = match read_header(&mut reader, DoubleHashing::ID)? {
+        KIND_SCALABLE_BLOOM_FILTER => false,
+        KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE => true,
+        kind => return Err(ReadError::InvalidKind(kind)),
+    };
+
+    let n = read_u64_as_usize(&mut reader)?;
+    let total_capacity = read_u64_as_usize(&mut reader)?;
+
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    let last_fpp = f64::from_le_bytes(buf);
+
+    let layer_count = read_u64_as_usize(&mut reader)?;
+
+    if layer_count == 0 || !(last_fpp > 0.0 && last_fpp < 1.0) {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    // Like payloads, layers are read incrementally to avoid trusting the count for an
+    // allocation.
+    let mut layers = Vec::new();
+
+    for _ in 0..layer_count {
+        layers.push(read_layer(&mut reader)?);
+    }
+
+    let negative_cache = if has_negative_cache {
+        Some(read_layer(&mut reader)?)
+    } else {
+        None
+    };
+
+    reader.finish()?;
+
+    Ok(ScalableParts {
+        n,
+        total_capacity,
+        last_fpp,
+        layers,
+        negative_cache,
+    })
+}
+
+pub(crate) fn read_quotient_filter<R>(reader: &mut R) -> Result<QuotientParts, ReadError>
+where
+    R: Read,
+{
+    let mut reader = ChecksumReader::new(reader);
+
+    match read_header(&mut reader, DoubleHashing::ID)? {
+        KIND_QUOTIENT_FILTER => {}
+        kind => return Err(ReadError::InvalidKind(kind)),
+    }
+
+    let q = read_u64_as_usize(&mut reader)?;
+    let r = read_u64_as_usize(&mut reader)?;
+    let n = read_u64_as_usize(&mut reader)?;
+    let payload_len = read_u64_as_usize(&mut reader)?;
+
+    // A slot, i.e., the remainder and 3 flags, must fit in a word, and the fingerprint, i.e., the
+    // quotient and remainder, must fit in a hash.
+    if q == 0 || r == 0 || r > 61 || q + r > 64 || q >= usize::BITS as usize {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    let word_count = (1usize << q)
+        .checked_mul(r + 3)
+        .map(|bits| bits.div_ceil(64))
+        .ok_or(ReadError::InvalidParameters)?;
+
+    if word_count.checked_mul(8) != Some(payload_len) {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    // Like Bloom filter payloads, the payload is read incrementally so that an invalid size cannot
+    // cause a large allocation up front.
+    let mut buf = Vec::new();
+    reader
+        .by_ref()
+        .take(payload_len as u64)
+        .read_to_end(&mut buf)?;
+
+    if buf.len() != payload_len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    reader.finish()?;
+
+    let slots = buf
+        .chunks_exact(8)
+        .map(|chunk| {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        })
+        .collect();
+
+    Ok(QuotientParts {
+        q: q as u32,
+        r: r as u32,
+        n,
+        slots,
+    })
+}
+
+pub(crate) fn read_cuckoo_filter<R>(reader: &mut R) -> Result<CuckooParts, ReadError>
+where
+    R: Read,
+{
+    let mut reader = ChecksumReader::new(reader);
+
+    match read_header(&mut reader, DoubleHashing::ID)? {
+        KIND_CUCKOO_FILTER => {}
+        kind => return Err(ReadError::InvalidKind(kind)),
+    }
+
+    let bucket_count = read_u64_as_usize(&mut reader)?;
+    let fingerprint_bits = read_u64_as_usize(&mut reader)?;
+    let n = read_u64_as_usize(&mut reader)?;
+    let payload_len = read_u64_as_usize(&mut reader)?;
+
+    if !bucket_count.is_power_of_two()
+        || !(1..=16).contains(&fingerprint_bits)
+        || bucket_count.checked_mul(8) != Some(payload_len)
+    {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    // Like Bloom filter payloads, the payload is read incrementally so that an invalid size cannot
+    // cause a large allocation up front.
+    let mut buf = Vec::new();
+    reader
+        .by_ref()
+        .take(payload_len as u64)
+        .read_to_end(&mut buf)?;
+
+    if buf.len() != payload_len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    reader.finish()?;
+
+    let entries: Vec<_> = buf
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+
+    let max_fingerprint = (1u32 << fingerprint_bits) - 1;
+
+    if entries.iter().any(|&e| u32::from(e) > max_fingerprint)
+        || entries.iter().filter(|&&e| e != 0).count() != n
+    {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    Ok(CuckooParts {
+        bucket_count,
+        fingerprint_bits: fingerprint_bits as u32,
+        n,
+        entries,
+    })
+}
+
+// Reads the header, checks the probe strategy, and returns the kind.
+fn read_header<R>(reader: &mut R, expected_probe_strategy: u8) -> Result<u8, ReadError>
+where
+    R: Read,
+{
+    let (_, kind, probe_strategy) = read_header_fields(reader)?;
+
+    if probe_strategy == expected_probe_strategy {
+        Ok(kind)
+    } else {
+        Err(ReadError::ProbeStrategyMismatch {
+            actual: probe_strategy,
+            expected: expected_probe_strategy,
+        })
+    }
+}
+
+// Reads the header, returning the version, kind, and probe strategy.
+fn read_header_fields<R>(reader: &mut R) -> Result<(u8, u8, u8), ReadError>
+where
+    R: Read,
+{
+    let mut magic_number = [0; 4];
+    reader.read_exact(&mut magic_number)?;
+
+    if magic_number != MAGIC_NUMBER {
+        return Err(ReadError::InvalidMagicNumber(magic_number));
+    }
+
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+
+    let [version, kind] = buf;
+
+    let probe_strategy = match version {
+        VERSION => DoubleHashing::ID,
+        VERSION_WITH_PROBE_STRATEGY => {
+            let mut buf = [0; 1];
+            reader.read_exact(&mut buf)?;
+            buf[0]
+        }
+        _ => return Err(ReadError::UnsupportedVersion(version)),
+    };
+
+    Ok((version, kind, probe_strategy))
+}
+
+fn read_layer<R>(reader: &mut R) -> Result<Parts, ReadError>
+where
+    R: Read,
+{
+    let (header, payload_len) = read_layer_header(reader)?;
+
+    // The payload is read incrementally so that an invalid size cannot cause a large
+    // allocation up front.
+    let mut bits = Vec::new();
+    reader.take(payload_len as u64).read_to_end(&mut bits)?;
+
+    if bits.len() != payload_len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(Parts {
+        m: header.m,
+        k: header.k,
+        n: header.n,
+        bits,
+        label: None,
+    })
+}
+
+// Reads the fields of a layer that precede the payload, returning them and the size of the
+// payload.
+fn read_layer_header<R>(reader: &mut R) -> Result<(Header, usize), ReadError>
+where
+    R: Read,
+{
+    let m = read_u64_as_usize(reader)?;
+    let k = read_u64_as_usize(reader)?;
+    let n = read_u64_as_usize(reader)?;
+    let payload_len = read_u64_as_usize(reader)?;
+
+    if m == 0 || k == 0 || payload_len != m.div_ceil(8) {
+        return Err(ReadError::InvalidParameters);
+    }
+
+    let header = Header {
+        m,
+        k,
+        n,
+        label: None,
+    };
+
+    Ok((header, payload_len))
+}
+
+fn read_u64_as_usize<R>(reader: &mut R) -> Result<usize, ReadError>
+where
+    R: Read,
+{
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    let n = u64::from_le_bytes(buf);
+    usize::try_from(n).map_err(|_| ReadError::InvalidParameters)
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    crc32: Crc32,
+}
+
+impl<W> ChecksumWriter<W>
+where
+    W: Write,
+{
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc32: Crc32::new(),
+        }
+    }
+
+    // Writes the checksum of all data written so far.
+    fn finish(mut self) -> io::Result<()> {
+        let checksum = self.crc32.sum();
+        self.inner.write_all(&checksum.to_le_bytes())
+    }
+}
+
+impl<W> Write for ChecksumWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc32.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    crc32: Crc32,
+}
+
+impl<R> ChecksumReader<R>
+where
+    R: Read,
+{
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            crc32: Crc32::new(),
+        }
+    }
+
+    // Reads the stored checksum and compares it to the checksum of all data read so far.
+    fn finish(mut self) -> Result<(), ReadError> {
+        let actual = self.crc32.sum();
+
+        let mut buf = [0; CHECKSUM_LEN];
+        self.inner.read_exact(&mut buf)?;
+        let expected = u32::from_le_bytes(buf);
+
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(ReadError::ChecksumMismatch { actual, expected })
+        }
+    }
+}
+
+impl<R> Read for ChecksumReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc32.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_data() -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, 12, 3, 1, &[0b1010_0000, 0b0001_0000], None, 0).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_write_bloom_filter() {
+        let buf = build_data();
+
+        assert_eq!(buf.len(), HEADER_LEN + 2 + CHECKSUM_LEN);
+        assert_eq!(&buf[0..4], b"BBLM");
+        assert_eq!(&buf[4..6], &[VERSION, KIND_BLOOM_FILTER]);
+        assert_eq!(&buf[6..14], &12u64.to_le_bytes());
+        assert_eq!(&buf[30..38], &2u64.to_le_bytes());
+        assert_eq!(&buf[38..40], &[0b1010_0000, 0b0001_0000]);
+    }
+
+    #[test]
+    fn test_read_bloom_filter() -> Result<(), ReadError> {
+        let buf = build_data();
+        let parts = read_bloom_filter(&mut &buf[..], DoubleHashing::ID)?;
+
+        assert_eq!(parts.m, 12);
+        assert_eq!(parts.k, 3);
+        assert_eq!(parts.n, 1);
+        assert_eq!(parts.bits, [0b1010_0000, 0b0001_0000]);
+        assert_eq!(parts.label, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_bloom_filter_payload() -> Result<(), ReadError> {
+        let payload: Vec<u8> = (0..PAYLOAD_CHUNK_LEN + 3).map(|i| i as u8).collect();
+        let m = payload.len() * 8;
+
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, m, 3, 5, &payload, Some(8), 0)?;
+
+        let mut chunks = Vec::new();
+
+        let header = read_bloom_filter_payload(
+            &mut &buf[..],
+            DoubleHashing::ID,
+            |_| Ok::<_, ReadError>(()),
+            |i, chunk| chunks.push((i, chunk.to_vec())),
+        )?;
+
+        assert_eq!(header.m, m);
+        assert_eq!(header.k, 3);
+        assert_eq!(header.n, 5);
+        assert_eq!(header.label, Some(8));
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0, 0);
+        assert_eq!(chunks[1].0, PAYLOAD_CHUNK_LEN / 8);
+        assert_eq!([&chunks[0].1[..], &chunks[1].1[..]].concat(), payload);
+
+        let result = read_bloom_filter_payload(
+            &mut &buf[..],
+            DoubleHashing::ID,
+            |_| Err(ReadError::InvalidParameters),
+            |_, _| panic!("unexpected chunk"),
+        );
+        assert!(matches!(result, Err(ReadError::InvalidParameters)));
+
+        let i = buf.len() - 1;
+        buf[i] ^= 0xff;
+
+        let result = read_bloom_filter_payload(
+            &mut &buf[..],
+            DoubleHashing::ID,
+            |_| Ok::<_, ReadError>(()),
+            |_, _| {},
+        );
+        assert!(matches!(result, Err(ReadError::ChecksumMismatch { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_bloom_filter_with_probe_strategy() -> Result<(), ReadError> {
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, 12, 3, 1, &[0b1010_0000, 0b0001_0000], None, 1)?;
+
+        assert_eq!(buf.len(), HEADER_LEN + 1 + 2 + CHECKSUM_LEN);
+        assert_eq!(
+            &buf[4..7],
+            &[VERSION_WITH_PROBE_STRATEGY, KIND_BLOOM_FILTER, 1]
+        );
+
+        let parts = read_bloom_filter(&mut &buf[..], 1)?;
+        assert_eq!(parts.m, 12);
+
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::ProbeStrategyMismatch {
+                actual: 1,
+                expected: 0
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_labeled_bloom_filter() -> Result<(), ReadError> {
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, 12, 3, 1, &[0b1010_0000, 0b0001_0000], Some(8), 0)?;
+
+        assert_eq!(buf.len(), HEADER_LEN + 8 + 2 + CHECKSUM_LEN);
+        assert_eq!(&buf[4..6], &[VERSION, KIND_LABELED_BLOOM_FILTER]);
+        assert_eq!(&buf[6..14], &8u64.to_le_bytes());
+
+        let parts = read_bloom_filter(&mut &buf[..], DoubleHashing::ID)?;
+
+        assert_eq!(parts.m, 12);
+        assert_eq!(parts.label, Some(8));
+
+        assert!(matches!(
+            read_scalable_bloom_filter(&mut &buf[..]),
+            Err(ReadError::InvalidKind(KIND_LABELED_BLOOM_FILTER))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_scalable_bloom_filter() -> Result<(), ReadError> {
+        let layers = [
+            (12, 3, 1, &[0b1010_0000, 0b0001_0000][..]),
+            (8, 2, 0, &[0][..]),
+        ];
+
+        let mut buf = Vec::new();
+        write_scalable_bloom_filter(&mut buf, 1, 3, 0.01, layers.iter().copied(), None)?;
+
+        assert_eq!(&buf[4..6], &[VERSION, KIND_SCALABLE_BLOOM_FILTER]);
+
+        let parts = read_scalable_bloom_filter(&mut &buf[..])?;
+
+        assert_eq!(parts.n, 1);
+        assert_eq!(parts.total_capacity, 3);
+        assert_eq!(parts.last_fpp, 0.01);
+        assert_eq!(parts.layers.len(), 2);
+        assert_eq!(parts.layers[1].m, 8);
+        assert!(parts.negative_cache.is_none());
+
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::InvalidKind(KIND_SCALABLE_BLOOM_FILTER))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_scalable_bloom_filter_with_negative_cache() -> Result<(), ReadError> {
+        let layers = [(12, 3, 1, &[0b1010_0000, 0b0001_0000][..])];
+        let negative_cache = (16, 2, 1, &[0b0100_0000, 0b0000_0010][..]);
+
+        let mut buf = Vec::new();
+        write_scalable_bloom_filter(
+            &mut buf,
+            1,
+            3,
+            0.01,
+            layers.iter().copied(),
+            Some(negative_cache),
+        )?;
+
+        assert_eq!(
+            &buf[4..6],
+            &[VERSION, KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE]
+        );
+
+        let parts = read_scalable_bloom_filter(&mut &buf[..])?;
+        assert_eq!(parts.layers.len(), 1);
+
+        let negative_cache = parts.negative_cache.unwrap();
+        assert_eq!(negative_cache.m, 16);
+        assert_eq!(negative_cache.k, 2);
+        assert_eq!(negative_cache.bits, [0b0100_0000, 0b0000_0010]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_quotient_filter() -> Result<(), ReadError> {
+        // 2 slots * 4 bits
+        let mut buf = Vec::new();
+        write_quotient_filter(&mut buf, 1, 1, 1, &[0b1011])?;
+
+        assert_eq!(&buf[4..6], &[VERSION, KIND_QUOTIENT_FILTER]);
+
+        let parts = read_quotient_filter(&mut &buf[..])?;
+
+        assert_eq!(parts.q, 1);
+        assert_eq!(parts.r, 1);
+        assert_eq!(parts.n, 1);
+        assert_eq!(parts.slots, [0b1011]);
+
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::InvalidKind(KIND_QUOTIENT_FILTER))
+        ));
+
+        let mut buf = Vec::new();
+        write_quotient_filter(&mut buf, 1, 62, 1, &[0, 0])?;
+        assert!(matches!(
+            read_quotient_filter(&mut &buf[..]),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        let mut buf = Vec::new();
+        write_quotient_filter(&mut buf, 1, 1, 1, &[0, 0])?;
+        assert!(matches!(
+            read_quotient_filter(&mut &buf[..]),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cuckoo_filter() -> Result<(), ReadError> {
+        let mut buf = Vec::new();
+        write_cuckoo_filter(&mut buf, 1, 8, 2, &[3, 0, 0, 255])?;
+
+        assert_eq!(&buf[4..6], &[VERSION, KIND_CUCKOO_FILTER]);
+
+        let parts = read_cuckoo_filter(&mut &buf[..])?;
+
+        assert_eq!(parts.bucket_count, 1);
+        assert_eq!(parts.fingerprint_bits, 8);
+        assert_eq!(parts.n, 2);
+        assert_eq!(parts.entries, [3, 0, 0, 255]);
+
+        // a fingerprint wider than 8 bits
+        let mut buf = Vec::new();
+        write_cuckoo_filter(&mut buf, 1, 8, 2, &[3, 0, 0, 256])?;
+        assert!(matches!(
+            read_cuckoo_filter(&mut &buf[..]),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        // a count that does not match the entries
+        let mut buf = Vec::new();
+        write_cuckoo_filter(&mut buf, 1, 8, 1, &[3, 0, 0, 255])?;
+        assert!(matches!(
+            read_cuckoo_filter(&mut &buf[..]),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_bloom_filter_with_invalid_data() {
+        let mut buf = build_data();
+        buf[0] = b'b';
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::InvalidMagicNumber(_))
+        ));
+
+        let mut buf = build_data();
+        buf[4] = 3;
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::UnsupportedVersion(3))
+        ));
+
+        let mut buf = build_data();
+        buf[30] = 3;
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        let mut buf = build_data();
+        buf[38] ^= 0xff;
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..], DoubleHashing::ID),
+            Err(ReadError::ChecksumMismatch { .. })
+        ));
+
+        let buf = build_data();
+        assert!(matches!(
+            read_bloom_filter(&mut &buf[..buf.len() - 1], DoubleHashing::ID),
+            Err(ReadError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
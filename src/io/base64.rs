@@ -0,0 +1,128 @@
+// Base64 (RFC 4648) encoding and decoding.
+
+const STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const PAD: u8 = b'=';
+
+// Encodes `buf` using the standard alphabet with padding.
+pub fn encode(buf: &[u8]) -> String {
+    encode_with(buf, STANDARD_ALPHABET, true)
+}
+
+// Encodes `buf` using the URL and filename safe alphabet without padding.
+pub fn encode_url_safe(buf: &[u8]) -> String {
+    encode_with(buf, URL_SAFE_ALPHABET, false)
+}
+
+fn encode_with(buf: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut s = String::with_capacity(buf.len().div_ceil(3) * 4);
+
+    for chunk in buf.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        let len = chunk.len() + 1;
+
+        for i in 0..4 {
+            if i < len {
+                let j = ((n >> (18 - 6 * i)) & 0x3f) as usize;
+                s.push(char::from(alphabet[j]));
+            } else if pad {
+                s.push(char::from(PAD));
+            }
+        }
+    }
+
+    s
+}
+
+// Decodes `s`, which may use either the standard or URL and filename safe alphabet, with or
+// without padding.
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    let s = s
+        .strip_suffix(b"==")
+        .or_else(|| s.strip_suffix(b"="))
+        .unwrap_or(s);
+
+    if s.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut buf = Vec::with_capacity(s.len() * 3 / 4);
+
+    for chunk in s.chunks(4) {
+        let mut n = 0;
+
+        for (i, &c) in chunk.iter().enumerate() {
+            n |= u32::from(decode_char(c)?) << (18 - 6 * i);
+        }
+
+        let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        let len = chunk.len() - 1;
+
+        // Unused bits must be 0.
+        if bytes[len..].iter().any(|&b| b != 0) {
+            return None;
+        }
+
+        buf.extend_from_slice(&bytes[..len]);
+    }
+
+    Some(buf)
+}
+
+fn decode_char(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foob"), "Zm9vYg==");
+        assert_eq!(encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_encode_url_safe() {
+        assert_eq!(encode_url_safe(b"foob"), "Zm9vYg");
+        assert_eq!(encode_url_safe(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(""), Some(Vec::new()));
+        assert_eq!(decode("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(decode("Zm9v"), Some(b"foo".to_vec()));
+        assert_eq!(decode("Zm9vYg"), Some(b"foob".to_vec()));
+        assert_eq!(decode("+/8="), Some(vec![0xfb, 0xff]));
+        assert_eq!(decode("-_8"), Some(vec![0xfb, 0xff]));
+
+        assert_eq!(decode("Z"), None);
+        assert_eq!(decode("Zh=="), None);
+        assert_eq!(decode("Zm9v!"), None);
+        assert_eq!(decode("Zm=9v"), None);
+    }
+}
@@ -0,0 +1,66 @@
+// CRC-32 (IEEE 802.3), as used by gzip and zlib.
+
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut c = i as u32;
+        let mut j = 0;
+
+        while j < 8 {
+            c = if c & 1 == 1 {
+                POLYNOMIAL ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+
+            j += 1;
+        }
+
+        table[i] = c;
+        i += 1;
+    }
+
+    table
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        for &b in buf {
+            let i = ((self.state ^ u32::from(b)) & 0xff) as usize;
+            self.state = TABLE[i] ^ (self.state >> 8);
+        }
+    }
+
+    pub fn sum(&self) -> u32 {
+        !self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum() {
+        let mut crc32 = Crc32::new();
+        assert_eq!(crc32.sum(), 0);
+
+        crc32.update(b"123456789");
+        assert_eq!(crc32.sum(), 0xcbf4_3926);
+    }
+}
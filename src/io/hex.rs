@@ -0,0 +1,57 @@
+// Base16 encoding and decoding.
+
+const ALPHABET: &[u8; 16] = b"0123456789abcdef";
+
+// Encodes `buf` using lowercase digits.
+pub fn encode(buf: &[u8]) -> String {
+    let mut s = String::with_capacity(buf.len() * 2);
+
+    for &b in buf {
+        s.push(char::from(ALPHABET[usize::from(b >> 4)]));
+        s.push(char::from(ALPHABET[usize::from(b & 0x0f)]));
+    }
+
+    s
+}
+
+// Decodes `s`, which may use uppercase or lowercase digits.
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    s.chunks(2)
+        .map(|c| Some((decode_digit(c[0])? << 4) | decode_digit(c[1])?))
+        .collect()
+}
+
+fn decode_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(encode(&[0x00, 0x9f, 0xff]), "009fff");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(""), Some(Vec::new()));
+        assert_eq!(decode("009fFF"), Some(vec![0x00, 0x9f, 0xff]));
+
+        assert_eq!(decode("0"), None);
+        assert_eq!(decode("0g"), None);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{hash::BuildHasher, io::Read};
+
+use super::{
+    read_header_fields, read_layer_header, read_u64_as_usize, ReadError, KIND_BLOOM_FILTER,
+    KIND_LABELED_BLOOM_FILTER, KIND_SCALABLE_BLOOM_FILTER,
+    KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE,
+};
+use crate::{bit_store::BitStore, probe_strategy::ProbeStrategy, BloomFilter};
+
+/// The kind of a serialized filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilterKind {
+    /// A [`BloomFilter`].
+    BloomFilter,
+    /// A [`ScalableBloomFilter`](crate::ScalableBloomFilter).
+    ScalableBloomFilter,
+}
+
+/// The parameters of a serialized filter, read from its header.
+///
+/// This is returned by [`read_metadata`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterMetadata {
+    pub(crate) version: u8,
+    pub(crate) kind: FilterKind,
+    pub(crate) probe_strategy: u8,
+    pub(crate) n: usize,
+    pub(crate) label: Option<u64>,
+    pub(crate) layout: Layout,
+}
+
+// The kind-specific parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Layout {
+    BloomFilter {
+        m: usize,
+        k: usize,
+        payload_len: usize,
+    },
+    ScalableBloomFilter {
+        total_capacity: usize,
+        last_fpp: f64,
+        layer_count: usize,
+        has_negative_cache: bool,
+    },
+}
+
+impl FilterMetadata {
+    /// Returns the format version.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the kind of the filter.
+    pub fn kind(&self) -> FilterKind {
+        self.kind
+    }
+
+    /// Returns the [identifier](`ProbeStrategy::ID`) of the probe strategy.
+    pub fn probe_strategy(&self) -> u8 {
+        self.probe_strategy
+    }
+
+    /// Returns the stored number of inserted elements.
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the stored number of inserted elements is 0.
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the label of a Bloom filter.
+    pub fn label(&self) -> Option<u64> {
+        self.label
+    }
+
+    /// Returns the size of the bit array `m` of a Bloom filter.
+    ///
+    /// This is `None` for a scalable Bloom filter, where each layer has its own bit array.
+    pub fn capacity(&self) -> Option<usize> {
+        match self.layout {
+            Layout::BloomFilter { m, .. } => Some(m),
+            Layout::ScalableBloomFilter { .. } => None,
+        }
+    }
+
+    /// Returns the number of hash functions `k` of a Bloom filter.
+    ///
+    /// This is `None` for a scalable Bloom filter, where each layer has its own number.
+    pub fn hash_count(&self) -> Option<usize> {
+        match self.layout {
+            Layout::BloomFilter { k, .. } => Some(k),
+            Layout::ScalableBloomFilter { .. } => None,
+        }
+    }
+
+    /// Returns the size of the payload in bytes of a Bloom filter, i.e., `ceil(m / 8)`.
+    ///
+    /// This is `None` for a scalable Bloom filter, where each layer has its own payload.
+    pub fn payload_len(&self) -> Option<usize> {
+        match self.layout {
+            Layout::BloomFilter { payload_len, .. } => Some(payload_len),
+            Layout::ScalableBloomFilter { .. } => None,
+        }
+    }
+
+    /// Returns the total number of elements all layers of a scalable Bloom filter are sized for.
+    pub fn total_capacity(&self) -> Option<usize> {
+        match self.layout {
+            Layout::BloomFilter { .. } => None,
+            Layout::ScalableBloomFilter { total_capacity, .. } => Some(total_capacity),
+        }
+    }
+
+    /// Returns the number of layers of a scalable Bloom filter.
+    pub fn layer_count(&self) -> Option<usize> {
+        match self.layout {
+            Layout::BloomFilter { .. } => None,
+            Layout::ScalableBloomFilter { layer_count, .. } => Some(layer_count),
+        }
+    }
+
+    /// Returns the false positive probability of the last layer of a scalable Bloom filter.
+    pub fn last_fpp(&self) -> Option<f64> {
+        match self.layout {
+            Layout::BloomFilter { .. } => None,
+            Layout::ScalableBloomFilter { last_fpp, .. } => Some(last_fpp),
+        }
+    }
+
+    /// Returns whether a scalable Bloom filter has a negative cache.
+    pub fn has_negative_cache(&self) -> bool {
+        match self.layout {
+            Layout::BloomFilter { .. } => false,
+            Layout::ScalableBloomFilter {
+                has_negative_cache, ..
+            } => has_negative_cache,
+        }
+    }
+
+    /// Returns whether the serialized filter can be combined with `filter`, e.g., by
+    /// [`BloomFilter::union_from_reader`].
+    ///
+    /// This is the case when the serialized filter is a Bloom filter with the same size of the bit
+    /// array `m`, number of hash functions `k`, label, and probe strategy. The hash builders are
+    /// not stored and cannot be checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{io::read_metadata, BloomFilter};
+    ///
+    /// let filter = BloomFilter::from_fpp(0.0001, 64);
+    /// let buf = filter.to_bytes();
+    /// let metadata = read_metadata(&mut &buf[..])?;
+    ///
+    /// assert!(metadata.is_compatible_with(&BloomFilter::from_fpp(0.0001, 64)));
+    /// assert!(!metadata.is_compatible_with(&BloomFilter::from_fpp(0.001, 64)));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn is_compatible_with<S, B, P>(&self, filter: &BloomFilter<S, B, P>) -> bool
+    where
+        S: BuildHasher,
+        B: BitStore,
+        P: ProbeStrategy,
+    {
+        match self.layout {
+            Layout::BloomFilter { m, k, .. } => {
+                m == filter.capacity()
+                    && k == filter.hash_count()
+                    && self.label == filter.label()
+                    && self.probe_strategy == P::ID
+            }
+            Layout::ScalableBloomFilter { .. } => false,
+        }
+    }
+}
+
+/// Reads the metadata of a Bloom filter or scalable Bloom filter in the binary format.
+///
+/// Only the header is read, i.e., the fields that precede the payload of a Bloom filter or the
+/// first layer of a scalable Bloom filter. The reader does not need to be seekable, and it is left
+/// at the end of the header. The parameters are validated like when reading a filter, but since
+/// the payload is not read, the checksum is not verified.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{
+///     io::{read_metadata, FilterKind},
+///     BloomFilter,
+/// };
+///
+/// let mut filter = BloomFilter::new(1227, 7);
+/// filter.insert("a");
+///
+/// let buf = filter.to_bytes();
+/// let metadata = read_metadata(&mut &buf[..])?;
+///
+/// assert_eq!(metadata.kind(), FilterKind::BloomFilter);
+/// assert_eq!(metadata.capacity(), Some(1227));
+/// assert_eq!(metadata.hash_count(), Some(7));
+/// assert_eq!(metadata.len(), 1);
+/// # Ok::<(), bbloom::io::ReadError>(())
+/// ```
+pub fn read_metadata<R>(reader: &mut R) -> Result<FilterMetadata, ReadError>
+where
+    R: Read,
+{
+    let (version, kind, probe_strategy) = read_header_fields(reader)?;
+
+    match kind {
+        KIND_BLOOM_FILTER | KIND_LABELED_BLOOM_FILTER => {
+            let label = if kind == KIND_LABELED_BLOOM_FILTER {
+                let mut buf = [0; 8];
+                reader.read_exact(&mut buf)?;
+                Some(u64::from_le_bytes(buf))
+            } else {
+                None
+            };
+
+            let (header, payload_len) = read_layer_header(reader)?;
+
+            Ok(FilterMetadata {
+                version,
+                kind: FilterKind::BloomFilter,
+                probe_strategy,
+                n: header.n,
+                label,
+                layout: Layout::BloomFilter {
+                    m: header.m,
+                    k: header.k,
+                    payload_len,
+                },
+            })
+        }
+        KIND_SCALABLE_BLOOM_FILTER | KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE => {
+            let n = read_u64_as_usize(reader)?;
+            let total_capacity = read_u64_as_usize(reader)?;
+
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            let last_fpp = f64::from_le_bytes(buf);
+
+            let layer_count = read_u64_as_usize(reader)?;
+
+            if layer_count == 0 || !(last_fpp > 0.0 && last_fpp < 1.0) {
+                return Err(ReadError::InvalidParameters);
+            }
+
+            Ok(FilterMetadata {
+                version,
+                kind: FilterKind::ScalableBloomFilter,
+                probe_strategy,
+                n,
+                label: None,
+                layout: Layout::ScalableBloomFilter {
+                    total_capacity,
+                    last_fpp,
+                    layer_count,
+                    has_negative_cache: kind == KIND_SCALABLE_BLOOM_FILTER_WITH_NEGATIVE_CACHE,
+                },
+            })
+        }
+        _ => Err(ReadError::InvalidKind(kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::write_bloom_filter, probe_strategy::EnhancedDoubleHashing};
+
+    #[test]
+    fn test_read_metadata_with_probe_strategy() -> Result<(), ReadError> {
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, 12, 3, 1, &[0, 0], None, EnhancedDoubleHashing::ID)?;
+
+        let metadata = read_metadata(&mut &buf[..])?;
+
+        assert_eq!(metadata.version(), 2);
+        assert_eq!(metadata.probe_strategy(), EnhancedDoubleHashing::ID);
+
+        let filter: BloomFilter = BloomFilter::new(12, 3);
+        assert!(!metadata.is_compatible_with(&filter));
+        assert!(metadata.is_compatible_with(&filter.with_probe_strategy::<EnhancedDoubleHashing>()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_metadata_with_invalid_data() {
+        let mut buf = Vec::new();
+        write_bloom_filter(&mut buf, 12, 3, 1, &[0, 0], None, 0).unwrap();
+
+        // truncated header
+        let data = &buf[..20];
+        assert!(matches!(
+            read_metadata(&mut &data[..]),
+            Err(ReadError::Io(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+
+        // The payload size does not match m.
+        let mut data = buf.clone();
+        data[30] = 3;
+        assert!(matches!(
+            read_metadata(&mut &data[..]),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        // quotient filter
+        let mut data = buf.clone();
+        data[5] = 3;
+        assert!(matches!(
+            read_metadata(&mut &data[..]),
+            Err(ReadError::InvalidKind(3))
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+// Reading and atomically writing files.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::ReadError;
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Writes a file at `dst` using `f`, replacing any existing file atomically.
+//
+// The data is written to a temporary file in the same directory as `dst`, which is synced and
+// renamed over `dst`. If `f` or any step fails, the temporary file is removed, and `dst` is left
+// untouched.
+pub fn write_atomically<P, F>(dst: P, f: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let dst = dst.as_ref();
+    let (tmp, file) = create_temp_file(dst)?;
+
+    let result = write_and_sync(file, f).and_then(|_| fs::rename(&tmp, dst));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    result?;
+
+    sync_dir(&parent_dir(dst))
+}
+
+// Reads a file at `src` using `f`, failing if there is data after what `f` reads.
+pub fn read_from_path<P, F, T>(src: P, f: F) -> Result<T, ReadError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufReader<File>) -> Result<T, ReadError>,
+{
+    let mut reader = File::open(src).map(BufReader::new)?;
+    let value = f(&mut reader)?;
+
+    if reader.fill_buf()?.is_empty() {
+        Ok(value)
+    } else {
+        Err(ReadError::TrailingData)
+    }
+}
+
+fn create_temp_file(dst: &Path) -> io::Result<(PathBuf, File)> {
+    let file_name = dst.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "destination path does not have a file name",
+        )
+    })?;
+
+    let dir = parent_dir(dst);
+
+    loop {
+        let i = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let mut tmp_file_name = OsString::from(".");
+        tmp_file_name.push(file_name);
+        tmp_file_name.push(format!(".{}.{}.tmp", process::id(), i));
+
+        let tmp = dir.join(tmp_file_name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn write_and_sync<F>(file: File, f: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let mut writer = BufWriter::new(file);
+    f(&mut writer)?;
+
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// Syncs the directory entry of a rename. Directories cannot be opened as files on all platforms,
+// so this is only done on Unix.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        io::{Read, Write},
+    };
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> io::Result<PathBuf> {
+        let dir = env::temp_dir().join(format!("bbloom-io-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn dir_entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    #[test]
+    fn test_write_atomically() -> io::Result<()> {
+        let dir = temp_dir("write-atomically")?;
+        let dst = dir.join("filter.bin");
+
+        write_atomically(&dst, |writer| writer.write_all(b"ndls"))?;
+        assert_eq!(fs::read(&dst)?, b"ndls");
+
+        write_atomically(&dst, |writer| writer.write_all(b"bbloom"))?;
+        assert_eq!(fs::read(&dst)?, b"bbloom");
+
+        assert_eq!(dir_entries(&dir)?, [dst]);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomically_with_partial_write() -> io::Result<()> {
+        let dir = temp_dir("write-atomically-with-partial-write")?;
+        let dst = dir.join("filter.bin");
+
+        write_atomically(&dst, |writer| writer.write_all(b"ndls"))?;
+
+        // The write fails midway.
+        let result = write_atomically(&dst, |writer| {
+            writer.write_all(b"bbl")?;
+            Err(io::Error::other("simulated failure"))
+        });
+        assert!(result.is_err());
+
+        // The temporary file is truncated midway before the write fails.
+        let result = write_atomically(&dst, |writer| {
+            writer.write_all(b"bbloom")?;
+            writer.flush()?;
+            writer.get_ref().set_len(3)?;
+            Err(io::Error::other("simulated failure"))
+        });
+        assert!(result.is_err());
+
+        assert_eq!(fs::read(&dst)?, b"ndls");
+        assert_eq!(dir_entries(&dir)?, [dst]);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomically_without_file_name() {
+        let result = write_atomically("..", |writer| writer.write_all(b"ndls"));
+
+        assert!(matches!(
+            result,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn test_read_from_path() -> Result<(), Box<dyn std::error::Error>> {
+        fn read_4(reader: &mut BufReader<File>) -> Result<[u8; 4], ReadError> {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(buf)
+        }
+
+        let dir = temp_dir("read-from-path")?;
+
+        let src = dir.join("a.bin");
+        fs::write(&src, b"ndls")?;
+        assert_eq!(&read_from_path(&src, read_4)?, b"ndls");
+
+        let src = dir.join("b.bin");
+        fs::write(&src, b"bbloom")?;
+        assert!(matches!(
+            read_from_path(&src, read_4),
+            Err(ReadError::TrailingData)
+        ));
+
+        assert!(matches!(
+            read_from_path(dir.join("c.bin"), read_4),
+            Err(ReadError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
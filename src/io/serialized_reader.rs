@@ -0,0 +1,225 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    mem,
+};
+
+use super::{crc32::Crc32, CHECKSUM_LEN};
+use crate::bit_store::BitStore;
+
+// The maximum number of payload bytes encoded at a time.
+const CHUNK_LEN: usize = 8192;
+
+/// A reader of a filter in the binary format (see [`crate::io`]).
+///
+/// This produces the same bytes as writing the filter, e.g., using
+/// [`BloomFilter::write_to`](crate::BloomFilter::write_to), but incrementally. Bit arrays are
+/// encoded in fixed-size chunks as they are read, and the checksum is computed along the way, so
+/// memory use does not depend on the size of the filter. This suits passing a filter to an
+/// uploader or [`io::copy`] without first encoding it into a buffer.
+///
+/// This is created by [`BloomFilter::serialized_reader`](crate::BloomFilter::serialized_reader)
+/// and [`ScalableBloomFilter::serialized_reader`](crate::ScalableBloomFilter::serialized_reader).
+pub struct SerializedReader<'a> {
+    segments: VecDeque<Segment<'a>>,
+    buf: Vec<u8>,
+    pos: usize,
+    crc32: Crc32,
+    len: usize,
+    is_finished: bool,
+}
+
+// A part of the encoded filter, not including the checksum.
+pub(crate) enum Segment<'a> {
+    // bytes that are encoded ahead of time, e.g., a header
+    Bytes(Vec<u8>),
+    // a bit array and the number of payload bytes already encoded
+    Payload(&'a dyn BitStore, usize),
+}
+
+impl<'a> SerializedReader<'a> {
+    pub(crate) fn new(segments: Vec<Segment<'a>>) -> Self {
+        let segments_len: usize = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Bytes(bytes) => bytes.len(),
+                Segment::Payload(bits, _) => payload_len(*bits),
+            })
+            .sum();
+
+        Self {
+            segments: segments.into(),
+            buf: Vec::new(),
+            pos: 0,
+            crc32: Crc32::new(),
+            len: segments_len + CHECKSUM_LEN,
+            is_finished: false,
+        }
+    }
+
+    /// Returns the total number of bytes of the encoded filter.
+    ///
+    /// This is known before any bytes are read, e.g., for a content length, and does not change
+    /// as the reader is consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::BloomFilter;
+    /// let filter = BloomFilter::new(1227, 7);
+    /// assert_eq!(filter.serialized_reader().len_hint(), filter.to_bytes().len());
+    /// ```
+    pub fn len_hint(&self) -> usize {
+        self.len
+    }
+
+    // Encodes the next part of the filter into the buffer. This returns false at the end.
+    fn fill(&mut self) -> bool {
+        self.buf.clear();
+        self.pos = 0;
+
+        match self.segments.front_mut() {
+            Some(Segment::Bytes(bytes)) => {
+                self.buf = mem::take(bytes);
+                self.segments.pop_front();
+            }
+            Some(Segment::Payload(bits, offset)) => {
+                let len = payload_len(*bits);
+                let end = (*offset + CHUNK_LEN).min(len);
+
+                // Payload bytes are most significant bit first.
+                for i in (*offset / 8)..end.div_ceil(8) {
+                    let word = bits.word(i);
+                    self.buf
+                        .extend(word.to_le_bytes().iter().map(|b| b.reverse_bits()));
+                }
+
+                self.buf.truncate(end - *offset);
+                *offset = end;
+
+                if end == len {
+                    self.segments.pop_front();
+                }
+            }
+            None if !self.is_finished => {
+                self.buf.extend(&self.crc32.sum().to_le_bytes());
+                self.is_finished = true;
+                return true;
+            }
+            None => return false,
+        }
+
+        self.crc32.update(&self.buf);
+
+        true
+    }
+}
+
+impl Read for SerializedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buf.len() {
+            if !self.fill() {
+                return Ok(0);
+            }
+        }
+
+        let src = &self.buf[self.pos..];
+        let n = src.len().min(buf.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+fn payload_len(bits: &dyn BitStore) -> usize {
+    bits.len_bits().div_ceil(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        probe_strategy::EnhancedDoubleHashing, BloomFilter, ScalableBloomFilter, SeededHashBuilder,
+    };
+
+    // Reads all bytes using reads of `chunk_len` bytes.
+    fn read_in_chunks(mut reader: SerializedReader<'_>, chunk_len: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut chunk = vec![0; chunk_len];
+
+        loop {
+            match reader.read(&mut chunk).unwrap() {
+                0 => break,
+                n => buf.extend(&chunk[..n]),
+            }
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_read_bloom_filter() {
+        // m spans multiple chunks and is not a multiple of 8.
+        let mut filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_fpp_with_hashers(
+            0.01,
+            20_000,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        );
+
+        for i in 0..20_000 {
+            filter.insert(&i);
+        }
+
+        assert!(filter.capacity() > 8 * CHUNK_LEN && !filter.capacity().is_multiple_of(8));
+
+        let expected = filter.to_bytes();
+
+        for &chunk_len in &[1, 3, 7, 8, 4096, CHUNK_LEN + 1, expected.len()] {
+            let reader = filter.serialized_reader();
+            assert_eq!(reader.len_hint(), expected.len());
+            assert_eq!(read_in_chunks(reader, chunk_len), expected);
+        }
+    }
+
+    #[test]
+    fn test_read_bloom_filter_with_label_and_probe_strategy() {
+        let mut filter = BloomFilter::new(1227, 7)
+            .with_label(8)
+            .with_probe_strategy::<EnhancedDoubleHashing>();
+
+        filter.insert("ndls");
+
+        let expected = filter.to_bytes();
+        let reader = filter.serialized_reader();
+        assert_eq!(reader.len_hint(), expected.len());
+        assert_eq!(read_in_chunks(reader, 5), expected);
+    }
+
+    #[test]
+    fn test_read_empty_bloom_filter() {
+        let filter = BloomFilter::new(0, 1);
+        let expected = filter.to_bytes();
+        assert_eq!(read_in_chunks(filter.serialized_reader(), 1), expected);
+    }
+
+    #[test]
+    fn test_read_scalable_bloom_filter() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 100, Default::default(), Default::default())
+                .with_negative_cache(0.01, 10_000);
+
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+
+        let expected = filter.to_bytes();
+
+        for &chunk_len in &[1, 13, CHUNK_LEN] {
+            let reader = filter.serialized_reader();
+            assert_eq!(reader.len_hint(), expected.len());
+            assert_eq!(read_in_chunks(reader, chunk_len), expected);
+        }
+    }
+}
@@ -25,10 +25,70 @@
 //! assert!(!filter.contains("c"));
 //! ```
 
+pub mod bit_store;
+pub mod codegen;
+pub mod io;
+pub mod params;
+pub mod probe_strategy;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+
+mod adaptive_filter;
 mod bloom_filter;
+mod bloom_iterator_ext;
+mod counting_bloom_filter;
+mod cuckoo_filter;
+mod deletable_bloom_filter;
 mod double_hasher;
+#[cfg(feature = "mmap")]
+mod durable_bloom_filter;
+mod filter;
+mod hash_quality_report;
+mod multi_bloom_filter;
+mod normalized_bloom_filter;
+#[cfg(feature = "instrumentation")]
+mod probe_stats;
+mod quotient_filter;
 mod scalable_bloom_filter;
+mod seeded_hasher;
+#[cfg(any(test, feature = "test-util"))]
+mod shadowed_bloom_filter;
+#[cfg(feature = "arc-swap")]
+mod shared_bloom_filter;
+#[cfg(all(unix, feature = "shared-memory"))]
+mod shared_mem_bloom_filter;
+#[cfg(feature = "sqlx")]
+mod sql;
+mod static_bloom_filter;
+mod verify_report;
+
+#[cfg(feature = "roaring")]
+pub use self::bloom_filter::OutOfRangeError;
+#[cfg(feature = "mmap")]
+pub use self::durable_bloom_filter::DurableBloomFilter;
+#[cfg(any(test, feature = "test-util"))]
+pub use self::shadowed_bloom_filter::ShadowedBloomFilter;
+#[cfg(feature = "arc-swap")]
+pub use self::shared_bloom_filter::SharedBloomFilter;
+#[cfg(all(unix, feature = "shared-memory"))]
+pub use self::shared_mem_bloom_filter::SharedMemBloomFilter;
 
-pub use self::{bloom_filter::BloomFilter, scalable_bloom_filter::ScalableBloomFilter};
+pub use self::{
+    adaptive_filter::AdaptiveFilter,
+    bloom_filter::{BloomFilter, BuildError, FoldReport, IncompatibleFilters, UnionError},
+    bloom_iterator_ext::{BloomIteratorExt, BloomRetain},
+    counting_bloom_filter::CountingBloomFilter,
+    cuckoo_filter::{CuckooFilter, FullError},
+    deletable_bloom_filter::DeletableBloomFilter,
+    filter::Filter,
+    hash_quality_report::HashQualityReport,
+    multi_bloom_filter::{LabelSet, MultiBloomFilter},
+    normalized_bloom_filter::NormalizedBloomFilter,
+    quotient_filter::{QuotientFilter, ResizeError},
+    scalable_bloom_filter::{GrowError, LayerStats, ScalableBloomFilter},
+    seeded_hasher::{SeededHashBuilder, SeededHasher},
+    static_bloom_filter::StaticBloomFilter,
+    verify_report::VerifyReport,
+};
 
 type DefaultHashBuilder = std::collections::hash_map::RandomState;
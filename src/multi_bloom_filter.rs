@@ -0,0 +1,436 @@
+mod label_set;
+
+pub use self::label_set::LabelSet;
+
+use std::{
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+use crate::{
+    bloom_filter::{optimal_number_of_hash_functions, optimal_required_bits},
+    double_hasher::DoubleHasher,
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    DefaultHashBuilder,
+};
+
+// The maximum number of labels, i.e., the number of bits in a `LabelSet`.
+const MAX_LABEL_COUNT: usize = 64;
+
+/// A set of Bloom filters, one per label, that share a single hash pass per key.
+///
+/// Every label has a filter with the same size `m`, number of hash functions `k`, and hash
+/// builders, so the probe indices of a key are the same in every filter. They are derived once
+/// per operation, and [`Self::contains_labels`] tests every label with the same `k` probes.
+///
+/// The bits are interleaved: the bits of all labels for probe index `i` are adjacent, so testing
+/// all labels costs about as much as testing one.
+///
+/// Labels are indices in `[0, label_count)`, where the label count is at most 64. A label type
+/// `L` can be any type that converts into an index, e.g., a `usize` or an enum with a
+/// `From<L> for usize` impl.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::MultiBloomFilter;
+///
+/// #[derive(Clone, Copy)]
+/// enum Category {
+///     Click,
+///     View,
+/// }
+///
+/// impl From<Category> for usize {
+///     fn from(category: Category) -> Self {
+///         category as usize
+///     }
+/// }
+///
+/// let mut filter = MultiBloomFilter::from_fpp(2, 0.0001, 64);
+/// filter.insert(Category::Click, "a");
+///
+/// assert!(filter.contains(Category::Click, "a"));
+/// assert!(!filter.contains(Category::View, "a"));
+///
+/// let labels = filter.contains_labels("a");
+/// assert!(labels.contains(Category::Click));
+/// assert!(!labels.contains(Category::View));
+/// ```
+pub struct MultiBloomFilter<L = usize, S = DefaultHashBuilder> {
+    // `label_count` bits per probe index, packed into 64-bit words
+    words: Vec<u64>,
+
+    // number of labels
+    label_count: usize,
+    // bit array length of each label
+    m: usize,
+    // number of hash functions
+    k: usize,
+    // number of inserted elements of each label
+    lens: Vec<usize>,
+
+    builder_1: S,
+    builder_2: S,
+
+    labels: PhantomData<fn(L)>,
+}
+
+impl<L> MultiBloomFilter<L, DefaultHashBuilder>
+where
+    L: Into<usize>,
+{
+    /// Creates a new multi-filter with `label_count` labels that each target a false positive
+    /// probability `p` ([0.0, 1.0]) with an expected number of inserted elements `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label_count` is 0 or > 64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    /// let _filter: MultiBloomFilter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// ```
+    pub fn from_fpp(label_count: usize, p: f64, n: usize) -> Self {
+        Self::from_fpp_with_hashers(
+            label_count,
+            p,
+            n,
+            DefaultHashBuilder::new(),
+            DefaultHashBuilder::new(),
+        )
+    }
+}
+
+impl<L, S> MultiBloomFilter<L, S>
+where
+    L: Into<usize>,
+    S: BuildHasher,
+{
+    /// Creates a new multi-filter with `label_count` labels that each target a false positive
+    /// probability `p` ([0.0, 1.0]) with an expected number of inserted elements `n`, using
+    /// `builder_1` and `builder_2` to hash the data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label_count` is 0 or > 64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{MultiBloomFilter, SeededHashBuilder};
+    ///
+    /// let _filter: MultiBloomFilter<usize, _> = MultiBloomFilter::from_fpp_with_hashers(
+    ///     8,
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    /// ```
+    pub fn from_fpp_with_hashers(
+        label_count: usize,
+        p: f64,
+        n: usize,
+        builder_1: S,
+        builder_2: S,
+    ) -> Self {
+        assert!(
+            (1..=MAX_LABEL_COUNT).contains(&label_count),
+            "label count must be in [1, 64]"
+        );
+
+        let m = optimal_required_bits(p, n);
+        let k = optimal_number_of_hash_functions(m, n);
+
+        let bit_count = m.checked_mul(label_count).expect("filter too large");
+
+        Self {
+            words: vec![0; bit_count.div_ceil(64)],
+            label_count,
+            m,
+            k,
+            lens: vec![0; label_count],
+            builder_1,
+            builder_2,
+            labels: PhantomData,
+        }
+    }
+
+    /// Returns the number of labels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    /// let filter: MultiBloomFilter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// assert_eq!(filter.label_count(), 8);
+    /// ```
+    pub fn label_count(&self) -> usize {
+        self.label_count
+    }
+
+    /// Tests whether an element may be in the filter of `label` or definitely not in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label` is not less than the label count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    ///
+    /// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// filter.insert(0usize, "a");
+    ///
+    /// assert!(filter.contains(0usize, "a"));
+    /// assert!(!filter.contains(1usize, "a"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, label: L, key: &H) -> bool {
+        let label = self.label_index(label);
+        self.indices(key).all(|i| self.get(i, label))
+    }
+
+    /// Returns the labels whose filters report an element may be present.
+    ///
+    /// The key is hashed once, and every label is tested with the same probes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    ///
+    /// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// filter.insert(2usize, "a");
+    /// filter.insert(3usize, "a");
+    ///
+    /// let labels = filter.contains_labels("a");
+    /// assert_eq!(labels.iter().collect::<Vec<_>>(), [2, 3]);
+    /// assert!(filter.contains_labels("b").is_empty());
+    /// ```
+    pub fn contains_labels<H: Hash + ?Sized>(&self, key: &H) -> LabelSet {
+        let mut labels = self.label_mask();
+
+        for i in self.indices(key) {
+            labels &= self.get_labels(i);
+
+            if labels == 0 {
+                break;
+            }
+        }
+
+        LabelSet(labels)
+    }
+
+    /// Adds a value to the filter of `label`.
+    ///
+    /// Returns whether the value was not previously in the filter of `label`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label` is not less than the label count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    ///
+    /// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// assert!(filter.insert(0usize, "a"));
+    /// assert!(!filter.insert(0usize, "a"));
+    /// assert!(filter.insert(1usize, "a"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, label: L, key: &H) -> bool {
+        let label = self.label_index(label);
+        let indices: Vec<_> = self.indices(key).collect();
+
+        let mut inserted = false;
+
+        for i in indices {
+            inserted |= self.set(i, label);
+        }
+
+        if inserted {
+            self.lens[label] += 1;
+        }
+
+        inserted
+    }
+
+    /// Returns the number of elements in the filter of `label`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label` is not less than the label count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    ///
+    /// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// filter.insert(0usize, "a");
+    ///
+    /// assert_eq!(filter.len(0usize), 1);
+    /// assert_eq!(filter.len(1usize), 0);
+    /// ```
+    pub fn len(&self, label: L) -> usize {
+        self.lens[self.label_index(label)]
+    }
+
+    /// Returns `true` if the filter of `label` contains no elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label` is not less than the label count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::MultiBloomFilter;
+    ///
+    /// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+    /// filter.insert(0usize, "a");
+    ///
+    /// assert!(!filter.is_empty(0usize));
+    /// assert!(filter.is_empty(1usize));
+    /// ```
+    pub fn is_empty(&self, label: L) -> bool {
+        self.len(label) == 0
+    }
+
+    fn label_index(&self, label: L) -> usize {
+        let i = label.into();
+        assert!(i < self.label_count, "label out of bounds");
+        i
+    }
+
+    fn label_mask(&self) -> u64 {
+        u64::MAX >> (MAX_LABEL_COUNT - self.label_count)
+    }
+
+    // Returns the indices of the `k` probes of a key.
+    fn indices<H: Hash + ?Sized>(&self, key: &H) -> impl Iterator<Item = usize> {
+        let (h1, h2) = DoubleHasher::new(key, &self.builder_1, &self.builder_2).hashes();
+        let m = self.m;
+        (0..self.k).map(move |i| DoubleHashing::index(h1, h2, i, m))
+    }
+
+    // Returns the bits of all labels at probe index `i`, where bit `j` is the bit of label `j`.
+    fn get_labels(&self, i: usize) -> u64 {
+        let start = i * self.label_count;
+        let (word_index, offset) = (start / 64, start % 64);
+
+        let mut labels = self.words[word_index] >> offset;
+
+        if offset + self.label_count > 64 {
+            labels |= self.words[word_index + 1] << (64 - offset);
+        }
+
+        labels & self.label_mask()
+    }
+
+    fn get(&self, i: usize, label: usize) -> bool {
+        let j = i * self.label_count + label;
+        (self.words[j / 64] >> (j % 64)) & 1 == 1
+    }
+
+    // Sets the bit of `label` at probe index `i`, returning whether it was unset.
+    fn set(&mut self, i: usize, label: usize) -> bool {
+        let j = i * self.label_count + label;
+        let bit = 1 << (j % 64);
+        let word = &mut self.words[j / 64];
+        let was_unset = *word & bit == 0;
+        *word |= bit;
+        was_unset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn build_filter(
+        label_count: usize,
+        p: f64,
+        n: usize,
+    ) -> MultiBloomFilter<usize, SeededHashBuilder> {
+        MultiBloomFilter::from_fpp_with_hashers(
+            label_count,
+            p,
+            n,
+            SeededHashBuilder::new(0),
+            SeededHashBuilder::new(1),
+        )
+    }
+
+    #[test]
+    fn test_label_isolation() {
+        const P: f64 = 0.01;
+        const N: usize = 1000;
+
+        // 7 labels do not divide 64, so label bits of some indices span two words.
+        let mut filter = build_filter(7, P, N);
+
+        for label in 0..7 {
+            for i in 0..N {
+                filter.insert(label, &(label * N + i));
+            }
+        }
+
+        for label in 0..7 {
+            assert!(filter.len(label) > N * 99 / 100);
+
+            for i in 0..N {
+                let key = label * N + i;
+                assert!(filter.contains(label, &key));
+                assert!(filter.contains_labels(&key).contains(label));
+            }
+
+            // Keys of the other labels are only reported at about the false positive rate.
+            let other_keys = (0..7 * N).filter(|key| key / N != label);
+            let false_positives = other_keys.filter(|key| filter.contains(label, key)).count();
+            let fpp = false_positives as f64 / (6 * N) as f64;
+            assert!(fpp < 2.0 * P, "{} >= {}", fpp, 2.0 * P);
+        }
+    }
+
+    #[test]
+    fn test_contains_labels() {
+        let mut filter = build_filter(64, 0.0001, 64);
+
+        filter.insert(0, "a");
+        filter.insert(31, "a");
+        filter.insert(63, "a");
+        filter.insert(63, "b");
+
+        assert_eq!(
+            filter.contains_labels("a").iter().collect::<Vec<_>>(),
+            [0, 31, 63]
+        );
+        assert_eq!(filter.contains_labels("b").iter().collect::<Vec<_>>(), [63]);
+        assert!(filter.contains_labels("c").is_empty());
+
+        for key in &["a", "b", "c"] {
+            let labels = filter.contains_labels(key);
+            assert!((0..64).all(|label| labels.contains(label) == filter.contains(label, key)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "label out of bounds")]
+    fn test_insert_with_out_of_bounds_label() {
+        let mut filter = build_filter(8, 0.0001, 64);
+        filter.insert(8, "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "label count must be in [1, 64]")]
+    fn test_from_fpp_with_too_many_labels() {
+        build_filter(65, 0.0001, 64);
+    }
+}
@@ -0,0 +1,57 @@
+/// A set of label indices of a [`MultiBloomFilter`](crate::MultiBloomFilter).
+///
+/// This is returned by
+/// [`MultiBloomFilter::contains_labels`](crate::MultiBloomFilter::contains_labels).
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::MultiBloomFilter;
+///
+/// let mut filter = MultiBloomFilter::from_fpp(8, 0.0001, 64);
+/// filter.insert(1usize, "a");
+/// filter.insert(5usize, "a");
+///
+/// let labels = filter.contains_labels("a");
+/// assert_eq!(labels.len(), 2);
+/// assert!(labels.contains(5usize));
+/// assert_eq!(labels.iter().collect::<Vec<_>>(), [1, 5]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LabelSet(pub(crate) u64);
+
+impl LabelSet {
+    /// Returns whether the set contains `label`.
+    pub fn contains<L>(&self, label: L) -> bool
+    where
+        L: Into<usize>,
+    {
+        let i = label.into();
+        i < 64 && (self.0 >> i) & 1 == 1
+    }
+
+    /// Returns the number of labels in the set.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the label indices in the set in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let mut bits = self.0;
+
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                None
+            } else {
+                let i = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(i)
+            }
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use std::{borrow::Cow, hash::BuildHasher};
+
+use crate::{BloomFilter, DefaultHashBuilder};
+
+type Normalizer = Box<dyn Fn(&str) -> Cow<'_, str> + Send + Sync>;
+
+/// A Bloom filter of strings that normalizes every key before hashing.
+///
+/// The same normalization is applied in both [`Self::insert`] and [`Self::contains`], so keys
+/// that normalize to the same string are always treated as the same element.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use bbloom::NormalizedBloomFilter;
+///
+/// let mut filter =
+///     NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| Cow::Owned(s.to_lowercase()));
+///
+/// filter.insert("Rust");
+///
+/// assert!(filter.contains("rust"));
+/// assert!(filter.contains("RUST"));
+/// assert!(!filter.contains("bbloom"));
+/// ```
+pub struct NormalizedBloomFilter<S = DefaultHashBuilder> {
+    inner: BloomFilter<S>,
+    normalizer: Normalizer,
+}
+
+impl NormalizedBloomFilter<DefaultHashBuilder> {
+    /// Creates a new bloom filter of strings that targets a false positive probability `p`
+    /// ([0.0, 1.0]) with an expected number of inserted elements `n`, normalizing keys using `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    /// let _filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// ```
+    pub fn with_normalizer<F>(p: f64, n: usize, f: F) -> Self
+    where
+        F: Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    {
+        Self::from_filter(BloomFilter::from_fpp(p, n), f)
+    }
+}
+
+impl<S> NormalizedBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Wraps an empty bloom filter, normalizing keys using `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `filter` is not empty, as its elements may not have been normalized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::hash_map::RandomState;
+    /// use bbloom::{BloomFilter, NormalizedBloomFilter};
+    ///
+    /// let filter = BloomFilter::with_hashers(1227, 7, RandomState::new(), RandomState::new());
+    /// let _filter = NormalizedBloomFilter::from_filter(filter, |s| s.trim().into());
+    /// ```
+    pub fn from_filter<F>(filter: BloomFilter<S>, f: F) -> Self
+    where
+        F: Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    {
+        assert!(filter.is_empty(), "filter must be empty");
+
+        Self {
+            inner: filter,
+            normalizer: Box::new(f),
+        }
+    }
+
+    /// Tests whether the normalized key may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    ///
+    /// let mut filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains(" a "));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains(&self, key: &str) -> bool {
+        let key = (self.normalizer)(key);
+        self.inner.contains(key.as_ref())
+    }
+
+    /// Adds the normalized key to the filter.
+    ///
+    /// Returns whether the normalized key is already (maybe) in the filter or not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    ///
+    /// let mut filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// assert!(filter.insert("a"));
+    /// assert!(!filter.insert(" a "));
+    /// ```
+    pub fn insert(&mut self, key: &str) -> bool {
+        let key = (self.normalizer)(key);
+        self.inner.insert(key.as_ref())
+    }
+
+    /// Returns the number of elements `n` in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    ///
+    /// let mut filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// filter.insert("a");
+    /// filter.insert(" a");
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    /// let filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the underlying bloom filter.
+    ///
+    /// Keys in the underlying filter are normalized. Querying it directly skips normalization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    ///
+    /// let mut filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// filter.insert(" a ");
+    ///
+    /// assert!(filter.get_ref().contains("a"));
+    /// ```
+    pub fn get_ref(&self) -> &BloomFilter<S> {
+        &self.inner
+    }
+
+    /// Unwraps and returns the underlying bloom filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::NormalizedBloomFilter;
+    /// let filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| s.trim().into());
+    /// let filter = filter.into_inner();
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn into_inner(self) -> BloomFilter<S> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_contains_use_the_same_normalization() {
+        let mut filter = NormalizedBloomFilter::with_normalizer(0.0001, 64, |s| {
+            if s.chars().any(|c| c.is_uppercase()) {
+                Cow::Owned(s.trim().to_lowercase())
+            } else {
+                Cow::Borrowed(s.trim())
+            }
+        });
+
+        assert!(filter.insert(" Rust "));
+        assert!(filter.insert("bbloom"));
+
+        assert!(filter.contains("rust"));
+        assert!(filter.contains("RUST\n"));
+        assert!(filter.contains(" BBloom"));
+        assert!(!filter.contains("noodles"));
+
+        assert!(!filter.insert("rust"));
+        assert_eq!(filter.len(), 2);
+    }
+}
@@ -0,0 +1,444 @@
+//! Filter parameter planning.
+
+use std::convert::TryFrom;
+
+use crate::bloom_filter::{
+    false_positive_probability, optimal_number_of_hash_functions, optimal_required_bits,
+};
+
+/// The parameters of a Bloom filter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterParams {
+    m: usize,
+    k: usize,
+    fpp: f64,
+}
+
+impl FilterParams {
+    /// Returns the size of the bit array `m`.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the number of hash functions `k`.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the expected false positive probability when the planned number of elements is
+    /// inserted.
+    pub fn fpp(&self) -> f64 {
+        self.fpp
+    }
+}
+
+/// Plans a two-tier membership structure for `n` elements that targets an overall false
+/// positive probability `p` ([0.0, 1.0]), where the first tier fits in `cache_bytes`.
+///
+/// Every element is inserted into both tiers. A query checks the first tier and only checks the
+/// second tier if the first reports the element may be present. Assuming the tiers hash
+/// independently, e.g., using different seeds, the combined false positive probability is
+/// `p1 * p2`, where `p1` and `p2` are the false positive probabilities of the first and second
+/// tiers, respectively.
+///
+/// The first tier uses as many bits as fit in `cache_bytes`, up to the optimal size for `p`, with
+/// the optimal number of hash functions for that size. The second tier is sized to meet
+/// `p2 = p / p1`. Because the number of hash functions is rounded to an integer, the planned
+/// combined probability may be slightly above `p`. If the first tier alone meets `p`, the second
+/// tier is a minimal filter (`m` = 1, `k` = 1) that accepts everything.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::optimal_two_tier;
+///
+/// let (tier_1, tier_2) = optimal_two_tier(0.0001, 10000, 4096);
+///
+/// assert_eq!(tier_1.m(), 4096 * 8);
+/// assert!(tier_1.fpp() * tier_2.fpp() < 0.00011);
+/// ```
+pub fn optimal_two_tier(p: f64, n: usize, cache_bytes: usize) -> (FilterParams, FilterParams) {
+    let optimal_m = optimal_required_bits(p, n);
+    let m1 = cache_bytes.saturating_mul(8).clamp(1, optimal_m);
+    let k1 = optimal_number_of_hash_functions(m1, n);
+    let p1 = false_positive_probability(m1, k1, n);
+
+    let tier_1 = FilterParams {
+        m: m1,
+        k: k1,
+        fpp: p1,
+    };
+
+    let p2 = p / p1;
+
+    let tier_2 = if p2 < 1.0 {
+        let m2 = optimal_required_bits(p2, n);
+        let k2 = optimal_number_of_hash_functions(m2, n);
+
+        FilterParams {
+            m: m2,
+            k: k2,
+            fpp: false_positive_probability(m2, k2, n),
+        }
+    } else {
+        FilterParams {
+            m: 1,
+            k: 1,
+            fpp: 1.0,
+        }
+    };
+
+    (tier_1, tier_2)
+}
+
+/// Returns whether a filter for `n` elements that targets a false positive probability `p`
+/// ([0.0, 1.0]) fits in `max_bytes`.
+///
+/// This compares the optimal size of the bit array for `p` and `n`, rounded up to whole bytes,
+/// with `max_bytes`. It is the size of the bit array in the binary format (see [`crate::io`]) and
+/// excludes the header and the in-memory overhead of the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::is_fpp_achievable;
+///
+/// // m = 1227 bits = 154 bytes
+/// assert!(is_fpp_achievable(0.01, 128, 154));
+/// assert!(!is_fpp_achievable(0.01, 128, 153));
+/// ```
+pub fn is_fpp_achievable(p: f64, n: usize, max_bytes: usize) -> bool {
+    let m = optimal_required_bits(p, n);
+    m.div_ceil(8) <= max_bytes
+}
+
+/// A planned Bloom filter.
+///
+/// This is returned by [`plan`] and [`plan_for_fpp`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plan {
+    m: usize,
+    k: usize,
+    fpp: f64,
+}
+
+impl Plan {
+    /// Returns the size of the bit array `m`.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the number of hash functions `k`.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the expected false positive probability when the planned number of elements is
+    /// inserted.
+    pub fn fpp(&self) -> f64 {
+        self.fpp
+    }
+
+    /// Returns the size of the bit array in bytes, i.e., `ceil(m / 8)`.
+    ///
+    /// Like [`is_fpp_achievable`], this is the size of the bit array in the binary format (see
+    /// [`crate::io`]) and excludes the header and the in-memory overhead of the filter.
+    pub fn bytes(&self) -> usize {
+        self.m.div_ceil(8)
+    }
+}
+
+/// Plans a Bloom filter for `n` elements that fits in `max_bytes`.
+///
+/// The bit array uses as many whole 64-bit words as fit in `max_bytes`, with the optimal number of
+/// hash functions for that size. The planned false positive probability is the lowest that a
+/// filter of this size can promise for `n` elements. Use [`crate::BloomFilter::with_hashers`] to
+/// create a filter with the planned `m` and `k`.
+///
+/// This does not allocate.
+///
+/// # Panics
+///
+/// Panics if `max_bytes` is less than 8, i.e., less than one word.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::plan;
+///
+/// // 512 MiB for 300M keys
+/// let plan = plan(512 << 20, 300_000_000);
+///
+/// assert_eq!(plan.m(), 512 << 23);
+/// assert_eq!(plan.k(), 10);
+/// assert!(plan.fpp() < 0.0011);
+/// ```
+pub fn plan(max_bytes: usize, n: usize) -> Plan {
+    const WORD_BYTES: usize = 8;
+    const WORD_BITS: usize = 64;
+
+    let word_count = max_bytes / WORD_BYTES;
+    assert!(word_count > 0, "memory budget must be at least one word");
+
+    let m = word_count
+        .checked_mul(WORD_BITS)
+        .expect("memory budget too large");
+    let k = optimal_number_of_hash_functions(m, n);
+    let fpp = false_positive_probability(m, k, n);
+
+    Plan { m, k, fpp }
+}
+
+/// Plans a Bloom filter for `n` elements that targets a false positive probability `p`
+/// ([0.0, 1.0]).
+///
+/// The planned parameters are the same as those of [`crate::BloomFilter::from_fpp`], and
+/// [`Plan::bytes`] is the memory required for the bit array.
+///
+/// This does not allocate.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::plan_for_fpp;
+///
+/// let plan = plan_for_fpp(0.01, 128);
+///
+/// assert_eq!(plan.m(), 1227);
+/// assert_eq!(plan.k(), 7);
+/// assert_eq!(plan.bytes(), 154);
+/// ```
+pub fn plan_for_fpp(p: f64, n: usize) -> Plan {
+    let m = optimal_required_bits(p, n);
+    let k = optimal_number_of_hash_functions(m, n);
+    let fpp = false_positive_probability(m, k, n);
+
+    Plan { m, k, fpp }
+}
+
+// The number of fractional bits of fixed-point values.
+const FRAC_BITS: u32 = 62;
+
+// 1 / ln(2) in Q60, which keeps its product with a logarithm in Q62 within 128 bits.
+const LOG2_E_Q60: u128 = 0x1715_4765_2b82_fe17;
+
+// ln(2) in Q62.
+const LN_2_Q62: u128 = 0x2c5c_85fd_f473_de6b;
+
+/// Calculates the optimal size of the bit array for a target false positive probability
+/// `p = p_numerator / p_denominator` and an expected number of inserted elements `n` using only
+/// integer arithmetic.
+///
+/// This is the integer counterpart of the size used by [`crate::BloomFilter::from_fpp`], for
+/// targets without a floating-point unit, where every `f64` operation is a software call. The
+/// logarithm is approximated in 62-bit fixed point, so the result differs from the
+/// floating-point calculation by at most 1.
+///
+/// This is at least 1.
+///
+/// # Panics
+///
+/// Panics if `p_numerator` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::{optimal_bits_q32, plan_for_fpp};
+///
+/// // p = 0.01
+/// assert_eq!(optimal_bits_q32(1, 100, 128), plan_for_fpp(0.01, 128).m());
+/// ```
+pub fn optimal_bits_q32(p_numerator: u32, p_denominator: u32, n: usize) -> usize {
+    assert!(p_numerator > 0, "p_numerator must be > 0");
+
+    // m = -n * ln(p) / ln(2)^2 = n * log2(1 / p) / ln(2)
+    let log2_den = log2_q62(p_denominator);
+    let log2_num = log2_q62(p_numerator);
+
+    if log2_den <= log2_num {
+        return 1;
+    }
+
+    // log2(1 / p) / ln(2) in Q62, < 2^68
+    let x = ((log2_den - log2_num) * LOG2_E_Q60) >> 60;
+
+    let n = n as u128;
+    let int = n * (x >> FRAC_BITS);
+    let frac = (n * (x & ((1 << FRAC_BITS) - 1))).div_ceil(1 << FRAC_BITS);
+
+    usize::try_from(int + frac).unwrap_or(usize::MAX).max(1)
+}
+
+/// Calculates the optimal number of hash functions given the size of the bit array `m` and the
+/// expected number of inserted elements `n` using only integer arithmetic.
+///
+/// This is the integer counterpart of the number of hash functions used by
+/// [`crate::BloomFilter::from_fpp`] (see [`optimal_bits_q32`]). The result differs from the
+/// floating-point calculation by at most 1.
+///
+/// This is at least 1.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::params::{optimal_bits_q32, optimal_hash_functions_q32, plan_for_fpp};
+///
+/// let m = optimal_bits_q32(1, 100, 128);
+/// assert_eq!(optimal_hash_functions_q32(m, 128), plan_for_fpp(0.01, 128).k());
+/// ```
+pub fn optimal_hash_functions_q32(m: usize, n: usize) -> usize {
+    if n == 0 {
+        return 1;
+    }
+
+    // k = m / n * ln(2)
+    let k = (m as u128 * LN_2_Q62).div_ceil((n as u128) << FRAC_BITS);
+    usize::try_from(k).unwrap_or(usize::MAX).max(1)
+}
+
+// Calculates log2(x) in Q62 using the binary logarithm algorithm: the mantissa is repeatedly
+// squared, and each time it reaches 2, a fractional bit is set.
+fn log2_q62(x: u32) -> u128 {
+    assert!(x > 0);
+
+    let int = 31 - x.leading_zeros();
+
+    // the mantissa in [1, 2) in Q62
+    let mut y = u128::from(x) << (FRAC_BITS - int);
+    let mut frac = 0;
+
+    for i in (0..FRAC_BITS).rev() {
+        y = (y * y) >> FRAC_BITS;
+
+        if y >= 2 << FRAC_BITS {
+            y >>= 1;
+            frac |= 1 << i;
+        }
+    }
+
+    (u128::from(int) << FRAC_BITS) | frac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BloomFilter, SeededHashBuilder};
+
+    #[test]
+    fn test_optimal_two_tier() {
+        let (tier_1, tier_2) = optimal_two_tier(0.001, 100000, 16384);
+
+        assert_eq!(tier_1.m(), 16384 * 8);
+        assert!(tier_1.fpp() > 0.001);
+        assert!(tier_1.fpp() * tier_2.fpp() <= 0.001 * 1.05);
+    }
+
+    #[test]
+    fn test_optimal_two_tier_when_first_tier_is_sufficient() {
+        let (tier_1, tier_2) = optimal_two_tier(0.01, 128, 1 << 20);
+
+        assert_eq!(tier_1.m(), 1227);
+        assert_eq!(tier_1.k(), 7);
+        assert_eq!(tier_2.m(), 1);
+        assert_eq!(tier_2.fpp(), 1.0);
+    }
+
+    #[test]
+    fn test_is_fpp_achievable() {
+        assert!(is_fpp_achievable(0.01, 128, 154));
+        assert!(!is_fpp_achievable(0.01, 128, 153));
+        assert!(!is_fpp_achievable(0.0001, 1000000, 1 << 20));
+        assert!(!is_fpp_achievable(0.01, 0, 0));
+        assert!(is_fpp_achievable(0.01, 0, 1));
+    }
+
+    #[test]
+    fn test_plan() {
+        for &(max_bytes, n) in &[(8, 1), (1000, 1000), (1 << 20, 1_000_000), (1 << 29, 0)] {
+            let plan = plan(max_bytes, n);
+
+            assert_eq!(plan.m() % 64, 0);
+            assert!(plan.bytes() <= max_bytes);
+            assert!(max_bytes - plan.bytes() < 8);
+
+            let filter = BloomFilter::with_hashers(
+                plan.m(),
+                plan.k(),
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            );
+
+            assert_eq!(filter.expected_fpp_for(n), plan.fpp());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "memory budget must be at least one word")]
+    fn test_plan_with_budget_less_than_one_word() {
+        plan(7, 1);
+    }
+
+    #[test]
+    fn test_plan_for_fpp() {
+        for &(p, n) in &[(0.5, 1), (0.01, 128), (0.0001, 1_000_000), (0.01, 0)] {
+            let plan = plan_for_fpp(p, n);
+
+            let filter = BloomFilter::from_fpp_with_hashers(
+                p,
+                n,
+                SeededHashBuilder::new(0),
+                SeededHashBuilder::new(1),
+            );
+
+            assert_eq!(plan.m(), filter.capacity());
+            assert_eq!(plan.k(), filter.hash_count());
+            assert_eq!(filter.expected_fpp_for(n), plan.fpp());
+            assert!(plan.fpp() <= p * 1.05);
+            assert!(is_fpp_achievable(p, n, plan.bytes()));
+        }
+    }
+
+    #[test]
+    fn test_log2_q62() {
+        for &x in &[1, 2, 3, 10, 100, 1000, 12345, u32::MAX] {
+            let actual = log2_q62(x) as f64 / (1u64 << FRAC_BITS) as f64;
+            assert!((actual - f64::from(x).log2()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_q32_functions_match_float_functions() {
+        const RATIOS: [(u32, u32); 8] = [
+            (1, 2),
+            (1, 3),
+            (1, 10),
+            (1, 100),
+            (3, 1000),
+            (1, 10_000),
+            (7, 1_000_000),
+            (1, u32::MAX),
+        ];
+
+        const NS: [usize; 8] = [0, 1, 7, 128, 1000, 65_537, 10_000_000, 1 << 40];
+
+        for &(numerator, denominator) in &RATIOS {
+            let p = f64::from(numerator) / f64::from(denominator);
+
+            for &n in &NS {
+                let m = optimal_bits_q32(numerator, denominator, n);
+                let expected_m = optimal_required_bits(p, n);
+                assert!(m.abs_diff(expected_m) <= 1, "p = {}, n = {}", p, n);
+
+                let k = optimal_hash_functions_q32(m, n);
+                let expected_k = optimal_number_of_hash_functions(m, n);
+                assert!(k.abs_diff(expected_k) <= 1, "p = {}, n = {}", p, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimal_bits_q32_with_p_at_least_1() {
+        assert_eq!(optimal_bits_q32(1, 1, 128), 1);
+        assert_eq!(optimal_bits_q32(2, 1, 128), 1);
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// A running count of queries and the number of bits examined by them.
+#[derive(Debug, Default)]
+pub struct ProbeStats {
+    queries: AtomicU64,
+    probes: AtomicU64,
+}
+
+impl ProbeStats {
+    pub fn record(&self, probes: usize) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.probes.fetch_add(probes as u64, Ordering::Relaxed);
+    }
+
+    pub fn average(&self) -> f64 {
+        let queries = self.queries.load(Ordering::Relaxed);
+
+        if queries == 0 {
+            0.0
+        } else {
+            let probes = self.probes.load(Ordering::Relaxed);
+            probes as f64 / queries as f64
+        }
+    }
+
+    pub fn reset(&self) {
+        self.queries.store(0, Ordering::Relaxed);
+        self.probes.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for ProbeStats {
+    fn clone(&self) -> Self {
+        Self {
+            queries: AtomicU64::new(self.queries.load(Ordering::Relaxed)),
+            probes: AtomicU64::new(self.probes.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average() {
+        let stats = ProbeStats::default();
+        assert_eq!(stats.average(), 0.0);
+
+        stats.record(1);
+        stats.record(4);
+        assert_eq!(stats.average(), 2.5);
+
+        stats.reset();
+        assert_eq!(stats.average(), 0.0);
+    }
+}
@@ -0,0 +1,284 @@
+//! Probe index strategies.
+//!
+//! A [`BloomFilter`](crate::BloomFilter) hashes a key twice, giving `h1` and `h2`, and derives the
+//! indices of its `k` probes from these using a [`ProbeStrategy`]. The default strategy is
+//! [`DoubleHashing`].
+//!
+//! The strategy is part of the layout of a filter, so a filter must be read using the same
+//! strategy it was built with. Its identifier is stored in the binary format (see [`crate::io`]).
+//!
+//! A strategy derives a 64-bit hash for each probe and maps it to an index in `[0, m)` using a
+//! [`Reduction`].
+
+/// A method of deriving probe indices from the two hashes of a key.
+pub trait ProbeStrategy {
+    /// The identifier of the strategy in the binary format.
+    ///
+    /// Identifiers 0 to 127 are reserved for strategies in this crate.
+    const ID: u8;
+
+    /// Returns the index of probe `i` in a bit array of size `m`, given the two hashes of a key.
+    ///
+    /// The index must be less than `m`, and the same inputs must always give the same index.
+    fn index(h1: u64, h2: u64, i: usize, m: usize) -> usize;
+
+    /// The method used to map a 64-bit hash to an index in `[0, m)`.
+    ///
+    /// This determines how a filter is folded to a smaller size (see
+    /// [`BloomFilter::union_folded`](crate::BloomFilter::union_folded)), so it must match
+    /// [`Self::index`].
+    const REDUCTION: Reduction = Reduction::Modulo;
+}
+
+/// A method of mapping a 64-bit hash to an index in `[0, m)`.
+///
+/// Both methods are uniform up to rounding: some indices have one more preimage than others out of
+/// about `2^64 / m`, which is far too small to affect the false positive probability. They differ
+/// in where these indices are and in cost. The modulo places them at the low indices and needs a
+/// division. The multiply-shift spreads them evenly over the range and needs only a
+/// multiplication.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reduction {
+    /// `hash mod m`.
+    Modulo,
+    /// `(hash * m) >> 64`, as a 128-bit product.
+    ///
+    /// See "[A fast alternative to the modulo reduction][1]".
+    ///
+    /// [1]: https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/
+    MultiplyShift,
+}
+
+impl Reduction {
+    /// Maps a hash to an index in `[0, m)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::probe_strategy::Reduction;
+    ///
+    /// assert_eq!(Reduction::Modulo.reduce(13, 10), 3);
+    /// assert_eq!(Reduction::MultiplyShift.reduce(u64::MAX, 10), 9);
+    /// assert_eq!(Reduction::MultiplyShift.reduce(1 << 63, 10), 5);
+    /// ```
+    pub fn reduce(self, hash: u64, m: usize) -> usize {
+        match self {
+            Self::Modulo => (hash % m as u64) as usize,
+            Self::MultiplyShift => ((u128::from(hash) * m as u128) >> 64) as usize,
+        }
+    }
+}
+
+/// Double hashing.
+///
+/// Probe `i` is `g(i) mod m`, where `g(0) = h1`, `g(1) = h2`, and `g(i) = h1 + i * h2` for
+/// `i` >= 2, with wrapping arithmetic.
+///
+/// See "[Double hashing][1]".
+///
+/// [1]: https://en.wikipedia.org/wiki/Double_hashing
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DoubleHashing;
+
+impl ProbeStrategy for DoubleHashing {
+    const ID: u8 = 0;
+
+    fn index(h1: u64, h2: u64, i: usize, m: usize) -> usize {
+        let hash = match i {
+            0 => h1,
+            1 => h2,
+            _ => h1.wrapping_add((i as u64).wrapping_mul(h2)),
+        };
+
+        (hash % m as u64) as usize
+    }
+}
+
+/// Enhanced double hashing.
+///
+/// Probe `i` is `(h1 + i * h2 + (i^3 - i) / 6) mod m`, with wrapping arithmetic. The cubic term
+/// keeps probes apart when `h2` is a multiple of a factor of `m`, which reduces collisions
+/// between the probes of a key.
+///
+/// See "[Enhanced double hashing][1]".
+///
+/// [1]: https://en.wikipedia.org/wiki/Double_hashing#Enhanced_double_hashing
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EnhancedDoubleHashing;
+
+impl ProbeStrategy for EnhancedDoubleHashing {
+    const ID: u8 = 1;
+
+    fn index(h1: u64, h2: u64, i: usize, m: usize) -> usize {
+        let i = i as u64;
+
+        // (i^3 - i) / 6 = (i - 1) * i * (i + 1) / 6, which is always an integer.
+        let cubic = i.wrapping_sub(1).wrapping_mul(i).wrapping_mul(i + 1) / 6;
+
+        let hash = h1.wrapping_add(i.wrapping_mul(h2)).wrapping_add(cubic);
+
+        (hash % m as u64) as usize
+    }
+}
+
+/// Double hashing with a multiply-shift reduction.
+///
+/// Probe `i` is `(g(i) * m) >> 64`, where `g` is the sequence of [`DoubleHashing`] and the
+/// product is computed with 128 bits (see [`Reduction::MultiplyShift`]). This avoids the division
+/// of the modulo reduction, which makes it faster when `m` is not a power of two.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FastRangeDoubleHashing;
+
+impl ProbeStrategy for FastRangeDoubleHashing {
+    const ID: u8 = 2;
+
+    const REDUCTION: Reduction = Reduction::MultiplyShift;
+
+    fn index(h1: u64, h2: u64, i: usize, m: usize) -> usize {
+        let hash = match i {
+            0 => h1,
+            1 => h2,
+            _ => h1.wrapping_add((i as u64).wrapping_mul(h2)),
+        };
+
+        Self::REDUCTION.reduce(hash, m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, Hasher};
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn hash_pairs() -> impl Iterator<Item = (u64, u64)> {
+        let builder = SeededHashBuilder::new(0);
+
+        let hash = move |n: u64| {
+            let mut hasher = builder.build_hasher();
+            hasher.write_u64(n);
+            hasher.finish()
+        };
+
+        (0..256).map(move |n| (hash(2 * n), hash(2 * n + 1))).chain(
+            [(0, 0), (u64::MAX, u64::MAX), (1, 0), (0, 1)]
+                .iter()
+                .copied(),
+        )
+    }
+
+    fn assert_indices_in_range_and_deterministic<P>()
+    where
+        P: ProbeStrategy,
+    {
+        for &m in &[1, 2, 7, 64, 1227, 1 << 20] {
+            for &k in &[1, 2, 7, 32] {
+                for (h1, h2) in hash_pairs() {
+                    let indices: Vec<_> = (0..k).map(|i| P::index(h1, h2, i, m)).collect();
+
+                    assert_eq!(indices.len(), k);
+                    assert!(indices.iter().all(|&i| i < m));
+
+                    let again: Vec<_> = (0..k).map(|i| P::index(h1, h2, i, m)).collect();
+                    assert_eq!(again, indices);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_hashing() {
+        assert_indices_in_range_and_deterministic::<DoubleHashing>();
+
+        assert_eq!(DoubleHashing::index(3, 5, 0, 100), 3);
+        assert_eq!(DoubleHashing::index(3, 5, 1, 100), 5);
+        assert_eq!(DoubleHashing::index(3, 5, 2, 100), 13);
+        assert_eq!(DoubleHashing::index(3, 5, 3, 10), 8);
+    }
+
+    #[test]
+    fn test_enhanced_double_hashing() {
+        assert_indices_in_range_and_deterministic::<EnhancedDoubleHashing>();
+
+        // h1 + i * h2 + (i^3 - i) / 6
+        assert_eq!(EnhancedDoubleHashing::index(3, 5, 0, 100), 3);
+        assert_eq!(EnhancedDoubleHashing::index(3, 5, 1, 100), 8);
+        assert_eq!(EnhancedDoubleHashing::index(3, 5, 2, 100), 14);
+        assert_eq!(EnhancedDoubleHashing::index(3, 5, 3, 100), 22);
+    }
+
+    #[test]
+    fn test_fast_range_double_hashing() {
+        assert_indices_in_range_and_deterministic::<FastRangeDoubleHashing>();
+
+        // (g(i) * m) >> 64
+        let h1 = 1 << 62;
+        let h2 = 3 << 62;
+        assert_eq!(FastRangeDoubleHashing::index(h1, h2, 0, 100), 25);
+        assert_eq!(FastRangeDoubleHashing::index(h1, h2, 1, 100), 75);
+        // h1 + 2 * h2 = 7 * 2^62 (mod 2^64) = 3 * 2^62
+        assert_eq!(FastRangeDoubleHashing::index(h1, h2, 2, 100), 75);
+    }
+
+    // Returns the chi-squared statistic of the indices of a strategy over equal-width buckets of
+    // the bit array, given the hashes of 16384 keys.
+    fn chi_squared<P: ProbeStrategy>(m: usize, k: usize, bucket_count: usize) -> f64 {
+        let mut counts = vec![0u64; bucket_count];
+
+        for (h1, h2) in (0..16_384).map(|n| {
+            let builder = SeededHashBuilder::new(0);
+            let mut hasher = builder.build_hasher();
+            hasher.write_u64(n);
+            let h1 = hasher.finish();
+            hasher.write_u64(n);
+            (h1, hasher.finish())
+        }) {
+            for i in 0..k {
+                let index = P::index(h1, h2, i, m);
+                counts[index * bucket_count / m] += 1;
+            }
+        }
+
+        let total: u64 = counts.iter().sum();
+        let expected = total as f64 / bucket_count as f64;
+
+        counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn test_fast_range_double_hashing_is_uniform() {
+        // a prime m, so the reduction is never a bit mask
+        const M: usize = 10_007;
+        const BUCKET_COUNT: usize = 16;
+        const K: usize = 4;
+
+        let fast_range = chi_squared::<FastRangeDoubleHashing>(M, K, BUCKET_COUNT);
+        let modulo = chi_squared::<DoubleHashing>(M, K, BUCKET_COUNT);
+
+        // The 99.9th percentile of the chi-squared distribution with 15 degrees of freedom is
+        // about 37.7.
+        assert!(fast_range < 37.7, "fast range chi-squared = {}", fast_range);
+        assert!(modulo < 37.7, "modulo chi-squared = {}", modulo);
+
+        // The bias of the modulo reduction of a 64-bit hash is about m / 2^64, far below sampling
+        // noise, so fast range is only required to be no worse than modulo within two standard
+        // deviations of the statistic (sqrt(2 * 15) ~= 5.5).
+        assert!(
+            fast_range <= modulo + 11.0,
+            "fast range chi-squared = {}, modulo chi-squared = {}",
+            fast_range,
+            modulo
+        );
+    }
+
+    #[test]
+    fn test_ids_are_unique() {
+        assert_ne!(DoubleHashing::ID, EnhancedDoubleHashing::ID);
+        assert_ne!(DoubleHashing::ID, FastRangeDoubleHashing::ID);
+        assert_ne!(EnhancedDoubleHashing::ID, FastRangeDoubleHashing::ID);
+    }
+}
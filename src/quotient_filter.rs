@@ -0,0 +1,1120 @@
+mod resize_error;
+
+pub use self::resize_error::ResizeError;
+
+use std::{
+    collections::VecDeque,
+    hash::{BuildHasher, Hash},
+    io::{self, Read, Write},
+};
+
+use crate::{
+    io::{read_quotient_filter, write_quotient_filter, ReadError},
+    DefaultHashBuilder, Filter, IncompatibleFilters,
+};
+
+// slot flags, from least significant bit
+const OCCUPIED: u64 = 0b001;
+const CONTINUATION: u64 = 0b010;
+const SHIFTED: u64 = 0b100;
+const FLAG_BITS: u32 = 3;
+
+// the maximum number of remainder bits, so that a slot fits in a word
+const MAX_REMAINDER_BITS: u32 = 64 - FLAG_BITS;
+
+/// A probabilistic data structure that stores compact fingerprints of elements.
+///
+/// A key is hashed to a fingerprint of `q + r` bits. The high `q` bits (the quotient) select one
+/// of `2^q` slots, and the low `r` bits (the remainder) are stored in the table, alongside 3 flags
+/// per slot that track runs of remainders pushed out of their canonical slots.
+///
+/// Unlike a Bloom filter, a quotient filter supports [removing](`Self::remove`) elements and
+/// [resizing](`Self::resize`) without rehashing the original keys: resizing moves bits from the
+/// remainder to the quotient. The false positive probability is roughly `a / 2^r`, where `a` is
+/// the load factor. The table is kept at most 75% full; inserting into a full filter doubles its
+/// capacity.
+///
+/// Elements that share a fingerprint are stored once. Removing one removes the other, and removing
+/// a key that was never inserted can remove another key that shares its fingerprint. Only remove
+/// keys that are known to be in the filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::QuotientFilter;
+///
+/// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+///
+/// filter.insert("a");
+/// filter.insert("b");
+/// assert!(filter.contains("a"));
+///
+/// filter.remove("a");
+/// assert!(!filter.contains("a"));
+/// assert!(filter.contains("b"));
+/// ```
+pub struct QuotientFilter<S = DefaultHashBuilder> {
+    // number of quotient bits `q`
+    q: u32,
+    // number of remainder bits `r`
+    r: u32,
+    // number of inserted elements `n`
+    n: usize,
+    // table of `2^q` packed slots
+    slots: Vec<u64>,
+    builder: S,
+}
+
+impl QuotientFilter<DefaultHashBuilder> {
+    /// Creates a new quotient filter given a false positive probability `p` ([0.0, 1.0]) and an
+    /// expected number of inserted elements `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fingerprint for `p` and `n` does not fit in 64 bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let _filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// ```
+    pub fn from_fpp(p: f64, n: usize) -> Self {
+        Self::from_fpp_with_hasher(p, n, DefaultHashBuilder::new())
+    }
+}
+
+impl<S> QuotientFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a new quotient filter given a false positive probability `p` ([0.0, 1.0]), an
+    /// expected number of inserted elements `n`, and the hash builder used to compute
+    /// fingerprints.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fingerprint for `p` and `n` does not fit in 64 bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{QuotientFilter, SeededHashBuilder};
+    /// let _filter = QuotientFilter::from_fpp_with_hasher(0.0001, 64, SeededHashBuilder::new(0));
+    /// ```
+    pub fn from_fpp_with_hasher(p: f64, n: usize, builder: S) -> Self {
+        let q = quotient_bits_for(n);
+        let r = optimal_number_of_remainder_bits(p);
+        assert!(q + r <= 64, "fingerprints do not fit in 64 bits");
+        Self::with_bits(q, r, builder)
+    }
+
+    fn with_bits(q: u32, r: u32, builder: S) -> Self {
+        Self {
+            q,
+            r,
+            n: 0,
+            slots: vec![0; word_count(q, r)],
+            builder,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    ///
+    /// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains_fingerprint(self.fingerprint(key))
+    }
+
+    /// Adds a value to the filter.
+    ///
+    /// Returns whether the value was not previously in the filter, i.e., whether its fingerprint
+    /// was not in the filter. If the filter is at capacity, it is first resized to double its
+    /// capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the filter is at capacity and the fingerprints have no remainder bits left to
+    /// grow (see [`Self::resize`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.insert("a"));
+    /// assert!(!filter.insert("a"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let fingerprint = self.fingerprint(key);
+
+        if self.n >= self.capacity() && !self.contains_fingerprint(fingerprint) {
+            let capacity = self.capacity().saturating_mul(2);
+            self.resize(capacity)
+                .expect("fingerprints have no remainder bits left to grow");
+        }
+
+        self.insert_fingerprint(fingerprint)
+    }
+
+    /// Removes a value from the filter.
+    ///
+    /// Returns whether the value's fingerprint was in the filter. This removes any other element
+    /// that shares the fingerprint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    ///
+    /// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.remove("a"));
+    /// assert!(!filter.remove("a"));
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn remove<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.remove_fingerprint(self.fingerprint(key))
+    }
+
+    /// Resizes the table to hold at least `capacity` elements.
+    ///
+    /// The fingerprints are kept, so keys are not rehashed: growing the table moves bits from the
+    /// remainder to the quotient, which raises the false positive probability at the same load
+    /// factor, and shrinking it does the reverse.
+    ///
+    /// This fails if the filter has more than `capacity` elements or if `capacity` needs every
+    /// fingerprint bit for the quotient.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    ///
+    /// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// filter.resize(1024)?;
+    /// assert!(filter.capacity() >= 1024);
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::ResizeError>(())
+    /// ```
+    pub fn resize(&mut self, capacity: usize) -> Result<(), ResizeError> {
+        if capacity < self.n {
+            return Err(ResizeError::TooSmall {
+                capacity,
+                len: self.n,
+            });
+        }
+
+        let fingerprint_bits = self.fingerprint_bits();
+
+        let q = quotient_bits_for(capacity)
+            .max(fingerprint_bits.saturating_sub(MAX_REMAINDER_BITS))
+            .max(1);
+
+        if q >= fingerprint_bits {
+            return Err(ResizeError::TooLarge {
+                capacity,
+                max_capacity: capacity_of(fingerprint_bits - 1),
+            });
+        }
+
+        let fingerprints = self.fingerprints().expect("invalid table");
+
+        self.q = q;
+        self.r = fingerprint_bits - q;
+        self.n = 0;
+        self.slots = vec![0; word_count(self.q, self.r)];
+
+        for fingerprint in fingerprints {
+            self.insert_fingerprint(fingerprint);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the elements of another filter to this filter.
+    ///
+    /// The filters must have the same number of fingerprint bits and use hash builders that
+    /// produce the same hashes. Their capacities may differ. If the union does not fit, this
+    /// filter is first resized to hold it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the union does not fit and the fingerprints have no remainder bits left to grow
+    /// (see [`Self::resize`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{QuotientFilter, SeededHashBuilder};
+    ///
+    /// let builder = SeededHashBuilder::new(0);
+    ///
+    /// let mut a = QuotientFilter::from_fpp_with_hasher(0.0001, 64, builder);
+    /// a.insert("a");
+    ///
+    /// let mut b = QuotientFilter::from_fpp_with_hasher(0.0001, 64, builder);
+    /// b.insert("b");
+    ///
+    /// a.merge(&b)?;
+    /// assert!(a.contains("a"));
+    /// assert!(a.contains("b"));
+    /// # Ok::<(), bbloom::IncompatibleFilters>(())
+    /// ```
+    pub fn merge(&mut self, other: &Self) -> Result<(), IncompatibleFilters> {
+        if self.fingerprint_bits() != other.fingerprint_bits() {
+            return Err(IncompatibleFilters::FingerprintBits {
+                expected: self.fingerprint_bits(),
+                actual: other.fingerprint_bits(),
+            });
+        }
+
+        let fingerprints = other.fingerprints().expect("invalid table");
+        let len = self.n + fingerprints.len();
+
+        if len > self.capacity() {
+            self.resize(len)
+                .expect("fingerprints have no remainder bits left to grow");
+        }
+
+        for fingerprint in fingerprints {
+            self.insert_fingerprint(fingerprint);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of elements the filter holds before it grows.
+    ///
+    /// This is 75% of the number of slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// assert_eq!(filter.capacity(), 96);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        capacity_of(self.q)
+    }
+
+    /// Returns the number of bits in a fingerprint, i.e., the number of quotient and remainder
+    /// bits.
+    ///
+    /// This does not change when the filter is resized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// assert_eq!(filter.fingerprint_bits(), 7 + 14);
+    /// ```
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.q + self.r
+    }
+
+    /// Returns the number of elements in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    ///
+    /// let mut filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// assert!(filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Writes the filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use bbloom::QuotientFilter;
+    ///
+    /// let filter = QuotientFilter::from_fpp(0.0001, 64);
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write_quotient_filter(writer, self.q, self.r, self.n, &self.slots)
+    }
+
+    /// Encodes the filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::QuotientFilter;
+    /// let filter = QuotientFilter::from_fpp(0.0001, 64);
+    /// let buf = filter.to_bytes();
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)
+            .expect("writing to a Vec should not fail");
+        buf
+    }
+
+    fn fingerprint<H: Hash + ?Sized>(&self, key: &H) -> u64 {
+        self.builder.hash_one(key) & mask(self.fingerprint_bits())
+    }
+
+    // Splits a fingerprint into its quotient and remainder.
+    fn split(&self, fingerprint: u64) -> (usize, u64) {
+        let quotient = (fingerprint >> self.r) as usize;
+        let remainder = fingerprint & mask(self.r);
+        (quotient, remainder)
+    }
+
+    fn contains_fingerprint(&self, fingerprint: u64) -> bool {
+        let (fq, fr) = self.split(fingerprint);
+
+        if self.get(fq) & OCCUPIED == 0 {
+            return false;
+        }
+
+        let mut s = self.find_run_start(fq);
+
+        loop {
+            let remainder = self.get(s) >> FLAG_BITS;
+
+            if remainder == fr {
+                return true;
+            } else if remainder > fr {
+                return false;
+            }
+
+            s = self.incr(s);
+
+            if self.get(s) & CONTINUATION == 0 {
+                return false;
+            }
+        }
+    }
+
+    // Inserts a fingerprint, keeping the remainders of each run sorted.
+    fn insert_fingerprint(&mut self, fingerprint: u64) -> bool {
+        let (fq, fr) = self.split(fingerprint);
+        let t_fq = self.get(fq);
+        let mut entry = fr << FLAG_BITS;
+
+        if is_empty(t_fq) {
+            self.set(fq, entry | OCCUPIED);
+            self.n += 1;
+            return true;
+        }
+
+        if t_fq & OCCUPIED == 0 {
+            self.set(fq, t_fq | OCCUPIED);
+        }
+
+        let start = self.find_run_start(fq);
+        let mut s = start;
+
+        if t_fq & OCCUPIED != 0 {
+            // Find the position of the remainder in the existing run.
+            loop {
+                let remainder = self.get(s) >> FLAG_BITS;
+
+                if remainder == fr {
+                    return false;
+                } else if remainder > fr {
+                    break;
+                }
+
+                s = self.incr(s);
+
+                if self.get(s) & CONTINUATION == 0 {
+                    break;
+                }
+            }
+
+            if s == start {
+                // The old head of the run becomes a continuation.
+                let head = self.get(start);
+                self.set(start, head | CONTINUATION);
+            } else {
+                entry |= CONTINUATION;
+            }
+        }
+
+        if s != fq {
+            entry |= SHIFTED;
+        }
+
+        self.insert_at(s, entry);
+        self.n += 1;
+
+        true
+    }
+
+    fn remove_fingerprint(&mut self, fingerprint: u64) -> bool {
+        let (fq, fr) = self.split(fingerprint);
+
+        if self.get(fq) & OCCUPIED == 0 {
+            return false;
+        }
+
+        let mut s = self.find_run_start(fq);
+
+        loop {
+            let remainder = self.get(s) >> FLAG_BITS;
+
+            if remainder == fr {
+                break;
+            } else if remainder > fr {
+                return false;
+            }
+
+            s = self.incr(s);
+
+            if self.get(s) & CONTINUATION == 0 {
+                return false;
+            }
+        }
+
+        let is_run_start = self.get(s) & CONTINUATION == 0;
+
+        // If the run only has this remainder, the canonical slot is no longer occupied.
+        if is_run_start && self.get(self.incr(s)) & CONTINUATION == 0 {
+            let t_fq = self.get(fq);
+            self.set(fq, t_fq & !OCCUPIED);
+        }
+
+        self.remove_at(s, fq);
+
+        if is_run_start {
+            // The next remainder of the run, if any, becomes the head of the run.
+            let next = self.get(s);
+            let mut updated_next = next & !CONTINUATION;
+
+            if s == fq && !is_empty(updated_next) {
+                updated_next &= !SHIFTED;
+            }
+
+            if updated_next != next {
+                self.set(s, updated_next);
+            }
+        }
+
+        self.n -= 1;
+
+        true
+    }
+
+    // Returns the slot of the first remainder of the run of quotient `fq`, or the slot where it
+    // would be.
+    fn find_run_start(&self, fq: usize) -> usize {
+        // Walk back to the start of the cluster.
+        let mut b = fq;
+
+        while self.get(b) & SHIFTED != 0 {
+            b = self.decr(b);
+        }
+
+        // Walk forward, pairing each occupied slot `b` with the start of its run `s`.
+        let mut s = b;
+
+        while b != fq {
+            loop {
+                s = self.incr(s);
+
+                if self.get(s) & CONTINUATION == 0 {
+                    break;
+                }
+            }
+
+            loop {
+                b = self.incr(b);
+
+                if self.get(b) & OCCUPIED != 0 {
+                    break;
+                }
+            }
+        }
+
+        s
+    }
+
+    // Inserts an entry at slot `s`, shifting the following entries of the cluster to the right.
+    // The occupied flag stays with the slot.
+    fn insert_at(&mut self, mut s: usize, entry: u64) {
+        let mut curr = entry;
+
+        loop {
+            let mut prev = self.get(s);
+            let was_empty = is_empty(prev);
+
+            if !was_empty {
+                prev |= SHIFTED;
+
+                if prev & OCCUPIED != 0 {
+                    curr |= OCCUPIED;
+                    prev &= !OCCUPIED;
+                }
+            }
+
+            self.set(s, curr);
+
+            if was_empty {
+                break;
+            }
+
+            curr = prev;
+            s = self.incr(s);
+        }
+    }
+
+    // Removes the entry at slot `s` of the run of quotient `quotient`, shifting the following
+    // entries of the cluster to the left. The occupied flag stays with the slot.
+    fn remove_at(&mut self, mut s: usize, mut quotient: usize) {
+        let orig = s;
+        let mut curr = self.get(s);
+        let mut sp = self.incr(s);
+
+        loop {
+            let next = self.get(sp);
+
+            if is_empty(next) || is_cluster_start(next) || sp == orig {
+                self.set(s, 0);
+                return;
+            }
+
+            let curr_occupied = curr & OCCUPIED;
+            let mut updated_next = next;
+
+            if next & CONTINUATION == 0 {
+                // A run starts at `sp`. Its quotient is the next occupied slot.
+                loop {
+                    quotient = self.incr(quotient);
+
+                    if self.get(quotient) & OCCUPIED != 0 {
+                        break;
+                    }
+                }
+
+                if curr_occupied != 0 && quotient == s {
+                    updated_next &= !SHIFTED;
+                }
+            }
+
+            self.set(s, (updated_next & !OCCUPIED) | curr_occupied);
+
+            s = sp;
+            sp = self.incr(sp);
+            curr = next;
+        }
+    }
+
+    // Returns the fingerprints in the table, or `None` if the flags are inconsistent.
+    fn fingerprints(&self) -> Option<Vec<u64>> {
+        let slot_count = self.slot_count();
+
+        if self.n == 0 {
+            return Some(Vec::new());
+        }
+
+        // Every cluster ends before an empty slot.
+        let start = (0..slot_count).find(|&i| is_empty(self.get(i)))?;
+
+        let mut fingerprints = Vec::with_capacity(self.n);
+        let mut quotients = VecDeque::new();
+        let mut quotient = None;
+
+        for j in 1..=slot_count {
+            let i = (start + j) & (slot_count - 1);
+            let slot = self.get(i);
+
+            if is_empty(slot) {
+                if !quotients.is_empty() {
+                    return None;
+                }
+
+                quotient = None;
+                continue;
+            }
+
+            if slot & OCCUPIED != 0 {
+                quotients.push_back(i);
+            }
+
+            if slot & CONTINUATION == 0 {
+                quotient = Some(quotients.pop_front()?);
+            }
+
+            let q = quotient?;
+
+            if (slot & SHIFTED != 0) != (q != i) {
+                return None;
+            }
+
+            fingerprints.push(((q as u64) << self.r) | (slot >> FLAG_BITS));
+        }
+
+        Some(fingerprints)
+    }
+
+    fn slot_count(&self) -> usize {
+        1 << self.q
+    }
+
+    fn incr(&self, i: usize) -> usize {
+        (i + 1) & (self.slot_count() - 1)
+    }
+
+    fn decr(&self, i: usize) -> usize {
+        i.wrapping_sub(1) & (self.slot_count() - 1)
+    }
+
+    fn get(&self, i: usize) -> u64 {
+        let width = (self.r + FLAG_BITS) as usize;
+        let bit = i * width;
+        let (word, offset) = (bit / 64, bit % 64);
+
+        let mut slot = self.slots[word] >> offset;
+
+        if offset + width > 64 {
+            slot |= self.slots[word + 1] << (64 - offset);
+        }
+
+        slot & mask(width as u32)
+    }
+
+    fn set(&mut self, i: usize, slot: u64) {
+        let width = (self.r + FLAG_BITS) as usize;
+        let bit = i * width;
+        let (word, offset) = (bit / 64, bit % 64);
+
+        let lo_mask = mask(width as u32) << offset;
+        self.slots[word] = (self.slots[word] & !lo_mask) | ((slot << offset) & lo_mask);
+
+        if offset + width > 64 {
+            let hi_mask = mask((offset + width - 64) as u32);
+            self.slots[word + 1] = (self.slots[word + 1] & !hi_mask) | (slot >> (64 - offset));
+        }
+    }
+}
+
+impl<S> QuotientFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Reads a filter in the binary format (see [`crate::io`]), creating the hash builder using
+    /// [`Default`].
+    ///
+    /// The hash builder must produce the same hashes as the one used to build the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{QuotientFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: QuotientFilter<SeededHashBuilder> =
+    ///     QuotientFilter::from_fpp_with_hasher(0.0001, 64, Default::default());
+    /// filter.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter: QuotientFilter<SeededHashBuilder> = QuotientFilter::read_from(&mut &buf[..])?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_from<R>(reader: &mut R) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        let parts = read_quotient_filter(reader)?;
+
+        let filter = Self {
+            q: parts.q,
+            r: parts.r,
+            n: parts.n,
+            slots: parts.slots,
+            builder: S::default(),
+        };
+
+        match filter.fingerprints() {
+            Some(fingerprints) if fingerprints.len() == filter.n => Ok(filter),
+            _ => Err(ReadError::InvalidParameters),
+        }
+    }
+
+    /// Decodes a filter in the binary format (see [`crate::io`]), creating the hash builder using
+    /// [`Default`].
+    ///
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{QuotientFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: QuotientFilter<SeededHashBuilder> =
+    ///     QuotientFilter::from_fpp_with_hasher(0.0001, 64, Default::default());
+    /// filter.insert("a");
+    ///
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter: QuotientFilter<SeededHashBuilder> = QuotientFilter::from_bytes(&buf)?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_bytes(mut buf: &[u8]) -> Result<Self, ReadError> {
+        let filter = Self::read_from(&mut buf)?;
+
+        if buf.is_empty() {
+            Ok(filter)
+        } else {
+            Err(ReadError::TrailingData)
+        }
+    }
+}
+
+impl<S> Filter for QuotientFilter<S>
+where
+    S: BuildHasher,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+fn is_empty(slot: u64) -> bool {
+    slot & (OCCUPIED | CONTINUATION | SHIFTED) == 0
+}
+
+fn is_cluster_start(slot: u64) -> bool {
+    slot & (OCCUPIED | CONTINUATION | SHIFTED) == OCCUPIED
+}
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+fn word_count(q: u32, r: u32) -> usize {
+    ((1usize << q) * (r + FLAG_BITS) as usize).div_ceil(64)
+}
+
+// Returns the number of elements a table with `q` quotient bits holds, i.e., 75% of `2^q` slots.
+// At least one slot is always left empty.
+fn capacity_of(q: u32) -> usize {
+    let slot_count = 1usize << q;
+    slot_count - (slot_count / 4).max(1)
+}
+
+// Returns the smallest number of quotient bits that holds `n` elements.
+fn quotient_bits_for(n: usize) -> u32 {
+    let mut q = 1;
+
+    while q < usize::BITS - 1 && capacity_of(q) < n {
+        q += 1;
+    }
+
+    q
+}
+
+// r = ceil(-log2(p))
+fn optimal_number_of_remainder_bits(p: f64) -> u32 {
+    let r = (-p.log2()).ceil();
+    (r as u32).clamp(1, MAX_REMAINDER_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{SeededHashBuilder, ShadowedBloomFilter};
+
+    #[test]
+    fn test_optimal_number_of_remainder_bits() {
+        assert_eq!(optimal_number_of_remainder_bits(0.01), 7);
+        assert_eq!(optimal_number_of_remainder_bits(0.5), 1);
+        assert_eq!(optimal_number_of_remainder_bits(1.0), 1);
+        assert_eq!(optimal_number_of_remainder_bits(0.0), MAX_REMAINDER_BITS);
+    }
+
+    #[test]
+    fn test_quotient_bits_for() {
+        assert_eq!(quotient_bits_for(0), 1);
+        assert_eq!(quotient_bits_for(1), 1);
+        assert_eq!(capacity_of(1), 1);
+        assert_eq!(quotient_bits_for(2), 2);
+        assert_eq!(quotient_bits_for(96), 7);
+        assert_eq!(quotient_bits_for(97), 8);
+    }
+
+    #[test]
+    fn test_get_and_set_across_words() {
+        // 11-bit slots span word boundaries.
+        let mut filter = QuotientFilter::with_bits(6, 8, SeededHashBuilder::default());
+
+        for i in 0..64 {
+            filter.set(i, (i as u64 * 0x2f) & mask(11));
+        }
+
+        for i in 0..64 {
+            assert_eq!(filter.get(i), (i as u64 * 0x2f) & mask(11));
+        }
+    }
+
+    // Compares the filter to an exact set of fingerprints. A small table with short remainders
+    // produces long clusters that wrap around the end of the table.
+    #[test]
+    fn test_fingerprints_against_model() {
+        const Q: u32 = 6;
+        const R: u32 = 3;
+
+        let mut filter = QuotientFilter::with_bits(Q, R, SeededHashBuilder::default());
+        let mut model = BTreeSet::new();
+
+        let capacity = filter.capacity();
+        let random = SeededHashBuilder::new(7);
+
+        for i in 0..20_000u64 {
+            let x = random.hash_one(i);
+            let fingerprint = x & mask(Q + R);
+
+            // Alternate between insert-heavy and deletion-heavy phases.
+            let remove_percent = if (i / 1000) % 2 == 0 { 20 } else { 80 };
+            let remove = (x >> 32) % 100 < remove_percent;
+
+            if remove || model.len() == capacity {
+                // Remove a present fingerprint half of the time.
+                let fingerprint = if x >> 63 == 0 && !model.is_empty() {
+                    let j = (x >> 40) as usize % model.len();
+                    *model.iter().nth(j).unwrap()
+                } else {
+                    fingerprint
+                };
+
+                assert_eq!(
+                    filter.remove_fingerprint(fingerprint),
+                    model.remove(&fingerprint)
+                );
+            } else {
+                assert_eq!(
+                    filter.insert_fingerprint(fingerprint),
+                    model.insert(fingerprint)
+                );
+            }
+
+            assert_eq!(filter.len(), model.len());
+
+            if i % 64 == 0 {
+                for fingerprint in 0..1 << (Q + R) {
+                    assert_eq!(
+                        filter.contains_fingerprint(fingerprint),
+                        model.contains(&fingerprint)
+                    );
+                }
+
+                let mut fingerprints = filter.fingerprints().unwrap();
+                fingerprints.sort_unstable();
+                assert!(fingerprints.iter().eq(model.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_false_positive_probability() {
+        const P: f64 = 0.01;
+        const N: usize = 1000;
+
+        let mut filter = ShadowedBloomFilter::new(QuotientFilter::from_fpp_with_hasher(
+            P,
+            N,
+            SeededHashBuilder::new(0),
+        ));
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        for i in 0..N + 100_000 {
+            filter.contains(&i);
+        }
+
+        let fpp = filter.observed_fpp();
+        assert!(fpp < P, "{} >= {}", fpp, P);
+    }
+
+    #[test]
+    fn test_insert_past_capacity() {
+        let mut filter = QuotientFilter::from_fpp_with_hasher(0.001, 16, SeededHashBuilder::new(0));
+        assert_eq!(filter.capacity(), 24);
+
+        for i in 0..100 {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.capacity(), 192);
+        assert_eq!(filter.fingerprint_bits(), 5 + 10);
+        assert!((0..100).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut filter = QuotientFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        for i in (0..64).step_by(2) {
+            assert!(filter.remove(&i));
+        }
+
+        assert_eq!(filter.len(), 32);
+        assert!((1..64).step_by(2).all(|i| filter.contains(&i)));
+        assert!((0..64).step_by(2).all(|i| !filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut filter = QuotientFilter::from_fpp_with_hasher(0.001, 64, SeededHashBuilder::new(0));
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        let mut expected = filter.fingerprints().unwrap();
+        expected.sort_unstable();
+
+        filter.resize(1000).unwrap();
+        assert_eq!(filter.capacity(), 1536);
+
+        filter.resize(64).unwrap();
+        assert_eq!(filter.capacity(), 96);
+
+        let mut actual = filter.fingerprints().unwrap();
+        actual.sort_unstable();
+        assert_eq!(actual, expected);
+
+        assert_eq!(
+            filter.resize(63),
+            Err(ResizeError::TooSmall {
+                capacity: 63,
+                len: 64
+            })
+        );
+
+        assert_eq!(
+            filter.resize(1 << 20),
+            Err(ResizeError::TooLarge {
+                capacity: 1 << 20,
+                max_capacity: capacity_of(16)
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let builder = SeededHashBuilder::new(0);
+
+        let mut a = QuotientFilter::from_fpp_with_hasher(0.001, 32, builder);
+        let mut b = QuotientFilter::from_fpp_with_hasher(0.001, 32, builder);
+        b.resize(64).unwrap();
+
+        for i in 0..32 {
+            a.insert(&i);
+            b.insert(&(i + 16));
+        }
+
+        assert_eq!(a.merge(&b), Ok(()));
+        assert_eq!(a.len(), 48);
+        assert!((0..48).all(|i| a.contains(&i)));
+
+        let c = QuotientFilter::from_fpp_with_hasher(0.1, 32, builder);
+        assert_eq!(
+            a.merge(&c),
+            Err(IncompatibleFilters::FingerprintBits {
+                expected: 16,
+                actual: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() -> Result<(), ReadError> {
+        let mut filter: QuotientFilter<SeededHashBuilder> =
+            QuotientFilter::from_fpp_with_hasher(0.001, 64, Default::default());
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        let buf = filter.to_bytes();
+        let actual: QuotientFilter<SeededHashBuilder> = QuotientFilter::from_bytes(&buf)?;
+
+        assert_eq!(actual.len(), 64);
+        assert_eq!(actual.slots, filter.slots);
+
+        // an extra element in an empty slot
+        let i = (0..).find(|&i| is_empty(filter.get(i))).unwrap();
+        filter.set(i, CONTINUATION);
+        let buf = filter.to_bytes();
+
+        assert!(matches!(
+            QuotientFilter::<SeededHashBuilder>::from_bytes(&buf),
+            Err(ReadError::InvalidParameters)
+        ));
+
+        Ok(())
+    }
+}
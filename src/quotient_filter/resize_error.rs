@@ -0,0 +1,42 @@
+use std::{error, fmt};
+
+/// An error returned when a quotient filter cannot be resized to a capacity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResizeError {
+    /// The capacity is less than the number of elements in the filter.
+    TooSmall {
+        /// The requested capacity.
+        capacity: usize,
+        /// The number of elements in the filter.
+        len: usize,
+    },
+    /// The capacity needs more quotient bits than the fingerprints have, leaving no remainder.
+    TooLarge {
+        /// The requested capacity.
+        capacity: usize,
+        /// The largest capacity the fingerprints allow.
+        max_capacity: usize,
+    },
+}
+
+impl error::Error for ResizeError {}
+
+impl fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall { capacity, len } => write!(
+                f,
+                "capacity too small: {} elements do not fit in {}",
+                len, capacity
+            ),
+            Self::TooLarge {
+                capacity,
+                max_capacity,
+            } => write!(
+                f,
+                "capacity too large: {} exceeds the maximum of {}",
+                capacity, max_capacity
+            ),
+        }
+    }
+}
@@ -1,11 +1,40 @@
-use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "borsh")]
+mod borsh;
+mod grow_error;
+mod layer;
+mod layer_stats;
 
-use crate::{BloomFilter, DefaultHashBuilder};
+pub use self::{grow_error::GrowError, layer_stats::LayerStats};
 
-// growth factor `s`
+use self::layer::Layer;
+
+use std::{
+    hash::{BuildHasher, Hash},
+    io::{self, Read, Write},
+    mem,
+    path::Path,
+};
+
+use crate::{
+    bit_store::BitStore,
+    bloom_filter::{
+        checked_optimal_required_bits, optimal_number_of_elements, optimal_number_of_hash_functions,
+    },
+    io::{
+        path, read_scalable_bloom_filter, scalable_bloom_filter_reader,
+        write_scalable_bloom_filter, ReadError, SerializedReader,
+    },
+    BloomFilter, DefaultHashBuilder, Filter,
+};
+
+// the default growth factor `s`
 const GROWTH_FACTOR: usize = 2;
-// tightening ratio `r`
-const TIGHTENING_RATIO: f64 = 0.85;
+// the default tightening ratio `r`
+pub(crate) const TIGHTENING_RATIO: f64 = 0.85;
+// the default lower bound of the false positive probability of a new layer
+const MIN_FPP: f64 = 1e-12;
+
+type EvictHandler = Box<dyn FnMut(LayerStats) + Send + Sync>;
 
 /// A variant of a Bloom filter that can adapt to to the number of elements inserted into the
 /// filter, targeting a given false positive probability.
@@ -42,9 +71,21 @@ pub struct ScalableBloomFilter<S = DefaultHashBuilder> {
     // total capacity of all filters
     total_capacity: usize,
     // a list of all filters in order they were created
-    filters: Vec<BloomFilter<S>>,
+    filters: Vec<Layer<S>>,
     // the (tightened) false positive probably of the last created filter
     last_fpp: f64,
+    // an optional filter of all elements that is checked before the layers
+    negative_cache: Option<BloomFilter<S>>,
+    // the factor `s` by which the capacity of a new layer exceeds the total capacity
+    growth_factor: usize,
+    // the ratio `r` of the false positive probability of a new layer to that of the last
+    tightening_ratio: f64,
+    // the lower bound of the false positive probability of a new layer
+    min_fpp: f64,
+    // the maximum number of layers, after which the oldest layer is evicted when growing
+    max_window: Option<usize>,
+    // a callback that is given the statistics of an evicted layer
+    on_evict: Option<EvictHandler>,
 }
 
 impl ScalableBloomFilter<DefaultHashBuilder> {
@@ -64,17 +105,29 @@ impl ScalableBloomFilter<DefaultHashBuilder> {
 
 impl<S> ScalableBloomFilter<S>
 where
-    S: BuildHasher + Default,
+    S: BuildHasher + Clone + Default,
 {
     /// Creates a new scalable Bloom filter that targets a false positive probability `p` ([0.0,
     /// 1.0]) with an initial expected number of inserted elements `n`, using `builder_1` and
-    /// `builder_2` to hash the data in the initial filter.
+    /// `builder_2` to hash the data.
+    ///
+    /// The builders are cloned into every layer added as the filter grows and into the negative
+    /// cache, if any (see [`Self::with_negative_cache`]).
     ///
     /// # Examples
     ///
     /// ```
-    /// use bbloom::ScalableBloomFilter;
-    /// let _filter = ScalableBloomFilter::new(0.0001, 64);
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter = ScalableBloomFilter::with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(0),
+    ///     SeededHashBuilder::new(1),
+    /// );
+    ///
+    /// filter.insert("a");
+    /// assert!(filter.contains("a"));
     /// ```
     pub fn with_hashers(p: f64, n: usize, builder_1: S, builder_2: S) -> Self {
         let initial_filter = BloomFilter::from_fpp_with_hashers(p, n, builder_1, builder_2);
@@ -82,13 +135,268 @@ where
         Self {
             n: 0,
             total_capacity: n,
-            filters: vec![initial_filter],
+            filters: vec![Layer::Memory(initial_filter)],
+            last_fpp: p,
+            negative_cache: None,
+            growth_factor: GROWTH_FACTOR,
+            tightening_ratio: TIGHTENING_RATIO,
+            min_fpp: MIN_FPP,
+            max_window: None,
+            on_evict: None,
+        }
+    }
+
+    /// Adds a negative cache that targets a false positive probability `p` ([0.0, 1.0]) with an
+    /// expected number of inserted elements `projected_n`.
+    ///
+    /// The negative cache is a single Bloom filter that every inserted element is also added to.
+    /// [`Self::contains`] checks it first and only checks the layers if it reports the element
+    /// may be present. Most lookups of absent elements then cost one filter rather than one per
+    /// layer, at the cost of the memory of the cache.
+    ///
+    /// `projected_n` should be the total number of elements expected over the lifetime of the
+    /// filter, and `p` can be looser than the target of the filter. Exceeding `projected_n` only
+    /// makes the cache less effective. It never causes false negatives.
+    ///
+    /// The negative cache is included in the binary format (see [`crate::io`]). A filter read from
+    /// data without one, e.g., written before it was added, does not have a negative cache. It
+    /// cannot be rebuilt, since the elements of a filter are not stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any elements were inserted into the filter, since they would be missing from the
+    /// negative cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64).with_negative_cache(0.01, 1 << 16);
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn with_negative_cache(mut self, p: f64, projected_n: usize) -> Self {
+        assert!(
+            self.n == 0,
+            "negative cache must be added before inserting elements"
+        );
+
+        let (builder_1, builder_2) = self.hash_builders();
+        let negative_cache =
+            BloomFilter::from_fpp_with_hashers(p, projected_n, builder_1, builder_2);
+        self.negative_cache = Some(negative_cache);
+
+        self
+    }
+
+    /// Sets the growth factor `s`.
+    ///
+    /// A new layer holds `s` times the total capacity of the existing layers. A larger growth
+    /// factor adds fewer layers, which makes lookups faster, at the cost of layers that are larger
+    /// than needed. The default is 2.
+    ///
+    /// This is not stored in the binary format (see [`crate::io`]). A filter that is read uses the
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `growth_factor` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64).with_growth_factor(4);
+    ///
+    /// for i in 0..65 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert_eq!(filter.capacity(), 64 + 4 * 64);
+    /// ```
+    pub fn with_growth_factor(mut self, growth_factor: usize) -> Self {
+        assert!(growth_factor >= 1, "growth factor must be at least 1");
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// Sets the tightening ratio `r`.
+    ///
+    /// A new layer targets `r` times the false positive probability of the last layer. The
+    /// compound false positive probability of the filter stays below about `p / (1 - r)`, so a
+    /// smaller ratio gives a tighter bound, at the cost of larger layers. The default is 0.85.
+    ///
+    /// This is not stored in the binary format (see [`crate::io`]). A filter that is read uses the
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tightening_ratio` is not in (0.0, 1.0).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    /// let _filter = ScalableBloomFilter::new(0.0001, 64).with_tightening_ratio(0.5);
+    /// ```
+    pub fn with_tightening_ratio(mut self, tightening_ratio: f64) -> Self {
+        assert!(
+            tightening_ratio > 0.0 && tightening_ratio < 1.0,
+            "tightening ratio must be in (0.0, 1.0)"
+        );
+
+        self.tightening_ratio = tightening_ratio;
+        self
+    }
+
+    /// Sets the lower bound of the false positive probability of a new layer.
+    ///
+    /// Every new layer tightens the false positive probability of the last, which would approach
+    /// 0 after many layers and make their bit arrays impractically large. Instead, new layers
+    /// target at least `min_fpp`. The default is 1e-12.
+    ///
+    /// This is not stored in the binary format (see [`crate::io`]). A filter that is read uses the
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_fpp` is not in (0.0, 1.0).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    /// let _filter = ScalableBloomFilter::new(0.0001, 64).with_min_fpp(1e-9);
+    /// ```
+    pub fn with_min_fpp(mut self, min_fpp: f64) -> Self {
+        assert!(
+            min_fpp > 0.0 && min_fpp < 1.0,
+            "minimum false positive probability must be in (0.0, 1.0)"
+        );
+
+        self.min_fpp = min_fpp;
+        self
+    }
+
+    /// Limits the filter to `max_layers` layers, evicting the oldest layer when a new layer would
+    /// exceed the limit.
+    ///
+    /// This makes the filter forget: an element that is only in an evicted layer is reported
+    /// absent, i.e., it becomes a false negative. The filter then answers whether an element was
+    /// inserted in the recent past rather than ever. The number of elements, total capacity, and
+    /// statistics only account for the remaining layers.
+    ///
+    /// Layers grow as usual until the window is full. After that, every new layer has the same
+    /// size and false positive probability as the last, so memory stays bounded. The false
+    /// positive probability of the filter is then at most `max_layers` times that of the last
+    /// layer.
+    ///
+    /// A negative cache (see [`Self::with_negative_cache`]) is not windowed. It keeps reporting
+    /// evicted elements as possibly present, which only makes it less effective.
+    ///
+    /// This is not stored in the binary format (see [`crate::io`]). A filter that is read is not
+    /// windowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_layers` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 8).with_max_window(2);
+    /// filter.insert("a");
+    ///
+    /// for i in 0..64 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert_eq!(filter.layer_stats().len(), 2);
+    /// assert!(!filter.contains("a"));
+    /// assert!(filter.contains(&63));
+    /// ```
+    pub fn with_max_window(mut self, max_layers: usize) -> Self {
+        assert!(max_layers > 0, "max window must be at least 1 layer");
+        self.max_window = Some(max_layers);
+        self
+    }
+
+    /// Sets a callback that is given the statistics of every layer evicted from a windowed filter
+    /// (see [`Self::with_max_window`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 8)
+    ///     .with_max_window(2)
+    ///     .on_evict(move |stats| tx.send(stats.len()).unwrap());
+    ///
+    /// for i in 0..64 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert_eq!(rx.try_recv(), Ok(8));
+    /// ```
+    pub fn on_evict<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(LayerStats) + Send + Sync + 'static,
+    {
+        self.on_evict = Some(Box::new(f));
+        self
+    }
+
+    /// Creates a new scalable Bloom filter that uses an existing filter as its first layer and
+    /// targets a false positive probability `p` ([0.0, 1.0]).
+    ///
+    /// The initial capacity is the number of elements `filter` can hold while meeting `p`. If the
+    /// filter is already at or over this capacity, the next insert adds a new layer. Subsequent
+    /// growth follows the usual schedule, starting from `p`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ScalableBloomFilter};
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let filter = ScalableBloomFilter::from_filter(filter, 0.0001);
+    /// assert!(filter.contains("a"));
+    /// ```
+    pub fn from_filter(filter: BloomFilter<S>, p: f64) -> Self {
+        let n = filter.len();
+        let total_capacity = optimal_number_of_elements(p, filter.capacity());
+
+        Self {
+            n,
+            total_capacity,
+            filters: vec![Layer::Memory(filter)],
             last_fpp: p,
+            negative_cache: None,
+            growth_factor: GROWTH_FACTOR,
+            tightening_ratio: TIGHTENING_RATIO,
+            min_fpp: MIN_FPP,
+            max_window: None,
+            on_evict: None,
         }
     }
 
     /// Tests all filters for whether an element may be in the filter or definitely not in the filter.
     ///
+    /// If the filter has a negative cache (see [`Self::with_negative_cache`]), the layers are only
+    /// tested if the cache reports the element may be present.
+    ///
     /// # Examples
     ///
     /// ```
@@ -102,7 +410,84 @@ where
     /// assert!(!filter.contains("b"));
     /// ```
     pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
-        self.filters.iter().any(|f| f.contains(key))
+        self.may_contain(key) && self.filters.iter().any(|f| f.contains(key))
+    }
+
+    /// Returns the index of the first layer that reports an element may be present.
+    ///
+    /// Layers are indexed in the order they were created, starting at 0. This is useful to
+    /// attribute false positives to a particular layer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.layer_of("a"), Some(0));
+    /// assert_eq!(filter.layer_of("b"), None);
+    /// ```
+    pub fn layer_of<H: Hash + ?Sized>(&self, key: &H) -> Option<usize> {
+        self.filters.iter().position(|f| f.contains(key))
+    }
+
+    /// Returns the indices of all layers that report an element may be present.
+    ///
+    /// See [`Self::layer_of`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// assert_eq!(filter.layers_of("a"), [0]);
+    /// assert!(filter.layers_of("b").is_empty());
+    /// ```
+    pub fn layers_of<H: Hash + ?Sized>(&self, key: &H) -> Vec<usize> {
+        self.filters
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.contains(key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the index of the layer with the highest match score for an element and that score.
+    ///
+    /// See [`BloomFilter::match_score`]. Layers can have different numbers of hash functions, so
+    /// scores are compared as fractions of `k`. On a tie, the earliest layer is returned. The
+    /// negative cache, if any, is not consulted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let (layer, (score, k)) = filter.match_score("a");
+    /// assert_eq!(layer, 0);
+    /// assert_eq!(score, k);
+    /// ```
+    pub fn match_score<H: Hash + ?Sized>(&self, key: &H) -> (usize, (usize, usize)) {
+        let mut best = (0, self.filters[0].match_score(key));
+
+        for (i, filter) in self.filters.iter().enumerate().skip(1) {
+            let (score, k) = filter.match_score(key);
+            let (_, (best_score, best_k)) = best;
+
+            if score * best_k > best_score * k {
+                best = (i, (score, k));
+            }
+        }
+
+        best
     }
 
     /// Adds a value to the bloom filter.
@@ -111,6 +496,10 @@ where
     /// may be present in the scalable Bloom filter but not in the last filter. When a duplicate
     /// value is in the last filter, it does not affect the load factor.
     ///
+    /// If the filter is at capacity but a layer cannot be added (see [`Self::try_insert`]), the
+    /// value is added to the last layer past its capacity, which increases the false positive
+    /// probability.
+    ///
     /// # Examples
     ///
     /// ```
@@ -123,11 +512,46 @@ where
     /// ```
     pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
         if self.n >= self.total_capacity {
-            self.grow();
+            // If a layer cannot be added, the last layer is filled past its capacity.
+            let _ = self.grow();
+        }
+
+        self.insert_into_last_layer(key)
+    }
+
+    /// Adds a value to the bloom filter, failing if a layer is needed but cannot be added.
+    ///
+    /// This is like [`Self::insert`], but the filter is unchanged if the total capacity of the
+    /// layers or the size of the bit array of the new layer overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert_eq!(filter.try_insert("a"), Ok(true));
+    /// assert_eq!(filter.try_insert("a"), Ok(false));
+    /// ```
+    pub fn try_insert<H: Hash + ?Sized>(&mut self, key: &H) -> Result<bool, GrowError> {
+        if self.n >= self.total_capacity {
+            self.grow()?;
+        }
+
+        Ok(self.insert_into_last_layer(key))
+    }
+
+    fn insert_into_last_layer<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        if let Some(negative_cache) = &mut self.negative_cache {
+            negative_cache.insert(key);
         }
 
-        let i = self.filters.len() - 1;
-        let filter = &mut self.filters[i];
+        let filter = self
+            .filters
+            .last_mut()
+            .and_then(Layer::as_memory_mut)
+            .expect("last layer must be in memory");
+
         let inserted = filter.insert(key);
 
         if inserted {
@@ -162,21 +586,1331 @@ where
             self.filters.len() - 1
         };
 
-        if self.filters.iter().take(n).any(|f| f.contains(key)) {
+        if self.may_contain(key) && self.filters.iter().take(n).any(|f| f.contains(key)) {
             true
         } else {
             !self.insert(key)
         }
     }
 
-    fn grow(&mut self) {
-        let p = self.last_fpp * TIGHTENING_RATIO;
-        let n = self.total_capacity * GROWTH_FACTOR;
+    /// Returns a one-line, human-readable description of the filter.
+    ///
+    /// This includes the number of layers, the total capacity, the number of elements `n`, and the
+    /// estimated false positive probability. The estimate combines the estimates of every layer
+    /// (see [`BloomFilter::summary`]), i.e., `1 - Π(1 - fpp_i)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    ///
+    /// assert_eq!(
+    ///     filter.summary(),
+    ///     "ScalableBloomFilter { layers: 1, capacity: 64, n: 0, est_fpp: 0.0000 }"
+    /// );
+    /// ```
+    pub fn summary(&self) -> String {
+        format!(
+            "ScalableBloomFilter {{ layers: {}, capacity: {}, n: {}, est_fpp: {:.4} }}",
+            self.filters.len(),
+            self.total_capacity,
+            self.n,
+            self.estimated_fpp(),
+        )
+    }
+
+    /// Estimates the number of distinct elements in the filter from the proportion of set bits of
+    /// every layer.
+    ///
+    /// This is the sum of the estimates of the layers (see [`BloomFilter::estimate_cardinality`]).
+    /// Unlike the count of novel inserts, it does not depend on bookkeeping that may be missing
+    /// or inaccurate, e.g., after reading a filter written by another implementation.
+    ///
+    /// An insert only checks the last layer for duplicates, so a key inserted again after the
+    /// filter grew is in more than one layer and counted once per layer. This inflates the
+    /// estimate by the number of such duplicates. For distinct keys, the estimate is typically
+    /// within a few percent of the true count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.001, 1000);
+    ///
+    /// for i in 0..10000 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!((filter.estimate_cardinality() - 10000.0).abs() < 500.0);
+    /// ```
+    pub fn estimate_cardinality(&self) -> f64 {
+        self.filters
+            .iter()
+            .map(|layer| layer.stats().estimated_cardinality())
+            .sum()
+    }
 
-        let filter = BloomFilter::from_fpp_with_hashers(p, n, S::default(), S::default());
-        self.filters.push(filter);
+    /// Estimates the current false positive probability of the filter.
+    ///
+    /// An element is a false positive if it is a false positive of any layer, so this is
+    /// `1 - Π(1 - p_i)`, where `p_i` is the estimated false positive probability of layer `i`
+    /// given its proportion of set bits (see [`LayerStats::estimated_fpp`]).
+    ///
+    /// Once the first layer is full, this is close to the target `p`, and new layers can push it
+    /// past `p`. The layers target `p`, `pr`, `pr^2`, etc., where `r` is the tightening ratio (see
+    /// [`Self::with_tightening_ratio`]), so it stays below about `p / (1 - r)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.01, 1000);
+    /// assert_eq!(filter.estimated_fpp(), 0.0);
+    ///
+    /// for i in 0..1500 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert!(filter.estimated_fpp() < 0.01 / (1.0 - 0.85));
+    /// ```
+    pub fn estimated_fpp(&self) -> f64 {
+        1.0 - self
+            .filters
+            .iter()
+            .map(|f| 1.0 - f.load_fpp())
+            .product::<f64>()
+    }
 
-        self.total_capacity += n;
-        self.last_fpp = p;
+    /// Returns statistics of every layer in the order they were created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.001, 8);
+    ///
+    /// for i in 0..16 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// let stats = filter.layer_stats();
+    /// assert_eq!(stats.len(), 2);
+    /// assert!(stats[1].capacity() > stats[0].capacity());
+    /// ```
+    pub fn layer_stats(&self) -> Vec<LayerStats> {
+        self.filters.iter().map(Layer::stats).collect()
+    }
+
+    /// Returns the approximate number of bytes of memory used by the filter.
+    ///
+    /// This includes the bit arrays of the layers and the negative cache, if any. The bit arrays of
+    /// layers that are memory-mapped (see [`Self::demote_layer`]) are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert!(filter.memory_usage() > 1227 / 8);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        let layers_bytes = self.filters.len() * mem::size_of::<Layer<S>>()
+            + self
+                .filters
+                .iter()
+                .map(Layer::bits_memory_usage)
+                .sum::<usize>();
+
+        let negative_cache_bytes = self
+            .negative_cache
+            .as_ref()
+            .map(|f| mem::size_of_val(f) + f.capacity().div_ceil(8))
+            .unwrap_or(0);
+
+        mem::size_of::<Self>() + layers_bytes + negative_cache_bytes
+    }
+
+    // Returns whether the negative cache, if any, reports an element may be present.
+    fn may_contain<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.negative_cache
+            .as_ref()
+            .map(|negative_cache| negative_cache.contains(key))
+            .unwrap_or(true)
+    }
+
+    /// Returns the number of elements in all layers.
+    ///
+    /// This counts novel inserts, i.e., inserts that set at least one bit of the last layer. A
+    /// value inserted again after the filter grew is counted again (see [`Self::insert`]). When
+    /// layers are evicted (see [`Self::with_max_window`]), their elements are no longer counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert_eq!(filter.len(), 0);
+    ///
+    /// filter.insert("a");
+    /// assert_eq!(filter.len(), 1);
+    ///
+    /// filter.insert("b");
+    /// assert_eq!(filter.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns `true` if the filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert!(filter.is_empty());
+    ///
+    /// filter.insert("a");
+    /// assert!(!filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the total capacity of the layers.
+    ///
+    /// This is the number of elements the filter can hold before a layer is added. Unlike
+    /// [`BloomFilter::capacity`], it is not a number of bits (see [`Self::memory_usage`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert_eq!(filter.capacity(), 64);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.total_capacity
+    }
+
+    /// Returns the number of layers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// assert_eq!(filter.num_filters(), 1);
+    ///
+    /// for i in 0..65 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// assert_eq!(filter.num_filters(), 2);
+    /// assert!(filter.capacity() > 64);
+    /// ```
+    pub fn num_filters(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Reads a scalable filter in the binary format (see [`crate::io`]), creating the hash
+    /// builders of every layer using [`Default`].
+    ///
+    /// The hash builders must produce the same hashes as the ones used to build the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+    ///     ScalableBloomFilter::with_hashers(0.0001, 64, Default::default(), Default::default());
+    /// filter.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter: ScalableBloomFilter<SeededHashBuilder> =
+    ///     ScalableBloomFilter::read_from(&mut &buf[..])?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_from<R>(reader: &mut R) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        Self::read_from_with_hashers(reader, S::default(), S::default())
+    }
+
+    /// Reads a scalable filter in the binary format (see [`crate::io`]), using clones of
+    /// `builder_1` and `builder_2` to hash the data in every layer.
+    ///
+    /// The hash builders must produce the same hashes as the ones used to build the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter = ScalableBloomFilter::with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(2),
+    ///     SeededHashBuilder::new(3),
+    /// );
+    /// filter.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// let filter = ScalableBloomFilter::read_from_with_hashers(
+    ///     &mut &buf[..],
+    ///     SeededHashBuilder::new(2),
+    ///     SeededHashBuilder::new(3),
+    /// )?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_from_with_hashers<R>(
+        reader: &mut R,
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError>
+    where
+        R: Read,
+    {
+        let parts = read_scalable_bloom_filter(reader)?;
+
+        let filters = parts
+            .layers
+            .into_iter()
+            .map(|layer| {
+                Layer::Memory(BloomFilter::from_parts(
+                    layer,
+                    builder_1.clone(),
+                    builder_2.clone(),
+                ))
+            })
+            .collect();
+
+        Ok(Self {
+            n: parts.n,
+            total_capacity: parts.total_capacity,
+            filters,
+            last_fpp: parts.last_fpp,
+            negative_cache: parts
+                .negative_cache
+                .map(|parts| BloomFilter::from_parts(parts, builder_1, builder_2)),
+            growth_factor: GROWTH_FACTOR,
+            tightening_ratio: TIGHTENING_RATIO,
+            min_fpp: MIN_FPP,
+            max_window: None,
+            on_evict: None,
+        })
+    }
+
+    /// Decodes a scalable filter in the binary format (see [`crate::io`]), creating the hash
+    /// builders of every layer using [`Default`].
+    ///
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+    ///     ScalableBloomFilter::with_hashers(0.0001, 64, Default::default(), Default::default());
+    /// filter.insert("a");
+    ///
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter: ScalableBloomFilter<SeededHashBuilder> = ScalableBloomFilter::from_bytes(&buf)?;
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, ReadError> {
+        Self::from_bytes_with_hashers(buf, S::default(), S::default())
+    }
+
+    /// Decodes a scalable filter in the binary format (see [`crate::io`]), using clones of
+    /// `builder_1` and `builder_2` to hash the data in every layer.
+    ///
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let mut filter = ScalableBloomFilter::with_hashers(
+    ///     0.0001,
+    ///     64,
+    ///     SeededHashBuilder::new(2),
+    ///     SeededHashBuilder::new(3),
+    /// );
+    /// filter.insert("a");
+    ///
+    /// let buf = filter.to_bytes();
+    ///
+    /// let filter = ScalableBloomFilter::from_bytes_with_hashers(
+    ///     &buf,
+    ///     SeededHashBuilder::new(2),
+    ///     SeededHashBuilder::new(3),
+    /// )?;
+    ///
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn from_bytes_with_hashers(
+        mut buf: &[u8],
+        builder_1: S,
+        builder_2: S,
+    ) -> Result<Self, ReadError> {
+        let filter = Self::read_from_with_hashers(&mut buf, builder_1, builder_2)?;
+
+        if buf.is_empty() {
+            Ok(filter)
+        } else {
+            Err(ReadError::TrailingData)
+        }
+    }
+
+    /// Reads a scalable filter in the binary format (see [`crate::io`]) from a file at `path`,
+    /// creating the hash builders of every layer using [`Default`].
+    ///
+    /// This fails if there is data after the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{ScalableBloomFilter, SeededHashBuilder};
+    ///
+    /// let filter: ScalableBloomFilter<SeededHashBuilder> =
+    ///     ScalableBloomFilter::read_from_path("filter.bin")?;
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn read_from_path<P>(path: P) -> Result<Self, ReadError>
+    where
+        P: AsRef<Path>,
+    {
+        path::read_from_path(path, |reader| Self::read_from(reader))
+    }
+
+    /// Writes the scalable filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.write_to(&mut buf)?;
+    ///
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let payloads: Vec<_> = self.filters.iter().map(|f| f.bits_to_bytes()).collect();
+
+        let layers = self
+            .filters
+            .iter()
+            .zip(&payloads)
+            .map(|(f, bits)| (f.capacity(), f.hash_count(), f.len(), &bits[..]));
+
+        let negative_cache = self
+            .negative_cache
+            .as_ref()
+            .map(|f| (f.capacity(), f.hash_count(), f.len(), f.bits_to_bytes()));
+
+        write_scalable_bloom_filter(
+            writer,
+            self.n,
+            self.total_capacity,
+            self.last_fpp,
+            layers,
+            negative_cache
+                .as_ref()
+                .map(|(m, k, n, bits)| (*m, *k, *n, &bits[..])),
+        )
+    }
+
+    /// Returns a reader of the scalable filter in the binary format (see [`crate::io`]).
+    ///
+    /// The reader produces the same bytes as [`Self::write_to`], but the bit arrays are encoded in
+    /// chunks as they are read rather than all at once. See [`SerializedReader`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read};
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let mut buf = Vec::new();
+    /// filter.serialized_reader().read_to_end(&mut buf)?;
+    ///
+    /// assert_eq!(buf, filter.to_bytes());
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn serialized_reader(&self) -> SerializedReader<'_> {
+        let layers = self
+            .filters
+            .iter()
+            .map(|f| (f.hash_count(), f.len(), f.bit_store()));
+
+        let negative_cache = self
+            .negative_cache
+            .as_ref()
+            .map(|f| (f.hash_count(), f.len(), f.bit_store() as &dyn BitStore));
+
+        scalable_bloom_filter_reader(
+            self.n,
+            self.total_capacity,
+            self.last_fpp,
+            layers,
+            negative_cache,
+        )
+    }
+
+    /// Writes the scalable filter in the binary format (see [`crate::io`]) to a file at `path`.
+    ///
+    /// An existing file at `path` is replaced atomically. See [`BloomFilter::write_to_path`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    /// filter.write_to_path("filter.bin")?;
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn write_to_path<P>(&self, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        path::write_atomically(path, |writer| self.write_to(writer))
+    }
+
+    /// Encodes the scalable filter in the binary format (see [`crate::io`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::ScalableBloomFilter;
+    /// let filter = ScalableBloomFilter::new(0.0001, 64);
+    /// let buf = filter.to_bytes();
+    /// assert_eq!(&buf[..4], b"BBLM");
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)
+            .expect("writing to a Vec should not fail");
+        buf
+    }
+
+    fn grow(&mut self) -> Result<(), GrowError> {
+        let window_is_full = self
+            .max_window
+            .map(|max_layers| self.filters.len() >= max_layers)
+            .unwrap_or(false);
+
+        let (p, n, m, k) = if window_is_full {
+            // Once the window is full, new layers repeat the last layer.
+            let last_layer = self.filters.last().expect("missing last layer");
+            let (p, m, k) = (
+                self.last_fpp,
+                last_layer.capacity(),
+                last_layer.hash_count(),
+            );
+            (p, optimal_number_of_elements(p, m), m, k)
+        } else {
+            let p = (self.last_fpp * self.tightening_ratio).max(self.min_fpp);
+
+            let n = self
+                .total_capacity
+                .checked_mul(self.growth_factor)
+                .ok_or(GrowError::CapacityOverflow)?;
+
+            let m = checked_optimal_required_bits(p, n).ok_or(GrowError::CapacityOverflow)?;
+            let k = optimal_number_of_hash_functions(m, n);
+
+            (p, n, m, k)
+        };
+
+        let total_capacity = self
+            .total_capacity
+            .checked_add(n)
+            .ok_or(GrowError::CapacityOverflow)?;
+
+        let (builder_1, builder_2) = self.hash_builders();
+        let filter = BloomFilter::with_hashers(m, k, builder_1, builder_2);
+        self.filters.push(Layer::Memory(filter));
+
+        self.total_capacity = total_capacity;
+        self.last_fpp = p;
+
+        self.evict_layers();
+
+        Ok(())
+    }
+
+    // Returns clones of the hash builders of the last layer.
+    fn hash_builders(&self) -> (S, S) {
+        let last_layer = self.filters.last().expect("missing last layer");
+        let (builder_1, builder_2) = last_layer.hash_builders();
+        (builder_1.clone(), builder_2.clone())
+    }
+
+    // Removes the oldest layers past the max window, if any.
+    fn evict_layers(&mut self) {
+        let max_layers = match self.max_window {
+            Some(max_layers) => max_layers,
+            None => return,
+        };
+
+        while self.filters.len() > max_layers {
+            let stats = self.filters.remove(0).stats();
+
+            // An old layer is full, so its capacity is its number of elements.
+            self.n = self.n.saturating_sub(stats.len());
+            self.total_capacity = self.total_capacity.saturating_sub(stats.len());
+
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(stats);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<S> ScalableBloomFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Moves the bit array of layer `i` to a memory-mapped file at `path`.
+    ///
+    /// The layer is written to `path` in the binary format of a Bloom filter (see [`crate::io`]),
+    /// which is then mapped read-only, and the bit array in memory is freed. Queries continue to
+    /// work across layers in memory and mapped layers, but pages of mapped layers are loaded by
+    /// the operating system as they are probed. This suits old layers, which rarely match.
+    ///
+    /// The file must not be modified or removed while the layer is mapped. It is not removed by
+    /// the filter, e.g., after [`Self::promote_layer`] or when the filter is dropped, so it can be
+    /// mapped again with [`BloomFilter::map_path`].
+    ///
+    /// Layers are indexed in the order they were created, starting at 0 (see [`Self::layer_of`]).
+    /// The last layer is active, i.e., it receives inserts, and cannot be demoted.
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`io::ErrorKind::InvalidInput`] if layer `i` is the last layer, or
+    /// any error from writing or mapping the file. The layer is unchanged on error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    ///
+    /// for i in 0..128 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// filter.demote_layer(0, "layer-0.bin")?;
+    /// assert!(filter.contains(&0));
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn demote_layer<P>(&mut self, i: usize, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        assert!(i < self.filters.len(), "layer index out of bounds");
+
+        if i == self.filters.len() - 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot demote the active layer",
+            ));
+        }
+
+        let layer = self.filters.remove(i);
+
+        match layer.into_mapped(path) {
+            Ok(layer) => {
+                self.filters.insert(i, layer);
+                Ok(())
+            }
+            Err((layer, e)) => {
+                self.filters.insert(i, layer);
+                Err(e)
+            }
+        }
+    }
+
+    /// Copies the bit array of a memory-mapped layer `i` back into memory.
+    ///
+    /// This reverses [`Self::demote_layer`]. The file of the layer is unmapped but not removed. If
+    /// the layer is not mapped, this does nothing.
+    ///
+    /// This is only available with the `mmap` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use bbloom::ScalableBloomFilter;
+    ///
+    /// let mut filter = ScalableBloomFilter::new(0.0001, 64);
+    ///
+    /// for i in 0..128 {
+    ///     filter.insert(&i);
+    /// }
+    ///
+    /// filter.demote_layer(0, "layer-0.bin")?;
+    /// filter.promote_layer(0);
+    /// assert!(filter.contains(&0));
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn promote_layer(&mut self, i: usize) {
+        assert!(i < self.filters.len(), "layer index out of bounds");
+        let layer = self.filters.remove(i);
+        self.filters.insert(i, layer.into_memory());
+    }
+}
+
+impl<S> Filter for ScalableBloomFilter<S>
+where
+    S: BuildHasher + Clone + Default,
+{
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.contains(key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.insert(key)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[test]
+    fn test_with_hashers() {
+        let new_filter = || {
+            ScalableBloomFilter::with_hashers(
+                0.001,
+                8,
+                SeededHashBuilder::new(2),
+                SeededHashBuilder::new(3),
+            )
+            .with_negative_cache(0.01, 1024)
+        };
+
+        let mut filter = new_filter();
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        assert!(filter.filters.len() > 2);
+
+        for layer in &filter.filters {
+            let (builder_1, builder_2) = layer.hash_builders();
+            assert_eq!(builder_1.seed(), 2);
+            assert_eq!(builder_2.seed(), 3);
+        }
+
+        let negative_cache = filter.negative_cache.as_ref().unwrap();
+        let (builder_1, builder_2) = negative_cache.hash_builders();
+        assert_eq!((builder_1.seed(), builder_2.seed()), (2, 3));
+
+        let mut other = new_filter();
+
+        for i in 0..64 {
+            other.insert(&i);
+        }
+
+        assert_eq!(other.to_bytes(), filter.to_bytes());
+        assert!((0..1024).all(|i| other.contains(&i) == filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_layer_of() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        filter.insert("first");
+
+        for i in 0..8 {
+            filter.insert(&i);
+        }
+
+        filter.insert("second");
+        assert_eq!(filter.filters.len(), 2);
+
+        assert_eq!(filter.layer_of("first"), Some(0));
+        assert_eq!(filter.layer_of("second"), Some(1));
+        assert_eq!(filter.layers_of("second"), [1]);
+        assert_eq!(filter.layer_of("third"), None);
+        assert!(filter.layers_of("third").is_empty());
+    }
+
+    #[test]
+    fn test_with_max_window() {
+        use std::sync::{Arc, Mutex};
+
+        const N: usize = 1000;
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let evicted_handle = Arc::clone(&evicted);
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, N, Default::default(), Default::default())
+                .with_max_window(3)
+                .on_evict(move |stats| evicted_handle.lock().unwrap().push(stats));
+
+        let layer_count = |filter: &ScalableBloomFilter<SeededHashBuilder>| {
+            filter.filters.len() + evicted.lock().unwrap().len()
+        };
+
+        // the first key of every layer created
+        let mut layer_starts = vec![0];
+        let mut full_memory_usage = None;
+        let mut max_memory_usage = 0;
+        let mut i = 0;
+
+        // The window fills after 2 growths, and 3 more growths evict the first 3 layers.
+        while layer_count(&filter) < 6 {
+            let prev_layer_count = layer_count(&filter);
+
+            filter.insert(&i);
+
+            if layer_count(&filter) > prev_layer_count {
+                layer_starts.push(i);
+
+                if layer_count(&filter) == 3 {
+                    full_memory_usage = Some(filter.memory_usage());
+                }
+
+                max_memory_usage = max_memory_usage.max(filter.memory_usage());
+            }
+
+            i += 1;
+        }
+
+        let evicted = evicted.lock().unwrap();
+        assert_eq!(evicted.len(), 3);
+        assert_eq!(evicted[0].len(), N);
+
+        let layer_stats = filter.layer_stats();
+        assert_eq!(layer_stats.len(), 3);
+
+        assert_eq!(
+            filter.len(),
+            layer_stats.iter().map(|stats| stats.len()).sum::<usize>()
+        );
+
+        // The layers after the window filled repeat the last layer, so at most, memory use
+        // doubles (layers of sizes 1, 2, and 6 become 6, 6, and 6).
+        assert_eq!(layer_stats[0].capacity(), evicted[2].capacity());
+        assert_eq!(layer_stats[2].capacity(), evicted[2].capacity());
+        assert!(max_memory_usage <= 2 * full_memory_usage.unwrap());
+
+        // Keys of evicted layers are forgotten.
+        let evicted_keys = 0..layer_starts[3];
+        let forgotten = evicted_keys.clone().filter(|i| !filter.contains(i)).count();
+        assert!(forgotten > evicted_keys.len() * 99 / 100);
+
+        // Recent keys are still present.
+        assert!((layer_starts[3]..i).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_max_window_with_no_layers() {
+        let _: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_max_window(0);
+    }
+
+    #[test]
+    fn test_match_score() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        filter.insert("first");
+
+        for i in 0..8 {
+            filter.insert(&i);
+        }
+
+        filter.insert("second");
+        assert_eq!(filter.filters.len(), 2);
+
+        let (layer, (score, k)) = filter.match_score("first");
+        assert_eq!(layer, 0);
+        assert_eq!(score, k);
+
+        let (layer, (score, k)) = filter.match_score("second");
+        assert_eq!(layer, 1);
+        assert_eq!(score, k);
+        assert_eq!(k, filter.filters[1].hash_count());
+
+        for i in 0..1000 {
+            let (layer, (score, k)) = filter.match_score(&i);
+            assert_eq!(score == k, filter.contains(&i));
+
+            if score == k {
+                assert_eq!(filter.layer_of(&i), Some(layer));
+            }
+        }
+    }
+
+    #[test]
+    fn test_estimate_cardinality() {
+        const N: usize = 50_000;
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 1000, Default::default(), Default::default());
+
+        assert_eq!(filter.estimate_cardinality(), 0.0);
+
+        for i in 0..N {
+            filter.insert(&i);
+        }
+
+        let stats = filter.layer_stats();
+        assert!(stats.len() > 3);
+
+        let layer_estimates: f64 = stats.iter().map(|s| s.estimated_cardinality()).sum();
+        assert_eq!(filter.estimate_cardinality(), layer_estimates);
+
+        // The estimate of distinct keys is within 5%.
+        let estimate = filter.estimate_cardinality();
+        let error = (estimate - N as f64).abs() / N as f64;
+        assert!(error < 0.05, "{} (error = {})", estimate, error);
+    }
+
+    #[test]
+    fn test_capacity_and_num_filters() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 64, Default::default(), Default::default());
+
+        assert_eq!(filter.capacity(), 64);
+        assert_eq!(filter.num_filters(), 1);
+
+        for i in 0..65 {
+            filter.insert(&i);
+        }
+
+        // The new layer holds `GROWTH_FACTOR` times the previous total capacity.
+        assert_eq!(filter.num_filters(), 2);
+        assert_eq!(filter.capacity(), 64 + 64 * GROWTH_FACTOR);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        assert!(filter.is_empty());
+
+        for i in 0..5 {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.len(), 5);
+
+        // A duplicate in the last layer is not counted.
+        filter.insert(&0);
+        assert_eq!(filter.len(), 5);
+
+        for i in 5..10 {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.num_filters(), 2);
+        assert_eq!(filter.len(), 10);
+
+        // A duplicate in an earlier layer is counted again.
+        filter.insert(&0);
+        assert_eq!(filter.len(), 11);
+        assert!(!filter.is_empty());
+    }
+
+    #[test]
+    fn test_estimated_fpp() {
+        const P: f64 = 0.01;
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(P, 1000, Default::default(), Default::default());
+
+        assert_eq!(filter.estimated_fpp(), 0.0);
+
+        for i in 0..2000 {
+            filter.insert(&i);
+        }
+
+        let stats = filter.layer_stats();
+        assert_eq!(stats.len(), 2);
+
+        let expected = 1.0 - (1.0 - stats[0].estimated_fpp()) * (1.0 - stats[1].estimated_fpp());
+        assert_eq!(filter.estimated_fpp(), expected);
+
+        let fpp = filter.estimated_fpp();
+        assert!(fpp > stats[0].estimated_fpp());
+
+        let bound = P / (1.0 - TIGHTENING_RATIO);
+        assert!(fpp < bound, "{} >= {}", fpp, bound);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        for i in 0..16 {
+            filter.insert(&i);
+        }
+
+        let summary = filter.summary();
+        assert!(summary.starts_with("ScalableBloomFilter { layers: 2, capacity: 24, n: 16,"));
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() -> Result<(), ReadError> {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        for i in 0..32 {
+            filter.insert(&i);
+        }
+
+        assert!(filter.filters.len() > 1);
+
+        let buf = filter.to_bytes();
+        let actual: ScalableBloomFilter<SeededHashBuilder> = ScalableBloomFilter::from_bytes(&buf)?;
+
+        assert_eq!(actual.n, filter.n);
+        assert_eq!(actual.total_capacity, filter.total_capacity);
+        assert_eq!(actual.last_fpp, filter.last_fpp);
+        assert_eq!(actual.filters.len(), filter.filters.len());
+
+        for i in 0..32 {
+            assert!(actual.contains(&i));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_path_and_read_from_path() -> Result<(), ReadError> {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("bbloom-scalable-bloom-filter-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("filter.bin");
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+        filter.insert(&0);
+        filter.write_to_path(&path)?;
+
+        for i in 1..32 {
+            filter.insert(&i);
+        }
+
+        filter.write_to_path(&path)?;
+
+        let actual: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::read_from_path(&path)?;
+        assert_eq!(actual.n, 32);
+        assert_eq!(actual.filters.len(), filter.filters.len());
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        for i in 0..32 {
+            assert!(actual.contains(&i));
+        }
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_negative_cache() -> Result<(), ReadError> {
+        let build = || -> ScalableBloomFilter<SeededHashBuilder> {
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+        };
+
+        let mut expected = build();
+        let mut filter = build().with_negative_cache(0.01, 1 << 12);
+
+        for i in 0..1024 {
+            expected.insert(&i);
+            filter.insert(&i);
+        }
+
+        assert!(filter.filters.len() > 4);
+        assert!((0..1024).all(|i| filter.contains(&i)));
+
+        // The negative cache can only remove false positives.
+        for i in 1024..8192 {
+            assert!(!filter.contains(&i) || expected.contains(&i));
+        }
+
+        let actual: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::from_bytes(&filter.to_bytes())?;
+        assert!(actual.negative_cache.is_some());
+        assert!((0..1024).all(|i| actual.contains(&i)));
+
+        // A filter written without a negative cache is read without one.
+        let actual: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::from_bytes(&expected.to_bytes())?;
+        assert!(actual.negative_cache.is_none());
+        assert!((0..1024).all(|i| actual.contains(&i)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains_or_insert_with_negative_cache() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_negative_cache(0.01, 1 << 12);
+
+        for i in 0..64 {
+            assert!(!filter.contains_or_insert(&i));
+        }
+
+        assert!(filter.filters.len() > 1);
+
+        for i in 0..64 {
+            assert!(filter.contains_or_insert(&i));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_negative_cache_after_insert() {
+        let mut filter = ScalableBloomFilter::new(0.001, 8);
+        filter.insert("a");
+        let _ = filter.with_negative_cache(0.01, 1 << 12);
+    }
+
+    #[test]
+    fn test_try_insert_with_capacity_overflow() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        // Doubling the total capacity overflows.
+        filter.total_capacity = usize::MAX / 2 + 1;
+        filter.n = filter.total_capacity;
+
+        assert_eq!(filter.try_insert("a"), Err(GrowError::CapacityOverflow));
+        assert_eq!(filter.filters.len(), 1);
+        assert!(!filter.contains("a"));
+
+        // Adding the new layer overflows the total capacity.
+        filter.total_capacity = usize::MAX / 3 + 1;
+        filter.n = filter.total_capacity;
+
+        assert_eq!(filter.try_insert("a"), Err(GrowError::CapacityOverflow));
+        assert_eq!(filter.filters.len(), 1);
+
+        assert!(filter.insert("a"));
+        assert!(filter.contains("a"));
+        assert_eq!(filter.filters.len(), 1);
+    }
+
+    #[test]
+    fn test_try_insert_with_bit_array_overflow() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+
+        // The total capacity fits, but the bit array of the new layer would exceed `isize::MAX`.
+        filter.total_capacity = usize::MAX / 8;
+        filter.n = filter.total_capacity;
+
+        assert_eq!(filter.try_insert("a"), Err(GrowError::CapacityOverflow));
+        assert_eq!(filter.filters.len(), 1);
+    }
+
+    #[test]
+    fn test_grow_with_growth_factor_and_tightening_ratio() {
+        const P: f64 = 0.001;
+        const N: usize = 64;
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(P, N, Default::default(), Default::default())
+                .with_growth_factor(4)
+                .with_tightening_ratio(0.5);
+
+        for i in 0..=N {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.num_filters(), 2);
+        assert_eq!(filter.capacity(), N + 4 * N);
+        assert_eq!(filter.last_fpp, P * 0.5);
+
+        let expected_m = BloomFilter::from_fpp(P * 0.5, 4 * N).capacity();
+        assert_eq!(filter.filters[1].capacity(), expected_m);
+
+        for i in (N + 1)..(5 * N + 1) {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.num_filters(), 3);
+        assert_eq!(filter.capacity(), 5 * N + 4 * 5 * N);
+        assert_eq!(filter.last_fpp, P * 0.25);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_growth_factor_with_zero() {
+        let _: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_growth_factor(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_tightening_ratio_with_ratio_of_1() {
+        let _: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_tightening_ratio(1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_min_fpp_with_fpp_of_1() {
+        let _: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_min_fpp(1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_min_fpp_with_nan() {
+        let _: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_min_fpp(f64::NAN);
+    }
+
+    #[test]
+    fn test_grow_with_min_fpp() {
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default())
+                .with_min_fpp(1e-6);
+
+        // Simulate many layers of tightening.
+        filter.last_fpp = 1e-6;
+        filter.n = filter.total_capacity;
+
+        assert_eq!(filter.try_insert("a"), Ok(true));
+        assert_eq!(filter.filters.len(), 2);
+        assert_eq!(filter.last_fpp, 1e-6);
+
+        let expected_m = BloomFilter::from_fpp(1e-6, 16).capacity();
+        assert_eq!(filter.filters[1].capacity(), expected_m);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_demote_layer_and_promote_layer() -> Result<(), Box<dyn std::error::Error>> {
+        use std::{env, fs, process};
+
+        use crate::bit_store::MappedBits;
+
+        let dir = env::temp_dir().join(format!("bbloom-demote-layer-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let mut filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 1000, Default::default(), Default::default());
+
+        for i in 0..3500 {
+            filter.insert(&i);
+        }
+
+        assert_eq!(filter.filters.len(), 3);
+
+        let expected: Vec<_> = (0..10000).map(|i| filter.contains(&i)).collect();
+        let expected_buf = filter.to_bytes();
+        let expected_memory_usage = filter.memory_usage();
+        let layer_0_bytes = filter.filters[0].capacity().div_ceil(8);
+        let layer_1_bytes = filter.filters[1].capacity().div_ceil(8);
+
+        filter.demote_layer(0, dir.join("layer-0.bin"))?;
+        filter.demote_layer(1, dir.join("layer-1.bin"))?;
+
+        let actual: Vec<_> = (0..10000).map(|i| filter.contains(&i)).collect();
+        assert_eq!(actual, expected);
+        assert_eq!(filter.to_bytes(), expected_buf);
+        assert_eq!(
+            filter.memory_usage(),
+            expected_memory_usage - layer_0_bytes - layer_1_bytes
+        );
+
+        assert!(matches!(
+            filter.demote_layer(2, dir.join("layer-2.bin")),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+
+        filter.insert(&3500);
+        assert!(filter.contains(&3500));
+
+        filter.promote_layer(1);
+        assert_eq!(filter.memory_usage(), expected_memory_usage - layer_0_bytes);
+        assert_eq!(filter.layer_of(&1500), Some(1));
+
+        drop(filter);
+
+        let layer: BloomFilter<SeededHashBuilder, MappedBits> =
+            unsafe { BloomFilter::map_path(dir.join("layer-0.bin"))? };
+        assert!((0..1000).all(|i| layer.contains(&i)));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
     }
 }
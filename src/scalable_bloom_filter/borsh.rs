@@ -0,0 +1,141 @@
+//! Borsh support for [`ScalableBloomFilter`].
+//!
+//! A scalable filter is serialized as the following fields, in order:
+//!
+//! | name             | type                  | description                                  |
+//! |------------------|-----------------------|----------------------------------------------|
+//! | `n`              | `u64`                 | number of inserted elements                  |
+//! | `total_capacity` | `u64`                 | total capacity of all layers                 |
+//! | `last_fpp`       | `f64`                 | false positive probability of the last layer |
+//! | `layers`         | `Vec<BloomFilter>`    | layers in the order they were created        |
+//! | `negative_cache` | `Option<BloomFilter>` | negative cache                               |
+//!
+//! Each filter is encoded as described in the Borsh support for [`BloomFilter`], including the
+//! seeds of its hash builders. Deserialization validates the parameters like the binary format
+//! (see [`crate::io`]): there must be at least one layer, and `last_fpp` must be in (0, 1).
+//! Layers are read incrementally, so a corrupt count fails at the end of the input rather than
+//! allocating for the count.
+//!
+//! [`BloomFilter`]: crate::BloomFilter
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{Layer, ScalableBloomFilter, GROWTH_FACTOR, MIN_FPP, TIGHTENING_RATIO};
+use crate::{
+    bloom_filter::borsh::{invalid_data, read_usize},
+    BloomFilter, SeededHashBuilder,
+};
+
+impl BorshSerialize for ScalableBloomFilter<SeededHashBuilder> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (self.n as u64).serialize(writer)?;
+        (self.total_capacity as u64).serialize(writer)?;
+        self.last_fpp.serialize(writer)?;
+
+        let layer_count = u32::try_from(self.filters.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many layers"))?;
+        layer_count.serialize(writer)?;
+
+        for layer in &self.filters {
+            layer.serialize_borsh(writer)?;
+        }
+
+        self.negative_cache.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for ScalableBloomFilter<SeededHashBuilder> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let n = read_usize(reader)?;
+        let total_capacity = read_usize(reader)?;
+        let last_fpp = f64::deserialize_reader(reader)?;
+        let layer_count = u32::deserialize_reader(reader)?;
+
+        if layer_count == 0 || !(last_fpp > 0.0 && last_fpp < 1.0) {
+            return Err(invalid_data("invalid filter parameters"));
+        }
+
+        let mut filters = Vec::new();
+
+        for _ in 0..layer_count {
+            let filter = BloomFilter::deserialize_reader(reader)?;
+            filters.push(Layer::Memory(filter));
+        }
+
+        let negative_cache = Option::<BloomFilter<SeededHashBuilder>>::deserialize_reader(reader)?;
+
+        Ok(Self {
+            n,
+            total_capacity,
+            filters,
+            last_fpp,
+            negative_cache,
+            growth_factor: GROWTH_FACTOR,
+            tightening_ratio: TIGHTENING_RATIO,
+            min_fpp: MIN_FPP,
+            max_window: None,
+            on_evict: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> io::Result<()> {
+        let mut filter = ScalableBloomFilter::with_hashers(
+            0.001,
+            8,
+            SeededHashBuilder::new(2),
+            SeededHashBuilder::new(3),
+        )
+        .with_negative_cache(0.01, 1024);
+
+        for i in 0..64 {
+            filter.insert(&i);
+        }
+
+        assert!(filter.filters.len() > 1);
+
+        let buf = borsh::to_vec(&filter)?;
+        let actual: ScalableBloomFilter<SeededHashBuilder> = borsh::from_slice(&buf)?;
+
+        assert_eq!(actual.filters.len(), filter.filters.len());
+        assert!(actual.negative_cache.is_some());
+        assert!((0..64).all(|i| actual.contains(&i)));
+        assert_eq!(actual.to_bytes(), filter.to_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_with_invalid_input() -> io::Result<()> {
+        let filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 8, Default::default(), Default::default());
+        let buf = borsh::to_vec(&filter)?;
+
+        // last_fpp = 0
+        let mut data = buf.clone();
+        data[16..24].copy_from_slice(&0f64.to_le_bytes());
+        assert!(borsh::from_slice::<ScalableBloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // no layers
+        let mut data = buf.clone();
+        data[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert!(borsh::from_slice::<ScalableBloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        // The layer count is much larger than the input.
+        let mut data = buf.clone();
+        data[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(borsh::from_slice::<ScalableBloomFilter<SeededHashBuilder>>(&data).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+use std::{error, fmt};
+
+/// An error returned when a scalable filter cannot add a layer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GrowError {
+    /// The total capacity of the layers or the size of the bit array of the new layer overflows.
+    CapacityOverflow,
+}
+
+impl error::Error for GrowError {}
+
+impl fmt::Display for GrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CapacityOverflow => f.write_str("capacity overflow"),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::hash::{BuildHasher, Hash};
+
+#[cfg(any(feature = "borsh", feature = "mmap"))]
+use std::io;
+#[cfg(feature = "borsh")]
+use std::io::Write;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+use super::LayerStats;
+use crate::{bit_store::BitStore, probe_strategy::ProbeStrategy, BloomFilter};
+#[cfg(feature = "mmap")]
+use crate::{bit_store::MappedBits, bloom_filter::mapped::map_bits, io::ReadError};
+#[cfg(feature = "borsh")]
+use crate::{bloom_filter::borsh::serialize, SeededHashBuilder};
+
+// A layer of a scalable Bloom filter.
+//
+// The last layer is the only one that is inserted into and is always in memory. Other layers can
+// be moved to a memory-mapped file.
+pub(super) enum Layer<S> {
+    Memory(BloomFilter<S>),
+    #[cfg(feature = "mmap")]
+    Mapped(BloomFilter<S, MappedBits>),
+}
+
+impl<S> Layer<S>
+where
+    S: BuildHasher,
+{
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        match self {
+            Self::Memory(filter) => filter.contains(key),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.contains(key),
+        }
+    }
+
+    pub fn match_score<H: Hash + ?Sized>(&self, key: &H) -> (usize, usize) {
+        match self {
+            Self::Memory(filter) => filter.match_score(key),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.match_score(key),
+        }
+    }
+
+    pub fn as_memory_mut(&mut self) -> Option<&mut BloomFilter<S>> {
+        match self {
+            Self::Memory(filter) => Some(filter),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_) => None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::Memory(filter) => filter.capacity(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.capacity(),
+        }
+    }
+
+    pub fn hash_count(&self) -> usize {
+        match self {
+            Self::Memory(filter) => filter.hash_count(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.hash_count(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Memory(filter) => filter.len(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.len(),
+        }
+    }
+
+    pub fn load_fpp(&self) -> f64 {
+        match self {
+            Self::Memory(filter) => filter.load_fpp(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.load_fpp(),
+        }
+    }
+
+    pub fn stats(&self) -> LayerStats {
+        match self {
+            Self::Memory(filter) => filter_stats(filter),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter_stats(filter),
+        }
+    }
+
+    pub fn bits_to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Memory(filter) => filter.bits_to_bytes(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.bits_to_bytes(),
+        }
+    }
+
+    // Returns the number of bytes of the bit array in memory. A mapped bit array is not counted.
+    pub fn bit_store(&self) -> &dyn BitStore {
+        match self {
+            Self::Memory(filter) => filter.bit_store(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.bit_store(),
+        }
+    }
+
+    pub fn hash_builders(&self) -> (&S, &S) {
+        match self {
+            Self::Memory(filter) => filter.hash_builders(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => filter.hash_builders(),
+        }
+    }
+
+    pub fn bits_memory_usage(&self) -> usize {
+        match self {
+            Self::Memory(filter) => filter.capacity().div_ceil(8),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_) => 0,
+        }
+    }
+
+    // Writes the layer to a file at `path` and replaces the bit array with a map of the file.
+    #[cfg(feature = "mmap")]
+    pub fn into_mapped<P>(self, path: P) -> Result<Self, (Self, io::Error)>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let result = match &self {
+            Self::Memory(filter) => filter.write_to_path(path),
+            Self::Mapped(filter) => filter.write_to_path(path),
+        };
+
+        if let Err(e) = result {
+            return Err((self, e));
+        }
+
+        // SAFETY: The file was just written by the layer. It is owned by the layer while it is
+        // mapped, which is documented in `ScalableBloomFilter::demote_layer`.
+        let bits = match unsafe { map_bits(path) } {
+            Ok((_, bits)) => bits,
+            Err(e) => return Err((self, read_error_into_io_error(e))),
+        };
+
+        let filter = match self {
+            Self::Memory(filter) => filter.with_bits(bits),
+            Self::Mapped(filter) => filter.with_bits(bits),
+        };
+
+        Ok(Self::Mapped(filter))
+    }
+
+    // Copies a mapped bit array into memory.
+    #[cfg(feature = "mmap")]
+    pub fn into_memory(self) -> Self {
+        match self {
+            Self::Memory(filter) => Self::Memory(filter),
+            Self::Mapped(filter) => Self::Memory(filter.into_memory()),
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+fn read_error_into_io_error(e: ReadError) -> io::Error {
+    match e {
+        ReadError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+fn filter_stats<S, B, P>(filter: &BloomFilter<S, B, P>) -> LayerStats
+where
+    S: BuildHasher,
+    B: BitStore,
+    P: ProbeStrategy,
+{
+    LayerStats {
+        capacity: filter.capacity(),
+        hash_count: filter.hash_count(),
+        len: filter.len(),
+        load: filter.load(),
+        estimated_fpp: filter.load_fpp(),
+        estimated_cardinality: filter.estimate_cardinality(),
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl Layer<SeededHashBuilder> {
+    pub fn serialize_borsh<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            Self::Memory(filter) => serialize(filter, writer),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(filter) => serialize(filter, writer),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/// Statistics of a layer of a scalable Bloom filter.
+///
+/// See [`ScalableBloomFilter::layer_stats`](crate::ScalableBloomFilter::layer_stats).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerStats {
+    pub(crate) capacity: usize,
+    pub(crate) hash_count: usize,
+    pub(crate) len: usize,
+    pub(crate) load: f64,
+    pub(crate) estimated_fpp: f64,
+    pub(crate) estimated_cardinality: f64,
+}
+
+impl LayerStats {
+    /// Returns the size of the bit array `m` of the layer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of hash functions `k` of the layer.
+    pub fn hash_count(&self) -> usize {
+        self.hash_count
+    }
+
+    /// Returns the number of elements that were inserted into the layer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no elements were inserted into the layer.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the proportion of set bits.
+    pub fn load(&self) -> f64 {
+        self.load
+    }
+
+    /// Returns the false positive probability of the layer given the proportion of set bits.
+    pub fn estimated_fpp(&self) -> f64 {
+        self.estimated_fpp
+    }
+
+    /// Returns the estimated number of distinct elements in the layer.
+    ///
+    /// See [`BloomFilter::estimate_cardinality`](crate::BloomFilter::estimate_cardinality).
+    pub fn estimated_cardinality(&self) -> f64 {
+        self.estimated_cardinality
+    }
+}
@@ -0,0 +1,173 @@
+use std::hash::{BuildHasher, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A hash builder that creates deterministic hashers from a fixed seed.
+///
+/// Unlike [`std::collections::hash_map::RandomState`], hashes are reproducible across processes
+/// and platforms, which makes this suitable for filters that are built in one place and queried
+/// in another.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{BloomFilter, SeededHashBuilder};
+///
+/// let mut filter = BloomFilter::from_fpp_with_hashers(
+///     0.0001,
+///     64,
+///     SeededHashBuilder::new(0),
+///     SeededHashBuilder::new(1),
+/// );
+///
+/// filter.insert("a");
+/// assert!(filter.contains("a"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SeededHashBuilder {
+    seed: u64,
+}
+
+impl SeededHashBuilder {
+    /// Creates a hash builder with the given seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::SeededHashBuilder;
+    /// let _builder = SeededHashBuilder::new(8);
+    /// ```
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Returns the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::SeededHashBuilder;
+    /// let builder = SeededHashBuilder::new(8);
+    /// assert_eq!(builder.seed(), 8);
+    /// ```
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for SeededHashBuilder {
+    type Hasher = SeededHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        SeededHasher {
+            state: FNV_OFFSET_BASIS ^ mix(self.seed),
+        }
+    }
+}
+
+/// A deterministic hasher created by [`SeededHashBuilder`].
+///
+/// This is FNV-1a with a seeded offset basis and a final avalanche step. Integers are always
+/// written in little-endian order, and `usize` values are widened to 64 bits, so the same key
+/// hashes identically on every platform. The hash function is a stable guarantee (see
+/// [`crate::io`]).
+#[derive(Clone, Debug)]
+pub struct SeededHasher {
+    state: u64,
+}
+
+impl Hasher for SeededHasher {
+    fn finish(&self) -> u64 {
+        mix(self.state)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state ^= u64::from(b);
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+// MurmurHash3 64-bit finalizer (fmix64)
+const fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_deterministic() {
+        let a = SeededHashBuilder::new(0);
+        let b = SeededHashBuilder::new(0);
+        assert_eq!(a.hash_one("bbloom"), b.hash_one("bbloom"));
+    }
+
+    #[test]
+    fn test_hash_depends_on_seed() {
+        let a = SeededHashBuilder::new(0);
+        let b = SeededHashBuilder::new(1);
+        assert_ne!(a.hash_one("bbloom"), b.hash_one("bbloom"));
+    }
+
+    #[test]
+    fn test_write_usize() {
+        let builder = SeededHashBuilder::new(0);
+
+        let mut a = builder.build_hasher();
+        a.write_usize(8);
+
+        let mut b = builder.build_hasher();
+        b.write_u64(8);
+
+        assert_eq!(a.finish(), b.finish());
+    }
+}
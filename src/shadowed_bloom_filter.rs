@@ -0,0 +1,322 @@
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{Filter, SeededHashBuilder};
+
+// The seed of the hash builder used to record inserted keys.
+//
+// This is unrelated to the hash builders of the inner filter. Two distinct keys only collide in
+// the shadow set if their 64-bit hashes are equal.
+const SHADOW_SEED: u64 = 0x6262_6c6f_6f6d;
+
+/// A filter wrapper that tracks the exact set of inserted keys to measure the real false positive
+/// probability of the inner filter.
+///
+/// Every inserted key is hashed to 64 bits and recorded in a shadow set. Each call to
+/// [`contains`](Self::contains) for a key that is not in the shadow set is counted as a negative
+/// query, and if the inner filter reports the key as present, as a false positive.
+///
+/// This is meant for tests, parameter tuning, and canary deployments. It is only compiled with the
+/// `test-util` feature.
+///
+/// # Panics
+///
+/// [`contains`](Self::contains) panics if the inner filter reports a false negative, i.e., the
+/// inner filter does not contain a key that was inserted. This is never valid for a filter.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::{BloomFilter, ShadowedBloomFilter};
+///
+/// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+///
+/// for i in 0..1000 {
+///     filter.insert(&i);
+/// }
+///
+/// for i in 0..2000 {
+///     filter.contains(&i);
+/// }
+///
+/// assert!(filter.observed_fpp() < 0.05);
+/// ```
+pub struct ShadowedBloomFilter<F> {
+    inner: F,
+    shadow: HashSet<u64>,
+    builder: SeededHashBuilder,
+    negative_count: u64,
+    false_positive_count: u64,
+}
+
+impl<F> ShadowedBloomFilter<F>
+where
+    F: Filter,
+{
+    /// Creates a shadowed filter that wraps `inner`.
+    ///
+    /// `inner` is expected to be empty. Keys inserted before wrapping are not in the shadow set and
+    /// are counted as false positives when queried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    /// let _filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// ```
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            shadow: HashSet::new(),
+            builder: SeededHashBuilder::new(SHADOW_SEED),
+            negative_count: 0,
+            false_positive_count: 0,
+        }
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// This forwards to the inner filter and records the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the inner filter does not contain a key that was inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.contains("a"));
+    /// assert!(!filter.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        let is_present = self.inner.contains(key);
+
+        if self.shadow.contains(&self.builder.hash_one(key)) {
+            assert!(is_present, "false negative for an inserted key");
+        } else {
+            self.negative_count += 1;
+
+            if is_present {
+                self.false_positive_count += 1;
+            }
+        }
+
+        is_present
+    }
+
+    /// Adds a value to the filter.
+    ///
+    /// Returns the result of the insert into the inner filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// assert!(filter.insert("a"));
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        self.shadow.insert(self.builder.hash_one(key));
+        self.inner.insert(key)
+    }
+
+    /// Returns the number of elements in the inner filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// assert_eq!(filter.len(), 0);
+    ///
+    /// filter.insert("a");
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the inner filter contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// assert!(filter.is_empty());
+    ///
+    /// filter.insert("a");
+    /// assert!(!filter.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<F> ShadowedBloomFilter<F> {
+    /// Returns the fraction of queries for keys that were not inserted that the inner filter
+    /// reported as present.
+    ///
+    /// This is 0 if no such queries were observed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    /// let filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// assert_eq!(filter.observed_fpp(), 0.0);
+    /// ```
+    pub fn observed_fpp(&self) -> f64 {
+        if self.negative_count == 0 {
+            0.0
+        } else {
+            self.false_positive_count as f64 / self.negative_count as f64
+        }
+    }
+
+    /// Returns the number of observed false positives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    /// let filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// assert_eq!(filter.false_positive_count(), 0);
+    /// ```
+    pub fn false_positive_count(&self) -> u64 {
+        self.false_positive_count
+    }
+
+    /// Returns the number of observed queries for keys that were not inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// filter.insert("a");
+    /// filter.contains("a");
+    /// filter.contains("b");
+    ///
+    /// assert_eq!(filter.negative_count(), 1);
+    /// ```
+    pub fn negative_count(&self) -> u64 {
+        self.negative_count
+    }
+
+    /// Resets the observed query and false positive counts.
+    ///
+    /// The inner filter and the shadow set are unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// filter.contains("a");
+    /// assert_eq!(filter.negative_count(), 1);
+    ///
+    /// filter.reset_observations();
+    /// assert_eq!(filter.negative_count(), 0);
+    /// ```
+    pub fn reset_observations(&mut self) {
+        self.negative_count = 0;
+        self.false_positive_count = 0;
+    }
+
+    /// Returns a reference to the inner filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// filter.insert("a");
+    ///
+    /// assert!(filter.get_ref().contains("a"));
+    /// ```
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Unwraps and returns the inner filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, ShadowedBloomFilter};
+    ///
+    /// let mut filter = ShadowedBloomFilter::new(BloomFilter::from_fpp(0.01, 1000));
+    /// filter.insert("a");
+    ///
+    /// let filter = filter.into_inner();
+    /// assert!(filter.contains("a"));
+    /// ```
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A filter that contains everything but forgets one key.
+    struct ForgetfulFilter(usize);
+
+    impl Filter for ForgetfulFilter {
+        fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+            SeededHashBuilder::new(0).hash_one(key) != SeededHashBuilder::new(0).hash_one("a")
+        }
+
+        fn insert<H: Hash + ?Sized>(&mut self, _: &H) -> bool {
+            self.0 += 1;
+            true
+        }
+
+        fn len(&self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_observations() {
+        let mut filter = ShadowedBloomFilter::new(ForgetfulFilter(0));
+
+        filter.insert("b");
+        assert!(filter.contains("b"));
+        assert_eq!(filter.negative_count(), 0);
+
+        assert!(filter.contains("c"));
+        assert!(!filter.contains("a"));
+        assert_eq!(filter.negative_count(), 2);
+        assert_eq!(filter.false_positive_count(), 1);
+        assert_eq!(filter.observed_fpp(), 0.5);
+
+        filter.reset_observations();
+        assert_eq!(filter.negative_count(), 0);
+        assert_eq!(filter.false_positive_count(), 0);
+        assert_eq!(filter.observed_fpp(), 0.0);
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "false negative")]
+    fn test_contains_with_false_negative() {
+        let mut filter = ShadowedBloomFilter::new(ForgetfulFilter(0));
+        filter.insert("a");
+        filter.contains("a");
+    }
+}
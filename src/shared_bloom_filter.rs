@@ -0,0 +1,247 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    path::Path,
+    sync::Arc,
+};
+
+use arc_swap::{ArcSwap, Guard};
+
+use crate::{io::ReadError, BloomFilter, DefaultHashBuilder};
+
+/// A read-only filter shared across threads that can be replaced atomically.
+///
+/// This is meant for services that query a filter from many threads and periodically replace it
+/// with a freshly built one, e.g., from disk. Readers never block: [`Self::load`] returns a guard
+/// to the current filter, which stays valid even if the filter is replaced while it is held.
+/// A reader that queries through one guard always sees a single filter, never a mix of the old
+/// and new filters.
+///
+/// This is only available with the `arc-swap` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::{sync::Arc, thread};
+///
+/// use bbloom::{BloomFilter, SharedBloomFilter};
+///
+/// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+/// filter.insert("a");
+///
+/// let shared = Arc::new(SharedBloomFilter::new(filter));
+///
+/// let reader = {
+///     let shared = shared.clone();
+///     thread::spawn(move || shared.load().contains("a") || shared.load().contains("b"))
+/// };
+///
+/// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+/// filter.insert("b");
+/// shared.swap(filter);
+///
+/// assert!(reader.join().unwrap());
+/// assert!(shared.contains("b"));
+/// ```
+pub struct SharedBloomFilter<S = DefaultHashBuilder> {
+    filter: ArcSwap<BloomFilter<S>>,
+}
+
+impl<S> SharedBloomFilter<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a shared filter that initially holds `filter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SharedBloomFilter};
+    /// let _shared = SharedBloomFilter::new(BloomFilter::from_fpp(0.0001, 64));
+    /// ```
+    pub fn new(filter: BloomFilter<S>) -> Self {
+        Self {
+            filter: ArcSwap::from_pointee(filter),
+        }
+    }
+
+    /// Returns a guard to the current filter.
+    ///
+    /// This does not block, and the guard is cheap to create. It is meant to be held for a short
+    /// time, e.g., a batch of queries. Use [`Guard::into_inner`] to keep the filter for longer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SharedBloomFilter};
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let shared = SharedBloomFilter::new(filter);
+    /// let filter = shared.load();
+    /// assert!(filter.contains("a"));
+    /// ```
+    pub fn load(&self) -> Guard<Arc<BloomFilter<S>>> {
+        self.filter.load()
+    }
+
+    /// Tests the current filter for whether an element may be in the filter or definitely not in
+    /// the filter.
+    ///
+    /// This is a shorthand for `self.load().contains(key)`. Use [`Self::load`] to query several
+    /// elements against the same filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SharedBloomFilter};
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let shared = SharedBloomFilter::new(filter);
+    /// assert!(shared.contains("a"));
+    /// assert!(!shared.contains("b"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        self.load().contains(key)
+    }
+
+    /// Atomically replaces the current filter with `filter`, returning the previous filter.
+    ///
+    /// Readers holding a guard to the previous filter keep using it until the guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::{BloomFilter, SharedBloomFilter};
+    ///
+    /// let shared = SharedBloomFilter::new(BloomFilter::from_fpp(0.0001, 64));
+    ///
+    /// let mut filter = BloomFilter::from_fpp(0.0001, 64);
+    /// filter.insert("a");
+    ///
+    /// let previous = shared.swap(filter);
+    /// assert!(previous.is_empty());
+    /// assert!(shared.contains("a"));
+    /// ```
+    pub fn swap(&self, filter: BloomFilter<S>) -> Arc<BloomFilter<S>> {
+        self.filter.swap(Arc::new(filter))
+    }
+}
+
+impl<S> SharedBloomFilter<S>
+where
+    S: BuildHasher + Default,
+{
+    /// Reads a filter in the binary format (see [`crate::io`]) from a file at `path` and replaces
+    /// the current filter with it.
+    ///
+    /// The hash builders are created using [`Default`] (see [`BloomFilter::read_from_path`]). The
+    /// file is read completely before the swap, so readers never see a partially read filter. If
+    /// reading fails, the current filter is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::{BloomFilter, SeededHashBuilder, SharedBloomFilter};
+    ///
+    /// let filter: BloomFilter<SeededHashBuilder> =
+    ///     BloomFilter::from_fpp_with_hashers(0.0001, 64, Default::default(), Default::default());
+    ///
+    /// let shared = SharedBloomFilter::new(filter);
+    /// shared.reload_from_path("filter.bin")?;
+    /// # Ok::<(), bbloom::io::ReadError>(())
+    /// ```
+    pub fn reload_from_path<P>(&self, path: P) -> Result<(), ReadError>
+    where
+        P: AsRef<Path>,
+    {
+        let filter = BloomFilter::read_from_path(path)?;
+        self.swap(filter);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        ops::Range,
+        process,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    };
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn build_filter(keys: Range<u32>) -> BloomFilter<SeededHashBuilder> {
+        let mut filter =
+            BloomFilter::from_fpp_with_hashers(0.0001, 256, Default::default(), Default::default());
+
+        for key in keys {
+            filter.insert(&key);
+        }
+
+        filter
+    }
+
+    #[test]
+    fn test_swap_with_concurrent_readers() {
+        const A: Range<u32> = 0..100;
+        const B: Range<u32> = 1000..1100;
+
+        let shared = Arc::new(SharedBloomFilter::new(build_filter(A)));
+        let is_done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                let is_done = is_done.clone();
+
+                thread::spawn(move || {
+                    while !is_done.load(Ordering::Relaxed) {
+                        let filter = shared.load();
+
+                        let has_a = A.clone().all(|i| filter.contains(&i));
+                        let has_b = B.clone().all(|i| filter.contains(&i));
+                        assert!(has_a != has_b, "observed a mix of filters");
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..1000 {
+            let keys = if i % 2 == 0 { B } else { A };
+            shared.swap(build_filter(keys));
+        }
+
+        is_done.store(true, Ordering::Relaxed);
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reload_from_path() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("bbloom-reload-from-path-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let src = dir.join("filter.bin");
+
+        let shared = SharedBloomFilter::new(build_filter(0..100));
+
+        build_filter(1000..1100).write_to_path(&src)?;
+        shared.reload_from_path(&src)?;
+        assert!(shared.contains(&1000));
+
+        fs::write(&src, b"ndls")?;
+        assert!(shared.reload_from_path(&src).is_err());
+        assert!(shared.contains(&1000));
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,535 @@
+//! A Bloom filter in named shared memory.
+
+use std::{
+    convert::TryFrom,
+    ffi::CString,
+    fs::File,
+    hash::Hash,
+    io,
+    os::unix::io::FromRawFd,
+    slice,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use memmap2::MmapRaw;
+
+use crate::{
+    bloom_filter::{optimal_number_of_hash_functions, optimal_required_bits},
+    double_hasher::DoubleHasher,
+    Filter, SeededHashBuilder,
+};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"BBLMSHM\0");
+
+// The layout version of the region, which is incremented whenever the header or bit array layout
+// changes.
+const LAYOUT_VERSION: u64 = 1;
+
+const MAGIC_INDEX: usize = 0;
+const VERSION_INDEX: usize = 1;
+const M_INDEX: usize = 2;
+const K_INDEX: usize = 3;
+const SEED_1_INDEX: usize = 4;
+const SEED_2_INDEX: usize = 5;
+const N_INDEX: usize = 6;
+
+// The number of header words. The bit array starts at the next cache line.
+const HEADER_WORDS: usize = 8;
+
+const WORD_BITS: usize = 64;
+
+/// A Bloom filter in a named shared memory region that any number of processes can insert into
+/// and query concurrently.
+///
+/// The region holds a header with the parameters of the filter (`m`, `k`, the hasher seeds, and a
+/// layout version) followed by the bit array as 64-bit words. Inserts set bits using atomic
+/// `fetch_or`, so they never block and never lose bits set by another process. An element
+/// inserted by one process is visible to every process that queries after the insert returns.
+///
+/// The hashes are computed using a [`SeededHashBuilder`] for each of the two seeds, and bit `i`
+/// is bit `i % 64` of word `i / 64`, as in [`crate::StaticBloomFilter`].
+///
+/// # Lifecycle
+///
+/// A region is created by [`Self::create`] and attached to by [`Self::open`]. Dropping a filter
+/// detaches it from the region but never removes the region, which outlives every process that
+/// uses it, until it is removed with [`Self::unlink`] or the system restarts. The process that
+/// creates the region owns it and is responsible for removing it, typically after all workers
+/// exit. Processes that are still attached when a region is removed keep using it, but new
+/// processes can no longer open it, and a new region can be created with the same name.
+///
+/// This is only available on Unix with the `shared-memory` feature. The region is a POSIX shared
+/// memory object (`shm_open`).
+///
+/// # Examples
+///
+/// ```no_run
+/// use bbloom::SharedMemBloomFilter;
+///
+/// let filter = SharedMemBloomFilter::create("bbloom-workers", 0.001, 100_000)?;
+/// filter.insert("a");
+///
+/// // in another process
+/// let filter = SharedMemBloomFilter::open("bbloom-workers")?;
+/// assert!(filter.contains("a"));
+///
+/// // once all processes are done
+/// SharedMemBloomFilter::unlink("bbloom-workers")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SharedMemBloomFilter {
+    map: MmapRaw,
+    m: usize,
+    k: usize,
+    builder_1: SeededHashBuilder,
+    builder_2: SeededHashBuilder,
+}
+
+impl SharedMemBloomFilter {
+    /// Creates a shared memory region named `name` with a filter for `n` elements and a target
+    /// false positive probability `p`.
+    ///
+    /// The hasher seeds are 0 and 1. See [`Self::create_with_seeds`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a region with the name already exists, the name is invalid (e.g., it
+    /// contains a `/`), or the region cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    /// let _filter = SharedMemBloomFilter::create("bbloom-workers", 0.001, 100_000)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create(name: &str, p: f64, n: usize) -> io::Result<Self> {
+        Self::create_with_seeds(name, p, n, (0, 1))
+    }
+
+    /// Creates a shared memory region named `name` with a filter for `n` elements and a target
+    /// false positive probability `p`, using hasher seeds `seeds`.
+    ///
+    /// The bit array is initially empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a region with the name already exists, the name is invalid (e.g., it
+    /// contains a `/`), or the region cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    /// let filter = SharedMemBloomFilter::create_with_seeds("bbloom-workers", 0.001, 100_000, (5, 8))?;
+    /// assert_eq!(filter.seeds(), (5, 8));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create_with_seeds(name: &str, p: f64, n: usize, seeds: (u64, u64)) -> io::Result<Self> {
+        let m = optimal_required_bits(p, n).max(1);
+        let k = optimal_number_of_hash_functions(m, n).max(1);
+
+        let file = shm_open(name, libc::O_RDWR | libc::O_CREAT | libc::O_EXCL)?;
+        let len = (HEADER_WORDS + m.div_ceil(WORD_BITS)) * 8;
+
+        let result = file
+            .set_len(len as u64)
+            .and_then(|_| MmapRaw::map_raw(&file));
+
+        let map = match result {
+            Ok(map) => map,
+            Err(e) => {
+                let _ = Self::unlink(name);
+                return Err(e);
+            }
+        };
+
+        let filter = Self {
+            map,
+            m,
+            k,
+            builder_1: SeededHashBuilder::new(seeds.0),
+            builder_2: SeededHashBuilder::new(seeds.1),
+        };
+
+        let header = filter.header();
+        header[VERSION_INDEX].store(LAYOUT_VERSION, Ordering::Relaxed);
+        header[M_INDEX].store(m as u64, Ordering::Relaxed);
+        header[K_INDEX].store(k as u64, Ordering::Relaxed);
+        header[SEED_1_INDEX].store(seeds.0, Ordering::Relaxed);
+        header[SEED_2_INDEX].store(seeds.1, Ordering::Relaxed);
+
+        // The magic number is written last, so the region is not opened before it is initialized.
+        header[MAGIC_INDEX].store(MAGIC, Ordering::Release);
+
+        Ok(filter)
+    }
+
+    /// Opens an existing shared memory region named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no region with the name exists, or the region is not a filter, is not
+    /// fully initialized, has a different layout version, or is too short
+    /// for its parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    /// let _filter = SharedMemBloomFilter::open("bbloom-workers")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn open(name: &str) -> io::Result<Self> {
+        let file = shm_open(name, libc::O_RDWR)?;
+
+        if file.metadata()?.len() < (HEADER_WORDS * 8) as u64 {
+            return Err(invalid_data("shared memory region is too short"));
+        }
+
+        let map = MmapRaw::map_raw(&file)?;
+
+        let mut filter = Self {
+            map,
+            m: 0,
+            k: 0,
+            builder_1: SeededHashBuilder::default(),
+            builder_2: SeededHashBuilder::default(),
+        };
+
+        let header = filter.header();
+
+        if header[MAGIC_INDEX].load(Ordering::Acquire) != MAGIC {
+            return Err(invalid_data(
+                "shared memory region is not an initialized filter",
+            ));
+        }
+
+        let version = header[VERSION_INDEX].load(Ordering::Relaxed);
+
+        if version != LAYOUT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported layout version: expected {LAYOUT_VERSION}, got {version}"
+            )));
+        }
+
+        let m = usize::try_from(header[M_INDEX].load(Ordering::Relaxed))
+            .map_err(|_| invalid_data("invalid m"))?;
+        let k = usize::try_from(header[K_INDEX].load(Ordering::Relaxed))
+            .map_err(|_| invalid_data("invalid k"))?;
+        let seeds = (
+            header[SEED_1_INDEX].load(Ordering::Relaxed),
+            header[SEED_2_INDEX].load(Ordering::Relaxed),
+        );
+
+        if m == 0 || k == 0 {
+            return Err(invalid_data("invalid parameters"));
+        }
+
+        let word_count = m.div_ceil(WORD_BITS);
+
+        if filter.map.len() / 8 < HEADER_WORDS + word_count {
+            return Err(invalid_data("shared memory region is too short"));
+        }
+
+        filter.m = m;
+        filter.k = k;
+        filter.builder_1 = SeededHashBuilder::new(seeds.0);
+        filter.builder_2 = SeededHashBuilder::new(seeds.1);
+
+        Ok(filter)
+    }
+
+    /// Removes the shared memory region named `name`.
+    ///
+    /// Processes that are attached to the region can keep using it. It is freed when the last one
+    /// detaches.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no region with the name exists or it cannot be removed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    /// SharedMemBloomFilter::unlink("bbloom-workers")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn unlink(name: &str) -> io::Result<()> {
+        let name = shm_name(name)?;
+
+        // SAFETY: `name` is a valid C string.
+        if unsafe { libc::shm_unlink(name.as_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the size of the bit array `m`.
+    pub fn capacity(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the number of hash functions `k`.
+    pub fn hash_count(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the hasher seeds.
+    pub fn seeds(&self) -> (u64, u64) {
+        (self.builder_1.seed(), self.builder_2.seed())
+    }
+
+    /// Returns the number of elements inserted by all processes.
+    pub fn len(&self) -> usize {
+        self.header()[N_INDEX].load(Ordering::Relaxed) as usize
+    }
+
+    /// Returns `true` if no elements were inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    ///
+    /// let filter = SharedMemBloomFilter::open("bbloom-workers")?;
+    /// filter.insert("a");
+    /// assert!(filter.contains("a"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        let words = self.words();
+
+        self.indices(key).all(|i| {
+            let mask = 1 << (i % WORD_BITS);
+            words[i / WORD_BITS].load(Ordering::Acquire) & mask != 0
+        })
+    }
+
+    /// Inserts an element into the filter.
+    ///
+    /// This takes `&self`: inserts from any number of threads and processes may run concurrently.
+    /// This returns whether the element was not previously in the filter, i.e., whether this
+    /// insert set any bits. When two processes concurrently insert the same element, both may
+    /// return `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bbloom::SharedMemBloomFilter;
+    ///
+    /// let filter = SharedMemBloomFilter::open("bbloom-workers")?;
+    /// filter.insert("a");
+    /// assert!(!filter.insert("a"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn insert<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        let words = self.words();
+        let mut is_new = false;
+
+        for i in self.indices(key) {
+            let mask = 1 << (i % WORD_BITS);
+            let word = &words[i / WORD_BITS];
+
+            // Skip the write if the bit is already set, which avoids contending for the cache
+            // line.
+            if word.load(Ordering::Relaxed) & mask == 0
+                && word.fetch_or(mask, Ordering::AcqRel) & mask == 0
+            {
+                is_new = true;
+            }
+        }
+
+        if is_new {
+            self.header()[N_INDEX].fetch_add(1, Ordering::Relaxed);
+        }
+
+        is_new
+    }
+
+    fn indices<H: Hash + ?Sized>(&self, key: &H) -> impl Iterator<Item = usize> {
+        let hasher = DoubleHasher::new(key, &self.builder_1, &self.builder_2);
+        let m = self.m as u64;
+        hasher.take(self.k).map(move |hash| (hash % m) as usize)
+    }
+
+    fn header(&self) -> &[AtomicU64] {
+        &self.atomic_words()[..HEADER_WORDS]
+    }
+
+    fn words(&self) -> &[AtomicU64] {
+        &self.atomic_words()[HEADER_WORDS..HEADER_WORDS + self.m.div_ceil(WORD_BITS)]
+    }
+
+    fn atomic_words(&self) -> &[AtomicU64] {
+        let ptr = self.map.as_mut_ptr().cast::<AtomicU64>();
+
+        // SAFETY: The map is page-aligned, so it is aligned for `AtomicU64`, and it is valid for
+        // its length for as long as it is mapped. The pointer is derived from the raw map rather
+        // than a shared reference to its bytes, and the region is only ever accessed through
+        // atomics, by this and other processes, whose interior mutability permits the writes.
+        unsafe { slice::from_raw_parts(ptr, self.map.len() / 8) }
+    }
+}
+
+impl Filter for SharedMemBloomFilter {
+    fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        SharedMemBloomFilter::contains(self, key)
+    }
+
+    fn insert<H: Hash + ?Sized>(&mut self, key: &H) -> bool {
+        SharedMemBloomFilter::insert(self, key)
+    }
+
+    fn len(&self) -> usize {
+        SharedMemBloomFilter::len(self)
+    }
+}
+
+fn shm_name(name: &str) -> io::Result<CString> {
+    if name.is_empty() || name.contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid shared memory region name",
+        ));
+    }
+
+    CString::new(format!("/{name}")).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn shm_open(name: &str, flags: libc::c_int) -> io::Result<File> {
+    let name = shm_name(name)?;
+
+    // SAFETY: `name` is a valid C string.
+    let fd = unsafe { libc::shm_open(name.as_ptr(), flags, 0o600 as libc::c_uint) };
+
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: `fd` is an open file descriptor that is owned by nothing else.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{process, sync::Arc, thread};
+
+    use super::*;
+    use crate::BloomFilter;
+
+    fn name(suffix: &str) -> String {
+        format!("bbloom-{}-{}", suffix, process::id())
+    }
+
+    #[test]
+    fn test_create_and_open() -> io::Result<()> {
+        let name = name("create-and-open");
+
+        let filter = SharedMemBloomFilter::create_with_seeds(&name, 0.001, 1024, (5, 8))?;
+        assert!(SharedMemBloomFilter::create(&name, 0.001, 1024).is_err());
+
+        for i in 0..1024 {
+            filter.insert(&i);
+        }
+
+        let other = SharedMemBloomFilter::open(&name)?;
+        assert_eq!(other.capacity(), filter.capacity());
+        assert_eq!(other.hash_count(), filter.hash_count());
+        assert_eq!(other.seeds(), (5, 8));
+        assert_eq!(other.len(), filter.len());
+        assert!((0..1024).all(|i| other.contains(&i)));
+
+        // The bits are the same as a filter with the same parameters.
+        let mut expected = BloomFilter::with_hashers(
+            filter.capacity(),
+            filter.hash_count(),
+            SeededHashBuilder::new(5),
+            SeededHashBuilder::new(8),
+        );
+
+        for i in 0..1024 {
+            expected.insert(&i);
+        }
+
+        let actual: Vec<_> = other
+            .words()
+            .iter()
+            .map(|word| word.load(Ordering::Relaxed))
+            .collect();
+
+        assert_eq!(actual, expected.to_words());
+        assert_eq!(other.len(), expected.len());
+
+        SharedMemBloomFilter::unlink(&name)?;
+        assert!(SharedMemBloomFilter::open(&name).is_err());
+
+        // A removed region stays usable by processes that are still attached.
+        assert!(other.contains(&0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_with_invalid_region() -> io::Result<()> {
+        let name = name("invalid-region");
+
+        let filter = SharedMemBloomFilter::create(&name, 0.001, 64)?;
+        filter.header()[VERSION_INDEX].store(LAYOUT_VERSION + 1, Ordering::Relaxed);
+
+        let e = SharedMemBloomFilter::open(&name).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        filter.header()[MAGIC_INDEX].store(0, Ordering::Relaxed);
+
+        let e = SharedMemBloomFilter::open(&name).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        SharedMemBloomFilter::unlink(&name)?;
+
+        let e = SharedMemBloomFilter::open("a/b").err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_from_threads() -> io::Result<()> {
+        let name = name("threads");
+        let filter = Arc::new(SharedMemBloomFilter::create(&name, 0.001, 4000)?);
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let filter = filter.clone();
+
+                thread::spawn(move || {
+                    for i in (t * 1000)..((t + 1) * 1000) {
+                        filter.insert(&i);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!((0..4000).all(|i| filter.contains(&i)));
+
+        SharedMemBloomFilter::unlink(&name)
+    }
+}
@@ -0,0 +1,550 @@
+//! Background snapshots of shared filters.
+//!
+//! A [`Snapshotter`] periodically writes a filter shared behind an [`Arc<RwLock<_>>`] to a file
+//! in the binary format (see [`crate::io`]), e.g., to persist the filter of a long-running
+//! service. Each snapshot atomically replaces the file (see [`BloomFilter::write_to_path`]), so
+//! it can be read at any time using `read_from_path`.
+//!
+//! This module requires the `snapshot` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::{
+//!     sync::{Arc, RwLock},
+//!     time::Duration,
+//! };
+//!
+//! use bbloom::{
+//!     snapshot::{Policy, Snapshotter},
+//!     BloomFilter, SeededHashBuilder,
+//! };
+//!
+//! let filter: BloomFilter<SeededHashBuilder> =
+//!     BloomFilter::from_fpp_with_hashers(0.0001, 1024, Default::default(), Default::default());
+//! let filter = Arc::new(RwLock::new(filter));
+//!
+//! let snapshotter = Snapshotter::new(
+//!     filter.clone(),
+//!     "filter.bin",
+//!     Policy::Interval(Duration::from_secs(60)),
+//!     |e| eprintln!("snapshot failed: {}", e),
+//! )?;
+//!
+//! filter.write().unwrap().insert("a");
+//!
+//! snapshotter.shutdown()?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    hash::BuildHasher,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bit_vec::BitVec;
+
+use crate::{io::path, probe_strategy::ProbeStrategy, BloomFilter, ScalableBloomFilter};
+
+// How often the number of inserted elements is checked for `Policy::EveryNInserts`.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A filter that can be written as a snapshot.
+pub trait Snapshot {
+    /// Returns the number of elements inserted into the filter.
+    fn len(&self) -> usize;
+
+    /// Returns whether the filter is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the filter in the binary format (see [`crate::io`]).
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write;
+}
+
+impl<S, P> Snapshot for BloomFilter<S, BitVec, P>
+where
+    S: BuildHasher,
+    P: ProbeStrategy,
+{
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        self.write_to(writer)
+    }
+}
+
+impl<S> Snapshot for ScalableBloomFilter<S>
+where
+    S: BuildHasher + Clone + Default,
+{
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        self.write_to(writer)
+    }
+}
+
+/// When a snapshot is taken.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Policy {
+    /// A snapshot is taken when the interval has elapsed since the last snapshot attempt.
+    Interval(Duration),
+    /// A snapshot is taken when at least `n` (> 0) elements were inserted since the last
+    /// snapshot.
+    ///
+    /// The number of inserted elements is checked every 100 ms.
+    EveryNInserts(u64),
+    /// A snapshot is taken when either the interval has elapsed or at least `n` (> 0) elements
+    /// were inserted since the last snapshot.
+    Both(Duration, u64),
+}
+
+impl Policy {
+    fn interval(&self) -> Option<Duration> {
+        match *self {
+            Self::Interval(interval) | Self::Both(interval, _) => Some(interval),
+            Self::EveryNInserts(_) => None,
+        }
+    }
+
+    fn insert_count(&self) -> Option<u64> {
+        match *self {
+            Self::EveryNInserts(n) | Self::Both(_, n) => Some(n.max(1)),
+            Self::Interval(_) => None,
+        }
+    }
+}
+
+type ErrorHandler = Box<dyn FnMut(io::Error) + Send>;
+
+struct Progress {
+    last_snapshot_at: Option<SystemTime>,
+    last_snapshot_instant: Instant,
+    last_len: usize,
+}
+
+struct Shared<F> {
+    filter: Arc<RwLock<F>>,
+    path: PathBuf,
+    policy: Policy,
+    progress: Mutex<Progress>,
+    is_shut_down: Mutex<bool>,
+    shutdown_signal: Condvar,
+    on_error: Mutex<ErrorHandler>,
+}
+
+impl<F> Shared<F>
+where
+    F: Snapshot,
+{
+    fn snapshot(&self) -> io::Result<()> {
+        // Holding the progress lock serializes snapshots.
+        let mut progress = lock(&self.progress);
+
+        // A failed snapshot is retried after the next interval rather than immediately.
+        progress.last_snapshot_instant = Instant::now();
+
+        let (buf, len) = {
+            let filter = self.filter.read().unwrap_or_else(PoisonError::into_inner);
+            let mut buf = Vec::new();
+            filter.write_to(&mut buf)?;
+            (buf, filter.len())
+        };
+
+        path::write_atomically(&self.path, |writer| writer.write_all(&buf))?;
+
+        progress.last_snapshot_at = Some(SystemTime::now());
+        progress.last_len = len;
+
+        Ok(())
+    }
+
+    fn is_due(&self) -> bool {
+        let progress = lock(&self.progress);
+
+        let is_interval_elapsed = self
+            .policy
+            .interval()
+            .map(|interval| progress.last_snapshot_instant.elapsed() >= interval)
+            .unwrap_or(false);
+
+        let is_insert_count_reached = self
+            .policy
+            .insert_count()
+            .map(|n| {
+                let len = self
+                    .filter
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .len();
+                len.saturating_sub(progress.last_len) as u64 >= n
+            })
+            .unwrap_or(false);
+
+        is_interval_elapsed || is_insert_count_reached
+    }
+
+    fn next_timeout(&self) -> Duration {
+        let remaining = self.policy.interval().map(|interval| {
+            let elapsed = lock(&self.progress).last_snapshot_instant.elapsed();
+            interval.saturating_sub(elapsed)
+        });
+
+        match (remaining, self.policy.insert_count()) {
+            (Some(remaining), Some(_)) => remaining.min(POLL_INTERVAL),
+            (Some(remaining), None) => remaining,
+            (None, _) => POLL_INTERVAL,
+        }
+    }
+
+    fn run(&self) {
+        loop {
+            let timeout = self.next_timeout();
+
+            let is_shut_down = lock(&self.is_shut_down);
+            let (is_shut_down, _) = self
+                .shutdown_signal
+                .wait_timeout_while(is_shut_down, timeout, |is_shut_down| !*is_shut_down)
+                .unwrap_or_else(PoisonError::into_inner);
+
+            if *is_shut_down {
+                return;
+            }
+
+            drop(is_shut_down);
+
+            if self.is_due() {
+                if let Err(e) = self.snapshot() {
+                    (lock(&self.on_error))(e);
+                }
+            }
+        }
+    }
+}
+
+/// A background thread that writes snapshots of a shared filter to a file.
+///
+/// Snapshots are taken according to a [`Policy`]. Failed background snapshots are reported to
+/// an error handler.
+///
+/// Dropping a snapshotter stops the background thread without taking a final snapshot. Use
+/// [`Self::shutdown`] to take one.
+pub struct Snapshotter<F> {
+    shared: Arc<Shared<F>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<F> Snapshotter<F>
+where
+    F: Snapshot + Send + Sync + 'static,
+{
+    /// Starts a background thread that writes snapshots of `filter` to a file at `path`
+    /// according to `policy`.
+    ///
+    /// `on_error` is called with the error of every failed background snapshot.
+    ///
+    /// This fails if the thread cannot be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::{Arc, RwLock};
+    ///
+    /// use bbloom::{
+    ///     snapshot::{Policy, Snapshotter},
+    ///     ScalableBloomFilter,
+    /// };
+    ///
+    /// let filter = Arc::new(RwLock::new(ScalableBloomFilter::new(0.0001, 1024)));
+    ///
+    /// let _snapshotter = Snapshotter::new(filter, "filter.bin", Policy::EveryNInserts(1000), |e| {
+    ///     eprintln!("snapshot failed: {}", e)
+    /// })?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn new<P, E>(
+        filter: Arc<RwLock<F>>,
+        path: P,
+        policy: Policy,
+        on_error: E,
+    ) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+        E: FnMut(io::Error) + Send + 'static,
+    {
+        let last_len = filter.read().unwrap_or_else(PoisonError::into_inner).len();
+
+        let shared = Arc::new(Shared {
+            filter,
+            path: path.as_ref().to_path_buf(),
+            policy,
+            progress: Mutex::new(Progress {
+                last_snapshot_at: None,
+                last_snapshot_instant: Instant::now(),
+                last_len,
+            }),
+            is_shut_down: Mutex::new(false),
+            shutdown_signal: Condvar::new(),
+            on_error: Mutex::new(Box::new(on_error)),
+        });
+
+        let handle = {
+            let shared = shared.clone();
+
+            thread::Builder::new()
+                .name(String::from("bbloom-snapshotter"))
+                .spawn(move || shared.run())?
+        };
+
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// Takes a snapshot immediately on the current thread.
+    ///
+    /// The error of a failed snapshot is returned rather than reported to the error handler.
+    pub fn flush_now(&self) -> io::Result<()> {
+        self.shared.snapshot()
+    }
+
+    /// Returns when the last successful snapshot was taken.
+    ///
+    /// This is `None` if no snapshot has been taken.
+    pub fn last_snapshot_at(&self) -> Option<SystemTime> {
+        lock(&self.shared.progress).last_snapshot_at
+    }
+
+    /// Stops the background thread and takes a final snapshot.
+    ///
+    /// The error of a failed final snapshot is returned.
+    pub fn shutdown(mut self) -> io::Result<()> {
+        self.stop();
+        self.shared.snapshot()
+    }
+}
+
+impl<F> Snapshotter<F> {
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            *lock(&self.shared.is_shut_down) = true;
+            self.shared.shutdown_signal.notify_all();
+
+            // The background thread only panics if the error handler does, which has already
+            // been reported by the panic hook.
+            let _ = handle.join();
+        }
+    }
+}
+
+impl<F> Drop for Snapshotter<F> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, sync::mpsc};
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    fn temp_dir(name: &str) -> io::Result<PathBuf> {
+        let dir = env::temp_dir().join(format!("bbloom-snapshot-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn build_filter() -> Arc<RwLock<BloomFilter<SeededHashBuilder>>> {
+        let filter =
+            BloomFilter::from_fpp_with_hashers(0.0001, 64, Default::default(), Default::default());
+        Arc::new(RwLock::new(filter))
+    }
+
+    fn wait_for<P>(mut predicate: P) -> bool
+    where
+        P: FnMut() -> bool,
+    {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while Instant::now() < deadline {
+            if predicate() {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        false
+    }
+
+    fn read(path: &Path) -> BloomFilter<SeededHashBuilder> {
+        BloomFilter::read_from_path(path).expect("snapshot should be valid")
+    }
+
+    #[test]
+    fn test_interval() -> io::Result<()> {
+        let dir = temp_dir("interval")?;
+        let path = dir.join("filter.bin");
+
+        let filter = build_filter();
+        filter.write().unwrap().insert("a");
+
+        let snapshotter = Snapshotter::new(
+            filter.clone(),
+            &path,
+            Policy::Interval(Duration::from_millis(10)),
+            |e| panic!("{}", e),
+        )?;
+
+        assert!(wait_for(|| path.exists()));
+        assert!(read(&path).contains("a"));
+
+        filter.write().unwrap().insert("b");
+        assert!(wait_for(|| read(&path).contains("b")));
+        assert!(snapshotter.last_snapshot_at().is_some());
+
+        snapshotter.shutdown()?;
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_n_inserts() -> io::Result<()> {
+        let dir = temp_dir("every-n-inserts")?;
+        let path = dir.join("filter.bin");
+
+        let filter = build_filter();
+
+        let snapshotter = Snapshotter::new(filter.clone(), &path, Policy::EveryNInserts(3), |e| {
+            panic!("{}", e)
+        })?;
+
+        filter.write().unwrap().insert("a");
+        filter.write().unwrap().insert("b");
+        thread::sleep(POLL_INTERVAL * 2);
+        assert!(!path.exists());
+        assert!(snapshotter.last_snapshot_at().is_none());
+
+        filter.write().unwrap().insert("c");
+        assert!(wait_for(|| path.exists()));
+        assert_eq!(read(&path).len(), 3);
+
+        snapshotter.shutdown()?;
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_now() -> io::Result<()> {
+        let dir = temp_dir("flush-now")?;
+        let path = dir.join("filter.bin");
+
+        let filter = build_filter();
+        filter.write().unwrap().insert("a");
+
+        let snapshotter = Snapshotter::new(
+            filter,
+            &path,
+            Policy::Interval(Duration::from_secs(3600)),
+            |e| panic!("{}", e),
+        )?;
+
+        assert!(snapshotter.last_snapshot_at().is_none());
+
+        snapshotter.flush_now()?;
+        assert!(snapshotter.last_snapshot_at().is_some());
+        assert!(read(&path).contains("a"));
+
+        drop(snapshotter);
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown() -> io::Result<()> {
+        let dir = temp_dir("shutdown")?;
+        let path = dir.join("filter.bin");
+
+        let filter = build_filter();
+
+        let snapshotter = Snapshotter::new(
+            filter.clone(),
+            &path,
+            Policy::Both(Duration::from_secs(3600), 1000),
+            |e| panic!("{}", e),
+        )?;
+
+        for i in 0..64 {
+            filter.write().unwrap().insert(&i);
+        }
+
+        snapshotter.shutdown()?;
+
+        let actual = read(&path);
+        assert_eq!(actual.len(), filter.read().unwrap().len());
+        assert!((0..64).all(|i| actual.contains(&i)));
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() -> io::Result<()> {
+        let dir = temp_dir("errors")?;
+        let path = dir.join("missing").join("filter.bin");
+
+        let (tx, rx) = mpsc::channel();
+
+        let snapshotter = Snapshotter::new(
+            build_filter(),
+            &path,
+            Policy::Interval(Duration::from_millis(10)),
+            move |e| {
+                let _ = tx.send(e.kind());
+            },
+        )?;
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).ok(),
+            Some(io::ErrorKind::NotFound)
+        );
+
+        assert!(snapshotter.flush_now().is_err());
+        assert!(snapshotter.last_snapshot_at().is_none());
+        assert!(snapshotter.shutdown().is_err());
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! sqlx support.
+//!
+//! [`BloomFilter`] and [`ScalableBloomFilter`] are stored as binary values (e.g., `BYTEA` in
+//! PostgreSQL and `BLOB` in SQLite) in the binary format (see [`crate::io`]). Decoding verifies
+//! the checksum, and an invalid filter is returned as a decode error.
+//!
+//! Decoded filters create their hash builders using [`Default`], so they should use
+//! deterministic hash builders, e.g., [`crate::SeededHashBuilder`].
+
+use std::hash::BuildHasher;
+
+use sqlx::{encode::IsNull, error::BoxDynError, Database, Decode, Encode, Type};
+
+use crate::{BloomFilter, ScalableBloomFilter};
+
+impl<DB, S> Type<DB> for BloomFilter<S>
+where
+    DB: Database,
+    Vec<u8>: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <Vec<u8> as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <Vec<u8> as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, DB, S> Encode<'q, DB> for BloomFilter<S>
+where
+    DB: Database,
+    Vec<u8>: Encode<'q, DB>,
+    S: BuildHasher,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer<'q>) -> Result<IsNull, BoxDynError> {
+        <Vec<u8> as Encode<'q, DB>>::encode(self.to_bytes(), buf)
+    }
+}
+
+impl<'r, DB, S> Decode<'r, DB> for BloomFilter<S>
+where
+    DB: Database,
+    &'r [u8]: Decode<'r, DB>,
+    S: BuildHasher + Default,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let buf = <&'r [u8] as Decode<'r, DB>>::decode(value)?;
+        Self::from_bytes(buf).map_err(|e| e.into())
+    }
+}
+
+impl<DB, S> Type<DB> for ScalableBloomFilter<S>
+where
+    DB: Database,
+    Vec<u8>: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <Vec<u8> as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <Vec<u8> as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, DB, S> Encode<'q, DB> for ScalableBloomFilter<S>
+where
+    DB: Database,
+    Vec<u8>: Encode<'q, DB>,
+    S: BuildHasher + Clone + Default,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer<'q>) -> Result<IsNull, BoxDynError> {
+        <Vec<u8> as Encode<'q, DB>>::encode(self.to_bytes(), buf)
+    }
+}
+
+impl<'r, DB, S> Decode<'r, DB> for ScalableBloomFilter<S>
+where
+    DB: Database,
+    &'r [u8]: Decode<'r, DB>,
+    S: BuildHasher + Clone + Default,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let buf = <&'r [u8] as Decode<'r, DB>>::decode(value)?;
+        Self::from_bytes(buf).map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Connection, Row, SqliteConnection};
+
+    use super::*;
+    use crate::SeededHashBuilder;
+
+    #[tokio::test]
+    async fn test_sqlite_round_trip() -> Result<(), sqlx::Error> {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await?;
+
+        sqlx::query("CREATE TABLE filters (id INTEGER PRIMARY KEY, plain BLOB, scalable BLOB)")
+            .execute(&mut conn)
+            .await?;
+
+        let mut filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::with_hashers(1227, 7, Default::default(), Default::default());
+        filter.insert("a");
+
+        let mut scalable_filter: ScalableBloomFilter<SeededHashBuilder> =
+            ScalableBloomFilter::with_hashers(0.001, 4, Default::default(), Default::default());
+
+        for i in 0..16 {
+            scalable_filter.insert(&i);
+        }
+
+        sqlx::query("INSERT INTO filters (id, plain, scalable) VALUES (1, ?, ?)")
+            .bind(&filter)
+            .bind(&scalable_filter)
+            .execute(&mut conn)
+            .await?;
+
+        let row = sqlx::query("SELECT plain, scalable FROM filters WHERE id = 1")
+            .fetch_one(&mut conn)
+            .await?;
+
+        let actual: BloomFilter<SeededHashBuilder> = row.try_get("plain")?;
+        assert!(actual.contains("a"));
+        assert!(!actual.contains("b"));
+
+        let actual: ScalableBloomFilter<SeededHashBuilder> = row.try_get("scalable")?;
+
+        for i in 0..16 {
+            assert!(actual.contains(&i));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_decode_corrupt_filter() -> Result<(), sqlx::Error> {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await?;
+
+        let filter: BloomFilter<SeededHashBuilder> =
+            BloomFilter::with_hashers(64, 3, Default::default(), Default::default());
+        let mut buf = filter.to_bytes();
+        buf[38] ^= 0xff;
+
+        let row = sqlx::query("SELECT ? AS plain")
+            .bind(buf)
+            .fetch_one(&mut conn)
+            .await?;
+
+        let result: Result<BloomFilter<SeededHashBuilder>, _> = row.try_get("plain");
+        assert!(matches!(result, Err(sqlx::Error::ColumnDecode { .. })));
+
+        Ok(())
+    }
+}
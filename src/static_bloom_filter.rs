@@ -0,0 +1,138 @@
+use std::hash::Hash;
+
+use crate::{
+    bit_store::StaticBits, bloom_filter::probe_bits, double_hasher::DoubleHasher,
+    probe_strategy::DoubleHashing, SeededHashBuilder,
+};
+
+/// A read-only Bloom filter over a statically allocated bit array.
+///
+/// This is typically constructed from a snippet generated by [`crate::codegen::generate`] in a
+/// build script, in which case the bit array is embedded in the binary and querying it requires
+/// no startup work or copying.
+///
+/// Bit `i` of the filter is bit `i % 64` of word `i / 64` (see [`StaticBits`]). The hashes are
+/// computed using a [`SeededHashBuilder`] for each of the two seeds, and the bits are probed as in
+/// a [`crate::BloomFilter`] with the [`DoubleHashing`] strategy.
+///
+/// # Examples
+///
+/// ```
+/// use bbloom::StaticBloomFilter;
+///
+/// static WORDS: [u64; 1] = [0; 1];
+/// static FILTER: StaticBloomFilter = StaticBloomFilter::from_static(&WORDS, 64, 3, (0, 1));
+///
+/// assert!(!FILTER.contains("a"));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct StaticBloomFilter {
+    bits: StaticBits,
+    k: usize,
+    seeds: (u64, u64),
+}
+
+impl StaticBloomFilter {
+    /// Creates a static bloom filter over `words` with a bit array size `m`, number of hash
+    /// functions `k`, and hasher seeds `seeds`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is 0, `k` is 0, or `words` is too short to hold `m` bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::StaticBloomFilter;
+    /// static WORDS: [u64; 20] = [0; 20];
+    /// let _filter = StaticBloomFilter::from_static(&WORDS, 1227, 7, (0, 1));
+    /// ```
+    pub const fn from_static(words: &'static [u64], m: usize, k: usize, seeds: (u64, u64)) -> Self {
+        assert!(m > 0, "m must be > 0");
+        assert!(k > 0, "k must be > 0");
+        assert!(words.len() >= m.div_ceil(64), "words is too short for m");
+
+        Self {
+            bits: StaticBits::new(words, m),
+            k,
+            seeds,
+        }
+    }
+
+    /// Returns the size of the bit array `m`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::StaticBloomFilter;
+    /// static WORDS: [u64; 20] = [0; 20];
+    /// let filter = StaticBloomFilter::from_static(&WORDS, 1227, 7, (0, 1));
+    /// assert_eq!(filter.capacity(), 1227);
+    /// ```
+    pub const fn capacity(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns the hasher seeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::StaticBloomFilter;
+    /// static WORDS: [u64; 20] = [0; 20];
+    /// let filter = StaticBloomFilter::from_static(&WORDS, 1227, 7, (0, 1));
+    /// assert_eq!(filter.seeds(), (0, 1));
+    /// ```
+    pub const fn seeds(&self) -> (u64, u64) {
+        self.seeds
+    }
+
+    /// Tests whether an element may be in the filter or definitely not in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bbloom::StaticBloomFilter;
+    ///
+    /// static WORDS: [u64; 1] = [0; 1];
+    /// let filter = StaticBloomFilter::from_static(&WORDS, 64, 3, (0, 1));
+    ///
+    /// assert!(!filter.contains("a"));
+    /// ```
+    pub fn contains<H: Hash + ?Sized>(&self, key: &H) -> bool {
+        let builder_1 = SeededHashBuilder::new(self.seeds.0);
+        let builder_2 = SeededHashBuilder::new(self.seeds.1);
+        let hasher = DoubleHasher::new(key, &builder_1, &builder_2);
+        let (present, _) = probe_bits::<_, DoubleHashing>(&self.bits, self.k, hasher);
+        present
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_contains() {
+        let seeds = (5, 8);
+
+        let mut filter = BloomFilter::from_fpp_with_hashers(
+            0.001,
+            16,
+            SeededHashBuilder::new(seeds.0),
+            SeededHashBuilder::new(seeds.1),
+        );
+
+        filter.insert("a");
+        filter.insert("b");
+
+        let words = Box::leak(filter.to_words().into_boxed_slice());
+        let static_filter =
+            StaticBloomFilter::from_static(words, filter.capacity(), filter.hash_count(), seeds);
+
+        assert!(static_filter.contains("a"));
+        assert!(static_filter.contains("b"));
+        assert!(!static_filter.contains("c"));
+    }
+}
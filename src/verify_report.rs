@@ -0,0 +1,32 @@
+/// The result of verifying a filter against a list of keys.
+///
+/// A filter has no false negatives, so if any key from the list the filter was built from is
+/// reported absent, the filter is corrupt or was built from different data.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VerifyReport {
+    pub(crate) checked: u64,
+    pub(crate) false_negatives: u64,
+    pub(crate) first_false_negative: Option<u64>,
+}
+
+impl VerifyReport {
+    /// Returns the number of keys that were checked.
+    pub fn checked(&self) -> u64 {
+        self.checked
+    }
+
+    /// Returns the number of keys that the filter reported as absent.
+    pub fn false_negatives(&self) -> u64 {
+        self.false_negatives
+    }
+
+    /// Returns the 1-based line number of the first key that the filter reported as absent.
+    pub fn first_false_negative(&self) -> Option<u64> {
+        self.first_false_negative
+    }
+
+    /// Returns whether every checked key may be in the filter.
+    pub fn is_ok(&self) -> bool {
+        self.false_negatives == 0
+    }
+}
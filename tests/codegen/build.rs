@@ -0,0 +1,18 @@
+use std::{env, io, path::PathBuf};
+
+fn main() -> io::Result<()> {
+    let out_dir = env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .expect("missing OUT_DIR");
+
+    let keys: Vec<_> = (0..1024).map(|i| format!("key-{}", i)).collect();
+    bbloom::codegen::generate(
+        keys.iter().map(|s| s.as_str()),
+        0.001,
+        &out_dir.join("keys.rs"),
+    )?;
+
+    println!("cargo:rerun-if-changed=build.rs");
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! An end-to-end test of a filter generated by `bbloom::codegen` in a build script.
+
+include!(concat!(env!("OUT_DIR"), "/keys.rs"));
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn test_generated_filter_contains_keys() {
+        for i in 0..1024 {
+            assert!(FILTER.contains(&format!("key-{}", i)));
+        }
+
+        let false_positives = (1024..11024)
+            .filter(|i| FILTER.contains(&format!("key-{}", i)))
+            .count();
+
+        assert!(false_positives < 30);
+    }
+
+    #[test]
+    fn test_generated_blob_matches_filter() {
+        let out_dir = PathBuf::from(env!("OUT_DIR"));
+        let buf = fs::read(out_dir.join("keys.bin")).unwrap();
+
+        let values: Vec<_> = buf
+            .chunks(8)
+            .map(|c| {
+                let mut b = [0; 8];
+                b.copy_from_slice(c);
+                u64::from_le_bytes(b)
+            })
+            .collect();
+
+        assert_eq!(values[0] as usize, FILTER.capacity());
+        assert_eq!((values[2], values[3]), FILTER.seeds());
+        assert_eq!(values[4] as usize, values.len() - 5);
+        assert_eq!(&values[5..], &WORDS[..]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Reading the metadata of serialized filters (see [`bbloom::io::read_metadata`]).
+//!
+//! The fixtures were written by this crate and must stay readable, so any change to the binary
+//! format must fail this test.
+
+use std::io::{self, Read};
+
+use bbloom::{
+    io::{read_metadata, FilterKind, ReadError},
+    BloomFilter, ScalableBloomFilter, SeededHashBuilder,
+};
+
+// A Bloom filter with p = 0.01, n = 128, label 8, and default `SeededHashBuilder`s, with the keys
+// 0..100 (`u64`) inserted.
+const BLOOM_FILTER: &[u8] = include_bytes!("fixtures/bloom_filter.bin");
+
+// A scalable Bloom filter with p = 0.01, an initial capacity of 32, and default
+// `SeededHashBuilder`s, with the keys 0..100 (`u64`) inserted.
+const SCALABLE_BLOOM_FILTER: &[u8] = include_bytes!("fixtures/scalable_bloom_filter.bin");
+
+// A reader that is not seekable and counts the bytes read.
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<R> Read for CountingReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_read_metadata_of_bloom_filter() -> Result<(), ReadError> {
+    let mut reader = CountingReader::new(BLOOM_FILTER);
+    let metadata = read_metadata(&mut reader)?;
+
+    assert_eq!(metadata.version(), 1);
+    assert_eq!(metadata.kind(), FilterKind::BloomFilter);
+    assert_eq!(metadata.probe_strategy(), 0);
+    assert_eq!(metadata.len(), 100);
+    assert_eq!(metadata.label(), Some(8));
+    assert_eq!(metadata.capacity(), Some(1227));
+    assert_eq!(metadata.hash_count(), Some(7));
+    assert_eq!(metadata.payload_len(), Some(154));
+    assert_eq!(metadata.layer_count(), None);
+
+    // magic number, version, kind, label, m, k, n, payload size
+    assert_eq!(reader.count, 4 + 1 + 1 + 8 + 8 + 8 + 8 + 8);
+
+    let filter: BloomFilter<SeededHashBuilder> = BloomFilter::from_bytes(BLOOM_FILTER)?;
+    assert!((0..100u64).all(|i| filter.contains(&i)));
+    assert!(metadata.is_compatible_with(&filter));
+
+    let other = BloomFilter::new(1227, 7);
+    assert!(!metadata.is_compatible_with(&other));
+    assert!(metadata.is_compatible_with(&other.with_label(8)));
+
+    Ok(())
+}
+
+#[test]
+fn test_read_metadata_of_scalable_bloom_filter() -> Result<(), ReadError> {
+    let mut reader = CountingReader::new(SCALABLE_BLOOM_FILTER);
+    let metadata = read_metadata(&mut reader)?;
+
+    assert_eq!(metadata.version(), 1);
+    assert_eq!(metadata.kind(), FilterKind::ScalableBloomFilter);
+    assert_eq!(metadata.len(), 100);
+    assert_eq!(metadata.capacity(), None);
+    assert_eq!(metadata.total_capacity(), Some(288));
+    assert_eq!(metadata.layer_count(), Some(3));
+    assert!(!metadata.has_negative_cache());
+    assert!(metadata.last_fpp().unwrap() < 0.01);
+
+    // magic number, version, kind, n, total capacity, last fpp, layer count
+    assert_eq!(reader.count, 4 + 1 + 1 + 8 + 8 + 8 + 8);
+
+    let filter: ScalableBloomFilter<SeededHashBuilder> =
+        ScalableBloomFilter::from_bytes(SCALABLE_BLOOM_FILTER)?;
+    assert!((0..100u64).all(|i| filter.contains(&i)));
+    assert!(!metadata.is_compatible_with(&BloomFilter::new(1227, 7)));
+
+    Ok(())
+}
+
+#[test]
+fn test_read_metadata_with_truncated_header() {
+    for data in &[
+        &BLOOM_FILTER[..3],
+        &BLOOM_FILTER[..30],
+        &SCALABLE_BLOOM_FILTER[..20],
+    ] {
+        assert!(matches!(
+            read_metadata(&mut CountingReader::new(*data)),
+            Err(ReadError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
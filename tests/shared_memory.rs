@@ -0,0 +1,68 @@
+//! Sharing a [`bbloom::SharedMemBloomFilter`] across processes.
+//!
+//! The parent test runs this test binary again as a child process, filtered to
+//! `insert_from_child`, which inserts keys into the region named by `BBLOOM_SHM_NAME`.
+
+#![cfg(all(unix, feature = "shared-memory"))]
+
+use std::{
+    env, io,
+    process::{self, Command},
+};
+
+use bbloom::SharedMemBloomFilter;
+
+const NAME_VAR: &str = "BBLOOM_SHM_NAME";
+const KEY_COUNT: u64 = 10_000;
+
+#[test]
+fn insert_from_child() -> io::Result<()> {
+    // This only runs as the child of `test_insert_from_another_process`.
+    let name = match env::var(NAME_VAR) {
+        Ok(name) => name,
+        Err(_) => return Ok(()),
+    };
+
+    let filter = SharedMemBloomFilter::open(&name)?;
+
+    for i in 0..KEY_COUNT {
+        filter.insert(&i);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_insert_from_another_process() -> io::Result<()> {
+    let name = format!("bbloom-test-{}", process::id());
+    let filter = SharedMemBloomFilter::create(&name, 0.001, 2 * KEY_COUNT as usize)?;
+
+    for i in KEY_COUNT..(2 * KEY_COUNT) {
+        filter.insert(&i);
+    }
+
+    let status = Command::new(env::current_exe()?)
+        .args(["insert_from_child", "--exact", "--quiet"])
+        .env(NAME_VAR, &name)
+        .status();
+
+    let result = status.and_then(|status| {
+        assert!(status.success(), "child process failed: {}", status);
+
+        // Another process can still open the region.
+        let other = SharedMemBloomFilter::open(&name)?;
+
+        for i in 0..(2 * KEY_COUNT) {
+            assert!(filter.contains(&i), "false negative: {}", i);
+            assert!(other.contains(&i), "false negative: {}", i);
+        }
+
+        assert!(filter.len() > KEY_COUNT as usize);
+
+        Ok(())
+    });
+
+    SharedMemBloomFilter::unlink(&name)?;
+
+    result
+}
@@ -0,0 +1,131 @@
+//! Golden vectors for the derivation of probe indices (see `vectors.json` and [`bbloom::io`]).
+//!
+//! The vectors are shared with implementations in other languages, so any change to the
+//! derivation must fail this test.
+
+use std::hash::{BuildHasher, Hasher};
+
+use bbloom::{
+    probe_strategy::{DoubleHashing, ProbeStrategy},
+    BloomFilter, SeededHashBuilder,
+};
+use serde_json::Value;
+
+const VECTORS: &str = include_str!("vectors.json");
+
+// The value `h2` is mixed with when it is equal to `h1`.
+const PERTURBATION: u64 = 0x9e37_79b9_7f4a_7c15;
+
+enum Key {
+    Bytes(Vec<u8>),
+    Str(String),
+    U64(u64),
+}
+
+impl Key {
+    fn from_vector(vector: &Value) -> Self {
+        let key = vector["key"].as_str().unwrap();
+
+        match vector["key_type"].as_str().unwrap() {
+            "bytes" => Self::Bytes(decode_hex(key)),
+            "str" => Self::Str(key.into()),
+            "u64" => Self::U64(key.parse().unwrap()),
+            key_type => panic!("invalid key type: {}", key_type),
+        }
+    }
+
+    fn hash(&self, builder: &SeededHashBuilder) -> u64 {
+        match self {
+            Self::Bytes(buf) => {
+                let mut hasher = builder.build_hasher();
+                hasher.write(buf);
+                hasher.finish()
+            }
+            Self::Str(s) => builder.hash_one(s.as_str()),
+            Self::U64(n) => builder.hash_one(n),
+        }
+    }
+
+    fn insert_into(&self, filter: &mut BloomFilter<SeededHashBuilder>) {
+        match self {
+            Self::Bytes(buf) => filter.insert_with(|hasher| hasher.write(buf)),
+            Self::Str(s) => filter.insert(s.as_str()),
+            Self::U64(n) => filter.insert(n),
+        };
+    }
+}
+
+fn decode_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn parse_hash(value: &Value) -> u64 {
+    u64::from_str_radix(value.as_str().unwrap(), 16).unwrap()
+}
+
+fn as_usize(value: &Value) -> usize {
+    value.as_u64().unwrap() as usize
+}
+
+// The finalizer of SplitMix64.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[test]
+fn test_vectors() {
+    let doc: Value = serde_json::from_str(VECTORS).unwrap();
+    let configurations = doc["configurations"].as_array().unwrap();
+    assert!(!configurations.is_empty());
+
+    for configuration in configurations {
+        let builder_1 = SeededHashBuilder::new(configuration["seed_1"].as_u64().unwrap());
+        let builder_2 = SeededHashBuilder::new(configuration["seed_2"].as_u64().unwrap());
+
+        let filters = configuration["filters"].as_array().unwrap();
+
+        for vector in configuration["vectors"].as_array().unwrap() {
+            let key = Key::from_vector(vector);
+
+            let hash_1 = key.hash(&builder_1);
+            let hash_2 = key.hash(&builder_2);
+            assert_eq!(hash_1, parse_hash(&vector["hash_1"]), "{}", vector);
+            assert_eq!(hash_2, parse_hash(&vector["hash_2"]), "{}", vector);
+
+            let h1 = hash_1;
+            let h2 = if hash_1 == hash_2 {
+                mix(hash_1 ^ PERTURBATION)
+            } else {
+                hash_2
+            };
+            assert_eq!(h1, parse_hash(&vector["h1"]), "{}", vector);
+            assert_eq!(h2, parse_hash(&vector["h2"]), "{}", vector);
+
+            let indices = vector["indices"].as_array().unwrap();
+            assert_eq!(indices.len(), filters.len());
+
+            for (params, expected) in filters.iter().zip(indices) {
+                let (m, k) = (as_usize(&params["m"]), as_usize(&params["k"]));
+                let expected: Vec<_> = expected.as_array().unwrap().iter().map(as_usize).collect();
+
+                let actual: Vec<_> = (0..k).map(|i| DoubleHashing::index(h1, h2, i, m)).collect();
+                assert_eq!(actual, expected, "{}", vector);
+
+                // The filter sets exactly the bits at the probe indices.
+                let mut filter = BloomFilter::with_hashers(m, k, builder_1, builder_2);
+                key.insert_into(&mut filter);
+
+                let mut expected = expected;
+                expected.sort_unstable();
+                expected.dedup();
+
+                assert!(filter.iter_ones().eq(expected), "{}", vector);
+            }
+        }
+    }
+}